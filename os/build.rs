@@ -2,11 +2,28 @@ use std::io::{Result, Write};
 use std::fs::{self, File};
 
 static TARGET_PATH: &str = "./user/target/riscv64gc-unknown-none-elf/release/";
+/// Scheduling policies in `src/task/scheduler`, one of which is picked by the `SCHEDULER` environment variable.
+static SCHEDULERS: [&str; 2] = ["mlfq", "cfs"];
 
 fn main() {
+    // the script runs again when any of these changes, rather than when any file of the package does.
+    println!("cargo:rerun-if-changed=build.rs");
+    println!("cargo:rerun-if-changed=application.txt");
+    println!("cargo:rerun-if-changed=src");
+    println!("cargo:rerun-if-env-changed=SCHEDULER");
+    check_scheduler();
     insert_app_data().unwrap();
 }
 
+/// A typo in `SCHEDULER` fails the build, instead of the kernel at boot.
+fn check_scheduler() {
+    if let Ok(name) = std::env::var("SCHEDULER") {
+        if !SCHEDULERS.contains(&name.as_str()) {
+            panic!("unknown SCHEDULER: {}, expected one of {:?}", name, SCHEDULERS);
+        }
+    }
+}
+
 fn insert_app_data() -> Result<()> {
     let mut f = File::create("src/link_app.asm").unwrap();
     let mut apps: Vec<_> = fs::read_to_string("application.txt").unwrap()
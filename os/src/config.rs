@@ -1,4 +1,5 @@
 pub const MAX_TASK_NUMBER: usize = 64;
+pub const MAX_HART_NUMBER: usize = 4; // the number of boot stacks in entry.asm.
 pub const FRAME_SIZE: usize = 4096;
 pub const KERNEL_MAPPING_OFFSET: usize = 0xFFFFFFC000000000;
 // pub const KERNEL_MAPPING_OFFSET: usize = 0;
@@ -11,6 +12,12 @@ pub const RAM_SIZE: usize = 0x800_000;
 pub const RAM_SIZE: usize = 0x600_000;
 pub const MAX_USER_ADDRESS: usize = 0x4_000_000_000;
 pub const MMAP_START_ADDRESS: usize = 0x2_000_000_000;
+/// The stack starts with this size, and grows on page faults up to `RLIMIT_STACK`.
+pub const USER_STACK_SIZE: usize = FRAME_SIZE * 2;
+/// The default soft limit of `RLIMIT_STACK`, well below `RAM_SIZE` so one task can not take up all the frames.
+pub const USER_STACK_LIMIT: usize = 0x100_000;
+/// The stack never grows closer to the region below it, so an overflow faults instead of running into it.
+pub const STACK_GUARD_GAP: usize = FRAME_SIZE * 256;
 #[cfg(feature = "board_qemu")]
 pub const UART_BASE_ADDRESS: usize = 0x1000_0000;
 #[cfg(feature = "board_k210")]
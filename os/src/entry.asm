@@ -8,7 +8,7 @@ _start:
     add sp, t1, t0
 
     bgtz a0, end_clear_bss  # only hart0 invokes clear_bss
-    lla t0, __bss_start
+    lla t0, boot_stack_top  # other harts may be running on their boot stacks.
     lla t1, __bss_end
 clear_bss:
     beq t0, t1, end_clear_bss
@@ -23,4 +23,6 @@ end_clear_bss:
 .section .bss.stack
 .globl boot_stack
 boot_stack:
-    .space 4096 * 16 * 2
\ No newline at end of file
+    .space 4096 * 16 * 4    # MAX_HART_NUMBER boot stacks
+.globl boot_stack_top
+boot_stack_top:
@@ -24,7 +24,7 @@ use processor::enable_other_harts;
 use processor::set_hart_id;
 
 use crate::processor::CPU_NUMS;
-use crate::task::increase_alive_hart;
+use crate::config::MAX_HART_NUMBER;
 use crate::mm::heap::heap_allocator;
 
 #[macro_use]
@@ -66,14 +66,17 @@ pub extern "C" fn kmain(hart_id: usize, _: usize) -> ! {
         timer::enable_time_interrupt();
         plic::enable_external_interrupt();
         plic::init();
-        increase_alive_hart();
+        #[cfg(feature = "board_qemu")]
+        processor::enable_ipi();
         task::print_app_names();
+        info!("scheduler: {}", task::SCHEDULER);
         task::load_init_tasks();
-        // enable_other_harts();
+        // the second hart of k210 is left suspended, since the software interrupt is taken by plic.
+        #[cfg(feature = "board_qemu")]
+        enable_other_harts();
         info!("start running");
         processor::run_on_current_hart();
     } else {
-        increase_alive_hart();
         other_hart_init_task();
         info!("start running");
         processor::run_on_current_hart();
@@ -83,11 +86,13 @@ pub extern "C" fn kmain(hart_id: usize, _: usize) -> ! {
 }
 
 fn environment_check() {
-    // Should make the constant CPU_NUMS >= the environment variable when panic.
-    assert!(CPU_NUMS >= env!("CPU_NUMS").parse::<usize>().unwrap());
+    // Each hart has its own boot stack in entry.asm.
+    assert!(CPU_NUMS > 0 && CPU_NUMS <= MAX_HART_NUMBER);
 }
 
 fn other_hart_init_task() {
     trap::init_stvec();
+    timer::enable_time_interrupt();
+    processor::enable_ipi();
 }
 
@@ -230,12 +230,9 @@ impl From<PhysicalPageNum> for PhysicalAddress {
 
 impl From<VirtualAddress> for PhysicalAddress {
     fn from(va: VirtualAddress) -> Self {
-        let vpn = va.floor();
         let cur_task = get_cur_task_in_this_hart();
         let cur_task_inner = cur_task.acquire_inner_lock();
-        let ppn = cur_task_inner.mem_manager.page_table.translate(vpn).unwrap();
-
-        PhysicalAddress::from(ppn).add(va.offset())
+        cur_task_inner.mem_manager.lock().translate_for_read(va).unwrap()
     }
 }
 
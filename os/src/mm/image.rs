@@ -0,0 +1,49 @@
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use crate::config::FRAME_SIZE;
+use crate::mm::alloc_frame;
+use crate::mm::address::PhysicalAddress;
+use crate::mm::frame_allocator::FrameTracker;
+use share::syscall::error::SysError;
+
+/// The file data of a segment or a private file mapping, kept in frames so that the pages of the
+/// region are filled from it when they are first touched instead of up front.
+pub struct Image {
+    frames: Vec<Arc<FrameTracker>>,
+    len: usize,
+}
+
+impl Image {
+    pub fn new(data: &[u8]) -> Result<Arc<Self>, SysError> {
+        let mut frames = Vec::new();
+        for chunk in data.chunks(FRAME_SIZE) {
+            let frame = alloc_frame()?;
+            frame.fill_with(chunk);
+            frames.push(Arc::new(frame));
+        }
+
+        Ok(Arc::new(Self { frames, len: data.len() }))
+    }
+
+    /// Return a frame holding `[offset, offset + FRAME_SIZE)` of the data, zero filled beyond its end.
+    ///
+    /// A whole page of the data is shared with the image unless `private` is set, it is mapped
+    /// read-only then and copied when it is written.
+    pub fn page(&self, offset: usize, private: bool) -> Result<Arc<FrameTracker>, SysError> {
+        assert_eq!(offset % FRAME_SIZE, 0);
+        if !private && offset + FRAME_SIZE <= self.len {
+            return Ok(self.frames[offset / FRAME_SIZE].clone());
+        }
+
+        let frame = alloc_frame()?;
+        match self.frames.get(offset / FRAME_SIZE) {
+            Some(data_frame) => {
+                let data: &[u8; FRAME_SIZE] = PhysicalAddress::from(data_frame.0).as_ref();
+                frame.fill_with(&data[..usize::min(FRAME_SIZE, self.len - offset)]);
+            }
+            None => frame.fill_with(&[]),
+        }
+
+        Ok(Arc::new(frame))
+    }
+}
@@ -2,14 +2,19 @@ use crate::mm::page_table::{PageTable, PTEFlags};
 use alloc::vec::Vec;
 use crate::mm::frame_allocator::FrameTracker;
 use crate::mm::address::{VirtualAddress, VirtualPageNum, PhysicalAddress};
-use crate::config::{FRAME_SIZE, MAX_USER_ADDRESS, MMAP_START_ADDRESS};
+use crate::config::{FRAME_SIZE, MAX_USER_ADDRESS, MMAP_START_ADDRESS, USER_STACK_SIZE, USER_STACK_LIMIT, STACK_GUARD_GAP};
 use alloc::boxed::Box;
+use alloc::sync::Arc;
 use core::fmt::{Debug, Formatter};
 use crate::mm::{alloc_frame, address, alloc_continuous_frames};
 use core::arch::asm;
-use share::syscall::error::{SysError, EACCES, ENOMEM};
+use share::syscall::error::{SysError, EACCES, ENOMEM, EINVAL, EFAULT};
 use alloc::vec;
-use crate::syscall::file::do_write;
+use crate::processor::flush_tlb_of_other_harts;
+use crate::mm::image::Image;
+use crate::mm::page_cache::SharedFile;
+use share::resource::{Rlimit, RLIM_INFINITY};
+use share::mmap::{MapEntry, MapKind, Prot};
 
 pub struct MemoryManager {
     pub page_table: PageTable,
@@ -18,6 +23,9 @@ pub struct MemoryManager {
     pub brk_start: VirtualAddress,
     /// Current programme break.
     pub brk: VirtualAddress,
+    /// `RLIMIT_STACK` of the task, the stack below `MAX_USER_ADDRESS` grows on page faults up to
+    /// the soft limit.
+    pub stack_limit: Rlimit,
 }
 
 impl MemoryManager {
@@ -29,6 +37,7 @@ impl MemoryManager {
             region_list,
             brk_start: VirtualAddress::new(0),
             brk: VirtualAddress::new(0),
+            stack_limit: Rlimit::new(USER_STACK_LIMIT, RLIM_INFINITY),
         };
 
         let elf = xmas_elf::ElfFile::new(data).unwrap();
@@ -61,9 +70,8 @@ impl MemoryManager {
         }
 
         let stack_top = MAX_USER_ADDRESS;
-        let stack_size = FRAME_SIZE * 2;
         mem_manager.add_area(
-            VirtualAddress::new(stack_top - stack_size), stack_size,
+            VirtualAddress::new(stack_top - USER_STACK_SIZE), USER_STACK_SIZE,
             RegionFlags::R | RegionFlags::W, RegionType::Default, None,
         )?;
 
@@ -75,14 +83,24 @@ impl MemoryManager {
         Ok((mem_manager, pc, stack_top))
     }
 
-    pub fn clone(&self) -> Result<Self, SysError> {
+    /// Copy the address space of current task for a forked child. Private frames are shared with
+    /// the child and mapped read-only on both sides, until either of them writes to them. Other
+    /// harts running the address space still have the writable translations, the caller has to
+    /// shoot them down.
+    pub fn clone(&mut self) -> Result<Self, SysError> {
         let mut page_table = PageTable::new_user_table()?;
         let mut region_list = RegionList::empty();
         for mem in self.region_list.iter() {
-            let new_region = mem.clone_with_new_frames()?;
+            let new_region = mem.clone_for_fork()?;
             new_region.mapped_by(&mut page_table)?;
             region_list.insert(Box::new(new_region));
         }
+        for mem in self.region_list.iter() {
+            mem.write_protect(&mut self.page_table);
+        }
+        unsafe {
+            asm!("sfence.vma");
+        }
 
         Ok(
             Self {
@@ -90,6 +108,7 @@ impl MemoryManager {
                 region_list,
                 brk_start: self.brk_start,
                 brk: self.brk,
+                stack_limit: self.stack_limit,
             }
         )
     }
@@ -98,11 +117,9 @@ impl MemoryManager {
      flags: RegionFlags, region_type: RegionType, data: Option<&[u8]>) -> Result<(), SysError> {
         let mut memory_region =
             MemoryRegion::new(start, size, flags, region_type)?;
-        let data = match data {
-            Some(data) => data,
-            None => &[]
-        };
-        memory_region.fill(data)?;
+        if let Some(data) = data {
+            memory_region.fill(data)?;
+        }
         memory_region.mapped_by(&mut self.page_table)?;
 
         self.region_list.insert(Box::new(memory_region));
@@ -120,8 +137,9 @@ impl MemoryManager {
                 .ok_or(SysError::new(ENOMEM))?;
 
         let mut memory_region = MemoryRegion::new(region_start, size, flags, region_type)?;
-        let data = data.unwrap_or(&[]);
-        memory_region.fill(data)?;
+        if let Some(data) = data {
+            memory_region.fill(data)?;
+        }
         memory_region.mapped_by(&mut self.page_table)?;
 
         self.region_list.insert(Box::new(memory_region));
@@ -129,6 +147,48 @@ impl MemoryManager {
         Ok(region_start)
     }
 
+    /// Map `frames` of a shared memory object starting from `MMAP_START_ADDRESS`.
+    pub fn map_shared_memory(&mut self, frames: Vec<Arc<FrameTracker>>, flags: RegionFlags)
+        -> Result<VirtualAddress, SysError> {
+        let size = frames.len() * FRAME_SIZE;
+        let alloc_start = VirtualAddress::new(MMAP_START_ADDRESS);
+        let region_start =
+            self.region_list.
+                find_unused_region_and_return_start_addr(size, Some(alloc_start))
+                .ok_or(SysError::new(ENOMEM))?;
+
+        let frames = frames.into_iter().map(Some).collect();
+        let memory_region = MemoryRegion::with_frames(region_start, frames, flags, RegionType::SharedMemory);
+        memory_region.mapped_by(&mut self.page_table)?;
+        self.region_list.insert(Box::new(memory_region));
+
+        Ok(region_start)
+    }
+
+    /// Return the frames and flags of the shared memory region which starts at `start`.
+    pub fn get_shared_memory(&self, start: VirtualAddress) -> Result<(Vec<Arc<FrameTracker>>, RegionFlags), SysError> {
+        let region = self.find_shared_memory(start)?;
+        let frames = region.frames.iter().map(|frame| frame.clone().unwrap()).collect();
+        Ok((frames, region.flags))
+    }
+
+    /// Unmap the whole shared memory region which starts at `start`. Its frames are freed after
+    /// every task has unmapped them.
+    pub fn unmap_shared_memory(&mut self, start: VirtualAddress) -> Result<(), SysError> {
+        let size = self.find_shared_memory(start)?.region_size;
+        assert!(self.delete_area(start, size));
+
+        Ok(())
+    }
+
+    fn find_shared_memory(&self, start: VirtualAddress) -> Result<&MemoryRegion, SysError> {
+        self.region_list.iter()
+            .find(|region| region.start == start)
+            .filter(|region| region.region_type == RegionType::SharedMemory)
+            .map(|region| &**region)
+            .ok_or(SysError::new(EINVAL))
+    }
+
     pub fn delete_area(&mut self, start: VirtualAddress, size: usize) -> bool {
         if self.region_list.is_region_exists(start, size) {
             assert!(self.region_list.delete(start, size));
@@ -139,10 +199,309 @@ impl MemoryManager {
         }
     }
 
-    pub fn sync(&self) {
+    /// Unmap every page in `[start, start + size)`, which may cover several regions and the holes
+    /// between them. Return whether any page was mapped.
+    pub fn unmap_range(&mut self, start: VirtualAddress, size: usize) -> bool {
+        let deleted = self.region_list.delete_range(start, size);
+        if deleted {
+            self.unmap_area(start, size);
+        }
+        deleted
+    }
+
+    /// Change the permissions of `[start, start + size)` to `flags`, the regions are split at both
+    /// ends. ENOMEM is returned if any page in the range is not mapped, and EACCES if a shared
+    /// mapping of a file not opened for writing is made writable.
+    pub fn protect_area(&mut self, start: VirtualAddress, size: usize, flags: RegionFlags) -> Result<(), SysError> {
+        let end = start.add(size);
+        // a file not opened for writing can't be written through a shared mapping.
+        if flags.contains(RegionFlags::W) && self.region_list.iter()
+            .filter(|region| region.start < end && region.end() > start)
+            .any(|region| matches!((region.region_type, &region.backing), (RegionType::Shared, Backing::File(_, _, false)))) {
+            return Err(SysError::new(EACCES));
+        }
+        if !self.region_list.protect(start, size, flags) {
+            return Err(SysError::new(ENOMEM));
+        }
+        for region in self.region_list.iter().filter(|region| region.start < end && region.end() > start) {
+            region.protect(&mut self.page_table)?;
+        }
+        let start_vpn: VirtualPageNum = start.into();
+        for vpn in start_vpn..end.into() {
+            flush_tlb_entry(vpn);
+        }
+
+        Ok(())
+    }
+
+    /// Resize the private mapping `[start, start + old_size)` to `new_size`. It grows in place if the
+    /// pages after it are free, or is moved to a free range when `may_move` is set. Return the start
+    /// of the mapping afterwards.
+    pub fn remap_area(&mut self, start: VirtualAddress, old_size: usize, new_size: usize, may_move: bool)
+        -> Result<VirtualAddress, SysError> {
+        let region = self.region_list.iter()
+            .find(|region| region.contain(start))
+            .filter(|region| start.add(old_size) <= region.end())
+            .ok_or(SysError::new(EFAULT))?;
+        if region.region_type != RegionType::Default {
+            return Err(SysError::new(EINVAL));
+        }
+        if new_size <= old_size {
+            if new_size < old_size {
+                assert!(self.unmap_range(start.add(new_size), old_size - new_size));
+            }
+            return Ok(start);
+        }
+
+        let old_end = start.add(old_size);
+        let new_end = start.add(new_size);
+        let is_free = new_end.0 <= MAX_USER_ADDRESS &&
+            self.region_list.iter().all(|region| region.end() <= old_end || region.start >= new_end);
+        if is_free {
+            self.region_list.extend(start, new_size - old_size);
+            return Ok(start);
+        }
+        if !may_move {
+            return Err(SysError::new(ENOMEM));
+        }
+
+        let new_start = self.region_list
+            .find_unused_region_and_return_start_addr(new_size, Some(VirtualAddress::new(MMAP_START_ADDRESS)))
+            .ok_or(SysError::new(ENOMEM))?;
+        let mut region = self.region_list.take(start, old_size).unwrap();
+        self.unmap_area(start, old_size);
+        region.start = new_start;
+        region.extend(new_size - old_size);
+        region.mapped_by(&mut self.page_table)?;
+        self.region_list.insert(region);
+
+        Ok(new_start)
+    }
+
+    /// Translate user address `va` of an aligned `u32`, which may not be in the current address space.
+    /// A copy-on-write page is copied first, but it may be shared again by a fork and copied by the
+    /// next write, so the address is only stable for the pages of [`is_shared`](Self::is_shared).
+    pub fn translate_u32(&mut self, va: VirtualAddress) -> Result<PhysicalAddress, SysError> {
+        let size = core::mem::size_of::<u32>();
+        if va.0 % size != 0 || !self.region_list.is_region_exists(va, size) {
+            return Err(SysError::new(EFAULT));
+        }
+        self.translate_for_write(va)
+    }
+
+    /// Whether the page of `va` is shared with other address spaces on purpose, by shared memory or
+    /// a file mapped shared. Its frame is never copied.
+    pub fn is_shared(&self, va: VirtualAddress) -> bool {
+        self.region_list.iter()
+            .any(|region| region.contain(va) && matches!(region.region_type, RegionType::Shared | RegionType::SharedMemory))
+    }
+
+    /// Copy `data` to user address `va` through the physical addresses page by page, so it works
+    /// whichever address space is current, and a page which is not mapped returns EFAULT rather
+    /// than faulting in the kernel. The stack grows to hold it, and every page must be writable.
+    pub fn write_bytes(&mut self, va: VirtualAddress, data: &[u8]) -> Result<(), SysError> {
+        let end = va.0.checked_add(data.len()).ok_or(SysError::new(EFAULT))?;
+        if end > MAX_USER_ADDRESS {
+            return Err(SysError::new(EFAULT));
+        }
+        let mut copied = 0;
+        while copied < data.len() {
+            let va = va.add(copied);
+            let _ = self.grow_stack_to(va);
+            if !self.region_list.iter().any(|region| region.contain(va) && region.flags.contains(RegionFlags::W)) {
+                return Err(SysError::new(EFAULT));
+            }
+            let len = (data.len() - copied).min(FRAME_SIZE - va.0 % FRAME_SIZE);
+            let pa = self.translate_for_write(va)?;
+            unsafe { core::ptr::copy(data[copied..].as_ptr(), pa.as_raw_mut::<u8>(), len) };
+            copied += len;
+        }
+
+        Ok(())
+    }
+
+    pub fn write_u32(&mut self, va: VirtualAddress, value: u32) -> Result<(), SysError> {
+        let pa = self.translate_u32(va)?;
+        unsafe { pa.as_raw_mut::<u32>().write(value) };
+
+        Ok(())
+    }
+
+    /// Translate user address `va` for the kernel to read through the physical address, which may
+    /// not be in the current address space. A page which hasn't been touched is backed first.
+    pub fn translate_for_read(&mut self, va: VirtualAddress) -> Result<PhysicalAddress, SysError> {
+        self.translate_user(va, false)
+    }
+
+    /// Translate user address `va` for the kernel to write to through the physical address, which may
+    /// not be in the current address space. A copy-on-write page is copied first, or the write would
+    /// show up in every task sharing the frame.
+    pub fn translate_for_write(&mut self, va: VirtualAddress) -> Result<PhysicalAddress, SysError> {
+        self.translate_user(va, true)
+    }
+
+    /// Handle a page fault at `va` caused by an `access` of R, W or X, return whether a shared frame
+    /// has been copied. The other harts running this address space have to flush their stale
+    /// translation of the page in that case.
+    ///
+    /// A page which hasn't been touched is backed by a frame, and a copy-on-write page written to
+    /// gets a frame of its own. EFAULT is returned if `va` is not mapped or doesn't allow `access`.
+    pub fn handle_page_fault(&mut self, va: VirtualAddress, access: RegionFlags) -> Result<bool, SysError> {
+        self.grow_stack_to(va)?;
+        let region = self.region_list.find_first_region_containing(va)
+            .filter(|region| region.flags.contains(access))
+            .ok_or(SysError::new(EFAULT))?;
+        let vpn = va.floor();
+        let copied = region.fault_in(vpn, &mut self.page_table, access.contains(RegionFlags::W))?;
+        flush_tlb_entry(vpn);
+
+        Ok(copied)
+    }
+
+    /// The kernel is not restricted by the permissions of the region.
+    fn translate_user(&mut self, va: VirtualAddress, for_write: bool) -> Result<PhysicalAddress, SysError> {
+        self.grow_stack_to(va)?;
+        let region = self.region_list.find_first_region_containing(va)
+            .filter(|region| !region.flags.is_empty())
+            .ok_or(SysError::new(EFAULT))?;
+        if region.fault_in(va.floor(), &mut self.page_table, for_write)? {
+            flush_tlb_entry(va.floor());
+            flush_tlb_of_other_harts(self.page_table.satp(), false);
+        }
+        self.page_table.translate_va(va).ok_or(SysError::new(EFAULT))
+    }
+
+    /// Grow the stack down to the page of `va` if `va` is not mapped but within the reach of the
+    /// stack: above `MAX_USER_ADDRESS - stack_limit.cur`, and `STACK_GUARD_GAP` away from the region
+    /// below the stack. EFAULT is returned for any other address which is not mapped.
+    pub fn grow_stack_to(&mut self, va: VirtualAddress) -> Result<(), SysError> {
+        if self.region_list.is_region_exists(va, 1) {
+            return Ok(());
+        }
+        let stack_bottom = self.region_list.iter()
+            .find(|region| region.end().0 == MAX_USER_ADDRESS)
+            .map(|region| region.start)
+            .ok_or(SysError::new(EFAULT))?;
+        if va >= stack_bottom {
+            return Err(SysError::new(EFAULT));
+        }
+
+        let guard_end = self.region_list.iter()
+            .take_while(|region| region.start < stack_bottom)
+            .last()
+            .map_or(0, |region| region.end().0 + STACK_GUARD_GAP);
+        let lowest = usize::max(guard_end, MAX_USER_ADDRESS.saturating_sub(self.stack_limit.cur));
+        if va.0 < lowest {
+            if va.0 + STACK_GUARD_GAP >= lowest {
+                info!("stack overflow at {:#x}, the stack is {:#x} bytes", va.0, MAX_USER_ADDRESS - stack_bottom.0);
+            }
+            return Err(SysError::new(EFAULT));
+        }
+
+        let start: VirtualAddress = va.floor().into();
+        self.add_area(start, stack_bottom.0 - start.0, RegionFlags::R | RegionFlags::W, RegionType::Default, None)
+    }
+
+    /// The regions of the address space in order, what each region is used for is told by its type
+    /// and its place.
+    pub fn memory_map(&self) -> Vec<MapEntry> {
+        self.region_list.iter().map(|region| {
+            let kind = match region.region_type {
+                RegionType::Continuous => MapKind::Device,
+                RegionType::Shared | RegionType::SharedMemory => MapKind::Shared,
+                RegionType::Default if region.end().0 == MAX_USER_ADDRESS => MapKind::Stack,
+                RegionType::Default if region.start >= self.brk_start && region.start < self.brk => MapKind::Heap,
+                RegionType::Default => match region.backing {
+                    Backing::Image(_, _) | Backing::File(_, _, _) => MapKind::Image,
+                    Backing::Zero => MapKind::Anonymous,
+                },
+            };
+            MapEntry {
+                start: region.start.0,
+                end: region.end().0,
+                prot: Prot::from_bits_truncate(region.flags.bits() as u32), // the bits are the same.
+                kind,
+            }
+        }).collect()
+    }
+
+    /// Free the user address space of an exiting task, except the root page table which is freed
+    /// along with the manager. It must not be used afterwards.
+    pub fn release(&mut self) {
+        self.region_list = RegionList::empty();
+        self.page_table.release_sub_tables();
+    }
+
+    /// Map `size` bytes at `offset` of `file` to `start`, or to a free range from `MMAP_START_ADDRESS`
+    /// if `start` is not given. A `Default` region gets private copies of the pages it writes, and
+    /// a `Shared` region writes to the page cache of the file, which it can only do if `writable`.
+    pub fn map_file(&mut self, start: Option<VirtualAddress>, size: usize, flags: RegionFlags, region_type: RegionType,
+                    file: Arc<SharedFile>, offset: usize, writable: bool) -> Result<VirtualAddress, SysError> {
+        assert!(matches!(region_type, RegionType::Default | RegionType::Shared));
+        let region_start = match start {
+            Some(start) => start,
+            None => self.region_list
+                .find_unused_region_and_return_start_addr(size, Some(VirtualAddress::new(MMAP_START_ADDRESS)))
+                .ok_or(SysError::new(ENOMEM))?,
+        };
+
+        let mut memory_region = MemoryRegion::new(region_start, size, flags, region_type)?;
+        memory_region.backing = Backing::File(file, offset, writable);
+        memory_region.mapped_by(&mut self.page_table)?;
+        self.region_list.insert(Box::new(memory_region));
+
+        Ok(region_start)
+    }
+
+    /// Return the file and the index of the page in it, if `va` is in a file mapping and the page
+    /// has to be read in from fs server before it is touched.
+    pub fn missing_file_page(&self, va: VirtualAddress) -> Option<(Arc<SharedFile>, usize)> {
+        let region = self.region_list.iter().find(|region| region.contain(va))?;
+        let index = region.index_of(va.floor());
+        match &region.backing {
+            Backing::File(file, offset, _) if region.frames[index].is_none() => {
+                let page = offset / FRAME_SIZE + index;
+                file.page(page).is_none().then(|| (file.clone(), page))
+            }
+            _ => None,
+        }
+    }
+
+    /// Map the page at `index` of `file` read-only in the shared mappings of the file, once it has
+    /// become clean. Return whether any page has been remapped, the TLB of this hart is flushed.
+    pub fn write_protect_file_page(&mut self, file: &Arc<SharedFile>, index: usize) -> bool {
+        let mut protected = false;
         for region in self.region_list.iter() {
-            region.sync();
+            let first = match (region.region_type, &region.backing) {
+                (RegionType::Shared, Backing::File(region_file, offset, _)) if Arc::ptr_eq(region_file, file) =>
+                    offset / FRAME_SIZE,
+                _ => continue,
+            };
+            if index < first || index - first >= region.frames.len() {
+                continue;
+            }
+            if let Some(frame) = &region.frames[index - first] {
+                let vpn = VirtualPageNum(VirtualPageNum::from(region.start).0 + index - first);
+                self.page_table.remap(frame.0, vpn, region.pte_flags(index - first, frame));
+                flush_tlb_entry(vpn);
+                protected = true;
+            }
         }
+        protected
+    }
+
+    /// The files mapped shared in `[start, start + size)`.
+    pub fn shared_files(&self, start: VirtualAddress, size: usize) -> Vec<Arc<SharedFile>> {
+        let end = start.add(size);
+        let mut files: Vec<Arc<SharedFile>> = Vec::new();
+        for region in self.region_list.iter().filter(|region| region.start < end && region.end() > start) {
+            if let (RegionType::Shared, Backing::File(file, _, _)) = (region.region_type, &region.backing) {
+                if !files.iter().any(|other| Arc::ptr_eq(other, file)) {
+                    files.push(file.clone());
+                }
+            }
+        }
+        files
     }
 
     fn unmap_area(&mut self, start: VirtualAddress, size: usize) {
@@ -154,16 +513,20 @@ impl MemoryManager {
                 continue;
             }
 
-            unsafe {
-                asm!(
-                "sfence.vma {}, x0",
-                in(reg) vpn.0 << 12,
-                );
-            }
+            flush_tlb_entry(vpn);
         }
     }
 }
 
+fn flush_tlb_entry(vpn: VirtualPageNum) {
+    unsafe {
+        asm!(
+        "sfence.vma {}, x0",
+        in(reg) vpn.0 << 12,
+        );
+    }
+}
+
 pub struct RegionList {
     region_head: Option<Box<MemoryRegion>>,
     length: usize,
@@ -290,6 +653,80 @@ impl RegionList {
         true
     }
 
+    /// Delete the parts of the regions in `[start, start + size)`, which may cover several regions
+    /// and the holes between them. Return whether anything has been deleted.
+    pub fn delete_range(&mut self, start: VirtualAddress, size: usize) -> bool {
+        let end = start.add(size);
+        let overlaps: Vec<(VirtualAddress, VirtualAddress)> = self.iter()
+            .filter(|region| region.start < end && region.end() > start)
+            .map(|region| (VirtualAddress::max(region.start, start), VirtualAddress::min(region.end(), end)))
+            .collect();
+        for (del_start, del_end) in overlaps.iter() {
+            assert!(self.delete(*del_start, del_end.0 - del_start.0));
+        }
+
+        !overlaps.is_empty()
+    }
+
+    /// Set the flags of `[start, start + size)` to `flags`, splitting the regions at both ends. Return
+    /// false without changing anything if the range is not wholly mapped.
+    pub fn protect(&mut self, start: VirtualAddress, size: usize, flags: RegionFlags) -> bool {
+        if !self.is_region_exists(start, size) {
+            return false;
+        }
+        let end = start.add(size);
+        self.split_at(start);
+        self.split_at(end);
+
+        let mut cur = self.find_first_region_containing(start);
+        while let Some(region) = cur {
+            if region.start >= end {
+                break;
+            }
+            region.flags = flags;
+            cur = region.next.as_mut();
+        }
+        self.shrink();
+        true
+    }
+
+    /// Grow the region containing `start` by `size` bytes at its end, the new pages are backed when
+    /// they are touched. The pages after the region must be free.
+    pub fn extend(&mut self, start: VirtualAddress, size: usize) {
+        let region = self.find_first_region_containing(start).unwrap();
+        assert!(region.next.as_ref().map_or(true, |next| region.end().add(size) <= next.start));
+        region.extend(size);
+        self.shrink();
+    }
+
+    /// Remove `[start, start + size)` from the list as a region of its own.
+    pub fn take(&mut self, start: VirtualAddress, size: usize) -> Option<Box<MemoryRegion>> {
+        if !self.is_region_exists(start, size) {
+            return None;
+        }
+        self.split_at(start);
+        self.split_at(start.add(size));
+
+        let mut region = if self.region_head.as_ref().unwrap().start == start {
+            let mut region = self.region_head.take().unwrap();
+            self.region_head = region.next.take();
+            region
+        } else {
+            let mut cur = self.region_head.as_mut().unwrap();
+            while cur.next.as_ref().unwrap().start != start {
+                cur = cur.next.as_mut().unwrap();
+            }
+            let mut region = cur.next.take().unwrap();
+            cur.next = region.next.take();
+            region
+        };
+        self.length -= 1;
+        assert_eq!(region.region_size, size);
+        region.next = None;
+
+        Some(region)
+    }
+
     pub fn iter(&self) -> RegionListIter {
         RegionListIter::new(self.region_head.as_ref())
     }
@@ -309,6 +746,22 @@ impl RegionList {
         Some(start)
     }
 
+    /// Split the region containing `va` into two at `va`, unless `va` is its start.
+    fn split_at(&mut self, va: VirtualAddress) {
+        let region = match self.find_first_region_containing(va) {
+            Some(region) if region.start != va => region,
+            _ => return,
+        };
+        let index = region.index_of(va.into());
+        let frames = region.frames.split_off(index);
+        let mut next_region = MemoryRegion::with_frames(va, frames, region.flags, region.region_type);
+        next_region.backing = region.backing.advance(va.0 - region.start.0);
+        next_region.next = region.next.take();
+        region.region_size = va.0 - region.start.0;
+        region.next = Some(Box::new(next_region));
+        self.length += 1;
+    }
+
     fn remove_empty_region(&mut self) {
         if self.region_head.is_none() { return; }
 
@@ -371,7 +824,7 @@ impl RegionList {
         let mut next = pre.next.as_ref().unwrap();
 
         loop {
-            if pre.flags == next.flags && pre.end() == next.start { // merge
+            if pre.flags == next.flags && pre.end() == next.start && pre.is_mergeable_with(next) { // merge
                 let mut _next = pre.next.take().unwrap();
                 pre.region_size += _next.region_size;
                 pre.frames.append(&mut _next.frames);
@@ -429,7 +882,9 @@ impl<'a> Iterator for RegionListIter<'a> {
 }
 
 pub struct MemoryRegion {
-    frames: Vec<FrameTracker>,
+    /// Frames are reference counted, so that a shared memory object can be mapped by several tasks.
+    /// A page which hasn't been touched has no frame.
+    frames: Vec<Option<Arc<FrameTracker>>>,
     start: VirtualAddress,
     region_size: usize,
     flags: RegionFlags,
@@ -439,13 +894,42 @@ pub struct MemoryRegion {
     /// `region_type` only equals RegionType::CONTINUOUS
     /// when the block device driver needs continuous physical memory for DMA.
     region_type: RegionType,
+    /// Where the pages of a `Default` region come from, they are backed by frames when they are
+    /// first touched.
+    backing: Backing,
 }
 
-#[derive(Copy, Clone)]
+/// The content of the pages of a region which haven't been touched.
+#[derive(Clone)]
+enum Backing {
+    Zero,
+    /// The region starts at `offset` of the image, and is zero filled beyond the image.
+    Image(Arc<Image>, usize),
+    /// The region starts at `offset` of the file, its pages are shared with the page cache. The last
+    /// field tells whether the file was opened for writing.
+    File(Arc<SharedFile>, usize, bool),
+}
+
+impl Backing {
+    /// The backing of the region after its first `size` bytes are cut off.
+    fn advance(&self, size: usize) -> Self {
+        match self {
+            Backing::Zero => Backing::Zero,
+            Backing::Image(image, offset) => Backing::Image(image.clone(), offset + size),
+            Backing::File(file, offset, writable) => Backing::File(file.clone(), offset + size, *writable),
+        }
+    }
+}
+
+#[derive(Copy, Clone, PartialEq)]
 pub enum RegionType {
     Default,
     Continuous,
-    Shared(usize, usize, usize), // fd, offset, len
+    /// A file mapped shared, the frames are those of the page cache, rather than copied when the
+    /// task is forked.
+    Shared,
+    /// Frames are shared with other tasks, rather than copied when the task is forked.
+    SharedMemory,
 }
 
 impl Debug for MemoryRegion {
@@ -461,13 +945,17 @@ impl MemoryRegion {
 
         let mut frames = Vec::new();
         match region_type {
-            RegionType::Default | RegionType::Shared(_, _, _) => {
+            RegionType::Default | RegionType::Shared => { // backed when the pages are touched.
+                frames = vec![None; region_size / FRAME_SIZE];
+            }
+            RegionType::SharedMemory => {
                 for _ in (0..region_size).step_by(FRAME_SIZE) {
-                    frames.push(alloc_frame()?);
+                    frames.push(Some(Arc::new(alloc_frame()?)));
                 }
             }
             RegionType::Continuous => {
-                frames = alloc_continuous_frames(region_size / FRAME_SIZE)?;
+                frames = alloc_continuous_frames(region_size / FRAME_SIZE)?
+                    .into_iter().map(|frame| Some(Arc::new(frame))).collect();
             }
         }
 
@@ -479,18 +967,53 @@ impl MemoryRegion {
                 flags,
                 next: None,
                 region_type,
+                backing: Backing::Zero,
             }
         )
     }
 
+    fn with_frames(start: VirtualAddress, frames: Vec<Option<Arc<FrameTracker>>>, flags: RegionFlags, region_type: RegionType) -> Self {
+        assert!(start.is_aligned());
+        Self {
+            region_size: frames.len() * FRAME_SIZE,
+            frames,
+            start,
+            flags,
+            next: None,
+            region_type,
+            backing: Backing::Zero,
+        }
+    }
+
+    /// Copy the region for a forked child: private frames are shared copy-on-write, continuous frames
+    /// are copied at once since a device may be using them.
+    pub fn clone_for_fork(&self) -> Result<Self, SysError> {
+        if self.region_type == RegionType::Continuous {
+            return self.clone_with_new_frames();
+        }
+        let mut region = MemoryRegion::with_frames(self.start, self.frames.clone(), self.flags, self.region_type);
+        region.backing = self.backing.clone();
+        Ok(region)
+    }
+
+    /// Copy the region with new frames, except that the frames of shared memory are shared.
     pub fn clone_with_new_frames(&self) -> Result<Self, SysError> {
-        let mut frames = Vec::new();
-        for i in 0..self.region_size / FRAME_SIZE {
-            let frame = alloc_frame()?;
-            let frame_data: &[u8; FRAME_SIZE] = PhysicalAddress::from(self.frames[i].0).as_mut();
-            frame.fill_with(frame_data);
+        if self.region_type == RegionType::SharedMemory {
+            return Ok(MemoryRegion::with_frames(self.start, self.frames.clone(), self.flags, self.region_type));
+        }
 
-            frames.push(frame);
+        let mut frames = Vec::new();
+        for old_frame in self.frames.iter() {
+            let new_frame = match old_frame {
+                Some(old_frame) => {
+                    let frame = alloc_frame()?;
+                    let frame_data: &[u8; FRAME_SIZE] = PhysicalAddress::from(old_frame.0).as_mut();
+                    frame.fill_with(frame_data);
+                    Some(Arc::new(frame))
+                }
+                None => None,
+            };
+            frames.push(new_frame);
         }
 
         Ok(
@@ -501,15 +1024,23 @@ impl MemoryRegion {
                 flags: self.flags,
                 next: None,
                 region_type: self.region_type,
+                backing: self.backing.clone(),
             }
         )
     }
 
+    /// Fill the region with `data`, and zeros after it. A `Default` region keeps `data` as its
+    /// backing, and only its pages touched get filled.
     pub fn fill(&mut self, data: &[u8]) -> Result<(), SysError> {
+        if self.region_type == RegionType::Default {
+            self.backing = if data.is_empty() { Backing::Zero } else { Backing::Image(Image::new(data)?, 0) };
+            return Ok(());
+        }
+
         let mut start = 0;
         let len = data.len();
-        for frame in self.frames.as_mut_slice() {
-            frame.fill_with(&data[start..len.min(start + FRAME_SIZE)]);
+        for frame in self.frames.iter() {
+            frame.as_ref().unwrap().fill_with(&data[start..len.min(start + FRAME_SIZE)]);
 
             if start + FRAME_SIZE >= len {
                 start = len;
@@ -521,24 +1052,133 @@ impl MemoryRegion {
         Ok(())
     }
 
+    /// Map the pages backed by frames, the others are mapped when they are touched. Nothing is
+    /// mapped if the region allows no access.
     pub fn mapped_by(&self, page_table: &mut PageTable) -> Result<(), SysError> {
-        let mut flags = PTEFlags::V | PTEFlags::U;
-        if self.flags.contains(RegionFlags::R) { flags |= PTEFlags::R };
-        if self.flags.contains(RegionFlags::W) { flags |= PTEFlags::W };
-        if self.flags.contains(RegionFlags::X) { flags |= PTEFlags::X };
+        if self.flags.is_empty() {
+            return Ok(());
+        }
+        let start_vpn: VirtualPageNum = self.start.into();
+        for (index, (vpn, frame)) in (start_vpn..self.end().into()).zip(self.frames.iter()).enumerate() {
+            if let Some(frame) = frame {
+                page_table.map(frame.0, vpn, self.pte_flags(index, frame))?;
+            }
+        }
 
+        Ok(())
+    }
+
+    /// Map the pages backed by frames again after the flags of the region have changed.
+    pub fn protect(&self, page_table: &mut PageTable) -> Result<(), SysError> {
         let start_vpn: VirtualPageNum = self.start.into();
-        let end_vpn: VirtualPageNum = self.end().into();
-        let mut frame_iter = self.frames.iter();
+        for vpn in start_vpn..self.end().into() {
+            page_table.unmap(vpn);
+        }
+        self.mapped_by(page_table)
+    }
 
-        for vpn in start_vpn..end_vpn {
-            let frame = frame_iter.next().unwrap();
-            page_table.map(frame.0, vpn, flags)?;
+    /// Grow the region by `size` bytes at its end, the new pages are backed when they are touched.
+    pub fn extend(&mut self, size: usize) {
+        assert_eq!(size & (FRAME_SIZE - 1), 0);
+        self.frames.resize(self.frames.len() + size / FRAME_SIZE, None);
+        self.region_size += size;
+    }
+
+    /// Map the frames shared with a forked child read-only.
+    pub fn write_protect(&self, page_table: &mut PageTable) {
+        if !self.is_copy_on_write() || !self.flags.contains(RegionFlags::W) {
+            return;
         }
+        let start_vpn: VirtualPageNum = self.start.into();
+        for (index, (vpn, frame)) in (start_vpn..self.end().into()).zip(self.frames.iter()).enumerate() {
+            if let Some(frame) = frame {
+                page_table.remap(frame.0, vpn, self.pte_flags(index, frame));
+            }
+        }
+    }
+
+    /// Make the page at `vpn` accessible, and writable as well if `for_write` is set. Return whether
+    /// a shared frame has been copied.
+    pub fn fault_in(&mut self, vpn: VirtualPageNum, page_table: &mut PageTable, for_write: bool) -> Result<bool, SysError> {
+        self.populate(vpn, page_table, for_write)?;
+        if for_write {
+            if let (RegionType::Shared, Backing::File(file, offset, _)) = (self.region_type, &self.backing) {
+                file.mark_dirty(offset / FRAME_SIZE + self.index_of(vpn));
+            }
+            self.unshare(vpn, page_table)
+        } else {
+            Ok(false)
+        }
+    }
+
+    /// Back the page at `vpn` with a frame from `backing` and map it if it hasn't been touched. A
+    /// page of the image is mapped itself unless it is going to be written, and a page of a file
+    /// must have been read in.
+    pub fn populate(&mut self, vpn: VirtualPageNum, page_table: &mut PageTable, for_write: bool) -> Result<(), SysError> {
+        let index = self.index_of(vpn);
+        if self.frames[index].is_some() {
+            return Ok(());
+        }
+
+        let frame = match &self.backing {
+            Backing::Zero => {
+                let frame = alloc_frame()?;
+                frame.fill_with(&[]);
+                Arc::new(frame)
+            }
+            Backing::Image(image, offset) => image.page(offset + index * FRAME_SIZE, for_write)?,
+            Backing::File(file, offset, _) => file.page(offset / FRAME_SIZE + index).ok_or(SysError::new(EFAULT))?,
+        };
+        page_table.map(frame.0, vpn, self.pte_flags(index, &frame))?;
+        self.frames[index] = Some(frame);
 
         Ok(())
     }
 
+    /// Give the region its own copy of the frame at `vpn` if the frame is shared copy-on-write, and
+    /// map the page with the permissions of the region. Return whether the frame has been copied.
+    pub fn unshare(&mut self, vpn: VirtualPageNum, page_table: &mut PageTable) -> Result<bool, SysError> {
+        let index = self.index_of(vpn);
+        let frame = self.frames[index].as_ref().expect("unshare a page which hasn't been touched");
+        let copied = self.is_copy_on_write() && Arc::strong_count(frame) > 1;
+        if copied {
+            let new_frame = alloc_frame()?;
+            let frame_data: &[u8; FRAME_SIZE] = PhysicalAddress::from(frame.0).as_mut();
+            new_frame.fill_with(frame_data);
+            self.frames[index] = Some(Arc::new(new_frame));
+        }
+        let frame = self.frames[index].as_ref().unwrap();
+        page_table.remap(frame.0, vpn, self.pte_flags(index, frame));
+
+        Ok(copied)
+    }
+
+    fn index_of(&self, vpn: VirtualPageNum) -> usize {
+        vpn.0 - VirtualPageNum::from(self.start).0
+    }
+
+    /// A frame shared copy-on-write is never writable, the task which writes to it first gets a copy.
+    /// Neither is a clean page of a shared file, so that it is marked dirty when it is written.
+    fn pte_flags(&self, index: usize, frame: &Arc<FrameTracker>) -> PTEFlags {
+        let mut flags = PTEFlags::V | PTEFlags::U;
+        if self.flags.contains(RegionFlags::R) { flags |= PTEFlags::R };
+        let clean = match (self.region_type, &self.backing) {
+            (RegionType::Shared, Backing::File(file, offset, _)) => !file.is_dirty(offset / FRAME_SIZE + index),
+            _ => false,
+        };
+        if self.flags.contains(RegionFlags::W) && !(self.is_copy_on_write() && Arc::strong_count(frame) > 1) && !clean {
+            flags |= PTEFlags::W
+        };
+        if self.flags.contains(RegionFlags::X) { flags |= PTEFlags::X };
+        flags
+    }
+
+    /// Frames of shared memory and shared files are shared on purpose, and continuous ones are never
+    /// shared.
+    fn is_copy_on_write(&self) -> bool {
+        self.region_type == RegionType::Default
+    }
+
     pub fn delete(&mut self, del_region_start: VirtualAddress, size: usize) -> bool {
         let del_region_end = del_region_start.add(size);
         assert!(del_region_start.is_aligned() && del_region_end.is_aligned());
@@ -555,12 +1195,12 @@ impl MemoryRegion {
             self.region_size -= size;
         } else if del_region_start > self.start { // delete in the mid
             let new_region_size = self.end().0 - del_region_end.0;
-            let remained_frames: Vec<FrameTracker> = self.frames.drain(end_index..).collect();
+            let remained_frames: Vec<Option<Arc<FrameTracker>>> = self.frames.drain(end_index..).collect();
             deleted_frames = self.frames.drain(start_index..);
 
             let mut next_region =
-                MemoryRegion::new(del_region_end, new_region_size, self.flags, RegionType::Default).unwrap();
-            next_region.frames = remained_frames;
+                MemoryRegion::with_frames(del_region_end, remained_frames, self.flags, self.region_type);
+            next_region.backing = self.backing.advance(del_region_end.0 - self.start.0);
             next_region.next = self.next.take();
 
             self.next = Some(Box::new(next_region));
@@ -570,6 +1210,7 @@ impl MemoryRegion {
             assert_eq!(del_region_start, self.start);
             deleted_frames = self.frames.drain(..end_index);
 
+            self.backing = self.backing.advance(size);
             self.start = del_region_end;
             self.region_size -= size;
         }
@@ -581,28 +1222,15 @@ impl MemoryRegion {
         self.start.add(self.region_size)
     }
 
-    pub fn contain(&self, va: VirtualAddress) -> bool {
-        va >= self.start && va < self.end()
+    /// Each shared memory region stands for its own object, so it's never merged with others, and
+    /// neither is a region filled from an image.
+    fn is_mergeable_with(&self, other: &MemoryRegion) -> bool {
+        self.region_type == other.region_type && self.region_type != RegionType::SharedMemory
+            && matches!((&self.backing, &other.backing), (Backing::Zero, Backing::Zero))
     }
 
-    pub fn sync(&self) {
-        match self.region_type {
-            RegionType::Shared(fd, offset, len) => {
-                let mut data: Vec<u8> = vec![0; len];
-                let mut current_start = 0;
-                let mut total = len;
-
-                for i in 0..((len - 1)/ FRAME_SIZE + 1) {
-                    let size = usize::min(FRAME_SIZE, total);
-                    self.frames[i].read_into(&mut data.as_mut_slice()[current_start..size]);
-                    current_start += size;
-                    total -= size;
-                }
-
-                do_write(fd, data.as_ptr() as usize, len).unwrap();
-            },
-            _ => {}
-        }
+    pub fn contain(&self, va: VirtualAddress) -> bool {
+        va >= self.start && va < self.end()
     }
 }
 
@@ -638,7 +1266,7 @@ mod test {
                               RegionFlags::R, RegionType::Default)
                 .unwrap();
         memory_region.fill(&[]).unwrap();
-        memory_region.mapped_by(&mut page_table).unwrap();
+        populate(&mut memory_region, &mut page_table);
         memory_region.delete(start, FRAME_SIZE);
 
         assert_eq!(memory_region.start.0, FRAME_SIZE);
@@ -648,11 +1276,83 @@ mod test {
 
         let mut vpn = VirtualPageNum::new(1);
         for frame in memory_region.frames.iter() {
-            assert_eq!(frame.0, page_table.translate(vpn).unwrap());
+            assert_eq!(frame.as_ref().unwrap().0, page_table.translate(vpn).unwrap());
             vpn = vpn.add(1);
         }
     }
 
+    #[test]
+    pub fn test_copy_on_write_on_memory_region() {
+        let _ = init_frame_allocator();
+
+        let mut page_table = PageTable::new().unwrap();
+        let mut child_page_table = PageTable::new().unwrap();
+        let start = VirtualAddress::new(0);
+        let mut memory_region =
+            MemoryRegion::new(start, FRAME_SIZE * 2,
+                              RegionFlags::R | RegionFlags::W, RegionType::Default)
+                .unwrap();
+        memory_region.fill(&[1, 2, 3]).unwrap();
+        populate(&mut memory_region, &mut page_table);
+        assert!(page_table.is_writable(VirtualPageNum::new(0)));
+
+        // both sides are read-only after fork.
+        let mut child_region = memory_region.clone_for_fork().unwrap();
+        child_region.mapped_by(&mut child_page_table).unwrap();
+        memory_region.write_protect(&mut page_table);
+        for vpn in 0..2 {
+            assert!(!page_table.is_writable(VirtualPageNum::new(vpn)));
+            assert!(!child_page_table.is_writable(VirtualPageNum::new(vpn)));
+            assert_eq!(page_table.translate(VirtualPageNum::new(vpn)),
+                       child_page_table.translate(VirtualPageNum::new(vpn)));
+        }
+
+        // the first writer gets a copy, the last owner takes the frame back.
+        assert!(child_region.unshare(VirtualPageNum::new(0), &mut child_page_table).unwrap());
+        assert!(child_page_table.is_writable(VirtualPageNum::new(0)));
+        assert_ne!(page_table.translate(VirtualPageNum::new(0)), child_page_table.translate(VirtualPageNum::new(0)));
+        let data: &[u8; FRAME_SIZE] = PhysicalAddress::from(child_region.frames[0].as_ref().unwrap().0).as_mut();
+        assert_eq!(data[..3], [1, 2, 3]);
+        assert!(!memory_region.unshare(VirtualPageNum::new(0), &mut page_table).unwrap());
+        assert!(page_table.is_writable(VirtualPageNum::new(0)));
+        assert!(!page_table.is_writable(VirtualPageNum::new(1)));
+    }
+
+    #[test]
+    pub fn test_populate_on_memory_region() {
+        let _ = init_frame_allocator();
+
+        let mut page_table = PageTable::new().unwrap();
+        let start = VirtualAddress::new(0);
+        let mut memory_region =
+            MemoryRegion::new(start, FRAME_SIZE * 3,
+                              RegionFlags::R | RegionFlags::W, RegionType::Default)
+                .unwrap();
+        let data = [7; FRAME_SIZE + FRAME_SIZE / 2];
+        memory_region.fill(&data).unwrap();
+        memory_region.mapped_by(&mut page_table).unwrap();
+        for vpn in 0..3 {
+            assert!(page_table.translate(VirtualPageNum::new(vpn)).is_none());
+        }
+
+        // a whole page of the image is mapped read-only, until it is written.
+        assert!(!memory_region.fault_in(VirtualPageNum::new(0), &mut page_table, false).unwrap());
+        assert!(!page_table.is_writable(VirtualPageNum::new(0)));
+        assert!(memory_region.fault_in(VirtualPageNum::new(0), &mut page_table, true).unwrap());
+        assert!(page_table.is_writable(VirtualPageNum::new(0)));
+
+        // the rest of the image is followed by zeros.
+        memory_region.fault_in(VirtualPageNum::new(1), &mut page_table, false).unwrap();
+        memory_region.fault_in(VirtualPageNum::new(2), &mut page_table, false).unwrap();
+        for &(vpn, expected) in [(0, 7), (1, 7), (2, 0)].iter() {
+            let ppn = page_table.translate(VirtualPageNum::new(vpn)).unwrap();
+            let page: &[u8; FRAME_SIZE] = PhysicalAddress::from(ppn).as_ref();
+            assert_eq!(page[0], expected);
+        }
+        let page: &[u8; FRAME_SIZE] = PhysicalAddress::from(page_table.translate(VirtualPageNum::new(1)).unwrap()).as_ref();
+        assert_eq!(page[FRAME_SIZE / 2], 0);
+    }
+
     #[test]
     pub fn test_delete_middle_on_memory_region() {
         let _ = init_frame_allocator();
@@ -665,7 +1365,7 @@ mod test {
                               RegionFlags::R, RegionType::Default)
                 .unwrap();
         memory_region.fill(&[]).unwrap();
-        memory_region.mapped_by(&mut page_table).unwrap();
+        populate(&mut memory_region, &mut page_table);
         memory_region.delete(start.add(FRAME_SIZE * 2), FRAME_SIZE);
 
         assert_eq!(memory_region.start.0, 0);
@@ -675,7 +1375,7 @@ mod test {
 
         let mut vpn = VirtualPageNum::new(0);
         for frame in memory_region.frames.iter() {
-            assert_eq!(frame.0, page_table.translate(vpn).unwrap());
+            assert_eq!(frame.as_ref().unwrap().0, page_table.translate(vpn).unwrap());
             vpn = vpn.add(1);
         }
 
@@ -687,7 +1387,7 @@ mod test {
 
         let mut vpn = VirtualPageNum::new(3);
         for frame in next_region.frames.iter() {
-            assert_eq!(frame.0, page_table.translate(vpn).unwrap());
+            assert_eq!(frame.as_ref().unwrap().0, page_table.translate(vpn).unwrap());
             vpn = vpn.add(1);
         }
     }
@@ -704,7 +1404,7 @@ mod test {
                               RegionFlags::R, RegionType::Default)
                 .unwrap();
         memory_region.fill(&[]).unwrap();
-        memory_region.mapped_by(&mut page_table).unwrap();
+        populate(&mut memory_region, &mut page_table);
         memory_region.delete(start.add(FRAME_SIZE * 4), FRAME_SIZE);
 
         assert_eq!(memory_region.start.0, 0);
@@ -714,7 +1414,7 @@ mod test {
 
         let mut vpn = VirtualPageNum::new(0);
         for frame in memory_region.frames.iter() {
-            assert_eq!(frame.0, page_table.translate(vpn).unwrap());
+            assert_eq!(frame.as_ref().unwrap().0, page_table.translate(vpn).unwrap());
             vpn = vpn.add(1);
         }
     }
@@ -875,6 +1575,128 @@ mod test {
         assert!(region_iter.next().is_none());
     }
 
+    #[test]
+    pub fn test_shared_memory_on_region_list() {
+        let _ = init_frame_allocator();
+        let mut page_table = PageTable::new().unwrap();
+        let start = VirtualAddress::new(0);
+        let region1 = MemoryRegion::new(
+            start, FRAME_SIZE * 2,
+            RegionFlags::R | RegionFlags::W, RegionType::SharedMemory,
+        ).unwrap();
+        let region2 = MemoryRegion::new(
+            start.add(FRAME_SIZE * 2), FRAME_SIZE,
+            RegionFlags::R | RegionFlags::W, RegionType::SharedMemory,
+        ).unwrap();
+        let mut region3 = MemoryRegion::new(
+            start.add(FRAME_SIZE * 3), FRAME_SIZE,
+            RegionFlags::R | RegionFlags::W, RegionType::Default,
+        ).unwrap();
+        populate(&mut region3, &mut page_table);
+
+        // frames of shared memory are shared by the clone, others are copied.
+        let cloned_region1 = region1.clone_with_new_frames().unwrap();
+        for (frame, cloned_frame) in region1.frames.iter().zip(cloned_region1.frames.iter()) {
+            assert_eq!(frame.as_ref().unwrap().0, cloned_frame.as_ref().unwrap().0);
+        }
+        let cloned_region3 = region3.clone_with_new_frames().unwrap();
+        assert_ne!(region3.frames[0].as_ref().unwrap().0, cloned_region3.frames[0].as_ref().unwrap().0);
+
+        // shared memory regions are never merged.
+        let mut region_list = RegionList::empty();
+        region_list.insert(Box::new(region1));
+        region_list.insert(Box::new(region2));
+        region_list.insert(Box::new(region3));
+        assert_eq!(region_list.length, 3);
+        assert_eq!(region_list.length(), 3);
+    }
+
+    #[test]
+    pub fn test_delete_range_on_region_list() {
+        let _ = init_frame_allocator();
+        let mut page_table = PageTable::new().unwrap();
+        let mut region_list = create_a_testing_region_list(&mut page_table).unwrap();
+
+        // 1. make a hole at 4th frame
+        assert!(region_list.delete(VirtualAddress::new(FRAME_SIZE * 3), FRAME_SIZE));
+        assert_eq!(region_list.length, 5);
+
+        // 2. delete 2nd-8th frames across regions and the hole
+        assert!(region_list.delete_range(VirtualAddress::new(FRAME_SIZE), FRAME_SIZE * 7));
+        assert_eq!(region_list.length, 2);
+        assert_eq!(region_list.length(), 2);
+        assert!(region_list.is_region_exists(VirtualAddress::new(0), FRAME_SIZE));
+        assert!(region_list.is_region_exists(VirtualAddress::new(FRAME_SIZE * 8), FRAME_SIZE));
+
+        // 3. nothing is left in the range
+        assert!(!region_list.delete_range(VirtualAddress::new(FRAME_SIZE), FRAME_SIZE * 7));
+        assert_eq!(region_list.length(), 2);
+    }
+
+    #[test]
+    pub fn test_protect_on_region_list() {
+        let _ = init_frame_allocator();
+        let mut page_table = PageTable::new().unwrap();
+        let mut region_list = create_a_testing_region_list(&mut page_table).unwrap();
+
+        // 1. 3rd-4th frames of the second region become read-only, it is split in two.
+        assert!(region_list.protect(VirtualAddress::new(FRAME_SIZE * 2), FRAME_SIZE * 2, RegionFlags::R));
+        assert_eq!(region_list.length, 5);
+        assert_eq!(region_list.length(), 5);
+        let region = region_list.iter().nth(1).unwrap();
+        assert_eq!(region.start, VirtualAddress::new(FRAME_SIZE * 2));
+        assert_eq!(region.region_size, FRAME_SIZE * 2);
+        assert_eq!(region.frames.len(), 2);
+        assert_eq!(region.flags, RegionFlags::R);
+
+        // 2. they are merged back with the same flags.
+        assert!(region_list.protect(VirtualAddress::new(FRAME_SIZE * 2), FRAME_SIZE * 2, RegionFlags::W));
+        assert_eq!(region_list.length, 4);
+        assert_eq!(region_list.length(), 4);
+
+        // 3. all regions have the same flags.
+        assert!(region_list.protect(VirtualAddress::new(0), FRAME_SIZE * 9, RegionFlags::R));
+        assert_eq!(region_list.length, 1);
+        assert_eq!(region_list.length(), 1);
+
+        // 4. a range with a hole is not changed.
+        assert!(region_list.delete(VirtualAddress::new(FRAME_SIZE * 4), FRAME_SIZE));
+        assert!(!region_list.protect(VirtualAddress::new(0), FRAME_SIZE * 9, RegionFlags::X));
+        assert!(region_list.iter().all(|region| region.flags == RegionFlags::R));
+    }
+
+    #[test]
+    pub fn test_take_and_extend_on_region_list() {
+        let _ = init_frame_allocator();
+        let mut page_table = PageTable::new().unwrap();
+        let mut region_list = create_a_testing_region_list(&mut page_table).unwrap();
+
+        // 1. take 4th frame out of the second region
+        let region = region_list.take(VirtualAddress::new(FRAME_SIZE * 3), FRAME_SIZE).unwrap();
+        assert_eq!(region.start, VirtualAddress::new(FRAME_SIZE * 3));
+        assert_eq!(region.region_size, FRAME_SIZE);
+        assert!(region.next.is_none());
+        assert_eq!(region_list.length, 5);
+        assert_eq!(region_list.length(), 5);
+        assert!(region_list.take(VirtualAddress::new(FRAME_SIZE * 3), FRAME_SIZE).is_none());
+
+        // 2. extend 3rd frame over the hole, it is merged with 5th frame again.
+        region_list.extend(VirtualAddress::new(FRAME_SIZE * 2), FRAME_SIZE);
+        assert_eq!(region_list.length, 4);
+        assert_eq!(region_list.length(), 4);
+        let region = region_list.iter().nth(1).unwrap();
+        assert_eq!(region.region_size, FRAME_SIZE * 3);
+        assert_eq!(region.frames.len(), 3);
+    }
+
+    /// Back and map every page of `region` as if the task has written to them.
+    fn populate(region: &mut MemoryRegion, page_table: &mut PageTable) {
+        let start_vpn: VirtualPageNum = region.start.into();
+        for vpn in start_vpn..region.end().into() {
+            region.populate(vpn, page_table, true).unwrap();
+        }
+    }
+
     // TODO: test region_list's sortable feature
     fn init_frame_allocator() -> Box<[u8; REGION_SIZE]> {
         let frame_region: Box<[u8; REGION_SIZE]> = Box::new([0; REGION_SIZE]);
@@ -5,4 +5,6 @@ pub mod page_table;
 pub mod address;
 pub mod heap;
 pub mod memory_manager;
+pub mod image;
+pub mod page_cache;
 
@@ -0,0 +1,187 @@
+use alloc::collections::{BTreeMap, BTreeSet};
+use alloc::sync::{Arc, Weak};
+use alloc::vec::Vec;
+use spin::Mutex;
+use crate::config::FRAME_SIZE;
+use crate::mm::alloc_frame;
+use crate::mm::address::VirtualAddress;
+use crate::mm::frame_allocator::FrameTracker;
+use crate::mm::memory_manager::MemoryManager;
+use crate::processor::{get_cur_task_in_this_hart, flush_tlb_of_other_harts};
+use crate::task::get_all_tasks;
+use crate::syscall::file::{map_file, read_page, write_page, unmap_file};
+use share::syscall::error::{SysError, EACCES};
+
+lazy_static! {
+    /// Page caches of the files mapped shared, by the key of the inode given by fs server.
+    static ref SHARED_FILES: Mutex<BTreeMap<usize, Weak<SharedFile>>> = Mutex::new(BTreeMap::new());
+    /// Keys of the files which are not mapped any more, fs server is told to unpin them later since
+    /// locks may be held when the last mapping is gone.
+    static ref RELEASED_FILES: Mutex<Vec<usize>> = Mutex::new(Vec::new());
+}
+
+/// The pages of a file mapped with `MMAPFlags::SHARED`, which are shared by every task mapping the
+/// file. Pages are read in from fs server when they are first touched, and the dirty ones are
+/// written back on `msync`, `munmap`, exec and exit.
+///
+/// Talking to fs server blocks current task, so it is never done with a lock held. The kernel
+/// itself only touches pages which have been read in.
+pub struct SharedFile {
+    /// The file is pinned by fs server under `key` until the page cache is gone.
+    key: usize,
+    inner: Mutex<SharedFileInner>,
+}
+
+struct SharedFileInner {
+    /// Frames of the pages which have been read in, by the index of the page in the file.
+    pages: BTreeMap<usize, Arc<FrameTracker>>,
+    /// Pages which have been written since they were read in or written back last time.
+    dirty: BTreeSet<usize>,
+}
+
+impl SharedFile {
+    /// Return the page cache of the file opened as `fd` by current task, and whether the file is
+    /// opened for writing.
+    pub fn open(fd: usize) -> Result<(Arc<Self>, bool), SysError> {
+        release_files();
+        let (key, writable) = match map_file(fd, true) {
+            Ok(key) => (key, true),
+            Err(error) if error.errno == EACCES => (map_file(fd, false)?, false),
+            Err(error) => return Err(error),
+        };
+
+        let mut shared_files = SHARED_FILES.lock();
+        shared_files.retain(|_, file| file.strong_count() > 0);
+        if let Some(file) = shared_files.get(&key).and_then(|file| file.upgrade()) {
+            drop(shared_files);
+            // pinned by the page cache already.
+            unmap_file(key)?;
+            return Ok((file, writable));
+        }
+        let file = Arc::new(Self {
+            key,
+            inner: Mutex::new(SharedFileInner {
+                pages: BTreeMap::new(),
+                dirty: BTreeSet::new(),
+            }),
+        });
+        shared_files.insert(key, Arc::downgrade(&file));
+
+        Ok((file, writable))
+    }
+
+    pub fn page(&self, index: usize) -> Option<Arc<FrameTracker>> {
+        self.inner.lock().pages.get(&index).cloned()
+    }
+
+    /// Read the page at `index` in from fs server unless it is in the cache, zero filled beyond the
+    /// end of the file.
+    pub fn read_in(&self, index: usize) -> Result<(), SysError> {
+        if self.page(index).is_some() {
+            return Ok(());
+        }
+        let frame = alloc_frame()?;
+        frame.fill_with(&[]);
+        read_page(self.key, index * FRAME_SIZE, &frame)?;
+        // another task may have read it in meanwhile, which might have been written already.
+        self.inner.lock().pages.entry(index).or_insert_with(|| Arc::new(frame));
+
+        Ok(())
+    }
+
+    pub fn mark_dirty(&self, index: usize) {
+        self.inner.lock().dirty.insert(index);
+    }
+
+    pub fn is_dirty(&self, index: usize) -> bool {
+        self.inner.lock().dirty.contains(&index)
+    }
+
+    /// Write the dirty pages back to the file. They are clean afterwards, and are mapped read-only
+    /// before they are written, so that a write meanwhile marks them dirty again.
+    pub fn sync(self: &Arc<Self>) -> Result<(), SysError> {
+        let dirty_pages: Vec<(usize, Arc<FrameTracker>)> = {
+            let mut inner = self.inner.lock();
+            let dirty = core::mem::take(&mut inner.dirty);
+            dirty.into_iter()
+                .filter_map(|index| inner.pages.get(&index).map(|frame| (index, frame.clone())))
+                .collect()
+        };
+        self.write_protect(&dirty_pages);
+        for (done, (index, frame)) in dirty_pages.iter().enumerate() {
+            if let Err(error) = write_page(self.key, index * FRAME_SIZE, frame) {
+                self.inner.lock().dirty.extend(dirty_pages[done..].iter().map(|(index, _)| *index));
+                return Err(error);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Map `pages` read-only in every address space mapping the file shared.
+    fn write_protect(self: &Arc<Self>, pages: &[(usize, Arc<FrameTracker>)]) {
+        if pages.is_empty() {
+            return;
+        }
+        let mut mem_managers: Vec<Arc<Mutex<MemoryManager>>> = Vec::new();
+        for task in get_all_tasks() {
+            let mem_manager = task.acquire_inner_lock().mem_manager.clone();
+            if !mem_managers.iter().any(|other| Arc::ptr_eq(other, &mem_manager)) {
+                mem_managers.push(mem_manager);
+            }
+        }
+        for mem_manager in mem_managers {
+            let mut mem_manager = mem_manager.lock();
+            let protected = pages.iter()
+                .fold(false, |protected, (index, _)| mem_manager.write_protect_file_page(self, *index) || protected);
+            let satp = mem_manager.page_table.satp();
+            drop(mem_manager);
+            if protected {
+                flush_tlb_of_other_harts(satp, true);
+            }
+        }
+    }
+}
+
+impl Drop for SharedFile {
+    fn drop(&mut self) {
+        RELEASED_FILES.lock().push(self.key);
+    }
+}
+
+/// Tell fs server to unpin the files which are not mapped any more.
+pub fn release_files() {
+    let keys: Vec<usize> = RELEASED_FILES.lock().drain(..).collect();
+    for key in keys {
+        let _ = unmap_file(key);
+    }
+}
+
+/// Write back the shared file mappings of current task in `[start, start + size)`.
+pub fn sync_shared_files(start: VirtualAddress, size: usize) -> Result<(), SysError> {
+    let mem_manager = get_cur_task_in_this_hart().acquire_inner_lock().mem_manager.clone();
+    let files = mem_manager.lock().shared_files(start, size);
+    for file in files.iter() {
+        file.sync()?;
+    }
+    drop(files);
+    release_files();
+
+    Ok(())
+}
+
+/// Read in the pages of shared file mappings in `[start, start + len)` of current task, so that
+/// they can be accessed by the kernel and servers later.
+pub fn read_in_user_range(start: usize, len: usize) {
+    let end = match start.checked_add(len) {
+        Some(end) if len > 0 => end,
+        _ => return,
+    };
+    let mem_manager = get_cur_task_in_this_hart().acquire_inner_lock().mem_manager.clone();
+    for vpn in VirtualAddress::new(start).floor()..VirtualAddress::new(end).ceil() {
+        let missing = mem_manager.lock().missing_file_page(vpn.into());
+        if let Some((file, index)) = missing {
+            let _ = file.read_in(index);
+        }
+    }
+}
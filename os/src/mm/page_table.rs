@@ -55,9 +55,25 @@ impl<T: Allocator> PageTable<T> {
         Ok(())
     }
 
-    pub fn unmap(&mut self, virtual_page_num: VirtualPageNum) {
+    /// Replace the mapping of `virtual_page_num`, which must be mapped already.
+    pub fn remap(&mut self,
+                 physical_page_num: PhysicalPageNum, virtual_page_num: VirtualPageNum,
+                 flags: PTEFlags) {
         let pte = self.find_pte(virtual_page_num).unwrap();
-        *pte = PageTableEntry::empty();
+        *pte = PageTableEntry::new(flags, physical_page_num);
+    }
+
+    /// Whether a store to `virtual_page_num` is allowed by its page table entry.
+    pub fn is_writable(&self, virtual_page_num: VirtualPageNum) -> bool {
+        self.find_pte(virtual_page_num)
+            .map_or(false, |pte| pte.flags().contains(PTEFlags::W))
+    }
+
+    /// Pages which haven't been mapped are skipped, they may never have been touched.
+    pub fn unmap(&mut self, virtual_page_num: VirtualPageNum) {
+        if let Some(pte) = self.find_pte(virtual_page_num) {
+            *pte = PageTableEntry::empty();
+        }
     }
 
     pub fn translate(&self, virtual_page_num: VirtualPageNum) -> Option<PhysicalPageNum> {
@@ -136,6 +152,12 @@ impl<T: Allocator> PageTable<T> {
     pub fn satp(&self) -> usize {
         self.root_table_frame.0.0
     }
+
+    /// Free the frames of sub tables. Entries of the root table are left dangling, so the table
+    /// must never be used again.
+    pub fn release_sub_tables(&mut self) {
+        self.sub_table_frames.clear();
+    }
 }
 
 impl PageTable {
@@ -202,6 +224,10 @@ impl PageTableEntry {
         (self.0 >> 10) & 0xFFFFFFFFFFF
     }
 
+    pub fn flags(&self) -> PTEFlags {
+        PTEFlags::from_bits_truncate(self.0 as u8)
+    }
+
     pub fn write_ppn(&mut self, ppn: PhysicalPageNum) {
         self.0 &= 0b11_1111_1111; // empty possible writen ppn.
         self.0 |= ppn.0 << 10;
@@ -3,11 +3,11 @@ use crate::mm::address::PhysicalAddress;
 #[cfg(feature = "board_k210")]
 use crate::sbi::interrupt::enable_mext;
 use crate::sbi::sbi_console_getchar;
-use crate::syscall::notify;
+use crate::syscall::{notify_irq, lookup_service};
 #[cfg(feature = "board_k210")]
 use core::arch::asm;
 use riscv::register::{sie, sip};
-use share::ipc::TERMINAL_PID;
+use share::ipc::{TERMINAL_SERVICE, RTC_SERVICE};
 
 #[cfg(feature = "board_qemu")]
 const UART_IRQ: u32 = 10;
@@ -30,8 +30,6 @@ const PLIC_S_CLAIM: usize = PLIC_START_ADDRESS + 0x20_1004;
 const PLIC_M_COMPLETE: usize = PLIC_M_CLAIM;
 const PLIC_S_COMPLETE: usize = PLIC_S_CLAIM;
 
-const RTC_PID: usize = 4;
-
 pub fn enable_external_interrupt() {
     unsafe {
         #[cfg(feature = "board_qemu")]
@@ -60,11 +58,19 @@ pub fn handle_interrupt() {
     if let Some(interrupt) = next_interrupt_number() {
         match interrupt {
             UART_IRQ => {
-                notify(TERMINAL_PID).unwrap();
+                // the interrupt is dropped if the terminal has exited or is not published yet, the
+                // terminal enables it again once it is ready to read.
+                let result = lookup_service(TERMINAL_SERVICE)
+                    .and_then(|terminal| notify_irq(terminal, UART_IRQ as usize));
+                if let Err(err) = result {
+                    warn!("uart interrupt dropped: {:?}", err);
+                }
                 disable_uart_interrupt();
             }
             RTC_IRQ => {
-                notify(RTC_PID);
+                if let Ok(rtc) = lookup_service(RTC_SERVICE) {
+                    notify_irq(rtc, RTC_IRQ as usize);
+                }
                 disable_rtc_interrupt();
             }
             _ => {
@@ -1,6 +1,8 @@
 use core::arch::asm;
+use riscv::register::sie;
 use crate::sbi::sbi_send_ipi;
 use crate::sbi::hart::sbi_hart_suspend;
+use crate::processor::CPU_NUMS;
 
 pub fn set_hart_id(hart_id: usize) {
     unsafe {
@@ -23,15 +25,35 @@ pub fn get_hart_id() -> usize {
     hart_id
 }
 
+/// Wake up the other harts suspended in `enable_paging`, they start running from `kmain`.
 #[allow(unused)]
 pub fn enable_other_harts() {
     assert_eq!(get_hart_id(), 0);
-    let hart_mask = usize::MAX ^ 0b1;
-    let sbi_ret = sbi_send_ipi(&hart_mask as *const _ as usize,0);
+    let hart_mask = ((1 << CPU_NUMS) - 1) ^ 0b1;
+    if hart_mask == 0 {
+        return;
+    }
+    let sbi_ret = sbi_send_ipi(hart_mask, 0);
     assert_eq!(sbi_ret.error, 0);
 }
 
+/// Let software interrupts in, which are the IPIs sent to kick an idle hart. On k210 the software
+/// interrupt is taken by external interrupts instead, see `plic::enable_external_interrupt`.
+pub fn enable_ipi() {
+    unsafe {
+        sie::set_ssoft();
+    }
+}
+
 pub fn suspend_current_hart() {
     let sbi_ret =  sbi_hart_suspend(0x00000000, 0,0);
     assert_eq!(sbi_ret.error, 0);
-}
\ No newline at end of file
+}
+
+/// Wait until an interrupt enabled in `sie` is pending. Interrupts are never taken in the kernel,
+/// so the pending ones are handled by the caller.
+pub fn wait_for_interrupt() {
+    unsafe {
+        asm!("wfi");
+    }
+}
@@ -5,16 +5,42 @@ pub use hart::{
     get_hart_id,
     set_hart_id,
     enable_other_harts,
-    suspend_current_hart
+    enable_ipi,
+    suspend_current_hart,
+    wait_for_interrupt,
 };
 pub use switch::__switch;
 use alloc::sync::Arc;
-use crate::task::{TaskStruct, TrapContext, fetch_a_task_from_manager, decrease_alive_hart, get_alive_hart_cnt, RuntimeFlags, TaskContext};
-use crate::timer::set_timer_ms;
+use crate::task::{TaskStruct, TrapContext, fetch_a_task_from_manager, has_ready_task, RuntimeFlags, TaskContext};
+use crate::timer::{set_timer_ms, get_time_us};
+use crate::trap::handle_idle_interrupts;
+use crate::sbi::sbi_send_ipi;
+use crate::mm::memory_manager::MemoryManager;
 use spin::Mutex;
 use core::arch::asm;
+use core::sync::atomic::{AtomicUsize, Ordering};
 
-pub const CPU_NUMS: usize = 4;
+/// Number of harts, given by the `CPU_NUMS` environment variable at build time.
+pub const CPU_NUMS: usize = parse_cpu_nums(env!("CPU_NUMS"));
+
+/// Bit `i` is set while hart `i` waits for interrupts in the idle loop.
+static IDLE_HARTS: AtomicUsize = AtomicUsize::new(0);
+
+/// Each hart flushes its TLB when `TLB_FLUSHES_DONE` is behind `TLB_FLUSH_REQUESTS`, and catches up.
+static TLB_FLUSH_REQUESTS: [AtomicUsize; CPU_NUMS] = [ZERO; CPU_NUMS];
+static TLB_FLUSHES_DONE: [AtomicUsize; CPU_NUMS] = [ZERO; CPU_NUMS];
+const ZERO: AtomicUsize = AtomicUsize::new(0);
+
+const fn parse_cpu_nums(s: &str) -> usize {
+    let bytes = s.as_bytes();
+    let mut num = 0;
+    let mut i = 0;
+    while i < bytes.len() {
+        num = num * 10 + (bytes[i] - b'0') as usize;
+        i += 1;
+    }
+    num
+}
 
 pub fn get_cur_task_context_in_this_hart() -> &'static mut TrapContext {
     PROCESSORS[get_hart_id()].get_current_task().unwrap()
@@ -26,15 +52,33 @@ pub fn get_cur_task_in_this_hart() -> Arc<TaskStruct> {
     PROCESSORS[get_hart_id()].get_current_task().unwrap()
 }
 
-#[allow(unused)]
-pub fn set_task_in_current_hart(new_task: Arc<TaskStruct>) {
-    PROCESSORS[get_hart_id()].set_current_task(new_task);
+/// The address space current hart is running, which stays the same while the hart is in the kernel
+/// except for exec.
+pub fn get_cur_mm_in_this_hart() -> Option<Arc<Mutex<MemoryManager>>> {
+    PROCESSORS[get_hart_id()].inner.lock().current_mm.as_ref().map(|(mm, _)| mm.clone())
+}
+
+/// Current task has replaced its address space with `mm`.
+pub fn set_cur_mm_in_this_hart(mm: Arc<Mutex<MemoryManager>>) {
+    let satp = mm.lock().page_table.satp();
+    PROCESSORS[get_hart_id()].inner.lock().current_mm = Some((mm, satp));
 }
 
 pub fn take_task_in_current_hart() -> Arc<TaskStruct> {
     PROCESSORS[get_hart_id()].take_current_task().unwrap()
 }
 
+/// Hand over the last reference of an exited task, which is dropped after the hart has switched away.
+pub fn put_exited_task_in_current_hart(task: Arc<TaskStruct>) {
+    PROCESSORS[get_hart_id()].inner.lock().exited_task = Some(task);
+}
+
+/// Make current hart run `task` next instead of fetching one from the task manager. `task` is given
+/// the rest of the time slice.
+pub fn put_next_task_in_current_hart(task: Arc<TaskStruct>) {
+    PROCESSORS[get_hart_id()].inner.lock().next_task = Some(task);
+}
+
 pub fn run_on_current_hart() {
     PROCESSORS[get_hart_id()].run();
 }
@@ -43,11 +87,62 @@ pub fn get_current_hart_context_ptr() -> usize {
     &PROCESSORS[get_hart_id()].inner.lock().switcher_context as *const _ as usize
 }
 
+/// Send an IPI to an idle hart after a task has been put into the run queue of hart `hart_id`:
+/// hart `hart_id` itself is preferred, otherwise any idle hart steals the task.
+pub fn kick_idle_hart(hart_id: usize) {
+    let idle_harts = IDLE_HARTS.load(Ordering::Acquire) & !(1 << get_hart_id());
+    if idle_harts == 0 {
+        return;
+    }
+    let target = if idle_harts & (1 << hart_id) != 0 { hart_id } else { idle_harts.trailing_zeros() as usize };
+    sbi_send_ipi(1 << target, 0);
+}
+
+/// Make the other harts running the address space of page table `satp` flush their TLB, after some
+/// of its mappings have lost permissions. If `wait` is set, return only when they are done, which
+/// is never to be done with a lock held.
+pub fn flush_tlb_of_other_harts(satp: usize, wait: bool) {
+    let this_hart = get_hart_id();
+    let mut tickets = [0; CPU_NUMS];
+    let mut hart_mask = 0;
+    for (hart_id, processor) in PROCESSORS.iter().enumerate() {
+        let running = matches!(processor.inner.lock().current_mm, Some((_, cur_satp)) if cur_satp == satp);
+        if hart_id != this_hart && running {
+            tickets[hart_id] = TLB_FLUSH_REQUESTS[hart_id].fetch_add(1, Ordering::AcqRel) + 1;
+            hart_mask |= 1 << hart_id;
+        }
+    }
+    if hart_mask == 0 {
+        return;
+    }
+    sbi_send_ipi(hart_mask, 0);
+
+    if wait {
+        for hart_id in (0..CPU_NUMS).filter(|hart_id| hart_mask & (1 << hart_id) != 0) {
+            while TLB_FLUSHES_DONE[hart_id].load(Ordering::Acquire) < tickets[hart_id] {
+                // the hart may be waiting for this one to flush as well.
+                flush_tlb_if_requested();
+                core::hint::spin_loop();
+            }
+        }
+    }
+}
+
+/// Flush the TLB of current hart if another hart has asked to, it is checked on each trap and
+/// before running a task.
+pub fn flush_tlb_if_requested() {
+    let hart_id = get_hart_id();
+    let requests = TLB_FLUSH_REQUESTS[hart_id].load(Ordering::Acquire);
+    if TLB_FLUSHES_DONE[hart_id].load(Ordering::Relaxed) < requests {
+        unsafe {
+            asm!("sfence.vma");
+        }
+        TLB_FLUSHES_DONE[hart_id].store(requests, Ordering::Release);
+    }
+}
+
 lazy_static! {
-    static ref PROCESSORS: [Processor; CPU_NUMS] = [
-        Processor::new(),Processor::new(),
-        Processor::new(),Processor::new(),
-    ];
+    static ref PROCESSORS: [Processor; CPU_NUMS] = [(); CPU_NUMS].map(|_| Processor::new());
 }
 
 
@@ -58,6 +153,14 @@ pub struct Processor {
 
 struct ProcessorInner {
     current_task: Option<Arc<TaskStruct>>,
+    /// An exited task is still running on its kernel stack when it switches away, so it is dropped
+    /// by the scheduler loop instead.
+    exited_task: Option<Arc<TaskStruct>>,
+    /// Set by [`yield_to`](crate::task::yield_to), it runs before the tasks in the task manager.
+    next_task: Option<Arc<TaskStruct>>,
+    /// The address space of current task and the root of its page table, which other harts look
+    /// for when they shoot down TLB entries.
+    current_mm: Option<(Arc<Mutex<MemoryManager>>, usize)>,
     switcher_context: TaskContext
 }
 
@@ -66,6 +169,9 @@ impl Processor{
         Self {
             inner: Mutex::new(ProcessorInner {
                 current_task: None,
+                exited_task: None,
+                next_task: None,
+                current_mm: None,
                 switcher_context: TaskContext::empty(),
             })
         }
@@ -77,15 +183,32 @@ impl Processor{
         drop(processor_inner);
 
         loop {
-            if let Some(next_task) = fetch_a_task_from_manager() {
+            let next_task = self.inner.lock().next_task.take();
+            let new_slice = next_task.is_none();
+            if let Some(next_task) = next_task.or_else(fetch_a_task_from_manager) {
+                // a task woken up on this hart may still be switching away on another one.
+                while next_task.on_cpu.swap(true, Ordering::Acquire) {
+                    core::hint::spin_loop();
+                }
+                next_task.hart.store(get_hart_id(), Ordering::Relaxed);
+                let now = get_time_us();
+                next_task.sched.lock().start(now);
                 let mut next_task_inner = next_task.acquire_inner_lock();
                 next_task_inner.flag = RuntimeFlags::RUNNING;
+                next_task_inner.rusage.resume(now);
                 let next_task_context_ptr = next_task_inner.task_context_ptr();
-                let satp = 8 << 60 | next_task_inner.mem_manager.page_table.satp();
+                let mm = next_task_inner.mem_manager.clone();
+                let root_satp = mm.lock().page_table.satp();
+                let satp = 8 << 60 | root_satp;
                 drop(next_task_inner);
-                self.set_current_task(next_task);
+                self.set_current_task(next_task.clone());
+                self.inner.lock().current_mm = Some((mm, root_satp));
+                // the whole TLB is flushed below anyway.
+                flush_tlb_if_requested();
 
-                set_timer_ms(10);
+                if new_slice {
+                    set_timer_ms(10);
+                }
 
                 riscv::register::satp::write(satp);
                 unsafe {
@@ -96,20 +219,32 @@ impl Processor{
                     __switch(hart_context_ptr,
                              next_task_context_ptr);
                 }
+                // the task context has been saved, other harts are free to run it now.
+                next_task.on_cpu.store(false, Ordering::Release);
+                let current_mm = self.inner.lock().current_mm.take();
+                drop(current_mm);
+                let exited_task = self.inner.lock().exited_task.take();
+                drop(exited_task);
 
             } else {
-                decrease_alive_hart();
-
-                if get_alive_hart_cnt() <= 0 {
-                    panic!("Every hart has stopped. Shutdown the system.");
-                } else {
-                    info!("stopped");
-                    suspend_current_hart();
-                }
+                self.idle();
             }
         }
     }
 
+    /// Wait for an interrupt when there is nothing to run. The idle bit is published before the
+    /// run queues are checked again, so a task enqueued in between is sure to kick this hart.
+    fn idle(&self) {
+        let mask = 1 << get_hart_id();
+        IDLE_HARTS.fetch_or(mask, Ordering::AcqRel);
+        if !has_ready_task() {
+            set_timer_ms(10);
+            wait_for_interrupt();
+            handle_idle_interrupts();
+        }
+        IDLE_HARTS.fetch_and(!mask, Ordering::AcqRel);
+    }
+
     fn get_current_task(&self) -> Option<Arc<TaskStruct>> {
         let inner = self.inner.lock();
         if inner.current_task.is_none() {
@@ -1,15 +1,21 @@
 use share::syscall::error::SysError;
-use crate::syscall::ipc::{kcall_send, kcall_receive};
-use share::ipc::{Msg, REPLY_STATUS, FSYSCALL, SYSCALL_TYPE, FS_SYSCALL_ARG0, FS_SYSCALL_ARG1, FS_SYSCALL_ARG2, FS_SYSCALL_ARG3, FS_SYSCALL_ARG4, FS_PID};
+use crate::syscall::ipc::kcall_sendrec;
+use share::ipc::{Msg, REPLY_STATUS, FSYSCALL, SYSCALL_TYPE, FS_SYSCALL_ARG0, FS_SYSCALL_ARG1, FS_SYSCALL_ARG2, FS_SYSCALL_ARG3, FS_SYSCALL_ARG4, FS_SERVICE, GrantFlags};
+use share::file::Stat;
+use crate::syscall::grant::{create_grant_for_cur_task, create_kernel_grant_for_cur_task, revoke_grant_for_cur_task};
+use crate::mm::FrameTracker;
+use crate::mm::address::PhysicalAddress;
+use crate::mm::page_cache::read_in_user_range;
+use crate::config::FRAME_SIZE;
 use crate::processor::get_cur_task_in_this_hart;
-use share::syscall::sys_const::{SYSCALL_GETCWD, SYSCALL_DUP, SYSCALL_DUP3, SYSCALL_CHDIR, SYSCALL_OPEN, SYSCALL_CLOSE, SYSCALL_WRITE, SYSCALL_MKDIRAT, SYSCALL_READ, SYSCALL_GETDENTS, SYSCALL_MOUNT, SYSCALL_UNMOUNT, SYSCALL_LSEEK, SYSCALL_FSTAT, SYSCALL_UNLINK, SYSCALL_RMDIR};
+use share::syscall::sys_const::{SYSCALL_GETCWD, SYSCALL_DUP, SYSCALL_DUP3, SYSCALL_CHDIR, SYSCALL_OPEN, SYSCALL_CLOSE, SYSCALL_WRITE, SYSCALL_MKDIRAT, SYSCALL_READ, SYSCALL_GETDENTS, SYSCALL_MOUNT, SYSCALL_UNMOUNT, SYSCALL_LSEEK, SYSCALL_FSTAT, SYSCALL_UNLINK, SYSCALL_RMDIR, FS_MAP_FILE, FS_READ_PAGE, FS_WRITE_PAGE, FS_UNMAP_FILE};
 
 pub fn do_lseek(fd: usize, offset: usize, whence: usize) -> Result<usize, SysError> {
     send_receive_fs(SYSCALL_LSEEK, [fd, offset, whence, 0, 0])
 }
 
 pub fn do_getcwd(buf: usize, length: usize) -> Result<usize, SysError> {
-    send_receive_fs(SYSCALL_GETCWD, [buf, length, 0, 0, 0])
+    send_receive_fs_with_buffer(SYSCALL_GETCWD, [buf, length, 0, 0], buf, length, GrantFlags::WRITE)
 }
 
 pub fn do_dup(old_fd: usize) -> Result<usize, SysError> {
@@ -41,17 +47,17 @@ pub fn do_close(fd: usize) -> Result<usize, SysError> {
 }
 
 pub fn do_get_dents(fd: usize, buf: usize, length: usize) -> Result<usize, SysError> {
-    send_receive_fs(SYSCALL_GETDENTS, [fd, buf, length, 0, 0])
+    send_receive_fs_with_buffer(SYSCALL_GETDENTS, [fd, buf, length, 0], buf, length, GrantFlags::WRITE)
 }
 
 ///TODO-FUTURE: current write behaviour is not the same as MINIX..
 pub fn do_write(fd: usize, buf_ptr: usize, length: usize) -> Result<usize, SysError> {
-    send_receive_fs(SYSCALL_WRITE, [fd, buf_ptr, length, 0, 0])
+    send_receive_fs_with_buffer(SYSCALL_WRITE, [fd, buf_ptr, length, 0], buf_ptr, length, GrantFlags::READ)
 }
 
 ///TODO-FUTURE: current read behaviour is not the same as MINIX..
 pub fn do_read(fd: usize, buf_ptr: usize, length: usize) -> Result<usize, SysError> {
-    send_receive_fs(SYSCALL_READ, [fd, buf_ptr, length, 0, 0])
+    send_receive_fs_with_buffer(SYSCALL_READ, [fd, buf_ptr, length, 0], buf_ptr, length, GrantFlags::WRITE)
 }
 
 pub fn do_mkdir_at(dir_fd: usize, path_ptr: usize, mode: usize) -> Result<usize, SysError> {
@@ -59,7 +65,20 @@ pub fn do_mkdir_at(dir_fd: usize, path_ptr: usize, mode: usize) -> Result<usize,
 }
 
 pub fn do_fstat(fd: usize, stat_ptr: usize) -> Result<usize, SysError> {
-    send_receive_fs(SYSCALL_FSTAT, [fd, stat_ptr, 0, 0, 0])
+    let length = core::mem::size_of::<Stat>();
+    send_receive_fs_with_buffer(SYSCALL_FSTAT, [fd, stat_ptr, 0, 0], stat_ptr, length, GrantFlags::WRITE)
+}
+
+/// Read `fd` into `buf` of the kernel, used by `exec` to load the image.
+pub fn read_into_kernel(fd: usize, buf: &mut [u8]) -> Result<usize, SysError> {
+    let (buf_ptr, length) = (buf.as_mut_ptr() as usize, buf.len());
+    send_receive_fs_with_kernel_buffer(SYSCALL_READ, [fd, buf_ptr, length, 0], buf_ptr, length, GrantFlags::WRITE)
+}
+
+pub fn fstat_into_kernel(fd: usize, stat: &mut Stat) -> Result<usize, SysError> {
+    let stat_ptr = stat as *mut _ as usize;
+    let length = core::mem::size_of::<Stat>();
+    send_receive_fs_with_kernel_buffer(SYSCALL_FSTAT, [fd, stat_ptr, 0, 0], stat_ptr, length, GrantFlags::WRITE)
 }
 
 pub fn do_unlink(path_ptr: usize) -> Result<usize, SysError> {
@@ -70,6 +89,30 @@ pub fn do_rmdir(path_ptr: usize) -> Result<usize, SysError> {
     send_receive_fs(SYSCALL_RMDIR, [path_ptr, 0, 0, 0, 0])
 }
 
+/// Pin the file opened as `fd` by current task for a shared mapping, and return the key of its
+/// inode, which is the same for every task mapping the file. EACCES is returned if `write` is set
+/// and the file is not opened for writing.
+pub fn map_file(fd: usize, write: bool) -> Result<usize, SysError> {
+    send_receive_fs(FS_MAP_FILE, [fd, write as usize, 0, 0, 0])
+}
+
+/// Read the page at `offset` of the file into `frame`, which is left as it is beyond the end of the file.
+/// The frame is lent to fs server through its address in the kernel, since it is in no user space.
+pub fn read_page(key: usize, offset: usize, frame: &FrameTracker) -> Result<usize, SysError> {
+    let addr = PhysicalAddress::from(frame.0).as_raw::<u8>() as usize;
+    send_receive_fs_with_kernel_buffer(FS_READ_PAGE, [key, offset, 0, 0], addr, FRAME_SIZE, GrantFlags::WRITE)
+}
+
+/// Write `frame` to the page at `offset` of the file, the file is not extended by it.
+pub fn write_page(key: usize, offset: usize, frame: &FrameTracker) -> Result<usize, SysError> {
+    let addr = PhysicalAddress::from(frame.0).as_raw::<u8>() as usize;
+    send_receive_fs_with_kernel_buffer(FS_WRITE_PAGE, [key, offset, 0, 0], addr, FRAME_SIZE, GrantFlags::READ)
+}
+
+pub fn unmap_file(key: usize) -> Result<usize, SysError> {
+    send_receive_fs(FS_UNMAP_FILE, [key, 0, 0, 0, 0])
+}
+
 fn send_receive_fs(syscall_id: usize, args: [usize; 5]) -> Result<usize, SysError> {
     let mut message = Msg::empty();
     let cur_pid = get_cur_task_in_this_hart().pid();
@@ -81,13 +124,47 @@ fn send_receive_fs(syscall_id: usize, args: [usize; 5]) -> Result<usize, SysErro
     message.args[FS_SYSCALL_ARG2] = args[2];
     message.args[FS_SYSCALL_ARG3] = args[3];
     message.args[FS_SYSCALL_ARG4] = args[4];
-    kcall_send(FS_PID, &message as *const _ as usize).unwrap();
-    kcall_receive(FS_PID as isize, &mut message as *mut _ as usize).unwrap();
+    kcall_sendrec(lookup_service(FS_SERVICE)?, &mut message as *mut _ as usize)?;
 
     let status = message.args[REPLY_STATUS] as isize;
     isize2result(status)
 }
 
+/// Grant `length` bytes at `buf` of current task to fs server during the call. The grant id is passed
+/// in `FS_SYSCALL_GRANT`, so only four args are left for the syscall itself.
+fn send_receive_fs_with_buffer(
+    syscall_id: usize,
+    args: [usize; 4],
+    buf: usize,
+    length: usize,
+    flags: GrantFlags
+) -> Result<usize, SysError> {
+    // fs server can't wait for the pages of a shared file mapping to be read in from itself.
+    read_in_user_range(buf, length);
+    let grant_id = create_grant_for_cur_task(lookup_service(FS_SERVICE)?, buf, length, flags)?;
+    let result = send_receive_fs(syscall_id, [args[0], args[1], args[2], args[3], grant_id]);
+    // the table may have been cleared meanwhile, e.g. when the task is exiting.
+    let _ = revoke_grant_for_cur_task(grant_id);
+
+    result
+}
+
+/// Like `send_receive_fs_with_buffer`, but `buf` is a buffer of the kernel.
+fn send_receive_fs_with_kernel_buffer(
+    syscall_id: usize,
+    args: [usize; 4],
+    buf: usize,
+    length: usize,
+    flags: GrantFlags
+) -> Result<usize, SysError> {
+    let grant_id = create_kernel_grant_for_cur_task(lookup_service(FS_SERVICE)?, buf, length, flags)?;
+    let result = send_receive_fs(syscall_id, [args[0], args[1], args[2], args[3], grant_id]);
+    // the table may have been cleared meanwhile, e.g. when the task is exiting.
+    let _ = revoke_grant_for_cur_task(grant_id);
+
+    result
+}
+
 fn isize2result(ret: isize) -> Result<usize, SysError> {
     if ret < 0 {
         Result::Err(SysError::new(-ret as i32))
@@ -0,0 +1,173 @@
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::sync::atomic::{AtomicU32, Ordering};
+use spin::Mutex;
+use share::futex::{FUTEX_WAIT, FUTEX_WAKE, FUTEX_REQUEUE, FUTEX_PRIVATE_FLAG};
+use share::syscall::error::{SysError, EINVAL, EAGAIN, ETIMEDOUT, EINTR};
+use share::time::Timespec;
+use crate::mm::address::{VirtualAddress, PhysicalAddress};
+use crate::processor::get_cur_task_in_this_hart;
+use crate::task::{TaskStruct, TaskStructInner, RuntimeFlags, schedule, return_task_to_manager};
+use crate::timer::{get_time_us, add_timer, cancel_timer, USEC_PER_SEC};
+
+/// Identifies a futex word.
+///
+/// A word in shared memory or in a file mapped shared is seen by the tasks mapping it through the
+/// same frame, so it is keyed by the physical address. Any other page is only shared by the threads
+/// of the address space, and its frame changes when it is copied after a fork, so it is keyed by the
+/// address space and the virtual address, as are the words of `FUTEX_PRIVATE_FLAG`.
+#[derive(Copy, Clone, PartialEq)]
+pub enum FutexKey {
+    /// The address of the memory manager, which is alive as long as a waiter holds a task of it.
+    Private(usize, VirtualAddress),
+    Shared(PhysicalAddress),
+}
+
+/// A task blocked in futex wait.
+struct Waiter {
+    key: FutexKey,
+    /// In us.
+    deadline: Option<usize>,
+    task: Arc<TaskStruct>,
+}
+
+lazy_static! {
+    /// Waiters in the order they have come. A waiter is removed by the task which wakes it up with
+    /// `FUTEX_WAKE`, or by itself after a timeout or a signal has woken it up.
+    static ref WAITERS: Mutex<Vec<Waiter>> = Mutex::new(Vec::new());
+}
+
+/// `uaddr` points to the futex word in current task.
+///
+/// * `FUTEX_WAIT`: block until woken up if the word still holds `val`, or return `EAGAIN`. If
+/// `timeout_ptr` isn't 0, it points to a relative [`Timespec`], after which `ETIMEDOUT` is returned.
+/// * `FUTEX_WAKE`: wake up at most `val` waiters and return how many have been woken up.
+/// * `FUTEX_REQUEUE`: the same as `FUTEX_WAKE`, then move at most `timeout_ptr` of the rest waiters
+/// to the futex at `uaddr2`.
+pub fn do_futex(uaddr: usize, op: usize, val: usize, timeout_ptr: usize, uaddr2: usize) -> Result<usize, SysError> {
+    let private = op & FUTEX_PRIVATE_FLAG != 0;
+    let (key, pa) = translate(uaddr, private)?;
+    match op & !FUTEX_PRIVATE_FLAG {
+        FUTEX_WAIT => futex_wait(key, pa, val as u32, timeout_ptr),
+        FUTEX_WAKE => Ok(futex_wake(key, val)),
+        FUTEX_REQUEUE => Ok(futex_requeue(key, val, translate(uaddr2, private)?.0, timeout_ptr)),
+        _ => Err(SysError::new(EINVAL)),
+    }
+}
+
+fn futex_wait(key: FutexKey, pa: PhysicalAddress, val: u32, timeout_ptr: usize) -> Result<usize, SysError> {
+    let deadline = if timeout_ptr != 0 {
+        let timeout = unsafe { (timeout_ptr as *const Timespec).read() };
+        Some(get_time_us() + timeout.tv_sec as usize * USEC_PER_SEC + timeout.tv_usec as usize)
+    } else {
+        None
+    };
+    let task = get_cur_task_in_this_hart();
+    let mut waiters = WAITERS.lock();
+    // the word is read with the lock held, so a waker changing it afterwards is sure to find us.
+    if load(pa) != val {
+        return Err(SysError::new(EAGAIN));
+    }
+    let mut inner = task.acquire_inner_lock();
+    if inner.signals.has_wanted_pending() {
+        return Err(SysError::new(EINTR));
+    }
+    inner.flag = RuntimeFlags::FUTEX;
+    drop(inner);
+    waiters.push(Waiter { key, deadline, task: task.clone() });
+    drop(waiters);
+    let timer = deadline.map(|deadline| {
+        let task = task.clone();
+        add_timer(deadline, move || wake_up_futex_waiter(task))
+    });
+    drop(task);
+    schedule(RuntimeFlags::FUTEX);
+
+    if let Some(timer) = timer {
+        cancel_timer(timer);
+    }
+    // still in the queue if it isn't woken up by `FUTEX_WAKE`.
+    let task = get_cur_task_in_this_hart();
+    let mut waiters = WAITERS.lock();
+    match waiters.iter().position(|waiter| Arc::ptr_eq(&waiter.task, &task)) {
+        None => Ok(0),
+        Some(index) => match waiters.remove(index).deadline {
+            Some(deadline) if deadline <= get_time_us() => Err(SysError::new(ETIMEDOUT)),
+            _ => Err(SysError::new(EINTR)),
+        },
+    }
+}
+
+/// Wake up at most `n` tasks waiting on the futex at `key`, and return how many have been woken up.
+pub fn futex_wake(key: FutexKey, n: usize) -> usize {
+    let woken = take_waiters(&mut WAITERS.lock(), key, n);
+    let count = woken.len();
+    woken.into_iter().for_each(wake_up_futex_waiter);
+    count
+}
+
+fn futex_requeue(key: FutexKey, n: usize, new_key: FutexKey, requeue_n: usize) -> usize {
+    let mut waiters = WAITERS.lock();
+    let woken = take_waiters(&mut waiters, key, n);
+    waiters.iter_mut()
+        .filter(|waiter| waiter.key == key && is_blocked(&waiter.task))
+        .take(requeue_n)
+        .for_each(|waiter| waiter.key = new_key);
+    drop(waiters);
+
+    let count = woken.len();
+    woken.into_iter().for_each(wake_up_futex_waiter);
+    count
+}
+
+/// Remove the first `n` waiters on `key` from the queue. Waiters which have been woken up by a
+/// timeout or a signal are skipped, they are going to fail anyway.
+fn take_waiters(waiters: &mut Vec<Waiter>, key: FutexKey, n: usize) -> Vec<Arc<TaskStruct>> {
+    let mut woken = Vec::new();
+    waiters.retain(|waiter| {
+        if woken.len() < n && waiter.key == key && is_blocked(&waiter.task) {
+            woken.push(waiter.task.clone());
+            return false;
+        }
+        true
+    });
+    woken
+}
+
+/// Make `task` runnable again if it is blocked in futex wait.
+pub fn wake_up_futex_waiter(task: Arc<TaskStruct>) {
+    let mut inner = task.acquire_inner_lock();
+    if let RuntimeFlags::FUTEX = inner.flag {
+        inner.flag = RuntimeFlags::READY;
+        drop(inner);
+        return_task_to_manager(task);
+    }
+}
+
+/// Return the key and the physical address of the futex word at `uaddr` in current task.
+fn translate(uaddr: usize, private: bool) -> Result<(FutexKey, PhysicalAddress), SysError> {
+    let task = get_cur_task_in_this_hart();
+    let inner = task.acquire_inner_lock();
+    futex_key(&inner, uaddr, private)
+}
+
+/// Return the key and the physical address of the futex word at `uaddr` in the task of `inner`.
+pub fn futex_key(inner: &TaskStructInner, uaddr: usize, private: bool) -> Result<(FutexKey, PhysicalAddress), SysError> {
+    let va = VirtualAddress::new(uaddr);
+    let mut mem_manager = inner.mem_manager.lock();
+    let pa = mem_manager.translate_u32(va)?;
+    let key = if !private && mem_manager.is_shared(va) {
+        FutexKey::Shared(pa)
+    } else {
+        FutexKey::Private(Arc::as_ptr(&inner.mem_manager) as usize, va)
+    };
+    Ok((key, pa))
+}
+
+fn is_blocked(task: &Arc<TaskStruct>) -> bool {
+    matches!(task.acquire_inner_lock().flag, RuntimeFlags::FUTEX)
+}
+
+fn load(key: PhysicalAddress) -> u32 {
+    unsafe { (*key.as_raw::<AtomicU32>()).load(Ordering::SeqCst) }
+}
@@ -0,0 +1,140 @@
+use alloc::sync::Arc;
+use crate::config::{FRAME_SIZE, MAX_USER_ADDRESS};
+use crate::mm::address::VirtualAddress;
+use crate::processor::get_cur_task_in_this_hart;
+use crate::syscall::registry::endpoint_to_pid;
+use crate::task::{get_task_by_pid, Grant, TaskStruct};
+use share::ipc::GrantFlags;
+use share::syscall::error::{SysError, EINVAL, ESRCH, EPERM, EFAULT};
+
+/// Allow `grantee` task to copy from or to `[addr, addr + len)` of current task, return the grant id.
+///
+/// The grant id is passed to `grantee` task inside a [`Msg`](share::ipc::Msg), which then uses
+/// [`kcall_safecopy_from`] or [`kcall_safecopy_to`] to access the memory until the grant is revoked.
+pub fn kcall_grant_create(grantee: usize, addr: usize, len: usize, flags: usize) -> Result<usize, SysError> {
+    let flags = GrantFlags::from_bits(flags).ok_or(SysError::new(EINVAL))?;
+    create_grant_for_cur_task(grantee, addr, len, flags)
+}
+
+pub fn kcall_grant_revoke(grant_id: usize) -> Result<usize, SysError> {
+    revoke_grant_for_cur_task(grant_id)?;
+    Ok(0)
+}
+
+/// Copy `len` bytes at `offset` of the grant `grant_id` made by `granter` task to `dst_ptr` of current task.
+pub fn kcall_safecopy_from(granter: usize, grant_id: usize, offset: usize, dst_ptr: usize, len: usize) -> Result<usize, SysError> {
+    let caller_task = get_cur_task_in_this_hart();
+    let granter_task = get_task_by_pid(granter).ok_or(SysError::new(ESRCH))?;
+    let (src_ptr, in_kernel) = check_grant(&granter_task, grant_id, caller_task.pid(), offset, len, GrantFlags::READ)?;
+    let src_task = if in_kernel { None } else { Some(&granter_task) };
+    copy(src_task, src_ptr, Some(&caller_task), dst_ptr, len)?;
+
+    Ok(0)
+}
+
+/// Copy `len` bytes at `src_ptr` of current task to `offset` of the grant `grant_id` made by `granter` task.
+pub fn kcall_safecopy_to(granter: usize, grant_id: usize, offset: usize, src_ptr: usize, len: usize) -> Result<usize, SysError> {
+    let caller_task = get_cur_task_in_this_hart();
+    let granter_task = get_task_by_pid(granter).ok_or(SysError::new(ESRCH))?;
+    let (dst_ptr, in_kernel) = check_grant(&granter_task, grant_id, caller_task.pid(), offset, len, GrantFlags::WRITE)?;
+    let dst_task = if in_kernel { None } else { Some(&granter_task) };
+    copy(Some(&caller_task), src_ptr, dst_task, dst_ptr, len)?;
+
+    Ok(0)
+}
+
+/// Used by the kernel as well, when it forwards a user buffer to a server on behalf of current task.
+/// The buffer must be in user space, the kernel space is mapped in every page table as well.
+pub fn create_grant_for_cur_task(grantee: usize, addr: usize, len: usize, flags: GrantFlags) -> Result<usize, SysError> {
+    let end = addr.checked_add(len).ok_or(SysError::new(EINVAL))?;
+    if end > MAX_USER_ADDRESS {
+        return Err(SysError::new(EFAULT));
+    }
+    insert_grant_for_cur_task(Grant { grantee: endpoint_to_pid(grantee)?, addr, len, flags, in_kernel: false })
+}
+
+/// Grant `[addr, addr + len)` of the kernel to `grantee` on behalf of current task, while the kernel
+/// talks to a server for it. The buffer is accessed as it is, rather than through a page table.
+pub fn create_kernel_grant_for_cur_task(grantee: usize, addr: usize, len: usize, flags: GrantFlags) -> Result<usize, SysError> {
+    addr.checked_add(len).ok_or(SysError::new(EINVAL))?;
+    insert_grant_for_cur_task(Grant { grantee: endpoint_to_pid(grantee)?, addr, len, flags, in_kernel: true })
+}
+
+fn insert_grant_for_cur_task(grant: Grant) -> Result<usize, SysError> {
+    let task = get_cur_task_in_this_hart();
+    let grant_id = task.acquire_inner_lock().grants.insert(grant)?;
+
+    Ok(grant_id)
+}
+
+pub fn revoke_grant_for_cur_task(grant_id: usize) -> Result<(), SysError> {
+    let task = get_cur_task_in_this_hart();
+    let result = task.acquire_inner_lock().grants.revoke(grant_id);
+    result
+}
+
+fn check_grant(
+    granter_task: &Arc<TaskStruct>,
+    grant_id: usize,
+    grantee: usize,
+    offset: usize,
+    len: usize,
+    flags: GrantFlags
+) -> Result<(usize, bool), SysError> {
+    let grant = granter_task.acquire_inner_lock().grants.get(grant_id);
+    grant.and_then(|grant| grant.check(grantee, offset, len, flags).map(|ptr| (ptr, grant.in_kernel)))
+        .ok_or(SysError::new(EPERM))
+}
+
+pub fn copy_between_tasks(
+    src_task: &Arc<TaskStruct>,
+    src_ptr: usize,
+    dst_task: &Arc<TaskStruct>,
+    dst_ptr: usize,
+    len: usize
+) -> Result<(), SysError> {
+    copy(Some(src_task), src_ptr, Some(dst_task), dst_ptr, len)
+}
+
+/// Copy page by page, because continuous virtual pages are not continuous in physical memory. A
+/// task of `None` stands for the kernel, whose buffers are copied as they are.
+fn copy(
+    src_task: Option<&Arc<TaskStruct>>,
+    src_ptr: usize,
+    dst_task: Option<&Arc<TaskStruct>>,
+    dst_ptr: usize,
+    len: usize
+) -> Result<(), SysError> {
+    let mut copied = 0;
+    while copied < len {
+        let src = translate(src_task, src_ptr + copied, false)?;
+        let dst = translate(dst_task, dst_ptr + copied, true)?;
+        let size = (len - copied)
+            .min(FRAME_SIZE - (src_ptr + copied) % FRAME_SIZE)
+            .min(FRAME_SIZE - (dst_ptr + copied) % FRAME_SIZE);
+        unsafe {
+            core::ptr::copy(src as *const u8, dst as *mut u8, size);
+        }
+        copied += size;
+    }
+
+    Ok(())
+}
+
+/// Return the address of `ptr` of `task` in the kernel. The page is backed first if it hasn't been
+/// touched, and copied first if it is to be written and shared copy-on-write.
+fn translate(task: Option<&Arc<TaskStruct>>, ptr: usize, for_write: bool) -> Result<usize, SysError> {
+    let task = match task {
+        Some(task) => task,
+        None => return Ok(ptr),
+    };
+    let task_inner = task.acquire_inner_lock();
+    let mut mem_manager = task_inner.mem_manager.lock();
+    let pa = if for_write {
+        mem_manager.translate_for_write(VirtualAddress::new(ptr))?
+    } else {
+        mem_manager.translate_for_read(VirtualAddress::new(ptr))?
+    };
+
+    Ok(pa.as_raw::<u8>() as usize)
+}
@@ -1,11 +1,28 @@
-use crate::task::{get_task_by_pid, RuntimeFlags, TaskStruct, return_task_to_manager, TaskStructInner, schedule};
-use crate::processor::get_cur_task_in_this_hart;
+use crate::task::{get_task_by_pid, get_all_tasks, RuntimeFlags, TaskStruct, return_task_to_manager, TaskStructInner, schedule, yield_to, PendingNotifications};
+use crate::processor::{get_cur_task_in_this_hart, get_cur_task_context_in_this_hart};
+use crate::timer::{get_time_us, add_timer, cancel_timer, TimerHandle};
+use crate::syscall::registry::endpoint_to_pid;
 use alloc::sync::Arc;
-use share::ipc::Msg;
-use share::syscall::error::{EINVAL, SysError, EDLOCK};
-use spin::MutexGuard;
+use alloc::vec::Vec;
+use alloc::vec;
+use share::ipc::{Msg, SHORT_MSG_ARGS};
+use share::syscall::error::{EINVAL, SysError, EDLOCK, EAGAIN, ETIMEDOUT, EDEADSRCDST, EINTR};
+use spin::{Mutex, MutexGuard};
 
-// TODO-FUTURE: using registers to pass the message could improve performance. L4 stuff.
+/// Held by a task from checking for a deadlock until it has blocked. Wait-for edges are only added
+/// under it, so two tasks can't complete a cycle at the same time on different harts.
+static WAIT_FOR_GRAPH: Mutex<()> = Mutex::new(());
+
+/// Describes how long an ipc caller is willing to wait for its peer.
+#[derive(Copy, Clone)]
+enum Blocking {
+    /// Block until the peer shows up.
+    Forever,
+    /// Return `EAGAIN` immediately if the peer is not ready.
+    NonBlocking,
+    /// Block until the peer shows up or the deadline(in us) has passed, then return `ETIMEDOUT`.
+    Deadline(usize),
+}
 
 /// Send a message which `msg_ptr` points to, from current task to `dst_pid` task.
 ///
@@ -13,100 +30,459 @@ use spin::MutexGuard;
 /// Caller task reads the message from `msg_ptr`, and If `dst_pid` task is receiving,
 /// it moves the message to dst task's [`TaskStruct`] and wakes it up. Otherwise it stores the message
 /// inside caller task's [`TaskStruct`] and blocks itself.
+///
+/// Like every ipc kcall, `dst_pid` may also be an [`Endpoint`](share::ipc::Endpoint) returned by
+/// `kcall_lookup`, which fails with `EDEADSRCDST` once the process behind it has gone.
 pub fn kcall_send(dst_pid: usize, msg_ptr: usize) -> Result<usize, SysError> {
+    send(dst_pid, read_message_from(msg_ptr), Blocking::Forever, return_task_to_manager)
+}
+
+/// The same as [`kcall_send`], except that it returns `EAGAIN` instead of blocking
+/// when `dst_pid` task is not receiving from the caller.
+pub fn kcall_send_nb(dst_pid: usize, msg_ptr: usize) -> Result<usize, SysError> {
+    send(dst_pid, read_message_from(msg_ptr), Blocking::NonBlocking, return_task_to_manager)
+}
+
+/// The same as [`kcall_send`], except that the caller gives up after `timeout_ms` milliseconds
+/// and returns `ETIMEDOUT`. The message is not delivered in that case.
+pub fn kcall_send_timeout(dst_pid: usize, msg_ptr: usize, timeout_ms: usize) -> Result<usize, SysError> {
+    let deadline = get_time_us() + timeout_ms * 1000;
+    send(dst_pid, read_message_from(msg_ptr), Blocking::Deadline(deadline), return_task_to_manager)
+}
+
+/// Send the message which `msg_ptr` points to `dst_pid` task, then wait for the reply from
+/// `dst_pid` task and save it to the same address.
+///
+/// Unlike a [`kcall_send`] followed by a [`kcall_receive`], the caller never becomes runnable between
+/// the two steps: if the message has to be queued, the receiver moves the caller directly into
+/// `RECEIVING(dst_pid)` state when it picks the message up, so the reply can always be delivered at once.
+///
+/// `dst_pid` task inherits the priority of the caller once it has the message, until it replies.
+pub fn kcall_sendrec(dst_pid: usize, msg_ptr: usize) -> Result<usize, SysError> {
+    let dst_pid = endpoint_to_pid(dst_pid)?;
     let dst_task = get_dst_task_or_err(dst_pid)?;
     let caller_task = get_cur_task_in_this_hart();
-    check_deadlock(caller_task.clone(), dst_task.clone())?;
 
-    let mut message = unsafe { (msg_ptr as *const Msg).read() };
+    let mut message = read_message_from(msg_ptr);
     message.src_pid = caller_task.pid();
-    let mut dst_task_inner =
-        dst_task.acquire_inner_lock(); // acquire lock to avoid race condition
+    let mut graph = None;
+    let mut dst_task_inner = loop {
+        let mut dst_task_inner = dst_task.acquire_inner_lock();
+        if dst_task_inner.exiting {
+            return Err(SysError::new(EDEADSRCDST));
+        }
 
-    if dst_task_inner.is_receiving_from(&caller_task) {
-        assert!(dst_task_inner.message_holder.is_none());
-        dst_task_inner.message_holder = Some(message);
-        dst_task_inner.flag = RuntimeFlags::READY;
-        drop(dst_task_inner);
+        if dst_task_inner.is_receiving_from(&caller_task) {
+            assert!(dst_task_inner.message_holder.is_none());
+            dst_task_inner.message_holder = Some(message);
+            dst_task_inner.flag = RuntimeFlags::READY;
+            drop(dst_task_inner);
+            drop(graph);
+            lend_priority(&caller_task, &dst_task);
+            return_task_to_manager(dst_task);
+            caller_task.acquire_inner_lock().sendrec = true;
+            drop(caller_task);
 
-        return_task_to_manager(dst_task.clone());
-    } else {
-        let mut src_task_inner = caller_task.acquire_inner_lock();
-        src_task_inner.message_holder = Some(message);
-        dst_task_inner.wait_queue.push(caller_task.clone());
-        drop(src_task_inner);
+            let reply = receive(dst_pid as isize, Blocking::Forever);
+            get_cur_task_in_this_hart().acquire_inner_lock().sendrec = false;
+            write_message_to(msg_ptr, reply?);
+            return Ok(0);
+        }
+
+        if graph.is_some() {
+            break dst_task_inner;
+        }
+        // the destination may start receiving from us while the graph is walked, check again.
         drop(dst_task_inner);
+        graph = Some(WAIT_FOR_GRAPH.lock());
+        check_deadlock(vec![caller_task.pid(), dst_pid], waiting_for(dst_task.acquire_inner_lock().flag))?;
+    };
 
-        schedule(RuntimeFlags::SENDING(dst_pid));
-    }
+    let mut src_task_inner = caller_task.acquire_inner_lock();
+    src_task_inner.message_holder = Some(message);
+    src_task_inner.sendrec = true;
+    src_task_inner.ipc_error = None;
+    src_task_inner.flag = RuntimeFlags::SENDING(dst_pid);
+    dst_task_inner.wait_queue.push(caller_task.clone());
+    drop(src_task_inner);
+    drop(dst_task_inner);
+    drop(graph);
+    drop(caller_task);
 
+    schedule(RuntimeFlags::SENDING(dst_pid));
+
+    // After the task is waked up the reply has been moved into `message_holder`, unless `dst_pid`
+    // task has exited.
+    let caller_task = get_cur_task_in_this_hart();
+    let mut caller_task_inner = caller_task.acquire_inner_lock();
+    caller_task_inner.sendrec = false;
+    if let Some(errno) = caller_task_inner.ipc_error.take() {
+        return Err(SysError::new(errno));
+    }
+    let reply = caller_task_inner.message_holder.take().unwrap();
+    write_message_to(msg_ptr, reply);
     Ok(0)
 }
 
 /// Receive a [`Msg`] from `dst_pid` task, and save it to `msg_ptr` address.
 ///
-/// If `interrupt_flag` is set for caller task, it return with an interrupt message immediately.
+/// If there are pending notifications accepted by the call, it returns with one of them immediately.
 /// Caller task finds out possible sending tasks, if there is someone sending, it moves the [`Msg`]
 /// from sending task to address where `msg_ptr` points to and wakes the sending task up. Otherwise
 /// it blocks itself, and after it is waked up, it moves message to that address.
 pub fn kcall_receive(dst_pid: isize, msg_ptr: usize) -> Result<usize, SysError>{
-    let src_task = get_cur_task_in_this_hart();
-    let mut src_task_inner = src_task.acquire_inner_lock();
-    if dst_pid == -1 && src_task_inner.interrupt_flag {
-        src_task_inner.interrupt_flag = false;
-        build_and_move_interrupt_message_to(msg_ptr);
-        return Ok(0);
+    let message = receive(dst_pid, Blocking::Forever)?;
+    write_message_to(msg_ptr, message);
+    Ok(0)
+}
+
+/// The same as [`kcall_receive`], except that it returns `EAGAIN` instead of blocking
+/// when there is no message for the caller.
+pub fn kcall_receive_nb(dst_pid: isize, msg_ptr: usize) -> Result<usize, SysError> {
+    let message = receive(dst_pid, Blocking::NonBlocking)?;
+    write_message_to(msg_ptr, message);
+    Ok(0)
+}
+
+/// The same as [`kcall_receive`], except that the caller gives up after `timeout_ms` milliseconds
+/// and returns `ETIMEDOUT`.
+pub fn kcall_receive_timeout(dst_pid: isize, msg_ptr: usize, timeout_ms: usize) -> Result<usize, SysError> {
+    let message = receive(dst_pid, Blocking::Deadline(get_time_us() + timeout_ms * 1000))?;
+    write_message_to(msg_ptr, message);
+    Ok(0)
+}
+
+/// Send a short message to `dst_pid` task through registers.
+///
+/// `mtype` and the first [`SHORT_MSG_ARGS`] args are taken from a1-a6 of the caller's
+/// [`TrapContext`](crate::task::TrapContext), so no user memory is touched. If `dst_pid` task is
+/// already receiving, the caller hands the rest of its time slice to it and switches to it directly,
+/// without going through the task manager queues. Otherwise it blocks just like [`kcall_send`] does,
+/// and the message can be picked up by either kind of receive.
+pub fn kcall_send_fast(dst_pid: usize) -> Result<usize, SysError> {
+    let context = get_cur_task_context_in_this_hart();
+    let mut message = Msg::empty();
+    message.mtype = context.x[11];
+    message.args[..SHORT_MSG_ARGS].copy_from_slice(&context.x[12..12 + SHORT_MSG_ARGS]);
+
+    send(dst_pid, message, Blocking::Forever, yield_to)
+}
+
+/// Receive a message from `dst_pid` task through registers.
+///
+/// On return, a1 holds `src_pid`, a2 holds `mtype` and a3-a7 hold the first [`SHORT_MSG_ARGS`] args.
+/// The remaining args of a message sent by [`kcall_send`] are dropped.
+pub fn kcall_receive_fast(dst_pid: isize) -> Result<usize, SysError> {
+    let message = receive(dst_pid, Blocking::Forever)?;
+    let context = get_cur_task_context_in_this_hart();
+    context.x[11] = message.src_pid;
+    context.x[12] = message.mtype;
+    context.x[13..13 + SHORT_MSG_ARGS].copy_from_slice(&message.args[..SHORT_MSG_ARGS]);
+    Ok(0)
+}
+
+/// Notify `dst_pid` task without blocking the caller.
+///
+/// Notifications from the same task are merged until `dst_pid` task receives them, see
+/// [`PendingNotifications`](crate::task::PendingNotifications).
+pub fn kcall_notify(dst_pid: usize) -> Result<usize, SysError> {
+    let caller_pid = get_cur_task_in_this_hart().pid();
+    notify(dst_pid, |notifications| notifications.add_pid(caller_pid))?;
+    Ok(0)
+}
+
+/// This function is only used by kernel to notify `dst_pid` task that `irq` line has fired.
+pub fn notify_irq(dst_pid: usize, irq: usize) -> Result<(), SysError> {
+    notify(dst_pid, |notifications| notifications.add_irq(irq))
+}
+
+/// This function is only used by kernel to notify `dst_pid` task about kernel `events`.
+#[allow(unused)]
+pub fn notify_kernel(dst_pid: usize, events: u64) -> Result<(), SysError> {
+    notify(dst_pid, |notifications| notifications.add_kernel_events(events))
+}
+
+/// Mark the notification as pending for `dst_pid` task, and deliver it at once if the task is waiting for it.
+fn notify(dst_pid: usize, add: impl FnOnce(&mut PendingNotifications)) -> Result<(), SysError> {
+    let dst_pid = endpoint_to_pid(dst_pid)?;
+    let dst_task = get_dst_task_or_err(dst_pid)?;
+    let mut dst_task_inner = dst_task.acquire_inner_lock();
+    add(&mut dst_task_inner.notifications);
+
+    if let RuntimeFlags::RECEIVING(receiving_pid) = dst_task_inner.flag {
+        if dst_task_inner.sendrec {
+            return Ok(());
+        }
+        if let Some(message) = dst_task_inner.notifications.take(receiving_pid) {
+            dst_task_inner.message_holder = Some(message);
+            dst_task_inner.flag = RuntimeFlags::READY;
+            drop(dst_task_inner);
+            return_task_to_manager(dst_task);
+        }
     }
-    let idx = find_possible_sending_task_index(&src_task_inner, dst_pid);
+    Ok(())
+}
 
-    if idx.is_some() {
-        let idx = idx.unwrap();
-        let dst_task = src_task_inner.wait_queue[idx].clone();
-        let mut dst_task_inner = dst_task.acquire_inner_lock();
-        assert!(dst_task_inner.is_sending_to(&src_task));
-        let message = dst_task_inner.message_holder.take().unwrap();
-        unsafe {
-            (msg_ptr as *mut Msg).write(message);
+/// Fail the ipc calls which are blocked on the exiting `dead_task` with `EDEADSRCDST`.
+///
+/// Senders in its wait queue lose their messages, and tasks receiving from it, including sendrec
+/// callers waiting for the reply, are woken up. Servers drop the priority inherited from it. It is
+/// called right before the task becomes a zombie, and any later ipc with the task fails at once.
+pub fn cancel_ipc_with(dead_task: &Arc<TaskStruct>) {
+    let dead_pid = dead_task.pid();
+    // senders check `exiting` under the same lock, so none of them can queue after the drain.
+    let mut dead_task_inner = dead_task.acquire_inner_lock();
+    dead_task_inner.exiting = true;
+    let senders: Vec<Arc<TaskStruct>> = dead_task_inner.wait_queue.drain(..).collect();
+    drop(dead_task_inner);
+    for sender in senders {
+        let mut sender_inner = sender.acquire_inner_lock();
+        if sender_inner.is_sending_to_pid(dead_pid) {
+            sender_inner.message_holder = None;
+            sender_inner.ipc_error = Some(EDEADSRCDST);
+            sender_inner.flag = RuntimeFlags::READY;
+            drop(sender_inner);
+            return_task_to_manager(sender);
+        }
+    }
+
+    for task in get_all_tasks() {
+        if Arc::ptr_eq(&task, dead_task) {
+            continue;
+        }
+        task.sched.lock().revert(Some(dead_pid));
+        let mut task_inner = task.acquire_inner_lock();
+        if let RuntimeFlags::RECEIVING(src_pid) = task_inner.flag {
+            if src_pid == dead_pid as isize {
+                task_inner.message_holder = None;
+                task_inner.ipc_error = Some(EDEADSRCDST);
+                task_inner.flag = RuntimeFlags::READY;
+                drop(task_inner);
+                return_task_to_manager(task);
+            }
+        }
+    }
+}
+
+/// `wake_up` decides how a receiving `dst_pid` task gets to run: either it is put back into
+/// the task manager, or the caller switches to it at once.
+fn send(
+    dst_pid: usize,
+    mut message: Msg,
+    blocking: Blocking,
+    wake_up: fn(Arc<TaskStruct>)
+) -> Result<usize, SysError> {
+    let dst_pid = endpoint_to_pid(dst_pid)?;
+    let dst_task = get_dst_task_or_err(dst_pid)?;
+    let caller_task = get_cur_task_in_this_hart();
+    // a reply to a sendrec caller, its priority is not needed any more.
+    caller_task.sched.lock().revert(Some(dst_pid));
+
+    message.src_pid = caller_task.pid();
+    let mut graph = None;
+    let mut dst_task_inner = loop {
+        let mut dst_task_inner =
+            dst_task.acquire_inner_lock(); // acquire lock to avoid race condition
+        if dst_task_inner.exiting {
+            return Err(SysError::new(EDEADSRCDST));
+        }
+
+        if dst_task_inner.is_receiving_from(&caller_task) {
+            assert!(dst_task_inner.message_holder.is_none());
+            dst_task_inner.message_holder = Some(message);
+            dst_task_inner.flag = RuntimeFlags::READY;
+            drop(dst_task_inner);
+            drop(graph);
+            drop(caller_task);
+
+            wake_up(dst_task);
+            return Ok(0);
+        }
+
+        if let Blocking::NonBlocking = blocking {
+            return Err(SysError::new(EAGAIN));
         }
-        dst_task_inner.flag = RuntimeFlags::READY;
-        src_task_inner.wait_queue.remove(idx);
 
+        if graph.is_some() {
+            break dst_task_inner;
+        }
+        // the destination may start receiving from us while the graph is walked, check again.
         drop(dst_task_inner);
-        return_task_to_manager(dst_task.clone());
-        return Ok(0);
+        graph = Some(WAIT_FOR_GRAPH.lock());
+        check_deadlock(vec![caller_task.pid(), dst_pid], waiting_for(dst_task.acquire_inner_lock().flag))?;
+    };
+
+    let mut src_task_inner = caller_task.acquire_inner_lock();
+    src_task_inner.message_holder = Some(message);
+    src_task_inner.ipc_error = None;
+    src_task_inner.flag = RuntimeFlags::SENDING(dst_pid);
+    dst_task_inner.wait_queue.push(caller_task.clone());
+    drop(src_task_inner);
+    drop(dst_task_inner);
+    drop(graph);
+    let timer = add_ipc_timer(blocking, &caller_task);
+    drop(caller_task);
+
+    schedule(RuntimeFlags::SENDING(dst_pid));
+
+    // After the task is waked up the message has been received, unless the call has failed.
+    if let Some(timer) = timer {
+        cancel_timer(timer);
+    }
+    let caller_task = get_cur_task_in_this_hart();
+    if let Some(errno) = caller_task.acquire_inner_lock().ipc_error.take() {
+        return Err(SysError::new(errno));
     }
+    Ok(0)
+}
+
+fn receive(dst_pid: isize, blocking: Blocking) -> Result<Msg, SysError> {
+    let dst_pid = if dst_pid < 0 { dst_pid } else { endpoint_to_pid(dst_pid as usize)? as isize };
+    let src_task = get_cur_task_in_this_hart();
+    // a server receiving from any task is done with the request it has inherited a priority for.
+    if dst_pid < 0 {
+        src_task.sched.lock().revert(None);
+    }
+    let mut graph = None;
+    let mut src_task_inner = loop {
+        let mut src_task_inner = src_task.acquire_inner_lock();
+        if !src_task_inner.sendrec {
+            if let Some(message) = src_task_inner.notifications.take(dst_pid) {
+                return Ok(message);
+            }
+        }
+        let idx = find_possible_sending_task_index(&src_task_inner, dst_pid);
+
+        if idx.is_some() {
+            let idx = idx.unwrap();
+            let dst_task = src_task_inner.wait_queue[idx].clone();
+            let mut dst_task_inner = dst_task.acquire_inner_lock();
+            assert!(dst_task_inner.is_sending_to(&src_task));
+            let message = dst_task_inner.message_holder.take().unwrap();
+            src_task_inner.wait_queue.remove(idx);
+
+            if dst_task_inner.sendrec { // the sender keeps blocking until we reply.
+                dst_task_inner.flag = RuntimeFlags::RECEIVING(src_task.pid() as isize);
+                lend_priority(&dst_task, &src_task);
+                return Ok(message);
+            }
+            dst_task_inner.flag = RuntimeFlags::READY;
+            drop(dst_task_inner);
+            return_task_to_manager(dst_task.clone());
+            return Ok(message);
+        }
+
+        if let Blocking::NonBlocking = blocking {
+            return Err(SysError::new(EAGAIN));
+        }
+
+        // the sender may have exited already, then nobody would ever wake us up.
+        if dst_pid >= 0 && get_task_by_pid(dst_pid as usize).is_none() {
+            return Err(SysError::new(EDEADSRCDST));
+        }
+        // receiving from any task doesn't wait for a specific one.
+        if dst_pid < 0 || graph.is_some() {
+            break src_task_inner;
+        }
+        // a message may arrive while the graph is walked, check again.
+        drop(src_task_inner);
+        graph = Some(WAIT_FOR_GRAPH.lock());
+        check_deadlock(vec![src_task.pid()], waiting_for(RuntimeFlags::RECEIVING(dst_pid)))?;
+    };
 
+    src_task_inner.ipc_error = None;
+    src_task_inner.flag = RuntimeFlags::RECEIVING(dst_pid);
     drop(src_task_inner);
+    drop(graph);
+    // the sender may start exiting meanwhile. Its `cancel_ipc_with` either finds us receiving
+    // already, or has set `exiting` before we look at it here.
+    if dst_pid >= 0 && is_exiting(dst_pid as usize) {
+        wake_up_with_error(src_task.clone(), EDEADSRCDST);
+    }
+    let timer = add_ipc_timer(blocking, &src_task);
     drop(src_task);
     schedule(RuntimeFlags::RECEIVING(dst_pid));
 
-    // After the task is waked up the message has been received.
+    // After the task is waked up the message has been received, unless the call has failed.
+    if let Some(timer) = timer {
+        cancel_timer(timer);
+    }
     let src_task = get_cur_task_in_this_hart();
     let mut src_task_inner = src_task.acquire_inner_lock();
-    unsafe {
-        (msg_ptr as *mut Msg).write(src_task_inner.message_holder.take().unwrap());
+    if let Some(errno) = src_task_inner.ipc_error.take() {
+        return Err(SysError::new(errno));
     }
-    Ok(0)
+    Ok(src_task_inner.message_holder.take().unwrap())
 }
 
-/// This function is only used by kernel to notify `dst_pid` task that there is an interrupt for it.
-pub fn notify(dst_pid: usize) -> Result<(), SysError> {
-    let dst_task = get_dst_task_or_err(dst_pid)?;
-    let mut dst_task_inner = dst_task.acquire_inner_lock();
-    match dst_task_inner.flag {
-        RuntimeFlags::RECEIVING(-1) => {
-            let mut message = Msg::empty();
-            message.mtype = 1;
-            dst_task_inner.message_holder = Some(message);
-            dst_task_inner.flag = RuntimeFlags::READY;
+/// Let `server` run at the priority of `client` until it replies. Only a sendrec caller lends its
+/// priority, since it is known to wait for the reply. A server calling another one lends the
+/// priority it has inherited in turn.
+fn lend_priority(client: &Arc<TaskStruct>, server: &Arc<TaskStruct>) {
+    let priority = client.sched.lock().effective_priority();
+    server.sched.lock().inherit(client.pid(), priority);
+}
+
+/// Wake up `task` blocked in a timed ipc call with `ETIMEDOUT` when its deadline has passed. The
+/// timer is cancelled by the task after it is woken up, a timer firing before that finds the task
+/// not blocked and does nothing.
+fn add_ipc_timer(blocking: Blocking, task: &Arc<TaskStruct>) -> Option<TimerHandle> {
+    if let Blocking::Deadline(deadline) = blocking {
+        let task = task.clone();
+        Some(add_timer(deadline, move || wake_up_with_error(task, ETIMEDOUT)))
+    } else {
+        None
+    }
+}
+
+/// Wake up a task blocked in ipc because a signal has arrived, its ipc call returns `EINTR`.
+pub fn interrupt_ipc(task: Arc<TaskStruct>) {
+    wake_up_with_error(task, EINTR);
+}
+
+/// Make a task blocked in an ipc call runnable again, and its ipc call returns `errno`.
+///
+/// A sending task is removed from the wait queue of its destination and its message is dropped.
+/// Locks are acquired in the same order as [`kcall_send`] does: destination first, then the sender.
+fn wake_up_with_error(task: Arc<TaskStruct>, errno: i32) {
+    let flag = task.acquire_inner_lock().flag;
+    match flag {
+        RuntimeFlags::SENDING(dst_pid) => {
+            let dst_task = get_task_by_pid(dst_pid);
+            let mut dst_task_inner = dst_task.as_ref().map(|dst_task| dst_task.acquire_inner_lock());
+            let mut task_inner = task.acquire_inner_lock();
+            if !task_inner.is_sending_to_pid(dst_pid) { // the message has been received meanwhile.
+                return;
+            }
+            if let Some(dst_task_inner) = dst_task_inner.as_mut() {
+                dst_task_inner.wait_queue.retain(|t| !Arc::ptr_eq(t, &task));
+            }
+            task_inner.message_holder = None;
+            task_inner.ipc_error = Some(errno);
+            task_inner.flag = RuntimeFlags::READY;
+            drop(task_inner);
             drop(dst_task_inner);
-            return_task_to_manager(dst_task);
+            return_task_to_manager(task);
         },
-        _ => {
-            dst_task_inner.interrupt_flag = true;
+        RuntimeFlags::RECEIVING(_) => {
+            let mut task_inner = task.acquire_inner_lock();
+            if let RuntimeFlags::RECEIVING(_) = task_inner.flag {
+                task_inner.ipc_error = Some(errno);
+                task_inner.flag = RuntimeFlags::READY;
+                drop(task_inner);
+                return_task_to_manager(task);
+            }
         },
+        _ => {},
+    }
+}
+
+/// Whether `pid` task has exited or is exiting, so that it never sends a message again.
+fn is_exiting(pid: usize) -> bool {
+    match get_task_by_pid(pid) {
+        Some(task) => task.acquire_inner_lock().exiting,
+        None => true,
     }
-    Ok(())
 }
 
 fn get_dst_task_or_err(dst_pid: usize) -> Result<Arc<TaskStruct>, SysError> {
@@ -117,37 +493,47 @@ fn get_dst_task_or_err(dst_pid: usize) -> Result<Arc<TaskStruct>, SysError> {
     }
 }
 
-// If task_a -sending-> task_b -sending-> task_c -sending-> task_a, then there is a dead lock.
-fn check_deadlock(src_task: Arc<TaskStruct>, mut dst_task: Arc<TaskStruct>) -> Result<(), SysError> {
-    let src_pid = src_task.pid();
-    loop {
-        let dst_task_inner = dst_task.acquire_inner_lock();
-        match dst_task_inner.flag {
-            RuntimeFlags::SENDING(target_pid) => {
-                if target_pid == src_pid {
-                    return Err(SysError::new(EDLOCK));
-                }
-
-                drop(dst_task_inner);
-                let wrapped_task = get_task_by_pid(target_pid);
-                if wrapped_task.is_none() {
-                    break;
-                }
-                dst_task = get_task_by_pid(target_pid).unwrap();
-            },
-
-            _ => {
-                break;
-            }
+/// Return `EDLOCK` if the caller task would wait for itself by blocking.
+///
+/// `path` starts with the caller task, and its last task waits for `next`. The wait-for graph is
+/// followed through tasks blocked in `SENDING(pid)` or `RECEIVING(pid)` state, a task receiving from
+/// any task does not wait for a specific one. Tasks in a cycle are reported to the kernel log.
+///
+/// It is called with [`WAIT_FOR_GRAPH`] held and no task locked, since each task on the path is
+/// locked in turn.
+fn check_deadlock(mut path: Vec<usize>, mut next: Option<usize>) -> Result<(), SysError> {
+    while let Some(pid) = next {
+        if pid == path[0] {
+            error!("ipc deadlock: {:?} -> {}", path, pid);
+            return Err(SysError::new(EDLOCK));
         }
+        if path.contains(&pid) { // a cycle which the caller is not part of.
+            break;
+        }
+        match get_task_by_pid(pid) {
+            Some(task) => next = waiting_for(task.acquire_inner_lock().flag),
+            None => break,
+        }
+        path.push(pid);
     }
 
     Ok(())
 }
 
-fn build_and_move_interrupt_message_to(msg_ptr: usize) {
-    let mut message = Msg::empty();
-    message.mtype = 1;
+/// The task which a task in `flag` state is waiting for.
+fn waiting_for(flag: RuntimeFlags) -> Option<usize> {
+    match flag {
+        RuntimeFlags::SENDING(pid) => Some(pid),
+        RuntimeFlags::RECEIVING(pid) if pid >= 0 => Some(pid as usize),
+        _ => None,
+    }
+}
+
+fn read_message_from(msg_ptr: usize) -> Msg {
+    unsafe { (msg_ptr as *const Msg).read() }
+}
+
+fn write_message_to(msg_ptr: usize, message: Msg) {
     unsafe {
         (msg_ptr as *mut Msg).write(message);
     }
@@ -163,4 +549,4 @@ fn find_possible_sending_task_index(src_task_inner:& MutexGuard<TaskStructInner>
     }
 
     idx
-}
\ No newline at end of file
+}
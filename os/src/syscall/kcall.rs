@@ -1,13 +1,17 @@
-use share::syscall::error::{SysError, EINVAL, ESRCH, EFAULT, ENAMETOOLONG, EBADF};
+use share::syscall::error::{SysError, EINVAL, ESRCH, ENAMETOOLONG, EBADF, EINTR};
 use crate::mm::address::{PhysicalAddress, VirtualAddress};
-use crate::task::{get_task_by_pid, RuntimeFlags, schedule};
+use crate::task::get_task_by_pid;
 use crate::processor::{get_cur_task_context_in_this_hart, get_cur_task_in_this_hart};
 use crate::mm::memory_manager::{RegionFlags, RegionType};
 use crate::config::FRAME_SIZE;
 use share::ffi::CStr;
 use crate::sbi::sbi_console_getchar;
-use share::ipc::{Msg, READ, DEVICE, PROC_NR, BUFFER, LENGTH, TERMINAL_PID, REPLY_STATUS, WRITE};
-use crate::syscall::ipc::{kcall_send, kcall_receive};
+use share::ipc::{Msg, READ, DEVICE, PROC_NR, BUFFER, LENGTH, CALLER_PID, TERMINAL_SERVICE, REPLY_STATUS, WRITE, GrantFlags};
+use crate::syscall::ipc::kcall_sendrec;
+use crate::syscall::grant::{create_grant_for_cur_task, revoke_grant_for_cur_task, copy_between_tasks};
+use crate::syscall::registry::lookup_service;
+use crate::syscall::time::sleep_until;
+use crate::timer::{get_time_us, USEC_PER_TICK};
 use core::str::from_utf8;
 use crate::paging::KERNEL_SATP;
 use core::arch::asm;
@@ -68,20 +72,21 @@ pub fn kcall_write_dev(dev_phys_addr: usize, val: usize, byte_size: usize) -> Re
 
     Ok(0)
 }
-/// Copy a slice from `src_proc` task to `dst_proc` task.
+/// Copy a slice from `src_proc` task to `dst_proc` task. Only system processes are allowed to do this,
+/// others should use grants instead.
 pub fn kcall_virt_copy(src_proc: usize, src_ptr: usize, dst_proc: usize, dst_ptr: usize, length: usize) -> Result<usize, SysError> {
-    let src_data = get_byte_slice_in_proc(src_proc, src_ptr, length)?;
-    let dst_data = get_mut_byte_slice_in_proc(dst_proc, dst_ptr, length)?;
-    dst_data.copy_from_slice(src_data);
+    let src_task = get_task_by_pid(src_proc).ok_or(SysError::new(EINVAL))?;
+    let dst_task = get_task_by_pid(dst_proc).ok_or(SysError::new(EINVAL))?;
+    copy_between_tasks(&src_task, src_ptr, &dst_task, dst_ptr, length)?;
 
     Ok(0)
 }
 
 pub fn kcall_continuous_alloc(size: usize) -> Result<usize, SysError> {
     let task = get_cur_task_in_this_hart();
-    let mut inner = task.acquire_inner_lock();
+    let inner = task.acquire_inner_lock();
     let size = (size + FRAME_SIZE) & !(FRAME_SIZE - 1);
-    let start = inner.mem_manager.alloc_area(
+    let start = inner.mem_manager.lock().alloc_area(
         size, RegionFlags::W | RegionFlags::R, RegionType::Continuous, None
     )?;
 
@@ -92,8 +97,8 @@ pub fn kcall_virt_to_phys(virt_addr: usize) -> Result<usize, SysError> {
     let task = get_cur_task_in_this_hart();
     let inner = task.acquire_inner_lock();
     let va = VirtualAddress::new(virt_addr);
-    let pa =
-        inner.mem_manager.page_table.translate_va(va).ok_or(SysError::new(EFAULT))?;
+    // a device writing to the frame must not see it shared with another task.
+    let pa = inner.mem_manager.lock().translate_for_write(va)?;
 
     Ok(pa.0)
 }
@@ -109,7 +114,7 @@ pub fn kcall_sdcard_read(block_id: usize, buf_ptr: usize, length: usize) -> Resu
     let buf = get_mut_byte_slice_in_proc(pid, buf_ptr, length)?;
     crate::sdcard::read_block(block_id, buf);
     let cur_task_inner = task.acquire_inner_lock();
-    let satp = cur_task_inner.mem_manager.page_table.satp();
+    let satp = cur_task_inner.mem_manager.lock().page_table.satp();
     switch_to_page_table_by_satp(satp);
     Ok(0)
 }
@@ -125,7 +130,7 @@ pub fn kcall_sdcard_write(block_id: usize, buf_ptr: usize, length: usize) -> Res
     let buf = get_byte_slice_in_proc(pid, buf_ptr, length)?;
     crate::sdcard::write_block(block_id, buf);
     let cur_task_inner = task.acquire_inner_lock();
-    let satp = cur_task_inner.mem_manager.page_table.satp();
+    let satp = cur_task_inner.mem_manager.lock().page_table.satp();
     switch_to_page_table_by_satp(satp);
     Ok(0)
 }
@@ -145,8 +150,7 @@ pub fn kcall_copy_c_path(proc: usize, path_ptr: usize, buf_ptr: usize, size: usi
     let path_proc = get_task_by_pid(proc).ok_or(SysError::new(ESRCH))?;
     let path_proc_inner = path_proc.acquire_inner_lock();
     let path_va = VirtualAddress::new(path_ptr);
-    let path_pa =
-        path_proc_inner.mem_manager.page_table.translate_va(path_va).ok_or(SysError::new(EFAULT))?;
+    let path_pa = path_proc_inner.mem_manager.lock().translate_for_read(path_va)?;
 
     let c_str = CStr::from_ptr(path_pa.as_raw());
     let path_length = c_str.as_bytes().len();
@@ -178,7 +182,10 @@ pub fn kcall_sbi_read(fd: usize, buf_ptr: *mut u8, length: usize) -> Result<usiz
             result = sbi_console_getchar();
             // info!("result is: {:#x}", result);
             if result == -1 {
-                schedule(RuntimeFlags::READY);
+                // no interrupt comes with the input here, so check it again on the next tick.
+                if !sleep_until(get_time_us() + USEC_PER_TICK) {
+                    return if cnt == 0 { Err(SysError::new(EINTR)) } else { Ok(cnt) };
+                }
                 continue;
             }
             break;
@@ -207,16 +214,21 @@ pub fn kcall_terminal_read(fd: usize, buf_ptr: usize, length: usize) -> Result<u
         return Err(SysError::new(EBADF));
     }
 
+    let terminal = lookup_service(TERMINAL_SERVICE)?;
     let mut message = Msg::empty();
     let cur_pid = get_cur_task_in_this_hart().pid();
     message.src_pid = cur_pid;
     message.mtype = READ;
     message.args[DEVICE] = 0;
     message.args[PROC_NR] = cur_pid;
-    message.args[BUFFER] = buf_ptr;
+    let grant_id = create_grant_for_cur_task(terminal, buf_ptr, length, GrantFlags::WRITE)?;
+    message.args[BUFFER] = grant_id;
     message.args[LENGTH] = length;
-    kcall_send(TERMINAL_PID, &message as *const _ as usize).unwrap();
-    kcall_receive(TERMINAL_PID as isize, &mut message as *mut _ as usize).unwrap();
+    message.args[CALLER_PID] = cur_pid;
+    let result = kcall_sendrec(terminal, &mut message as *mut _ as usize);
+    // the table may have been cleared meanwhile, e.g. when the task is exiting.
+    let _ = revoke_grant_for_cur_task(grant_id);
+    result?;
 
     Ok(message.args[REPLY_STATUS])
 }
@@ -226,37 +238,43 @@ pub fn kcall_terminal_write(fd: usize, buf_ptr: usize, length: usize) -> Result<
         return Err(SysError::new(EBADF));
     }
 
+    let terminal = lookup_service(TERMINAL_SERVICE)?;
     let mut message = Msg::empty();
     let cur_pid = get_cur_task_in_this_hart().pid();
     message.src_pid = cur_pid;
     message.mtype = WRITE;
     message.args[DEVICE] = 0;
     message.args[PROC_NR] = cur_pid;
-    message.args[BUFFER] = buf_ptr;
+    let grant_id = create_grant_for_cur_task(terminal, buf_ptr, length, GrantFlags::READ)?;
+    message.args[BUFFER] = grant_id;
     message.args[LENGTH] = length;
-    kcall_send(TERMINAL_PID, &message as *const _ as usize).unwrap();
-    kcall_receive(TERMINAL_PID as isize, &mut message as *mut _ as usize).unwrap();
+    message.args[CALLER_PID] = cur_pid;
+    let result = kcall_sendrec(terminal, &mut message as *mut _ as usize);
+    // the table may have been cleared meanwhile, e.g. when the task is exiting.
+    let _ = revoke_grant_for_cur_task(grant_id);
+    result?;
 
     Ok(message.args[REPLY_STATUS])
 }
 
+#[cfg(feature = "board_k210")]
 fn get_byte_slice_in_proc(pid: usize, ptr: usize, length: usize) -> Result<&'static [u8], SysError> {
     let task = get_task_by_pid(pid).ok_or(SysError::new(EINVAL))?;
     let task_inner = task.acquire_inner_lock();
     let ptr_va = VirtualAddress::new(ptr);
-    let ptr_pa = task_inner.mem_manager.page_table.translate_va(ptr_va)
-        .ok_or(SysError::new(EFAULT))?;
+    let ptr_pa = task_inner.mem_manager.lock().translate_for_read(ptr_va)?;
     unsafe {
         Ok(core::slice::from_raw_parts(ptr_pa.as_raw(), length))
     }
 }
 
+/// The page is copied first if it is shared copy-on-write.
+#[cfg(feature = "board_k210")]
 fn get_mut_byte_slice_in_proc(pid: usize, ptr: usize, length: usize) -> Result<&'static mut [u8], SysError> {
     let task = get_task_by_pid(pid).ok_or(SysError::new(EINVAL))?;
     let task_inner = task.acquire_inner_lock();
     let ptr_va = VirtualAddress::new(ptr);
-    let ptr_pa = task_inner.mem_manager.page_table.translate_va(ptr_va)
-        .ok_or(SysError::new(EFAULT))?;
+    let ptr_pa = task_inner.mem_manager.lock().translate_for_write(ptr_va)?;
     unsafe {
         Ok(core::slice::from_raw_parts_mut(ptr_pa.as_raw_mut(), length))
     }
@@ -1,19 +1,19 @@
 use crate::mm::address::{VirtualAddress, ceil};
-use crate::processor::get_cur_task_in_this_hart;
-use crate::config::{MMAP_START_ADDRESS, FRAME_SIZE};
+use crate::processor::{get_cur_task_in_this_hart, flush_tlb_of_other_harts};
+use crate::config::{MMAP_START_ADDRESS, FRAME_SIZE, MAX_USER_ADDRESS};
 use crate::mm::memory_manager::{RegionFlags, RegionType};
-use share::syscall::error::{SysError, ENOMEM};
-use share::mmap::{Prot, MMAPFlags};
-use crate::syscall::file::{do_lseek, do_read};
-use alloc::vec::Vec;
-use alloc::vec;
+use share::syscall::error::{SysError, ENOMEM, EINVAL, EPERM, EACCES};
+use share::resource::{Rlimit, RLIMIT_STACK};
+use share::mmap::{Prot, MMAPFlags, MremapFlags, MsyncFlags};
+use crate::mm::page_cache::{SharedFile, sync_shared_files};
 
 pub fn do_brk(new_brk: usize) -> Result<usize, SysError> {
     let mut new_brk = VirtualAddress::new(new_brk);
 
     let cur_task = get_cur_task_in_this_hart();
-    let mut inner = cur_task.acquire_inner_lock();
-    let mut brk = inner.mem_manager.brk;
+    let inner = cur_task.acquire_inner_lock();
+    let mut mem_manager = inner.mem_manager.lock();
+    let mut brk = mem_manager.brk;
     let mut size = new_brk.0.abs_diff(brk.0);
 
     if new_brk.0 == 0 {
@@ -27,67 +27,216 @@ pub fn do_brk(new_brk: usize) -> Result<usize, SysError> {
 
         if ! brk.is_aligned() {
             if size <= (FRAME_SIZE - brk.offset()) {
-                inner.mem_manager.brk = new_brk;
+                mem_manager.brk = new_brk;
                 return Ok(new_brk.0);
             }
             size -= FRAME_SIZE - brk.offset();
             brk = brk.ceil().into();
         }
-        inner.mem_manager.add_area(
+        mem_manager.add_area(
             brk, ceil(size),
             RegionFlags::W | RegionFlags::R, RegionType::Default, None
         )?;
     } else { // dealloc
-        let brk_start = inner.mem_manager.brk_start;
+        let brk_start = mem_manager.brk_start;
         if new_brk < brk_start {
             return Ok(0);
         }
         size += new_brk.offset();
         new_brk = new_brk.floor().into();
-        inner.mem_manager.delete_area(new_brk, ceil(size));
+        mem_manager.delete_area(new_brk, ceil(size));
     }
-    inner.mem_manager.brk = new_brk;
+    mem_manager.brk = new_brk;
     Ok(new_brk.0)
 }
 
+/// A file is mapped through its page cache, whose pages are read in from fs server when they are
+/// touched. A shared mapping writes to the page cache, and a private one gets its own copies.
 pub fn do_mmap(start: usize, len: usize, prot: u32, flags: u32, fd: usize, offset: usize) -> Result<usize, SysError> {
     let prot = Prot::from_bits(prot).unwrap();
     let flags = MMAPFlags::from_bits(flags).unwrap();
-    let data: Vec<u8>;
+    let region_flags = region_flags_of(prot);
+    if len == 0 || len > MAX_USER_ADDRESS {
+        return Err(SysError::new(if len == 0 { EINVAL } else { ENOMEM }));
+    }
+    let size = ceil(len);
+    let start = if start == 0 { None } else { Some(VirtualAddress::new(start)) };
 
-    if flags.contains(MMAPFlags::SHARED) | flags.contains(MMAPFlags::PRIVATE) {
-        do_lseek(fd, offset, 0)?;
-        data = vec![0; len];
-        do_read(fd,data.as_ptr() as usize, len)?;
-    } else { // ANONYMOUS
-        data = Vec::new();
+    if flags.contains(MMAPFlags::ANONYMOUS) {
+        let cur_task = get_cur_task_in_this_hart();
+        let inner = cur_task.acquire_inner_lock();
+        let mut mem_manager = inner.mem_manager.lock();
+        let return_addr = match start {
+            Some(start) => {
+                mem_manager.add_area(start, size, region_flags, RegionType::Default, None)?;
+                start
+            }
+            None => mem_manager.alloc_area(size, region_flags, RegionType::Default, None)?,
+        };
+        return Ok(return_addr.0);
     }
 
-    let mut region_flags = RegionFlags::empty();
-    if prot.contains(Prot::READ) { region_flags |= RegionFlags::R };
-    if prot.contains(Prot::WRITE) { region_flags |= RegionFlags::W };
-    if prot.contains(Prot::EXEC) { region_flags |= RegionFlags::X };
+    if offset % FRAME_SIZE != 0 {
+        return Err(SysError::new(EINVAL));
+    }
+    // fs server is talked to before the locks are taken.
+    let (file, writable) = SharedFile::open(fd)?;
+    let region_type = if flags.contains(MMAPFlags::SHARED) { RegionType::Shared } else { RegionType::Default };
+    if region_type == RegionType::Shared && region_flags.contains(RegionFlags::W) && !writable {
+        return Err(SysError::new(EACCES));
+    }
+
+    let cur_task = get_cur_task_in_this_hart();
+    let inner = cur_task.acquire_inner_lock();
+    let mut mem_manager = inner.mem_manager.lock();
+    let return_addr = mem_manager.map_file(start, size, region_flags, region_type, file, offset, writable)?;
+
+    Ok(return_addr.0)
+}
 
-    let region_type;
-    if flags.contains(MMAPFlags::SHARED) {
-        region_type = RegionType::Shared(fd, offset, len);
-    } else {
-        region_type = RegionType::Default;
+/// Only `RLIMIT_STACK` is supported.
+pub fn do_getrlimit(resource: usize, rlimit_ptr: usize) -> Result<usize, SysError> {
+    if resource != RLIMIT_STACK {
+        return Err(SysError::new(EINVAL));
+    }
+    let cur_task = get_cur_task_in_this_hart();
+    let stack_limit = cur_task.acquire_inner_lock().mem_manager.lock().stack_limit;
+    unsafe {
+        (rlimit_ptr as *mut Rlimit).write(stack_limit);
     }
 
+    Ok(0)
+}
+
+/// The hard limit can't be raised. A soft limit below the current size of the stack only stops it
+/// from growing.
+pub fn do_setrlimit(resource: usize, rlimit_ptr: usize) -> Result<usize, SysError> {
+    if resource != RLIMIT_STACK {
+        return Err(SysError::new(EINVAL));
+    }
+    let new_limit = unsafe { (rlimit_ptr as *const Rlimit).read() };
+    if new_limit.cur > new_limit.max {
+        return Err(SysError::new(EINVAL));
+    }
     let cur_task = get_cur_task_in_this_hart();
-    let mut inner = cur_task.acquire_inner_lock();
-    let size = ceil(len);
-    let mut return_addr = VirtualAddress::new(0);
-    if start == 0 {
-        return_addr = inner.mem_manager.alloc_area(size,  region_flags, region_type, Some(data.as_slice()))?;
-    } else {
-        inner.mem_manager.add_area(VirtualAddress::new(start), size, region_flags, region_type, Some(data.as_slice()))?;
+    let inner = cur_task.acquire_inner_lock();
+    let mut mem_manager = inner.mem_manager.lock();
+    if new_limit.max > mem_manager.stack_limit.max {
+        return Err(SysError::new(EPERM));
     }
+    mem_manager.stack_limit = new_limit;
 
-    Ok(return_addr.0)
+    Ok(0)
 }
 
+/// The range may cover several mappings and the holes between them. The files mapped shared in
+/// the range are written back first.
 pub fn do_munmap(start: usize, len: usize) -> Result<usize, SysError> {
+    let start = VirtualAddress::new(start);
+    if !start.is_aligned() || len == 0 {
+        return Err(SysError::new(EINVAL));
+    }
+    let size = user_range_size(start, len)?;
+    sync_shared_files(start, size)?;
+
+    let cur_task = get_cur_task_in_this_hart();
+    let inner = cur_task.acquire_inner_lock();
+    let mut mem_manager = inner.mem_manager.lock();
+    if mem_manager.unmap_range(start, size) {
+        let satp = mem_manager.page_table.satp();
+        drop(mem_manager);
+        drop(inner);
+        flush_tlb_of_other_harts(satp, true);
+    }
+
+    Ok(0)
+}
+
+/// The dirty pages of the files mapped shared in the range are written back before it returns,
+/// whatever `flags` is.
+pub fn do_msync(start: usize, len: usize, flags: u32) -> Result<usize, SysError> {
+    let start = VirtualAddress::new(start);
+    MsyncFlags::from_bits(flags).ok_or(SysError::new(EINVAL))?;
+    if !start.is_aligned() {
+        return Err(SysError::new(EINVAL));
+    }
+    if len == 0 {
+        return Ok(0);
+    }
+    let size = user_range_size(start, len).map_err(|_| SysError::new(ENOMEM))?;
+
+    let cur_task = get_cur_task_in_this_hart();
+    if !cur_task.acquire_inner_lock().mem_manager.lock().region_list.is_region_exists(start, size) {
+        return Err(SysError::new(ENOMEM));
+    }
+    sync_shared_files(start, size)?;
+
+    Ok(0)
+}
+
+pub fn do_mprotect(start: usize, len: usize, prot: u32) -> Result<usize, SysError> {
+    let start = VirtualAddress::new(start);
+    let prot = Prot::from_bits(prot).ok_or(SysError::new(EINVAL))?;
+    if !start.is_aligned() {
+        return Err(SysError::new(EINVAL));
+    }
+    if len == 0 {
+        return Ok(0);
+    }
+    let size = user_range_size(start, len)?;
+
+    let cur_task = get_cur_task_in_this_hart();
+    let inner = cur_task.acquire_inner_lock();
+    let mut mem_manager = inner.mem_manager.lock();
+    mem_manager.protect_area(start, size, region_flags_of(prot))?;
+    let satp = mem_manager.page_table.satp();
+    drop(mem_manager);
+    drop(inner);
+    flush_tlb_of_other_harts(satp, true);
+
     Ok(0)
+}
+
+/// Only private mappings can be resized, a mapping is moved only if `MremapFlags::MAYMOVE` is set.
+pub fn do_mremap(old_start: usize, old_len: usize, new_len: usize, flags: u32) -> Result<usize, SysError> {
+    let old_start = VirtualAddress::new(old_start);
+    let flags = MremapFlags::from_bits(flags).ok_or(SysError::new(EINVAL))?;
+    if !old_start.is_aligned() || old_len == 0 || new_len == 0 {
+        return Err(SysError::new(EINVAL));
+    }
+    let old_size = user_range_size(old_start, old_len)?;
+    // no mapping that large fits in the user space.
+    if new_len > MAX_USER_ADDRESS {
+        return Err(SysError::new(ENOMEM));
+    }
+
+    let cur_task = get_cur_task_in_this_hart();
+    let inner = cur_task.acquire_inner_lock();
+    let mut mem_manager = inner.mem_manager.lock();
+    let new_start = mem_manager.remap_area(
+        old_start, old_size, ceil(new_len), flags.contains(MremapFlags::MAYMOVE)
+    )?;
+    let satp = mem_manager.page_table.satp();
+    drop(mem_manager);
+    drop(inner);
+    flush_tlb_of_other_harts(satp, true);
+
+    Ok(new_start.0)
+}
+
+/// Round `len` up to whole pages. EINVAL is returned if the range at the aligned `start` goes
+/// beyond the user space, including a `len` so large that the rounding would wrap around.
+fn user_range_size(start: VirtualAddress, len: usize) -> Result<usize, SysError> {
+    match start.0.checked_add(len) {
+        Some(end) if end <= MAX_USER_ADDRESS => Ok(ceil(len)),
+        _ => Err(SysError::new(EINVAL)),
+    }
+}
+
+fn region_flags_of(prot: Prot) -> RegionFlags {
+    let mut region_flags = RegionFlags::empty();
+    if prot.contains(Prot::READ) { region_flags |= RegionFlags::R };
+    if prot.contains(Prot::WRITE) { region_flags |= RegionFlags::W };
+    if prot.contains(Prot::EXEC) { region_flags |= RegionFlags::X };
+    region_flags
 }
\ No newline at end of file
@@ -1,30 +1,72 @@
 pub(crate) mod file;
 mod ipc;
+mod grant;
+mod futex;
 mod kcall;
 mod mm;
 mod proc;
+mod registry;
+mod shm;
 mod time;
 
 use crate::mm::available_frame;
+use crate::mm::address::VirtualAddress;
 use crate::syscall::file::*;
-use crate::syscall::ipc::{kcall_receive, kcall_send};
+use crate::syscall::ipc::{kcall_receive, kcall_send, kcall_send_nb, kcall_receive_nb, kcall_send_timeout, kcall_receive_timeout, kcall_sendrec, kcall_send_fast, kcall_receive_fast, kcall_notify};
 use crate::syscall::kcall::*;
-use crate::syscall::mm::{do_brk, do_mmap, do_munmap};
+use crate::syscall::grant::{kcall_grant_create, kcall_grant_revoke, kcall_safecopy_from, kcall_safecopy_to};
+use crate::syscall::mm::{do_brk, do_mmap, do_munmap, do_mprotect, do_mremap, do_msync, do_getrlimit, do_setrlimit};
 use crate::syscall::proc::*;
+use crate::syscall::registry::{kcall_publish, kcall_lookup, endpoint_to_pid};
+use crate::syscall::shm::{kcall_shm_create, kcall_shm_map, kcall_shm_unmap};
+use crate::syscall::futex::do_futex;
 use crate::syscall::time::do_get_time;
-use share::syscall::error::{SysError, EUNKOWN};
+use share::syscall::error::{SysError, EUNKOWN, EPERM, ESRCH};
+use crate::processor::get_cur_task_in_this_hart;
+use crate::task::{get_task_by_pid, SERVICE_REGISTRY};
 use share::syscall::sys_const::*;
 
-pub use ipc::notify;
-pub use proc::{MAX_PRIORITY, MIN_PRIORITY};
+pub use ipc::notify_irq;
+pub use registry::lookup_service;
+pub use proc::{MAX_PRIORITY, MIN_PRIORITY, handle_signals};
 
 use self::time::{do_get_time_of_day, do_nanosleep};
-use share::time::Timespec;
+use crate::mm::page_cache::read_in_user_range;
+use core::mem::size_of;
+use share::ipc::Msg;
+use share::futex::{FUTEX_WAIT, FUTEX_PRIVATE_FLAG};
+use share::signal::{SigAction, SigSet};
+use share::system::Utsname;
+use share::resource::Rlimit;
+use share::time::{Timespec, Rusage};
+use share::mmap::MapEntry;
 
 pub fn syscall(syscall_id: usize, args: [usize; 6]) -> usize {
+    if let Err(err) = check_privilege(syscall_id, &args) {
+        return SysError::mux(Err(err));
+    }
+    read_in_user_args(syscall_id, &args);
+
     let result: Result<usize, SysError> = match syscall_id {
         KCALL_SEND => kcall_send(args[0], args[1]),
         KCALL_RECEIVE => kcall_receive(args[0] as isize, args[1]),
+        KCALL_SEND_NB => kcall_send_nb(args[0], args[1]),
+        KCALL_RECEIVE_NB => kcall_receive_nb(args[0] as isize, args[1]),
+        KCALL_SEND_TIMEOUT => kcall_send_timeout(args[0], args[1], args[2]),
+        KCALL_RECEIVE_TIMEOUT => kcall_receive_timeout(args[0] as isize, args[1], args[2]),
+        KCALL_SENDREC => kcall_sendrec(args[0], args[1]),
+        KCALL_SEND_FAST => kcall_send_fast(args[0]),
+        KCALL_RECEIVE_FAST => kcall_receive_fast(args[0] as isize),
+        KCALL_NOTIFY => kcall_notify(args[0]),
+        KCALL_GRANT_CREATE => kcall_grant_create(args[0], args[1], args[2], args[3]),
+        KCALL_GRANT_REVOKE => kcall_grant_revoke(args[0]),
+        KCALL_SAFECOPY_FROM => kcall_safecopy_from(args[0], args[1], args[2], args[3], args[4]),
+        KCALL_SAFECOPY_TO => kcall_safecopy_to(args[0], args[1], args[2], args[3], args[4]),
+        KCALL_SHM_CREATE => kcall_shm_create(args[0], args[1]),
+        KCALL_SHM_MAP => kcall_shm_map(args[0], args[1], args[2]),
+        KCALL_SHM_UNMAP => kcall_shm_unmap(args[0]),
+        KCALL_PUBLISH => kcall_publish(args[0], args[1]),
+        KCALL_LOOKUP => kcall_lookup(args[0], args[1]),
 
         KCALL_READ_DEV => kcall_read_dev(args[0], args[1]),
         KCALL_WRITE_DEV => kcall_write_dev(args[0], args[1], args[2]),
@@ -58,16 +100,30 @@ pub fn syscall(syscall_id: usize, args: [usize; 6]) -> usize {
         SYSCALL_UNLINK => do_unlink(args[0]),
         SYSCALL_RMDIR => do_rmdir(args[0]),
         SYSCALL_EXIT => do_exit(args[0] as isize),
+        SYSCALL_EXIT_GROUP => do_exit_group(args[0] as isize),
+        SYSCALL_FUTEX => do_futex(args[0], args[1], args[2], args[3], args[4]),
         SYSCALL_YIELD => do_yield(),
+        SYSCALL_KILL => do_kill(args[0] as isize, args[1]),
+        SYSCALL_SIGACTION => do_sigaction(args[0], args[1], args[2]),
+        SYSCALL_SIGPROCMASK => do_sigprocmask(args[0], args[1], args[2]),
+        SYSCALL_SIGRETURN => do_sigreturn(),
         SYSCALL_GET_PRIORITY => do_get_priority(args[0], args[1]),
         SYSCALL_SET_PRIORITY => do_set_priority(args[0], args[1], args[2] as isize),
+        SYSCALL_SETPGID => do_setpgid(args[0], args[1] as isize),
+        SYSCALL_GETPGID => do_getpgid(args[0]),
+        SYSCALL_GETSID => do_getsid(args[0]),
+        SYSCALL_SETSID => do_setsid(),
         SYSCALL_UNAME => do_uname(args[0]),
+        SYSCALL_GETRLIMIT => do_getrlimit(args[0], args[1]),
+        SYSCALL_SETRLIMIT => do_setrlimit(args[0], args[1]),
         SYSCALL_GET_TIME => do_get_time_of_day(args[0] as *mut Timespec),
-        SYSCALL_NANOSLEEP => do_nanosleep(args[0] as *mut Timespec, args[1] as *mut Timespec),
+        SYSCALL_NANOSLEEP => do_nanosleep(args[0] as *const Timespec, args[1] as *mut Timespec),
         SYSCALL_GETPID => do_get_pid(),
         SYSCALL_GETPPID => do_get_ppid(),
+        SYSCALL_GETTID => do_get_tid(),
         SYSCALL_BRK => do_brk(args[0]),
         SYSCALL_MUNMAP => do_munmap(args[0], args[1]),
+        SYSCALL_MREMAP => do_mremap(args[0], args[1], args[2], args[3] as u32),
         SYSCALL_FORK => do_fork(args[0] as u32, args[1], args[2], args[3], args[4]),
         SYSCALL_EXEC => do_exec(
             args[0],
@@ -82,11 +138,14 @@ pub fn syscall(syscall_id: usize, args: [usize; 6]) -> usize {
             args[4],
             args[5],
         ),
-        SYSCALL_WAITPID => do_waitpid(args[0] as isize, args[1], args[2]),
+        SYSCALL_MPROTECT => do_mprotect(args[0], args[1], args[2] as u32),
+        SYSCALL_MSYNC => do_msync(args[0], args[1], args[2] as u32),
+        SYSCALL_WAITPID => do_waitpid(args[0] as isize, args[1], args[2], args[3]),
 
         SYSCALL_TEST => do_test(),
 
         DEBUG_FRAME_USAGE => debug_frame_usage(),
+        DEBUG_MEMORY_MAP => debug_memory_map(args[0], args[1], args[2]),
 
         _ => Err(SysError::new(EUNKOWN)),
     };
@@ -94,6 +153,57 @@ pub fn syscall(syscall_id: usize, args: [usize; 6]) -> usize {
     SysError::mux(result)
 }
 
+/// Reject the kcalls and ipc destinations which are not allowed by the [`Privilege`](crate::task::Privilege)
+/// of current task.
+fn check_privilege(syscall_id: usize, args: &[usize; 6]) -> Result<(), SysError> {
+    let privilege = get_cur_task_in_this_hart().acquire_inner_lock().privilege;
+    if !privilege.allows_kcall(syscall_id) {
+        return Err(SysError::new(EPERM));
+    }
+
+    match syscall_id {
+        KCALL_SEND | KCALL_SEND_NB | KCALL_SEND_TIMEOUT | KCALL_SENDREC | KCALL_SEND_FAST | KCALL_NOTIFY => {
+            let dst_pid = endpoint_to_pid(args[0])?;
+            if let Some(dst_task) = get_task_by_pid(dst_pid) {
+                let dst_is_system = dst_task.acquire_inner_lock().privilege.is_system;
+                if !privilege.allows_ipc_to(dst_pid, dst_is_system, &SERVICE_REGISTRY.lock()) {
+                    return Err(SysError::new(EPERM));
+                }
+            }
+            Ok(())
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Read in the pages of shared file mappings which the kernel accesses directly for the syscall.
+/// A page fault taken by the kernel is handled with locks held, when nothing can be read in from fs
+/// server any more.
+fn read_in_user_args(syscall_id: usize, args: &[usize; 6]) {
+    let ranges: [(usize, usize); 2] = match syscall_id {
+        KCALL_SEND | KCALL_RECEIVE | KCALL_SEND_NB | KCALL_RECEIVE_NB | KCALL_SEND_TIMEOUT
+        | KCALL_RECEIVE_TIMEOUT | KCALL_SENDREC => [(args[1], size_of::<Msg>()), (0, 0)],
+        KCALL_PUBLISH | KCALL_LOOKUP => [(args[0], args[1]), (0, 0)],
+        KCALL_COPY_C_PATH => [(args[2], args[3]), (0, 0)],
+        KCALL_SBI_READ | KCALL_SBI_WRITE => [(args[1], args[2]), (0, 0)],
+        SYSCALL_FUTEX if args[1] & !FUTEX_PRIVATE_FLAG == FUTEX_WAIT =>
+            [(args[0], size_of::<u32>()), (args[3], size_of::<Timespec>())],
+        SYSCALL_SIGACTION => [(args[1], size_of::<SigAction>()), (args[2], size_of::<SigAction>())],
+        SYSCALL_SIGPROCMASK => [(args[1], size_of::<SigSet>()), (args[2], size_of::<SigSet>())],
+        SYSCALL_UNAME => [(args[0], size_of::<Utsname>()), (0, 0)],
+        SYSCALL_GETRLIMIT | SYSCALL_SETRLIMIT => [(args[1], size_of::<Rlimit>()), (0, 0)],
+        SYSCALL_GET_TIME => [(args[0], size_of::<Timespec>()), (0, 0)],
+        SYSCALL_NANOSLEEP => [(args[0], size_of::<Timespec>()), (args[1], size_of::<Timespec>())],
+        SYSCALL_WAITPID => [(args[1], size_of::<isize>()), (args[3], size_of::<Rusage>())],
+        _ => return,
+    };
+    for (start, len) in ranges.iter() {
+        if *start != 0 {
+            read_in_user_range(*start, *len);
+        }
+    }
+}
+
 pub fn do_test() -> Result<usize, SysError> {
     unimplemented!();
 }
@@ -101,3 +211,26 @@ pub fn do_test() -> Result<usize, SysError> {
 pub fn debug_frame_usage() -> Result<usize, SysError> {
     Ok(available_frame())
 }
+
+/// Copy at most `len` entries of the memory map of task `pid`(0 for current task) into `buf_ptr`,
+/// and return the number of all entries. Only system processes may look into other processes.
+pub fn debug_memory_map(pid: usize, buf_ptr: usize, len: usize) -> Result<usize, SysError> {
+    let cur_task = get_cur_task_in_this_hart();
+    let task = match pid {
+        0 => cur_task.clone(),
+        pid => get_task_by_pid(pid).ok_or(SysError::new(ESRCH))?,
+    };
+    if task.tgid() != cur_task.tgid() && !cur_task.acquire_inner_lock().privilege.is_system {
+        return Err(SysError::new(EPERM));
+    }
+    let memory_map = task.acquire_inner_lock().mem_manager.lock().memory_map();
+    drop(task);
+
+    let count = usize::min(len, memory_map.len());
+    let entries = unsafe {
+        core::slice::from_raw_parts(memory_map.as_ptr() as *const u8, count * size_of::<MapEntry>())
+    };
+    cur_task.acquire_inner_lock().mem_manager.lock().write_bytes(VirtualAddress::new(buf_ptr), entries)?;
+
+    Ok(memory_map.len())
+}
@@ -1,32 +1,44 @@
 use share::syscall::error::SysError;
 use crate::task::TrapContext;
-use crate::processor::get_cur_task_in_this_hart;
+use crate::processor::{get_cur_task_in_this_hart, set_cur_mm_in_this_hart};
 use crate::mm::memory_manager::MemoryManager;
 use core::arch::asm;
 use alloc::vec::Vec;
 use crate::mm::page_table::PageTable;
 use share::ffi::{CString, CStrArray, CStr};
-use crate::syscall::file::{do_open, do_read, do_fstat, do_close};
+use crate::syscall::file::{do_open, do_close, read_into_kernel, fstat_into_kernel};
 use share::file::{OpenFlag, Stat, AT_FD_CWD};
 use alloc::vec;
+use alloc::sync::Arc;
+use spin::Mutex;
+use crate::syscall::proc::kill_other_threads;
+use crate::mm::page_cache::{sync_shared_files, read_in_user_range};
+use crate::mm::address::VirtualAddress;
+use crate::config::{MAX_USER_ADDRESS, FRAME_SIZE};
 
 pub fn do_exec(path_ptr: usize, argv: *const *const u8, envp: *const *const u8) -> Result<usize, SysError> {
-    // read file data from fs server.
-    let path_cstr = CStr::from_ptr(path_ptr as *const _);
-    let path_cstring = CString::from(path_cstr);
+    // read file data from fs server, the path is copied by fs server from current task.
     let open_flag = OpenFlag::RDONLY;
-    let fd = do_open( AT_FD_CWD as usize,path_cstring.as_ptr() as usize, open_flag.bits() as usize, 0)?;
-    let stat = Stat::empty();
-    do_fstat(fd, &stat as *const _ as usize)?;
-    let data_buffer = vec![0; stat.size as usize];
-    do_read(fd, data_buffer.as_ptr() as usize, stat.size as usize)?;
+    let fd = do_open(AT_FD_CWD as usize, path_ptr, open_flag.bits() as usize, 0)?;
+    let mut stat = Stat::empty();
+    fstat_into_kernel(fd, &mut stat)?;
+    let mut data_buffer = vec![0; stat.size as usize];
+    read_into_kernel(fd, data_buffer.as_mut_slice())?;
     do_close(fd)?;
     let data = data_buffer.as_slice();
 
-    // create new address space.
-    let (mem_manager, pc, user_sp) = MemoryManager::new(data)?;
+    // the dirty pages of the files mapped shared are lost with the old address space.
+    sync_shared_files(VirtualAddress::new(0), MAX_USER_ADDRESS)?;
+    // create new address space, the other threads are gone with the old one.
+    let (mut mem_manager, pc, user_sp) = MemoryManager::new(data)?;
+    // resource limits are kept across exec.
+    mem_manager.stack_limit = get_cur_task_in_this_hart().acquire_inner_lock().mem_manager.lock().stack_limit;
+    let mem_manager = Arc::new(Mutex::new(mem_manager));
+    kill_other_threads();
     let (arg_vec, env_vec) = read_arg_and_env_in_current_addr_space(argv, envp);
-    switch_to_new_addr_space(&mem_manager.page_table);
+    switch_to_new_addr_space(&mem_manager.lock().page_table);
+    // the pages of the new stack are backed when they are touched, by the new address space.
+    set_cur_mm_in_this_hart(mem_manager.clone());
     let user_sp = push_arg_and_env_onto_stack(arg_vec, env_vec, user_sp);
 
     modify_current_task_struct(mem_manager, pc, user_sp);
@@ -37,6 +49,8 @@ pub fn do_exec(path_ptr: usize, argv: *const *const u8, envp: *const *const u8)
 
 fn read_arg_and_env_in_current_addr_space(argv_ptr: *const *const u8, envp_ptr: *const *const u8)
                                           -> (Vec<CString>, Vec<CString>) {
+    read_in_str_array(argv_ptr);
+    read_in_str_array(envp_ptr);
     let arg_cstring_vec = get_cstring_vec_from_str_array_ptr(argv_ptr);
     let env_cstring_vec = get_cstring_vec_from_str_array_ptr(envp_ptr);
 
@@ -68,12 +82,15 @@ fn push_arg_and_env_onto_stack(arg_vec: Vec<CString>, env_vec: Vec<CString>, mut
     user_sp
 }
 
-fn modify_current_task_struct(mem_manager: MemoryManager,pc: usize, user_sp: usize) {
+fn modify_current_task_struct(mem_manager: Arc<Mutex<MemoryManager>>, pc: usize, user_sp: usize) {
     let cur_task = get_cur_task_in_this_hart();
     let mut inner = cur_task.acquire_inner_lock();
     let trap_context_ref = inner.trap_context_ref();
     *trap_context_ref = TrapContext::new(pc, user_sp);
     inner.mem_manager = mem_manager;
+    inner.grants.clear(); // grants refer to the old address space.
+    inner.privilege = inner.privilege.reduced();
+    inner.signals.exec();
 }
 
 fn clear_i_cache() {
@@ -92,6 +109,27 @@ fn get_cstring_vec_from_str_array_ptr(str_array_ptr: *const *const u8) -> Vec<CS
     vec
 }
 
+/// Read in the pages of shared file mappings which hold the array or its strings, since they are
+/// copied by the kernel directly.
+fn read_in_str_array(str_array_ptr: *const *const u8) {
+    let mut slot = str_array_ptr as usize;
+    loop {
+        read_in_user_range(slot, core::mem::size_of::<usize>());
+        let mut ptr = unsafe { (slot as *const usize).read() };
+        if ptr == 0 {
+            break;
+        }
+        read_in_user_range(ptr, 1);
+        while unsafe { (ptr as *const u8).read() } != 0 {
+            ptr += 1;
+            if ptr % FRAME_SIZE == 0 {
+                read_in_user_range(ptr, 1);
+            }
+        }
+        slot += core::mem::size_of::<usize>();
+    }
+}
+
 /// return a vector containing pointers, each of them points to the first byte of a str.
 unsafe fn push_str_vector_onto_stack_in_c_style(vec: Vec<CString>, sp: &mut usize) -> Vec<usize> {
     let mut ptr_vec = Vec::new();
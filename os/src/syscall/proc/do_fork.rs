@@ -1,20 +1,43 @@
 use alloc::sync::Arc;
-use crate::task::{TaskStruct, add_a_task_to_manager, KernelStack, RuntimeFlags, TrapContext, TaskContext, alloc_pid, TaskStructInner};
-use crate::processor::get_cur_task_in_this_hart;
-use share::syscall::error::{SysError, EAGAIN};
+use crate::task::{TaskStruct, add_a_task_to_manager, KernelStack, RuntimeFlags, TrapContext, TaskContext, alloc_pid, TaskStructInner, PendingNotifications, GrantTable, Rusage};
+use crate::processor::{get_cur_task_in_this_hart, get_hart_id, flush_tlb_of_other_harts};
+use crate::mm::address::VirtualAddress;
+use share::syscall::error::{SysError, EAGAIN, EINVAL};
+use share::clone::CloneFlags;
 use alloc::vec::Vec;
 use spin::Mutex;
+use core::sync::atomic::{AtomicBool, AtomicUsize};
 use crate::syscall::ipc::kcall_send;
-use share::ipc::{Msg, FORK_PARENT, FORK_CHILD, FS_PID, FORK};
+use crate::syscall::registry::lookup_service;
+use share::ipc::{Msg, FORK_PARENT, FORK_CHILD, FS_SERVICE, FORK};
 
-#[allow(unused_variables)]
+/// Create a child task like linux clone, and return its tid.
+///
+/// The child shares the address space of the caller with `CLONE_VM`, otherwise it gets a copy.
+/// With `CLONE_THREAD` it joins the thread group of the caller: it is not a child of the caller,
+/// can't be waited and reports nothing to anyone when it exits. File descriptors are not shared,
+/// the fs server gives each child a copy. Faults when writing the tids are ignored like linux does.
 pub fn do_fork(flags: u32, stack: usize, ptid_ptr: usize, tls_ptr: usize, ctid_ptr: usize) -> Result<usize, SysError>{
+    let flags = CloneFlags::from_bits_truncate(flags);
+    if flags.contains(CloneFlags::THREAD) && !flags.contains(CloneFlags::VM) {
+        return Err(SysError::new(EINVAL));
+    }
     let cur_task = get_cur_task_in_this_hart();
 
-    let child_task = Arc::new(copy_process(flags, stack, ptid_ptr, tls_ptr, ctid_ptr, &cur_task)?);
+    let child_task = Arc::new(copy_process(flags, stack, tls_ptr, ctid_ptr, &cur_task)?);
     let mut inner = cur_task.acquire_inner_lock();
-    inner.children.push(Arc::clone(&child_task));
+    if !flags.contains(CloneFlags::THREAD) {
+        inner.children.push(Arc::clone(&child_task));
+    }
+    if flags.contains(CloneFlags::PARENT_SETTID) {
+        let _ = inner.mem_manager.lock().write_u32(VirtualAddress::new(ptid_ptr), child_task.pid() as u32);
+    }
+    let satp = inner.mem_manager.lock().page_table.satp();
     drop(inner);
+    if !flags.contains(CloneFlags::VM) {
+        // the other threads of the caller must not write to the frames shared with the child.
+        flush_tlb_of_other_harts(satp, true);
+    }
 
     let parent_pid = cur_task.pid();
     let child_pid = child_task.pid();
@@ -25,22 +48,33 @@ pub fn do_fork(flags: u32, stack: usize, ptid_ptr: usize, tls_ptr: usize, ctid_p
     message.mtype = FORK;
     message.args[FORK_PARENT] = parent_pid;
     message.args[FORK_CHILD] = child_pid;
-    kcall_send(FS_PID, &message as *const _ as usize)?;
+    kcall_send(lookup_service(FS_SERVICE)?, &message as *const _ as usize)?;
 
     Ok(child_pid)
 }
 
-#[allow(unused_variables)]
-fn copy_process(flags: u32, stack: usize, ptid_ptr: usize, tls_ptr: usize, ctid_ptr: usize, parent: &Arc<TaskStruct>) -> Result<TaskStruct, SysError>{
+fn copy_process(flags: CloneFlags, stack: usize, tls_ptr: usize, ctid_ptr: usize, parent: &Arc<TaskStruct>) -> Result<TaskStruct, SysError>{
     let mut parent_inner = parent.acquire_inner_lock();
 
-    let mem_manager = parent_inner.mem_manager.clone()?;
+    let mem_manager = if flags.contains(CloneFlags::VM) {
+        Arc::clone(&parent_inner.mem_manager)
+    } else {
+        Arc::new(Mutex::new(parent_inner.mem_manager.lock().clone()?))
+    };
 
     let pid_handle = alloc_pid();
     if pid_handle.is_none() {
         return Err(SysError::new(EAGAIN));
     }
-    let pid_handle = pid_handle.unwrap();
+    let pid_handle = Arc::new(pid_handle.unwrap());
+    let child_pid = pid_handle.0;
+
+    // a thread belongs to the same process, so its parent is the parent of the process.
+    let (tgid_handle, privilege, parent_task) = if flags.contains(CloneFlags::THREAD) {
+        (Arc::clone(&parent.tgid_handle), parent_inner.privilege, parent_inner.parent.clone())
+    } else {
+        (Arc::clone(&pid_handle), parent_inner.privilege.reduced(), Some(Arc::downgrade(parent)))
+    };
 
     let kernel_stack = KernelStack::new()?;
 
@@ -52,20 +86,46 @@ fn copy_process(flags: u32, stack: usize, ptid_ptr: usize, tls_ptr: usize, ctid_
         flag: RuntimeFlags::READY,
         task_context,
         message_holder: None,
-        interrupt_flag: false,
+        notifications: PendingNotifications::empty(),
+        ipc_error: None,
+        sendrec: false,
+        exiting: false,
+        grants: GrantTable::new(),
+        privilege,
+        signals: parent_inner.signals.fork(),
+        wait_event: None,
+        rusage: Rusage::new(),
+        clear_child_tid: if flags.contains(CloneFlags::CHILD_CLEARTID) { ctid_ptr } else { 0 },
+        pgrp_handle: Arc::clone(&parent_inner.pgrp_handle),
+        session_handle: Arc::clone(&parent_inner.session_handle),
         mem_manager,
-        priority: parent_inner.priority,
-        min_priority: parent_inner.min_priority,
         children: Vec::new(),
-        parent: Some(Arc::downgrade(parent))
+        zombies: Vec::new(),
+        parent: parent_task,
     };
+    if flags.contains(CloneFlags::CHILD_SETTID) {
+        let _ = child_inner.mem_manager.lock().write_u32(VirtualAddress::new(ctid_ptr), child_pid as u32);
+    }
 
     // push `trap_context` onto the `kernel_stack`
     let parent_trap_context_ref: &mut TrapContext = parent_inner.trap_context_ref();
     let mut child_trap_context = parent_trap_context_ref.clone();
     child_trap_context.x[10] = 0;
+    if stack != 0 {
+        child_trap_context.x[2] = stack;
+    }
+    if flags.contains(CloneFlags::SETTLS) {
+        child_trap_context.x[4] = tls_ptr;
+    }
     let child_trap_context_ref = child_inner.trap_context_ref();
     *child_trap_context_ref = child_trap_context;
 
-    Ok(TaskStruct {pid_handle, inner: Mutex::new(child_inner)})
-}
\ No newline at end of file
+    Ok(TaskStruct {
+        pid_handle,
+        tgid_handle,
+        on_cpu: AtomicBool::new(false),
+        hart: AtomicUsize::new(get_hart_id()),
+        sched: Mutex::new(parent.sched.lock().fork()),
+        inner: Mutex::new(child_inner),
+    })
+}
@@ -1,89 +1,92 @@
-use share::syscall::error::{SysError, ECHILD};
+use share::syscall::error::{SysError, ECHILD, EINVAL, EINTR};
+use share::wait::{WNOHANG, WUNTRACED, WCONTINUED, W_CONTINUED, w_stopcode};
+use share::time::Rusage;
 use crate::processor::get_cur_task_in_this_hart;
-use crate::task::{RuntimeFlags, schedule};
+use crate::task::{RuntimeFlags, TaskStructInner, WaitEvent, schedule};
 
-// TODO-FUTURE: implement WNOHANG, WUNTRACED and WCONTINUED for waitpid
-pub fn do_waitpid(pid: isize, status_ptr: usize, options: usize) -> Result<usize, SysError> {
-    let cur_task = get_cur_task_in_this_hart();
-    if cur_task.acquire_inner_lock().children.is_empty() {
-        return Err(SysError::new(ECHILD));
+/// Wait for a child to exit, or to stop and continue when `WUNTRACED` and `WCONTINUED` are given.
+///
+/// `pid` is -1 for any child, 0 for any child in the process group of the caller, `-pgid` for any
+/// child in group `pgid`, or the pid of a child. The caller is blocked in `WAITING` state until one
+/// of its children changes state, unless `WNOHANG` is given, in which case 0 is returned
+/// immediately. An exited child is reaped, its pid is freed and its resource usage is written to
+/// `rusage_ptr`.
+pub fn do_waitpid(pid: isize, status_ptr: usize, options: usize, rusage_ptr: usize) -> Result<usize, SysError> {
+    if options & !(WNOHANG | WUNTRACED | WCONTINUED) != 0 {
+        return Err(SysError::new(EINVAL));
     }
 
-    if pid == -1 {
-        wait_on_all_children(status_ptr, options)
-    } else {
-        wait_on_target_child(pid as usize, status_ptr, options)
-    }
-}
-
-fn wait_on_all_children(status_ptr: usize, _: usize) -> Result<usize, SysError> {
-    let cur_task = get_cur_task_in_this_hart();
-
     loop {
+        let cur_task = get_cur_task_in_this_hart();
         let mut inner = cur_task.acquire_inner_lock();
-        let mut exit_code = 0;
-        let mut pid = 0;
-        let result = inner.children.iter().enumerate().find(|(_, child)|{
-            let child_inner = child.acquire_inner_lock();
-            match child_inner.flag {
-                RuntimeFlags::ZOMBIE(exit) => {
-                    exit_code = exit;
-                    pid = child.pid();
-                    true
-                },
-                _ => false,
+        let pgid = inner.pgid();
+        let is_target = |child_pid: usize, child_pgid: usize| match pid {
+            -1 => true,
+            0 => child_pgid == pgid,
+            pid if pid > 0 => child_pid == pid as usize,
+            pid => child_pgid == -pid as usize,
+        };
+        if let Some(index) = inner.zombies.iter().position(|zombie| is_target(zombie.pid(), zombie.pgid())) {
+            let zombie = inner.zombies.remove(index);
+            if status_ptr != 0 {
+                write_wait_status(status_ptr, zombie.status);
+            }
+            if rusage_ptr != 0 {
+                unsafe { (rusage_ptr as *mut Rusage).write(zombie.rusage.to_user()) };
             }
-        });
-        if result.is_none() {
-            drop(inner);
-            schedule(RuntimeFlags::READY);
-            continue;
+            return Ok(zombie.pid());
         }
 
-        let (index,_) = result.unwrap();
-        if status_ptr != 0 {
-            write_exist_status(status_ptr, exit_code);
+        let mut has_child = false;
+        let mut result = None;
+        for child in inner.children.iter() {
+            let mut child_inner = child.acquire_inner_lock();
+            if !is_target(child.pid(), child_inner.pgid()) {
+                continue;
+            }
+            has_child = true;
+            if let Some(status) = take_wait_status(&mut child_inner, options) {
+                result = Some((child.pid(), status));
+                break;
+            }
         }
-        inner.children.remove(index);
-
-        return Ok(pid);
-    }
-}
-
-fn wait_on_target_child(pid: usize, status_ptr: usize, _: usize) -> Result<usize, SysError> {
-    let cur_task = get_cur_task_in_this_hart();
 
-    loop {
-        let mut inner = cur_task.acquire_inner_lock();
-        let result = inner.children.iter().enumerate().find(|(_, child)| {
-            child.pid() == pid as usize
-        });
-        if result.is_none() {
+        if let Some((child_pid, status)) = result {
+            if status_ptr != 0 {
+                write_wait_status(status_ptr, status);
+            }
+            return Ok(child_pid);
+        }
+        if !has_child {
             return Err(SysError::new(ECHILD));
         }
-
-        let (index, child) = result.unwrap();
-        let child_inner = child.acquire_inner_lock();
-        match child_inner.flag {
-            RuntimeFlags::ZOMBIE(exit_code) => {
-                if status_ptr != 0 {
-                    write_exist_status(status_ptr, exit_code);
-                }
-                drop(child_inner);
-                inner.children.remove(index);
-                return Ok(pid as usize);
-            },
-            _ => {
-                drop(child_inner);
-                drop(inner);
-                schedule(RuntimeFlags::READY);
-            },
+        if options & WNOHANG != 0 {
+            return Ok(0);
         }
+        if inner.signals.has_wanted_pending() {
+            return Err(SysError::new(EINTR));
+        }
+
+        inner.flag = RuntimeFlags::WAITING;
+        drop(inner);
+        drop(cur_task);
+        schedule(RuntimeFlags::WAITING);
     }
 }
 
-fn write_exist_status(status_ptr : usize, exit_code: isize) {
+/// Return the status of a child which has stopped or continued. Each event is only reported once.
+fn take_wait_status(child_inner: &mut TaskStructInner, options: usize) -> Option<isize> {
+    let status = match child_inner.wait_event {
+        Some(WaitEvent::Stopped(signo)) if options & WUNTRACED != 0 => w_stopcode(signo),
+        Some(WaitEvent::Continued) if options & WCONTINUED != 0 => W_CONTINUED,
+        _ => return None,
+    };
+    child_inner.wait_event = None;
+    Some(status as isize)
+}
+
+fn write_wait_status(status_ptr : usize, status: isize) {
     unsafe {
-        (status_ptr as *mut isize).write_volatile((exit_code & 0xff) << 8);
+        (status_ptr as *mut isize).write_volatile(status);
     }
-}
\ No newline at end of file
+}
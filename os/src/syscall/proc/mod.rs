@@ -3,34 +3,98 @@ mod do_exec;
 mod do_waitpid;
 mod priority;
 mod do_uname;
+mod signal;
+mod pgrp;
 
-use crate::task::{schedule, RuntimeFlags};
+use alloc::sync::Arc;
+use crate::task::{schedule, RuntimeFlags, get_all_tasks};
+use crate::mm::address::VirtualAddress;
+use crate::mm::page_cache::sync_shared_files;
+use crate::config::MAX_USER_ADDRESS;
 pub use do_fork::do_fork;
 pub use do_exec::do_exec;
 pub use do_waitpid::do_waitpid;
 pub use do_uname::do_uname;
+pub use signal::{do_kill, do_sigaction, do_sigprocmask, do_sigreturn, send_signal, handle_signals};
 pub use priority::*;
+pub use pgrp::{do_setpgid, do_getpgid, do_setsid, do_getsid};
 use share::syscall::error::SysError;
 use crate::processor::get_cur_task_in_this_hart;
-use share::ipc::{Msg, EXIT, EXIT_PID, FS_PID};
-use crate::syscall::ipc::kcall_send;
+use share::ipc::{Msg, EXIT, EXIT_PID, FS_SERVICE};
+use crate::syscall::ipc::{kcall_send, cancel_ipc_with};
+use crate::syscall::futex::{futex_wake, futex_key};
+use crate::syscall::registry::lookup_service;
+use crate::task::SERVICE_REGISTRY;
 pub use priority::{MAX_PRIORITY, MIN_PRIORITY};
+use share::signal::{SIGCHLD, SIGKILL};
+use share::wait::w_exitcode;
 
+/// Terminate the calling thread only.
 pub fn do_exit(exit_code: isize) -> Result<usize, SysError> {
+    exit_current_task(w_exitcode(exit_code as usize, 0) as isize);
+    Ok(0)
+}
+
+pub fn do_exit_group(exit_code: isize) -> Result<usize, SysError> {
+    exit_current_group(w_exitcode(exit_code as usize, 0) as isize);
+    Ok(0)
+}
+
+/// Terminate all the threads in the group of current task. The other threads are killed by
+/// `SIGKILL`, so the leader reports `SIGKILL` to the parent if it is not current task.
+pub fn exit_current_group(status: isize) {
+    kill_other_threads();
+    exit_current_task(status);
+}
+
+/// Send `SIGKILL` to the other threads in the group of current task.
+pub fn kill_other_threads() {
+    let cur_task = get_cur_task_in_this_hart();
+    get_all_tasks().into_iter()
+        .filter(|task| task.tgid() == cur_task.tgid() && !Arc::ptr_eq(task, &cur_task))
+        .for_each(|task| send_signal(task, SIGKILL));
+}
+
+/// Terminate current task with the wait `status` reported to its parent, which is either built
+/// from the exit code or the number of the signal that killed the task.
+pub fn exit_current_task(status: isize) {
     // get cur pid
     let cur_task = get_cur_task_in_this_hart();
     let pid = cur_task.pid();
+    let mut inner = cur_task.acquire_inner_lock();
+    inner.signals.block_all();
+    // the thread joining current task waits on the tid.
+    let clear_child_tid = match inner.clear_child_tid {
+        0 => None,
+        ptr => {
+            let written = inner.mem_manager.lock().write_u32(VirtualAddress::new(ptr), 0);
+            written.and_then(|_| futex_key(&inner, ptr, false)).ok()
+        },
+    };
+    drop(inner);
+    // the dirty pages of the files mapped shared are lost otherwise.
+    let _ = sync_shared_files(VirtualAddress::new(0), MAX_USER_ADDRESS);
+    if let Some((key, _)) = clear_child_tid {
+        futex_wake(key, 1);
+    }
     drop(cur_task);
+    SERVICE_REGISTRY.lock().remove_by_pid(pid);
 
-    // send EXIT message to fs server
+    // send EXIT message to fs server, unless fs server itself has gone.
     let mut message = Msg::empty();
     message.mtype = EXIT;
     message.args[EXIT_PID] = pid;
-    kcall_send(FS_PID, &message as *const _ as usize)?;
+    let _ = lookup_service(FS_SERVICE)
+        .and_then(|fs| kcall_send(fs, &message as *const _ as usize));
+
+    // nobody is able to talk to current task any more.
+    cancel_ipc_with(&get_cur_task_in_this_hart());
+    if let Some(parent) = signal::parent_of(&get_cur_task_in_this_hart()) {
+        send_signal(parent, SIGCHLD);
+    }
 
     // final step
-    schedule(RuntimeFlags::ZOMBIE(exit_code));
-    Ok(0)
+    schedule(RuntimeFlags::ZOMBIE(status));
 }
 
 pub fn do_yield() -> Result<usize, SysError> {
@@ -38,13 +102,20 @@ pub fn do_yield() -> Result<usize, SysError> {
     Ok(0)
 }
 
+/// Return the pid of the process, which is the tid of the thread group leader.
 pub fn do_get_pid() -> Result<usize, SysError> {
+    Ok(get_cur_task_in_this_hart().tgid())
+}
+
+pub fn do_get_tid() -> Result<usize, SysError> {
     Ok(get_cur_task_in_this_hart().pid())
 }
 
-/// Except init process, this function is always successful.
+/// A thread whose process has been adopted by init still refers to the old parent, which has
+/// gone, so init is returned in that case.
 pub fn do_get_ppid() -> Result<usize, SysError> {
     let cur_task = get_cur_task_in_this_hart();
     let inner = cur_task.acquire_inner_lock();
-    Ok(inner.parent.as_ref().unwrap().upgrade().unwrap().pid())
+    let parent = inner.parent.as_ref().and_then(|parent| parent.upgrade());
+    Ok(parent.map_or(0, |parent| parent.pid()))
 }
\ No newline at end of file
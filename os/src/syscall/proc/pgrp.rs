@@ -0,0 +1,104 @@
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use share::syscall::error::{SysError, EINVAL, EPERM, ESRCH};
+use crate::processor::get_cur_task_in_this_hart;
+use crate::task::{TaskStruct, PidHandle, get_task_by_pid, get_all_tasks};
+
+/// Move process `pid` into process group `pgid`. A `pid` of 0 stands for the caller, and a `pgid`
+/// of 0 stands for `pid` itself.
+///
+/// The target is either the caller or one of its children in the same session, and it must not be
+/// a session leader. A new group is created when `pgid` equals `pid`, otherwise group `pgid` must
+/// already exist in the session.
+pub fn do_setpgid(pid: usize, pgid: isize) -> Result<usize, SysError> {
+    if pgid < 0 {
+        return Err(SysError::new(EINVAL));
+    }
+    let cur_task = get_cur_task_in_this_hart();
+    let pid = if pid == 0 { cur_task.tgid() } else { pid };
+    let pgid = if pgid == 0 { pid } else { pgid as usize };
+
+    let cur_inner = cur_task.acquire_inner_lock();
+    let sid = cur_inner.sid();
+    let target = if pid == cur_task.tgid() {
+        cur_task.clone()
+    } else {
+        cur_inner.children.iter().find(|child| child.pid() == pid).cloned().ok_or(SysError::new(ESRCH))?
+    };
+    drop(cur_inner);
+
+    let target_sid = target.acquire_inner_lock().sid();
+    if target_sid != sid || target_sid == target.tgid() {
+        return Err(SysError::new(EPERM));
+    }
+    let pgrp_handle = if pgid == target.tgid() {
+        target.tgid_handle.clone()
+    } else {
+        find_pgrp_handle(pgid, sid).ok_or(SysError::new(EPERM))?
+    };
+    for_each_thread(target.tgid(), |task| task.acquire_inner_lock().pgrp_handle = pgrp_handle.clone());
+
+    Ok(0)
+}
+
+pub fn do_getpgid(pid: usize) -> Result<usize, SysError> {
+    let task = if pid == 0 {
+        get_cur_task_in_this_hart()
+    } else {
+        get_task_by_pid(pid).ok_or(SysError::new(ESRCH))?
+    };
+    let pgid = task.acquire_inner_lock().pgid();
+    Ok(pgid)
+}
+
+/// Make the caller the leader of a new session and a new process group, and return the new session
+/// id. It fails if a process group is already named after the caller.
+pub fn do_setsid() -> Result<usize, SysError> {
+    let cur_task = get_cur_task_in_this_hart();
+    let tgid = cur_task.tgid();
+    if get_all_tasks().iter().any(|task| task.acquire_inner_lock().pgid() == tgid) {
+        return Err(SysError::new(EPERM));
+    }
+    let handle = cur_task.tgid_handle.clone();
+    for_each_thread(tgid, |task| {
+        let mut inner = task.acquire_inner_lock();
+        inner.pgrp_handle = handle.clone();
+        inner.session_handle = handle.clone();
+    });
+
+    Ok(tgid)
+}
+
+pub fn do_getsid(pid: usize) -> Result<usize, SysError> {
+    let task = if pid == 0 {
+        get_cur_task_in_this_hart()
+    } else {
+        get_task_by_pid(pid).ok_or(SysError::new(ESRCH))?
+    };
+    let sid = task.acquire_inner_lock().sid();
+    Ok(sid)
+}
+
+/// Return the processes in group `pgid`, which are represented by their thread group leaders.
+pub fn get_pgrp_members(pgid: usize) -> Vec<Arc<TaskStruct>> {
+    get_all_tasks().into_iter()
+        .filter(|task| task.is_group_leader() && task.acquire_inner_lock().pgid() == pgid)
+        .collect()
+}
+
+fn find_pgrp_handle(pgid: usize, sid: usize) -> Option<Arc<PidHandle>> {
+    get_all_tasks().iter().find_map(|task| {
+        let inner = task.acquire_inner_lock();
+        if inner.pgid() == pgid && inner.sid() == sid {
+            Some(inner.pgrp_handle.clone())
+        } else {
+            None
+        }
+    })
+}
+
+fn for_each_thread<F: Fn(&Arc<TaskStruct>)>(tgid: usize, f: F) {
+    get_all_tasks().iter()
+        .filter(|task| task.tgid() == tgid)
+        .for_each(f);
+}
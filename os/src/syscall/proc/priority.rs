@@ -6,23 +6,25 @@ pub const MIN_PRIORITY: isize = 0;
 pub const MAX_PRIORITY: isize = 7;
 
 // TODO-FUTURE: get/set_priority should be updated after implementing process group and user privilege
+/// Return the priority current task runs at, which is the one inherited from a sendrec caller
+/// while it handles the request, if that is higher.
 pub fn do_get_priority(_: usize, _: usize) -> Result<usize, SysError> {
     let cur_task = get_cur_task_in_this_hart();
-    let inner = cur_task.acquire_inner_lock();
-    Ok(inner.priority as usize)
+    let priority = cur_task.sched.lock().effective_priority();
+    Ok(priority as usize)
 }
 
 pub fn do_set_priority(_: usize, _: usize, mut prio: isize) -> Result<usize, SysError> {
     let cur_task = get_cur_task_in_this_hart();
-    let mut inner = cur_task.acquire_inner_lock();
-    let min_priority = isize::max(MIN_PRIORITY, inner.min_priority);
+    let mut sched = cur_task.sched.lock();
+    let min_priority = isize::max(MIN_PRIORITY, sched.min_priority);
     if prio < min_priority{
         prio = min_priority;
     } else if prio > MAX_PRIORITY {
         prio = MAX_PRIORITY;
     }
-    inner.priority = prio;
-    drop(inner);
+    sched.priority = prio;
+    drop(sched);
     drop(cur_task);
     schedule(RuntimeFlags::READY);
 
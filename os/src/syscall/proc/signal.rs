@@ -0,0 +1,299 @@
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::mem::MaybeUninit;
+use share::signal::{SigAction, SigActionFlags, SigSet, SIGCONT, SIGKILL, SIGCHLD, SIGSEGV, SIG_DFL, SIG_IGN, SIG_BLOCK, SIG_SETMASK, sig_bit};
+use share::syscall::error::{SysError, EINVAL, ESRCH, EPERM, EFAULT};
+use crate::mm::address::VirtualAddress;
+use crate::config::{MAX_USER_ADDRESS, FRAME_SIZE};
+use crate::mm::memory_manager::RegionFlags;
+use crate::processor::get_cur_task_in_this_hart;
+use crate::task::{TaskStruct, TaskStructInner, RuntimeFlags, DefaultAction, WaitEvent, get_task_by_pid, return_task_to_manager, schedule, default_action, check_signo, wake_up_waiting_task, wake_up_sleeping_task};
+use crate::syscall::ipc::interrupt_ipc;
+use crate::syscall::futex::wake_up_futex_waiter;
+use crate::syscall::proc::exit_current_group;
+use crate::syscall::proc::pgrp::get_pgrp_members;
+use spin::MutexGuard;
+use share::wait::w_exitcode;
+
+/// Saved on the user stack when a handler is invoked, and restored by sigreturn.
+///
+/// `sstatus` is not saved, otherwise a task could return to supervisor mode by a forged frame.
+#[repr(C)]
+struct SignalFrame {
+    x: [usize; 32],
+    sepc: usize,
+    blocked: SigSet,
+}
+
+/// Send `signo` to task `pid`, to every process in the group of the caller if `pid` is 0, or to
+/// every process in group `-pid` if `pid` is less than -1. Signal 0 only checks whether the targets
+/// exist.
+///
+/// Broadcasting with a `pid` of -1 is not supported. Normal processes can not send signals to
+/// system processes, a group is signaled as long as one of its members accepts the signal.
+pub fn do_kill(pid: isize, signo: usize) -> Result<usize, SysError> {
+    if pid == -1 {
+        return Err(SysError::new(EINVAL));
+    }
+    if signo != 0 {
+        check_signo(signo)?;
+    }
+    let cur_task = get_cur_task_in_this_hart();
+    let cur_inner = cur_task.acquire_inner_lock();
+    let is_system = cur_inner.privilege.is_system;
+    let pgid = cur_inner.pgid();
+    drop(cur_inner);
+    drop(cur_task);
+
+    let targets: Vec<Arc<TaskStruct>> = match pid {
+        pid if pid > 0 => get_task_by_pid(pid as usize).into_iter().collect(),
+        0 => get_pgrp_members(pgid),
+        pid => get_pgrp_members(-pid as usize),
+    };
+    if targets.is_empty() {
+        return Err(SysError::new(ESRCH));
+    }
+    let targets: Vec<_> = targets.into_iter()
+        .filter(|task| is_system || !task.acquire_inner_lock().privilege.is_system)
+        .collect();
+    if targets.is_empty() {
+        return Err(SysError::new(EPERM));
+    }
+
+    if signo != 0 {
+        targets.into_iter().for_each(|task| send_signal(task, signo));
+    }
+    Ok(0)
+}
+
+pub fn do_sigaction(signo: usize, act_ptr: usize, old_act_ptr: usize) -> Result<usize, SysError> {
+    let task = get_cur_task_in_this_hart();
+    let mut inner = task.acquire_inner_lock();
+    check_signo(signo)?;
+    let old_action = if act_ptr != 0 {
+        let action = unsafe { (act_ptr as *const SigAction).read() };
+        // a handler has nowhere to return without a restorer.
+        let has_handler = action.handler != SIG_DFL && action.handler != SIG_IGN;
+        if has_handler && !action.flags.contains(SigActionFlags::RESTORER) {
+            return Err(SysError::new(EINVAL));
+        }
+        inner.signals.set_action(signo, action)?
+    } else {
+        inner.signals.action(signo)
+    };
+    if old_act_ptr != 0 {
+        unsafe { (old_act_ptr as *mut SigAction).write(old_action) };
+    }
+
+    Ok(0)
+}
+
+pub fn do_sigprocmask(how: usize, set_ptr: usize, old_set_ptr: usize) -> Result<usize, SysError> {
+    let task = get_cur_task_in_this_hart();
+    let mut inner = task.acquire_inner_lock();
+    let old_set = if set_ptr != 0 {
+        let set = unsafe { (set_ptr as *const SigSet).read() };
+        inner.signals.set_blocked(how, set)?
+    } else {
+        inner.signals.blocked
+    };
+    if old_set_ptr != 0 {
+        unsafe { (old_set_ptr as *mut SigSet).write(old_set) };
+    }
+
+    Ok(0)
+}
+
+/// Restore the context saved by [`handle_signals`] when the handler returns.
+///
+/// The return value is written into `a0` by the syscall dispatcher, so the saved `a0` is returned.
+pub fn do_sigreturn() -> Result<usize, SysError> {
+    let task = get_cur_task_in_this_hart();
+    let mut inner = task.acquire_inner_lock();
+    let context = inner.trap_context_ref();
+    let frame_ptr = context.x[2];
+    let mut frame = MaybeUninit::<SignalFrame>::uninit();
+    if copy_frame(&inner, frame_ptr, frame.as_mut_ptr(), false).is_err() {
+        inner.signals.force(SIGSEGV);
+        return Err(SysError::new(EFAULT));
+    }
+
+    let frame = unsafe { frame.assume_init() };
+    context.x = frame.x;
+    context.sepc = frame.sepc;
+    inner.signals.set_blocked(SIG_SETMASK, frame.blocked)?;
+
+    Ok(frame.x[10])
+}
+
+/// Mark `signo` pending for `task`.
+///
+/// A stopped task is resumed by `SIGCONT` and `SIGKILL`. A task blocked in ipc, waitpid, futex or
+/// sleep is woken up with `EINTR` if the signal does anything to it, except during a sendrec call
+/// whose reply would be lost, which is only interrupted by `SIGKILL`.
+pub fn send_signal(task: Arc<TaskStruct>, signo: usize) {
+    let mut inner = task.acquire_inner_lock();
+    inner.signals.add(signo);
+    match inner.flag {
+        RuntimeFlags::STOPPED(_) if signo == SIGCONT || signo == SIGKILL => {
+            inner.flag = RuntimeFlags::READY;
+            if signo == SIGCONT {
+                inner.wait_event = Some(WaitEvent::Continued);
+            }
+            drop(inner);
+            if signo == SIGCONT {
+                notify_parent(&task);
+            }
+            return_task_to_manager(task);
+        },
+        RuntimeFlags::WAITING if inner.signals.is_wanted(signo) => {
+            drop(inner);
+            wake_up_waiting_task(task);
+        },
+        RuntimeFlags::FUTEX if inner.signals.is_wanted(signo) => {
+            drop(inner);
+            wake_up_futex_waiter(task);
+        },
+        RuntimeFlags::SLEEPING(_) if inner.signals.is_wanted(signo) => {
+            drop(inner);
+            wake_up_sleeping_task(task);
+        },
+        RuntimeFlags::SENDING(_) | RuntimeFlags::RECEIVING(_) => {
+            let interrupt = inner.signals.is_wanted(signo) && (signo == SIGKILL || !inner.sendrec);
+            drop(inner);
+            if interrupt {
+                interrupt_ipc(task);
+            }
+        },
+        _ => {},
+    }
+}
+
+/// Send `SIGCHLD` to the parent of `task` which has stopped or continued, and wake the parent up if
+/// it is waiting for the change. An exiting task wakes its parent up when it has become a zombie.
+pub fn notify_parent(task: &Arc<TaskStruct>) {
+    if let Some(parent) = parent_of(task) {
+        send_signal(parent.clone(), SIGCHLD);
+        wake_up_waiting_task(parent);
+    }
+}
+
+/// Return the parent to report to. Only the thread group leader reports to its parent.
+pub fn parent_of(task: &Arc<TaskStruct>) -> Option<Arc<TaskStruct>> {
+    if !task.is_group_leader() {
+        return None;
+    }
+    task.acquire_inner_lock().parent.as_ref().and_then(|parent| parent.upgrade())
+}
+
+/// Deliver the pending signals of current task, called right before it returns to user mode.
+///
+/// Default actions are taken here: the task may exit or stop inside this function. When a handler
+/// is found, its frame is built and the rest of pending signals are left to the next return.
+pub fn handle_signals() {
+    loop {
+        let task = get_cur_task_in_this_hart();
+        let mut inner = task.acquire_inner_lock();
+        let signo = match inner.signals.take() {
+            Some(signo) => signo,
+            None => return,
+        };
+        let action = inner.signals.action(signo);
+        let (default, signo) = match action.handler {
+            SIG_IGN => continue,
+            SIG_DFL => (default_action(signo), signo),
+            _ => {
+                if build_signal_frame(&mut inner, signo, action).is_ok() {
+                    return;
+                }
+                // the user stack is broken, there is no way to run the handler.
+                (DefaultAction::Terminate, SIGSEGV)
+            },
+        };
+        drop(inner);
+        drop(task);
+
+        match default {
+            DefaultAction::Terminate => exit_current_group(w_exitcode(0, signo) as isize),
+            DefaultAction::Stop => {
+                let task = get_cur_task_in_this_hart();
+                let mut inner = task.acquire_inner_lock();
+                // resumed by a signal sent from another hart in the meantime.
+                if inner.signals.is_pending(SIGCONT) || inner.signals.is_pending(SIGKILL) {
+                    continue;
+                }
+                inner.wait_event = Some(WaitEvent::Stopped(signo));
+                inner.flag = RuntimeFlags::STOPPED(signo);
+                drop(inner);
+                notify_parent(&task);
+                drop(task);
+                schedule(RuntimeFlags::STOPPED(signo));
+            },
+            DefaultAction::Ignore | DefaultAction::Continue => {},
+        }
+    }
+}
+
+/// Push the trap context onto the user stack, and make the task enter `action.handler(signo)` which
+/// returns to `action.restorer`.
+fn build_signal_frame(inner: &mut MutexGuard<TaskStructInner>, signo: usize, action: SigAction) -> Result<(), SysError> {
+    let context = inner.trap_context_ref();
+    let frame_ptr = context.x[2].wrapping_sub(core::mem::size_of::<SignalFrame>()) & !0xf;
+    let mut frame = SignalFrame {
+        x: context.x,
+        sepc: context.sepc,
+        blocked: inner.signals.blocked,
+    };
+    copy_frame(inner, frame_ptr, &mut frame, true)?;
+    context.x[1] = action.restorer;
+    context.x[2] = frame_ptr;
+    context.x[10] = signo;
+    context.sepc = action.handler;
+
+    let mut blocked = action.mask;
+    if !action.flags.contains(SigActionFlags::NODEFER) {
+        blocked |= sig_bit(signo);
+    }
+    inner.signals.set_blocked(SIG_BLOCK, blocked)?;
+    if action.flags.contains(SigActionFlags::RESETHAND) {
+        inner.signals.reset_action(signo);
+    }
+    Ok(())
+}
+
+/// Copy the frame between `frame` and the user stack at `frame_ptr` through the physical addresses,
+/// page by page. The user address itself is never dereferenced, since its page may not be backed
+/// yet, or be unmapped by another thread meanwhile.
+///
+/// The stack grows to hold the frame if it is within the reach of the stack. EFAULT is returned if
+/// any page of the frame is not readable, or not writable when the frame is pushed.
+fn copy_frame(inner: &TaskStructInner, frame_ptr: usize, frame: *mut SignalFrame, to_user: bool) -> Result<(), SysError> {
+    let size = core::mem::size_of::<SignalFrame>();
+    if frame_ptr > MAX_USER_ADDRESS - size {
+        return Err(SysError::new(EFAULT));
+    }
+    let access = if to_user { RegionFlags::R | RegionFlags::W } else { RegionFlags::R };
+    let mut mem_manager = inner.mem_manager.lock();
+    let mut copied = 0;
+    while copied < size {
+        let va = VirtualAddress::new(frame_ptr + copied);
+        let _ = mem_manager.grow_stack_to(va);
+        if !mem_manager.region_list.iter().any(|region| region.contain(va) && region.flags.contains(access)) {
+            return Err(SysError::new(EFAULT));
+        }
+        let len = (size - copied).min(FRAME_SIZE - va.0 % FRAME_SIZE);
+        let kernel = unsafe { (frame as *mut u8).add(copied) };
+        unsafe {
+            if to_user {
+                let pa = mem_manager.translate_for_write(va)?;
+                core::ptr::copy(kernel, pa.as_raw_mut::<u8>(), len);
+            } else {
+                let pa = mem_manager.translate_for_read(va)?;
+                core::ptr::copy(pa.as_raw::<u8>(), kernel, len);
+            }
+        }
+        copied += len;
+    }
+
+    Ok(())
+}
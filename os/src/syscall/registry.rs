@@ -0,0 +1,53 @@
+use core::str::from_utf8;
+use crate::processor::get_cur_task_in_this_hart;
+use crate::task::{pid_to_endpoint, SERVICE_REGISTRY};
+use share::ipc::{Endpoint, MAX_SERVICE_NAME_LENGTH};
+use share::syscall::error::{SysError, EINVAL, ENAMETOOLONG, ESRCH, EDEADSRCDST};
+
+/// Publish current task as the server of service `name`, return its endpoint.
+///
+/// Only system processes are given this kcall by their [`Privilege`](crate::task::Privilege),
+/// so that a normal process can not pretend to be a server.
+pub fn kcall_publish(name_ptr: usize, name_len: usize) -> Result<usize, SysError> {
+    let name = read_service_name(name_ptr, name_len)?;
+    let task = get_cur_task_in_this_hart();
+    let endpoint = pid_to_endpoint(task.pid()).unwrap();
+    SERVICE_REGISTRY.lock().publish(name, endpoint)?;
+
+    Ok(endpoint.0)
+}
+
+/// Return the endpoint of service `name`, which can be used wherever a pid is expected by ipc kcalls.
+pub fn kcall_lookup(name_ptr: usize, name_len: usize) -> Result<usize, SysError> {
+    let name = read_service_name(name_ptr, name_len)?;
+    lookup_service(name)
+}
+
+/// Used by the kernel as well, when it forwards requests to a server on behalf of current task.
+pub fn lookup_service(name: &str) -> Result<usize, SysError> {
+    let endpoint = SERVICE_REGISTRY.lock().lookup(name).ok_or(SysError::new(ESRCH))?;
+    Ok(endpoint.0)
+}
+
+/// Return the pid of `endpoint`, or `EDEADSRCDST` if the process it refers to has gone.
+/// A plain pid has generation 0 and is returned as it is.
+pub fn endpoint_to_pid(endpoint: usize) -> Result<usize, SysError> {
+    let endpoint = Endpoint(endpoint);
+    if endpoint.generation() == 0 {
+        return Ok(endpoint.pid());
+    }
+    match pid_to_endpoint(endpoint.pid()) {
+        Some(current) if current == endpoint => Ok(endpoint.pid()),
+        _ => Err(SysError::new(EDEADSRCDST)),
+    }
+}
+
+fn read_service_name(name_ptr: usize, name_len: usize) -> Result<&'static str, SysError> {
+    if name_len > MAX_SERVICE_NAME_LENGTH {
+        return Err(SysError::new(ENAMETOOLONG));
+    }
+    let name = unsafe {
+        core::slice::from_raw_parts(name_ptr as *const u8, name_len)
+    };
+    from_utf8(name).map_err(|_| SysError::new(EINVAL))
+}
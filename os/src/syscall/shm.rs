@@ -0,0 +1,72 @@
+use crate::mm::address::{VirtualAddress, ceil};
+use crate::mm::memory_manager::{RegionFlags, RegionType};
+use crate::processor::get_cur_task_in_this_hart;
+use crate::task::{get_task_by_pid, TaskStruct};
+use alloc::sync::Arc;
+use share::mmap::Prot;
+use share::syscall::error::{SysError, EINVAL, ESRCH, EACCES, EPERM};
+
+/// Create a shared memory object of `size` bytes in current task and return its start address.
+///
+/// The object is identified by that address, which is passed to [`kcall_shm_map`] to map
+/// the same frames into another task.
+pub fn kcall_shm_create(size: usize, prot: usize) -> Result<usize, SysError> {
+    if size == 0 {
+        return Err(SysError::new(EINVAL));
+    }
+    let flags = prot_to_region_flags(prot)?;
+    let task = get_cur_task_in_this_hart();
+    let inner = task.acquire_inner_lock();
+    let start = inner.mem_manager.lock().alloc_area(ceil(size), flags, RegionType::SharedMemory, None)?;
+
+    Ok(start.0)
+}
+
+/// Map the shared memory object at `addr` of current task into `dst_pid` task with `prot`,
+/// and return its start address in `dst_pid` task. `prot` must not exceed the rights of current task,
+/// and `dst_pid` must be in the thread group of current task or a child of it, unless current task is
+/// a system process.
+pub fn kcall_shm_map(addr: usize, dst_pid: usize, prot: usize) -> Result<usize, SysError> {
+    let flags = prot_to_region_flags(prot)?;
+    let task = get_cur_task_in_this_hart();
+    let (frames, cur_flags) = task.acquire_inner_lock()
+        .mem_manager.lock().get_shared_memory(VirtualAddress::new(addr))?;
+    if !cur_flags.contains(flags) {
+        return Err(SysError::new(EACCES));
+    }
+
+    let dst_task = get_task_by_pid(dst_pid).ok_or(SysError::new(ESRCH))?;
+    if !may_map_into(&task, &dst_task) {
+        return Err(SysError::new(EPERM));
+    }
+    let dst_inner = dst_task.acquire_inner_lock();
+    let start = dst_inner.mem_manager.lock().map_shared_memory(frames, flags)?;
+
+    Ok(start.0)
+}
+
+pub fn kcall_shm_unmap(addr: usize) -> Result<usize, SysError> {
+    let task = get_cur_task_in_this_hart();
+    let inner = task.acquire_inner_lock();
+    inner.mem_manager.lock().unmap_shared_memory(VirtualAddress::new(addr))?;
+
+    Ok(0)
+}
+
+fn may_map_into(task: &Arc<TaskStruct>, dst_task: &Arc<TaskStruct>) -> bool {
+    if task.acquire_inner_lock().privilege.is_system || dst_task.tgid() == task.tgid() {
+        return true;
+    }
+    let parent = dst_task.acquire_inner_lock().parent.as_ref().and_then(|parent| parent.upgrade());
+    parent.map_or(false, |parent| parent.tgid() == task.tgid())
+}
+
+fn prot_to_region_flags(prot: usize) -> Result<RegionFlags, SysError> {
+    let prot = Prot::from_bits(prot as u32).ok_or(SysError::new(EINVAL))?;
+    let mut region_flags = RegionFlags::empty();
+    if prot.contains(Prot::READ) { region_flags |= RegionFlags::R };
+    if prot.contains(Prot::WRITE) { region_flags |= RegionFlags::W };
+    if prot.contains(Prot::EXEC) { region_flags |= RegionFlags::X };
+
+    Ok(region_flags)
+}
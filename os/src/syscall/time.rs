@@ -1,7 +1,7 @@
-use crate::timer::{get_time_ms, get_time_s, get_time_us};
-use share::syscall::error::SysError;
-
-use super::proc::do_yield;
+use crate::timer::{get_time_ms, get_time_us, add_timer, cancel_timer, USEC_PER_SEC};
+use crate::processor::get_cur_task_in_this_hart;
+use crate::task::{RuntimeFlags, schedule, wake_up_sleeping_task};
+use share::syscall::error::{SysError, EINVAL, EINTR};
 use share::time::Timespec;
 
 pub fn do_get_time() -> Result<usize, SysError> {
@@ -11,26 +11,49 @@ pub fn do_get_time() -> Result<usize, SysError> {
 pub fn do_get_time_of_day(time: *mut Timespec) -> Result<usize, SysError> {
     if time as usize != 0 {
         unsafe {
-            (*time).tv_sec = get_time_s() as u64;
-            (*time).tv_usec = get_time_us() as u64;
+            *time = Timespec::from_us(get_time_us());
         }
     }
 
     Ok(0)
 }
 
-pub fn do_nanosleep(req: *mut Timespec, rem: *mut Timespec) -> Result<usize, SysError> {
-    unsafe {
-        let end_sec = get_time_s() + (*req).tv_sec as usize;
-        let end_usec = get_time_us() + (*req).tv_usec as usize;
-        loop {
-            let cur_sec = get_time_s();
-            let cur_usec = get_time_us();
-            if cur_sec >= end_sec && cur_usec >= end_usec {
-                return Ok(0);
-            } else {
-                do_yield();
-            }
+/// Sleep for the time `req` points to. If a signal wakes the caller up earlier, `EINTR` is returned
+/// and the time left is written to `rem` unless it is null.
+pub fn do_nanosleep(req: *const Timespec, rem: *mut Timespec) -> Result<usize, SysError> {
+    let req = unsafe { req.read() };
+    if req.tv_usec as usize >= USEC_PER_SEC {
+        return Err(SysError::new(EINVAL));
+    }
+    let deadline = get_time_us() + req.tv_sec as usize * USEC_PER_SEC + req.tv_usec as usize;
+    if sleep_until(deadline) {
+        return Ok(0);
+    }
+
+    if !rem.is_null() {
+        let left = deadline.saturating_sub(get_time_us());
+        unsafe {
+            rem.write(Timespec::from_us(left));
         }
     }
+    Err(SysError::new(EINTR))
+}
+
+/// Block current task in `SLEEPING` state until `deadline`(in us). Return false if it is woken up
+/// earlier by a signal.
+pub fn sleep_until(deadline: usize) -> bool {
+    let task = get_cur_task_in_this_hart();
+    let mut inner = task.acquire_inner_lock();
+    if inner.signals.has_wanted_pending() {
+        return false;
+    }
+    inner.flag = RuntimeFlags::SLEEPING(deadline);
+    drop(inner);
+    let sleeper = task.clone();
+    let timer = add_timer(deadline, move || wake_up_sleeping_task(sleeper));
+    drop(task);
+    schedule(RuntimeFlags::SLEEPING(deadline));
+
+    cancel_timer(timer);
+    get_time_us() >= deadline
 }
@@ -0,0 +1,142 @@
+use alloc::vec::Vec;
+use share::ipc::GrantFlags;
+use share::syscall::error::{SysError, EINVAL, ENOMEM};
+
+/// A piece of memory that the owner task allows `grantee` task to copy from or to.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Grant {
+    pub grantee: usize,
+    pub addr: usize,
+    pub len: usize,
+    pub flags: GrantFlags,
+    /// `addr` is an address of the kernel, which lends a buffer of its own to a server on behalf of
+    /// the owner task. Only the kernel makes such grants.
+    pub in_kernel: bool,
+}
+
+impl Grant {
+    /// Check whether `pid` task may access `[offset, offset + len)` of this grant with `flags`,
+    /// and return the start address of that range in the owner's address space.
+    pub fn check(&self, pid: usize, offset: usize, len: usize, flags: GrantFlags) -> Option<usize> {
+        if self.grantee != pid || !self.flags.contains(flags) {
+            return None;
+        }
+        let end = offset.checked_add(len)?;
+        if end > self.len {
+            return None;
+        }
+        Some(self.addr + offset)
+    }
+}
+
+/// Bits of a grant id holding the index of its slot, the bits above hold the generation of the slot.
+const SLOT_BITS: usize = 16;
+const SLOT_MASK: usize = (1 << SLOT_BITS) - 1;
+
+/// The generation is bumped each time the slot is revoked, so the id of a revoked grant never refers
+/// to a later grant in the same slot.
+struct Slot {
+    generation: usize,
+    grant: Option<Grant>,
+}
+
+/// Grants created by a task, indexed by grant id.
+pub struct GrantTable {
+    slots: Vec<Slot>,
+}
+
+impl GrantTable {
+    pub fn new() -> Self {
+        Self {
+            slots: Vec::new(),
+        }
+    }
+
+    /// Save `grant` into the table and return its grant id. Revoked slots are reused first, under a
+    /// new id. ENOMEM is returned if every slot is in use.
+    pub fn insert(&mut self, grant: Grant) -> Result<usize, SysError> {
+        let index = match self.slots.iter().position(|slot| slot.grant.is_none()) {
+            Some(index) => index,
+            None if self.slots.len() <= SLOT_MASK => {
+                self.slots.push(Slot { generation: 0, grant: None });
+                self.slots.len() - 1
+            },
+            None => return Err(SysError::new(ENOMEM)),
+        };
+        let slot = &mut self.slots[index];
+        slot.grant = Some(grant);
+        Ok(slot.generation << SLOT_BITS | index)
+    }
+
+    pub fn revoke(&mut self, id: usize) -> Result<(), SysError> {
+        let slot = self.slot_mut(id).ok_or(SysError::new(EINVAL))?;
+        Self::release(slot);
+        Ok(())
+    }
+
+    pub fn get(&self, id: usize) -> Option<Grant> {
+        self.slots.get(id & SLOT_MASK)
+            .filter(|slot| slot.generation == id >> SLOT_BITS)
+            .and_then(|slot| slot.grant)
+    }
+
+    /// Revoke every grant. The generations are kept, so the ids given out before stay invalid.
+    pub fn clear(&mut self) {
+        self.slots.iter_mut()
+            .filter(|slot| slot.grant.is_some())
+            .for_each(Self::release);
+    }
+
+    fn slot_mut(&mut self, id: usize) -> Option<&mut Slot> {
+        self.slots.get_mut(id & SLOT_MASK)
+            .filter(|slot| slot.generation == id >> SLOT_BITS && slot.grant.is_some())
+    }
+
+    fn release(slot: &mut Slot) {
+        slot.grant = None;
+        slot.generation = (slot.generation + 1) & (usize::MAX >> SLOT_BITS);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Grant, GrantTable};
+    use share::ipc::GrantFlags;
+
+    #[test]
+    pub fn test_grant_table() {
+        info!("starting grant.rs test cases");
+
+        let mut table = GrantTable::new();
+        let grant = Grant { grantee: 3, addr: 0x1000, len: 0x100, flags: GrantFlags::READ, in_kernel: false };
+        let id0 = table.insert(grant).unwrap();
+        let id1 = table.insert(Grant { flags: GrantFlags::READ | GrantFlags::WRITE, ..grant }).unwrap();
+        assert_ne!(id0, id1);
+
+        // only the grantee may access the range with the granted rights.
+        assert_eq!(grant.check(3, 0x10, 0x10, GrantFlags::READ), Some(0x1010));
+        assert_eq!(grant.check(4, 0x10, 0x10, GrantFlags::READ), None);
+        assert_eq!(grant.check(3, 0x10, 0x10, GrantFlags::WRITE), None);
+        assert_eq!(grant.check(3, 0xf0, 0x20, GrantFlags::READ), None);
+        assert_eq!(grant.check(3, usize::MAX, 2, GrantFlags::READ), None);
+
+        // revoked grant can not be used any more, and its slot is reused under a new id.
+        table.revoke(id0).unwrap();
+        assert!(table.get(id0).is_none());
+        assert!(table.revoke(id0).is_err());
+        let id2 = table.insert(grant).unwrap();
+        assert_ne!(id2, id0);
+        assert!(table.get(id0).is_none());
+        assert_eq!(table.get(id2), Some(grant));
+
+        // ids given out before the table is cleared stay invalid.
+        table.clear();
+        assert!(table.get(id1).is_none());
+        assert!(table.revoke(id1).is_err());
+        let id3 = table.insert(grant).unwrap();
+        assert_ne!(id3, id2);
+        assert_ne!(id3, id0);
+
+        info!("end of grant.rs test\n");
+    }
+}
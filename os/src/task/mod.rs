@@ -4,19 +4,34 @@ mod trap_context;
 mod task_manager;
 mod pid;
 mod task_context;
-
-use crate::processor::{take_task_in_current_hart,get_current_hart_context_ptr};
+mod notification;
+mod grant;
+mod registry;
+mod privilege;
+mod signal;
+mod rusage;
+mod scheduler;
+
+use crate::processor::{take_task_in_current_hart, get_current_hart_context_ptr, put_next_task_in_current_hart, put_exited_task_in_current_hart};
+use crate::timer::get_time_us;
 use crate::loader::{get_app_ref_data, get_app_names};
-use spin::Mutex;
 
 pub use kernel_stack::KernelStack;
-pub use task_struct::{TaskStruct, TaskStructInner, RuntimeFlags};
-pub use task_manager::{fetch_a_task_from_manager, add_a_task_to_manager, get_task_by_pid};
+pub use task_struct::{TaskStruct, TaskStructInner, RuntimeFlags, WaitEvent, Zombie};
+pub use task_manager::{fetch_a_task_from_manager, has_ready_task, add_a_task_to_manager, get_task_by_pid, get_all_tasks};
 pub use task_context::TaskContext;
+pub use notification::PendingNotifications;
+pub use grant::{Grant, GrantTable};
 pub use trap_context::TrapContext;
-pub use pid::alloc_pid;
+pub use pid::{alloc_pid, pid_to_endpoint, PidHandle};
+pub use registry::{SERVICE_REGISTRY, ServiceRegistry};
+pub use privilege::Privilege;
+pub use rusage::Rusage;
+pub use scheduler::SCHEDULER;
+pub use signal::{Signals, DefaultAction, default_action, check_signo};
 use crate::task::task_manager::rm_task_from_manager;
 pub use crate::task::task_manager::return_task_to_manager;
+use crate::task::task_manager::requeue_task_to_manager;
 use alloc::sync::Arc;
 use crate::processor::__switch;
 use crate::paging::KERNEL_SATP;
@@ -24,6 +39,8 @@ use lazy_static::*;
 use alloc::vec::Vec;
 use alloc::vec;
 use share::ffi::CStr;
+use share::ipc::{TERMINAL_SERVICE, BLOCK_SERVICE, FS_SERVICE};
+use core::arch::asm;
 
 lazy_static! {
     pub static ref APP_NAMES: Vec<CStr<'static>> =get_app_names();
@@ -45,12 +62,28 @@ pub fn load_init_tasks() {
     let tasks = vec!["init", "terminal", "virtio-blk", "fs"];
     #[cfg(feature = "board_k210")]
         let tasks = vec!["init", "terminal", "sdcard", "fs"];
-    for task_name in tasks {
+    let tasks: Vec<(&str, Arc<TaskStruct>)> = tasks.into_iter().map(|task_name| {
         let data = get_task_data_by_name(task_name).unwrap_or_else(|| {
             panic!("{} doesn't exist!", task_name);
         });
-        let task = Arc::new(TaskStruct::new(data).unwrap());
+        (task_name, Arc::new(TaskStruct::new(data).unwrap()))
+    }).collect();
+
+    // publish the servers in the boot image before any client looks them up.
+    for (task_name, task) in tasks.iter() {
+        let service = match *task_name {
+            "terminal" => Some(TERMINAL_SERVICE),
+            "virtio-blk" | "sdcard" => Some(BLOCK_SERVICE),
+            "fs" => Some(FS_SERVICE),
+            _ => None,
+        };
+        if let Some(service) = service {
+            let endpoint = pid_to_endpoint(task.pid()).unwrap();
+            SERVICE_REGISTRY.lock().publish(service, endpoint).unwrap();
+        }
+    }
 
+    for (task_name, task) in tasks {
         // set min_priority for these tasks.
         let mut priority = 0;
         if task_name == "init" { // make sure normal user processes' min_priority is higher than device and fs.
@@ -58,9 +91,12 @@ pub fn load_init_tasks() {
         } else if task_name == "fs" { // make sure fs' min_priority is higher than device.
             priority = 2;
         }
+        let mut sched = task.sched.lock();
+        sched.min_priority = priority;
+        sched.priority = priority;
+        drop(sched);
         let mut inner = task.acquire_inner_lock();
-        inner.min_priority = priority;
-        inner.priority = priority;
+        inner.privilege = if task_name == "init" { Privilege::user() } else { Privilege::system() };
         drop(inner);
 
         add_a_task_to_manager(task);
@@ -76,15 +112,35 @@ pub fn get_task_data_by_name(name: &str) -> Option<&'static [u8]> {
     })
 }
 
+/// Switch current task away in `runtime_flag` state.
+///
+/// A task going to block sets its state before releasing the locks which its wakers check, so that a
+/// wake up from another hart is never lost. The task may then have been woken up before getting
+/// here, it is READY and inside the task manager already, and only switches away.
 pub fn schedule(runtime_flag: RuntimeFlags) {
+    switch_out(runtime_flag, false);
+}
+
+/// Switch current task away when its time slice is used up.
+pub fn preempt() {
+    switch_out(RuntimeFlags::READY, true);
+}
+
+fn switch_out(runtime_flag: RuntimeFlags, preempted: bool) {
     debug!("schedule...");
     let current_task = take_task_in_current_hart();
     let mut inner = current_task.acquire_inner_lock();
-    inner.flag = runtime_flag;
+    let blocking = runtime_flag.is_blocked();
+    if !blocking || matches!(inner.flag, RuntimeFlags::RUNNING) {
+        inner.flag = runtime_flag;
+    }
+    let now = get_time_us();
+    inner.rusage.kernel_until(now);
+    current_task.sched.lock().charge(now);
     let mut current_task_context_ptr= 0;
 
     match inner.flag {
-        RuntimeFlags::RECEIVING(_) | RuntimeFlags::SENDING(_) => {
+        _ if blocking => { // still blocked, or READY again if it has been woken up.
             current_task_context_ptr = inner.task_context_ptr();
             drop(inner);
             drop(current_task);
@@ -92,18 +148,40 @@ pub fn schedule(runtime_flag: RuntimeFlags) {
         RuntimeFlags::READY => {
             current_task_context_ptr = inner.task_context_ptr();
             drop(inner);
-            return_task_to_manager(current_task);
+            requeue_task_to_manager(current_task, preempted);
         },
-        RuntimeFlags::ZOMBIE(exit_code) => {
-            info!("task {} exit with exit_code:{}",current_task.pid(), exit_code);
-            move_cur_task_children_to_init(inner.children.clone());
-            drop(inner);
-            rm_task_from_manager(current_task);
-
+        RuntimeFlags::ZOMBIE(status) => {
+            info!("task {} exit with status:{:#x}",current_task.pid(), status);
+            // the user address space is released, keep running on the kernel page table.
             let kernel_satp = 8 << 60 | unsafe { KERNEL_SATP };
             riscv::register::satp::write(kernel_satp);
+            unsafe {
+                asm!{"sfence.vma"}
+            }
+            let zombie = Zombie::new(&current_task, &inner, status);
+            // other threads are not waited by anyone, they are gone at once.
+            let parent = if current_task.is_group_leader() {
+                inner.parent.as_ref().and_then(|parent| parent.upgrade())
+            } else {
+                None
+            };
+            let children = core::mem::take(&mut inner.children);
+            release_task_resources(&mut inner);
+            drop(inner);
+
+            move_children_to_init(children);
+            rm_task_from_manager(current_task.clone());
+            // the parent only finds out the zombie after it has been recorded.
+            if let Some(parent) = parent {
+                let mut parent_inner = parent.acquire_inner_lock();
+                parent_inner.children.retain(|child| !Arc::ptr_eq(child, &current_task));
+                parent_inner.zombies.push(zombie);
+                drop(parent_inner);
+                wake_up_waiting_task(parent);
+            }
+            put_exited_task_in_current_hart(current_task);
         },
-        RuntimeFlags::RUNNING => panic!("schedule error!")
+        _ => panic!("schedule error!")
     };
 
     let hart_context_ptr = get_current_hart_context_ptr();
@@ -112,46 +190,57 @@ pub fn schedule(runtime_flag: RuntimeFlags) {
     }
 }
 
-fn move_cur_task_children_to_init(children: Vec<Arc<TaskStruct>>) {
-    let init_task = get_task_by_pid(0).unwrap();
-    let mut init_task_inner = init_task.acquire_inner_lock();
-    init_task_inner.children.extend(children);
-}
-
-pub fn get_alive_hart_cnt() -> usize {
-    ALIVE_HARTS.lock().get_num()
+/// Give the rest of current time slice to `next_task`, which must be READY but not inside the task manager.
+///
+/// Current task is put back into the task manager, and the hart runs `next_task` next instead of
+/// fetching one from the task manager.
+pub fn yield_to(next_task: Arc<TaskStruct>) {
+    put_next_task_in_current_hart(next_task);
+    schedule(RuntimeFlags::READY);
 }
 
-pub fn increase_alive_hart() {
-    ALIVE_HARTS.lock().increase();
-}
-
-pub fn decrease_alive_hart() {
-    ALIVE_HARTS.lock().decrease();
-}
-
-lazy_static! {
-    pub static ref ALIVE_HARTS: Mutex<Counter> = Mutex::new(Counter::new());
+/// Make `task` runnable again if it is blocked in waitpid, so that it checks its children again.
+pub fn wake_up_waiting_task(task: Arc<TaskStruct>) {
+    let mut inner = task.acquire_inner_lock();
+    if let RuntimeFlags::WAITING = inner.flag {
+        inner.flag = RuntimeFlags::READY;
+        drop(inner);
+        return_task_to_manager(task);
+    }
 }
 
-pub struct Counter(usize);
-
-impl Counter {
-    pub fn new() -> Self {
-        Counter{
-            0: 0
-        }
+/// Make `task` runnable again if it is sleeping, when its deadline has come or a signal has arrived.
+pub fn wake_up_sleeping_task(task: Arc<TaskStruct>) {
+    let mut inner = task.acquire_inner_lock();
+    if let RuntimeFlags::SLEEPING(_) = inner.flag {
+        inner.flag = RuntimeFlags::READY;
+        drop(inner);
+        return_task_to_manager(task);
     }
+}
 
-    pub fn get_num(&self) -> usize {
-        self.0
+/// Free what an exited task doesn't need any more, only the kernel stack it is running on is left.
+/// Its unwaited zombies are reaped, since nobody is going to wait for them. The address space is
+/// released by the last thread using it.
+fn release_task_resources(inner: &mut TaskStructInner) {
+    if Arc::strong_count(&inner.mem_manager) == 1 {
+        inner.mem_manager.lock().release();
     }
+    inner.grants.clear();
+    inner.zombies.clear();
+    inner.wait_queue.clear();
+    inner.message_holder = None;
+}
 
-    pub fn decrease(&mut self) {
-        self.0 -= 1;
+/// Orphans are adopted by init, which waits for them.
+fn move_children_to_init(children: Vec<Arc<TaskStruct>>) {
+    if children.is_empty() {
+        return;
     }
-
-    pub fn increase(&mut self) {
-        self.0 += 1;
+    let init_task = get_task_by_pid(0).unwrap();
+    let mut init_task_inner = init_task.acquire_inner_lock();
+    for child in children.iter() {
+        child.acquire_inner_lock().parent = Some(Arc::downgrade(&init_task));
     }
-}
\ No newline at end of file
+    init_task_inner.children.extend(children);
+}
@@ -0,0 +1,123 @@
+use crate::config::MAX_TASK_NUMBER;
+use share::ipc::{Msg, HARDWARE, INTERRUPT, KERNEL, NOTIFY, NOTIFY_BITMAP};
+
+/// Notifications which have been sent to a task but not received yet.
+///
+/// There is one bitmap for each kind of source, so notifications from different sources never
+/// overwrite each other, while repeated notifications from the same source are merged.
+#[derive(Copy, Clone)]
+pub struct PendingNotifications {
+    /// Bit `n` is set when irq line `n` has fired.
+    irqs: u64,
+    /// Kernel events, their meaning is agreed between the kernel and the receiver.
+    kernel_events: u64,
+    /// Bit `n` is set when task `n` has notified.
+    pids: u64,
+}
+
+impl PendingNotifications {
+    pub const fn empty() -> Self {
+        Self {
+            irqs: 0,
+            kernel_events: 0,
+            pids: 0,
+        }
+    }
+
+    pub fn add_irq(&mut self, irq: usize) {
+        assert!(irq < 64);
+        self.irqs |= 1 << irq;
+    }
+
+    pub fn add_kernel_events(&mut self, events: u64) {
+        self.kernel_events |= events;
+    }
+
+    pub fn add_pid(&mut self, pid: usize) {
+        assert!(pid < MAX_TASK_NUMBER);
+        self.pids |= 1 << pid;
+    }
+
+    /// Take the pending notifications of a single source which a receive call from `dst_pid` accepts,
+    /// and build the message for them.
+    ///
+    /// Receiving from any task(`dst_pid` == -1) accepts every source, checked in the order of
+    /// hardware, kernel and then pids from low to high. Receiving from a specific task only accepts
+    /// the notification sent by that task.
+    pub fn take(&mut self, dst_pid: isize) -> Option<Msg> {
+        if dst_pid < 0 {
+            if self.irqs != 0 {
+                let irqs = self.irqs;
+                self.irqs = 0;
+                return Some(build_notify_message(HARDWARE, INTERRUPT, irqs));
+            }
+            if self.kernel_events != 0 {
+                let events = self.kernel_events;
+                self.kernel_events = 0;
+                return Some(build_notify_message(KERNEL, NOTIFY, events));
+            }
+            if self.pids != 0 {
+                let pid = self.pids.trailing_zeros() as usize;
+                self.pids ^= 1 << pid;
+                return Some(build_notify_message(pid, NOTIFY, 1 << pid));
+            }
+        } else if (dst_pid as usize) < MAX_TASK_NUMBER && (self.pids >> dst_pid) & 1 == 1 {
+            self.pids ^= 1 << dst_pid;
+            return Some(build_notify_message(dst_pid as usize, NOTIFY, 1 << dst_pid));
+        }
+
+        None
+    }
+}
+
+fn build_notify_message(src_pid: usize, mtype: usize, bitmap: u64) -> Msg {
+    let mut message = Msg::empty();
+    message.src_pid = src_pid;
+    message.mtype = mtype;
+    message.args[NOTIFY_BITMAP] = bitmap as usize;
+    message
+}
+
+#[cfg(test)]
+mod test {
+    use super::PendingNotifications;
+    use share::ipc::{HARDWARE, INTERRUPT, KERNEL, NOTIFY, NOTIFY_BITMAP};
+
+    #[test]
+    pub fn test_pending_notifications() {
+        info!("starting notification.rs test cases");
+
+        // notifications from the same source are merged, different sources are delivered one by one.
+        let mut pending = PendingNotifications::empty();
+        pending.add_irq(10);
+        pending.add_irq(20);
+        pending.add_kernel_events(0b10);
+        pending.add_pid(5);
+        pending.add_pid(5);
+        pending.add_pid(3);
+
+        let message = pending.take(-1).unwrap();
+        assert_eq!(message.src_pid, HARDWARE);
+        assert_eq!(message.mtype, INTERRUPT);
+        assert_eq!(message.args[NOTIFY_BITMAP], (1 << 10) | (1 << 20));
+        let message = pending.take(-1).unwrap();
+        assert_eq!(message.src_pid, KERNEL);
+        assert_eq!(message.mtype, NOTIFY);
+        assert_eq!(message.args[NOTIFY_BITMAP], 0b10);
+        let message = pending.take(-1).unwrap();
+        assert_eq!(message.src_pid, 3);
+        let message = pending.take(-1).unwrap();
+        assert_eq!(message.src_pid, 5);
+        assert!(pending.take(-1).is_none());
+
+        // receiving from a specific task only accepts notification from it.
+        pending.add_irq(10);
+        pending.add_pid(7);
+        assert!(pending.take(6).is_none());
+        assert_eq!(pending.take(7).unwrap().src_pid, 7);
+        assert_eq!(pending.take(-1).unwrap().src_pid, HARDWARE);
+        assert!(pending.take(-1).is_none());
+
+        info!("end of notification.rs test\n");
+    }
+}
@@ -1,11 +1,17 @@
 use crate::config::MAX_TASK_NUMBER;
 use core::fmt::{Debug, Formatter};
 use spin::Mutex;
+use share::ipc::Endpoint;
 
 pub fn alloc_pid() -> Option<PidHandle> {
     PID_ALLOCATOR.lock().alloc()
 }
 
+/// Return the endpoint of the process which owns `pid` now, or `None` if `pid` is not in use.
+pub fn pid_to_endpoint(pid: usize) -> Option<Endpoint> {
+    PID_ALLOCATOR.lock().endpoint(pid)
+}
+
 
 pub struct PidHandle(pub(crate) usize);
 
@@ -28,6 +34,8 @@ lazy_static!{
 pub struct PidAllocator {
     pub bit_map: u64,
     last_pid: usize,
+    /// Generation of each pid, which grows every time the pid is allocated.
+    generations: [usize; MAX_TASK_NUMBER],
 }
 
 impl PidAllocator {
@@ -35,6 +43,7 @@ impl PidAllocator {
         Self {
             bit_map: 0,
             last_pid: MAX_TASK_NUMBER - 1,
+            generations: [0; MAX_TASK_NUMBER],
         }
     }
 
@@ -51,6 +60,7 @@ impl PidAllocator {
             }
             self.bit_map |= 1 << pos;
             self.last_pid = pos;
+            self.generations[pos] += 1;
             Some(PidHandle(self.last_pid))
         }
     }
@@ -61,6 +71,14 @@ impl PidAllocator {
         self.bit_map ^= 1 << pid;
     }
 
+    fn endpoint(&self, pid: usize) -> Option<Endpoint> {
+        if pid < MAX_TASK_NUMBER && (self.bit_map >> pid) & 1 == 1 {
+            Some(Endpoint::new(pid, self.generations[pid]))
+        } else {
+            None
+        }
+    }
+
     #[allow(unused)]
     fn empty(&mut self) {
         self.bit_map = 0;
@@ -90,9 +108,24 @@ mod test {
         assert_eq!(pid_allocator.bit_map, u64::MAX);
         drop(pid_allocator);
         drop(pids);
-        let pid_allocator = PID_ALLOCATOR.lock();
+        let mut pid_allocator = PID_ALLOCATOR.lock();
         assert_eq!(pid_allocator.bit_map, 0);
+        assert!(pid_allocator.endpoint(0).is_none());
+
+        // a reallocated pid gets a new generation.
+        let pid = pid_allocator.alloc().unwrap();
+        let endpoint = pid_allocator.endpoint(pid.0).unwrap();
+        assert_eq!(endpoint.pid(), pid.0);
+        drop(pid_allocator);
+        drop(pid);
+        let mut pid_allocator = PID_ALLOCATOR.lock();
+        pid_allocator.empty();
+        let pid = pid_allocator.alloc().unwrap();
+        let new_endpoint = pid_allocator.endpoint(pid.0).unwrap();
+        assert_eq!(new_endpoint.pid(), endpoint.pid());
+        assert_eq!(new_endpoint.generation(), endpoint.generation() + 1);
         drop(pid_allocator);
+        drop(pid);
 
         info!("end of pid.rs test\n");
     }
@@ -0,0 +1,132 @@
+use share::ipc::{TERMINAL_SERVICE, BLOCK_SERVICE, FS_SERVICE, RTC_SERVICE};
+use share::syscall::sys_const::*;
+use crate::task::ServiceRegistry;
+
+/// Kcalls which a normal user process is allowed to use.
+const USER_KCALLS: [usize; 22] = [
+    KCALL_SEND, KCALL_RECEIVE, KCALL_SEND_NB, KCALL_RECEIVE_NB, KCALL_SEND_TIMEOUT, KCALL_RECEIVE_TIMEOUT,
+    KCALL_SENDREC, KCALL_SEND_FAST, KCALL_RECEIVE_FAST, KCALL_NOTIFY,
+    KCALL_GRANT_CREATE, KCALL_GRANT_REVOKE, KCALL_SAFECOPY_FROM, KCALL_SAFECOPY_TO,
+    KCALL_SHM_CREATE, KCALL_SHM_MAP, KCALL_SHM_UNMAP, KCALL_LOOKUP,
+    KCALL_SBI_READ, KCALL_SBI_WRITE, KCALL_TERMINAL_READ, KCALL_TERMINAL_WRITE,
+];
+
+/// Services which a task may be allowed to send to, each has a bit in `Privilege::ipc_targets`.
+const SERVICES: [&str; 4] = [TERMINAL_SERVICE, BLOCK_SERVICE, FS_SERVICE, RTC_SERVICE];
+
+/// What a task is allowed to do besides the normal syscalls, checked by the syscall dispatcher.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Privilege {
+    /// System processes are the drivers and servers loaded from the boot image.
+    pub is_system: bool,
+    /// Bit `n` is set when kcall `KCALL_MASK | n` is allowed.
+    kcalls: u64,
+    /// Bit `n` is set when the task may send to the server of `SERVICES[n]`, which is looked up on
+    /// each send, so a server restarted under a new pid is still reached. All bits set allows every
+    /// system process. Other processes can always be reached, so that user processes are able to
+    /// talk to each other.
+    ipc_targets: u64,
+}
+
+impl Privilege {
+    pub const fn system() -> Self {
+        Self {
+            is_system: true,
+            kcalls: u64::MAX,
+            ipc_targets: u64::MAX,
+        }
+    }
+
+    /// Normal user processes may only send to the terminal among system processes, which handles
+    /// their ioctl requests. Other servers are reached through syscalls handled by the kernel.
+    pub fn user() -> Self {
+        let kcalls = USER_KCALLS.iter()
+            .fold(0, |kcalls, kcall| kcalls | 1 << (kcall & !KCALL_MASK));
+        let ipc_targets = SERVICES.iter()
+            .position(|service| *service == TERMINAL_SERVICE)
+            .map_or(0, |n| 1 << n);
+
+        Self {
+            is_system: false,
+            kcalls,
+            ipc_targets,
+        }
+    }
+
+    /// Return the privilege passed on to a forked child or a new program image, which never
+    /// exceeds the privilege of a normal user process.
+    pub fn reduced(&self) -> Self {
+        let user = Self::user();
+        Self {
+            is_system: false,
+            kcalls: self.kcalls & user.kcalls,
+            ipc_targets: self.ipc_targets & user.ipc_targets,
+        }
+    }
+
+    /// Syscalls which are not kcalls are always allowed.
+    pub fn allows_kcall(&self, syscall_id: usize) -> bool {
+        if syscall_id & KCALL_MASK == 0 {
+            return true;
+        }
+        let nr = syscall_id & !KCALL_MASK;
+        nr < 64 && (self.kcalls >> nr) & 1 == 1
+    }
+
+    /// `registry` resolves the allowed services to the pids serving them now.
+    pub fn allows_ipc_to(&self, dst_pid: usize, dst_is_system: bool, registry: &ServiceRegistry) -> bool {
+        if !dst_is_system || self.ipc_targets == u64::MAX {
+            return true;
+        }
+        SERVICES.iter().enumerate()
+            .filter(|(n, _)| (self.ipc_targets >> n) & 1 == 1)
+            .any(|(_, service)| registry.lookup(service).map_or(false, |endpoint| endpoint.pid() == dst_pid))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Privilege;
+    use crate::task::ServiceRegistry;
+    use share::ipc::{Endpoint, TERMINAL_SERVICE, FS_SERVICE};
+    use share::syscall::sys_const::{KCALL_SEND, KCALL_VIRT_COPY, KCALL_WRITE_DEV, KCALL_PUBLISH, SYSCALL_WRITE};
+
+    #[test]
+    pub fn test_privilege() {
+        info!("starting privilege.rs test cases");
+
+        let mut registry = ServiceRegistry::new();
+        registry.publish(TERMINAL_SERVICE, Endpoint::new(3, 1)).unwrap();
+        registry.publish(FS_SERVICE, Endpoint::new(4, 1)).unwrap();
+
+        let system = Privilege::system();
+        assert!(system.allows_kcall(KCALL_WRITE_DEV));
+        assert!(system.allows_ipc_to(4, true, &registry));
+        assert!(system.allows_ipc_to(100, true, &registry));
+
+        // user processes may not touch devices or other tasks' memory, nor talk to servers directly.
+        let user = Privilege::user();
+        assert!(user.allows_kcall(SYSCALL_WRITE));
+        assert!(user.allows_kcall(KCALL_SEND));
+        assert!(!user.allows_kcall(KCALL_WRITE_DEV));
+        assert!(!user.allows_kcall(KCALL_VIRT_COPY));
+        assert!(!user.allows_kcall(KCALL_PUBLISH));
+        assert!(user.allows_ipc_to(10, false, &registry));
+        assert!(user.allows_ipc_to(3, true, &registry));
+        assert!(!user.allows_ipc_to(4, true, &registry));
+
+        // the terminal is still reached after it is restarted under a new pid, whatever the pid is.
+        registry.remove_by_pid(3);
+        registry.publish(TERMINAL_SERVICE, Endpoint::new(70, 1)).unwrap();
+        assert!(user.allows_ipc_to(70, true, &registry));
+        assert!(!user.allows_ipc_to(3, true, &registry));
+
+        // privilege never grows when it is passed on.
+        let reduced = system.reduced();
+        assert!(!reduced.is_system);
+        assert_eq!(reduced, user);
+        assert_eq!(user.reduced(), user);
+
+        info!("end of privilege.rs test\n");
+    }
+}
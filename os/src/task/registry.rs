@@ -0,0 +1,75 @@
+use alloc::string::String;
+use alloc::vec::Vec;
+use share::ipc::Endpoint;
+use share::syscall::error::{SysError, EEXIST};
+use spin::Mutex;
+
+lazy_static! {
+    pub static ref SERVICE_REGISTRY: Mutex<ServiceRegistry> = Mutex::new(ServiceRegistry::new());
+}
+
+/// Names of the services published by servers, so clients don't have to know their pids.
+pub struct ServiceRegistry {
+    services: Vec<(String, Endpoint)>,
+}
+
+impl ServiceRegistry {
+    pub fn new() -> Self {
+        Self {
+            services: Vec::new(),
+        }
+    }
+
+    /// Bind `name` to `endpoint`. A process may publish the same name again, which updates its
+    /// generation, but a name held by another process returns `EEXIST`.
+    pub fn publish(&mut self, name: &str, endpoint: Endpoint) -> Result<(), SysError> {
+        match self.services.iter_mut().find(|(service, _)| service == name) {
+            Some((_, old)) if old.pid() == endpoint.pid() => *old = endpoint,
+            Some(_) => return Err(SysError::new(EEXIST)),
+            None => self.services.push((String::from(name), endpoint)),
+        }
+
+        Ok(())
+    }
+
+    pub fn lookup(&self, name: &str) -> Option<Endpoint> {
+        self.services.iter()
+            .find(|(service, _)| service == name)
+            .map(|(_, endpoint)| *endpoint)
+    }
+
+    /// Remove all services published by `pid`, called when the process exits.
+    pub fn remove_by_pid(&mut self, pid: usize) {
+        self.services.retain(|(_, endpoint)| endpoint.pid() != pid);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::ServiceRegistry;
+    use share::ipc::Endpoint;
+
+    #[test]
+    pub fn test_service_registry() {
+        info!("starting registry.rs test cases");
+
+        let mut registry = ServiceRegistry::new();
+        registry.publish("fs", Endpoint::new(3, 1)).unwrap();
+        registry.publish("tty0", Endpoint::new(1, 1)).unwrap();
+        assert_eq!(registry.lookup("fs"), Some(Endpoint::new(3, 1)));
+        assert!(registry.lookup("blk0").is_none());
+
+        // only the owner may publish the name again.
+        assert!(registry.publish("fs", Endpoint::new(4, 1)).is_err());
+        registry.publish("fs", Endpoint::new(3, 2)).unwrap();
+        assert_eq!(registry.lookup("fs"), Some(Endpoint::new(3, 2)));
+
+        // the name is free again after its owner exits.
+        registry.remove_by_pid(3);
+        assert!(registry.lookup("fs").is_none());
+        registry.publish("fs", Endpoint::new(4, 1)).unwrap();
+        assert_eq!(registry.lookup("tty0"), Some(Endpoint::new(1, 1)));
+
+        info!("end of registry.rs test\n");
+    }
+}
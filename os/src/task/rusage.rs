@@ -0,0 +1,68 @@
+/// Cpu time used by a task, accounted when it traps into the kernel, returns to user mode and is
+/// switched out.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Rusage {
+    pub utime_us: usize,
+    pub stime_us: usize,
+    /// When the current period of user or kernel time began.
+    since: usize,
+}
+
+impl Rusage {
+    pub const fn new() -> Self {
+        Self {
+            utime_us: 0,
+            stime_us: 0,
+            since: 0,
+        }
+    }
+
+    /// The task has been running in user mode until `now`.
+    pub fn user_until(&mut self, now: usize) {
+        self.utime_us += now.saturating_sub(self.since);
+        self.since = now;
+    }
+
+    /// The task has been running in the kernel until `now`.
+    pub fn kernel_until(&mut self, now: usize) {
+        self.stime_us += now.saturating_sub(self.since);
+        self.since = now;
+    }
+
+    /// The task is switched in at `now`, time spent switched out is not counted.
+    pub fn resume(&mut self, now: usize) {
+        self.since = now;
+    }
+
+    pub fn to_user(&self) -> share::time::Rusage {
+        share::time::Rusage {
+            ru_utime: share::time::Timespec::from_us(self.utime_us),
+            ru_stime: share::time::Timespec::from_us(self.stime_us),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Rusage;
+
+    #[test]
+    pub fn test_rusage() {
+        info!("starting rusage.rs test cases");
+
+        let mut rusage = Rusage::new();
+        rusage.resume(100);
+        rusage.user_until(150);
+        rusage.kernel_until(160);
+        // switched out between 160 and 300.
+        rusage.resume(300);
+        rusage.kernel_until(310);
+        rusage.user_until(1000);
+        assert_eq!(rusage.utime_us, 740);
+        assert_eq!(rusage.stime_us, 20);
+        let user = rusage.to_user();
+        assert_eq!(user.ru_utime.tv_usec, 740);
+
+        info!("end of rusage.rs test\n");
+    }
+}
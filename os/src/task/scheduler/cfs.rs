@@ -0,0 +1,84 @@
+use alloc::collections::BTreeMap;
+use alloc::sync::Arc;
+use crate::task::TaskStruct;
+use crate::task::scheduler::Scheduler;
+
+/// A task woken up is put this far behind `min_vruntime` at most, so it runs soon but doesn't take
+/// over the hart after a long sleep. It is one time slice.
+const SLEEPER_CREDIT_US: usize = 10_000;
+/// A task is put this far ahead of `min_vruntime` at most, the vruntime of a task moved in from
+/// another hart is only meaningful on that hart.
+const MAX_LAG_US: usize = 50_000;
+
+/// A fair scheduler, which always runs the task with the smallest vruntime. The vruntime of a task
+/// grows slower with a higher priority, so it gets more cpu time.
+pub struct Cfs {
+    /// Sorted by vruntime, the tasks with the same vruntime run in the order they are put in.
+    tasks: BTreeMap<(usize, usize), Arc<TaskStruct>>,
+    /// The vruntime of the task taken last time, which never goes back.
+    min_vruntime: usize,
+    next_seq: usize,
+}
+
+impl Cfs {
+    pub fn new() -> Self {
+        Self {
+            tasks: BTreeMap::new(),
+            min_vruntime: 0,
+            next_seq: 0,
+        }
+    }
+
+    /// Put `task` into the tree with its vruntime moved into `[floor, min_vruntime + MAX_LAG_US]`.
+    fn place(&mut self, task: Arc<TaskStruct>, floor: usize) {
+        let mut sched = task.sched.lock();
+        let vruntime = usize::max(sched.vruntime, floor).min(self.min_vruntime + MAX_LAG_US);
+        sched.vruntime = vruntime;
+        drop(sched);
+
+        let seq = self.next_seq;
+        self.next_seq += 1;
+        self.tasks.insert((vruntime, seq), task);
+    }
+
+    fn take(&mut self, key: Option<(usize, usize)>) -> Option<Arc<TaskStruct>> {
+        self.tasks.remove(&key?)
+    }
+}
+
+impl Scheduler for Cfs {
+    /// A new task starts from `min_vruntime`, or it would run until catching up with the others.
+    fn enqueue(&mut self, task: Arc<TaskStruct>) {
+        self.place(task, self.min_vruntime);
+    }
+
+    fn dequeue(&mut self) -> Option<Arc<TaskStruct>> {
+        let key = self.tasks.keys().next().copied();
+        let task = self.take(key)?;
+        self.min_vruntime = usize::max(self.min_vruntime, key.unwrap().0);
+        Some(task)
+    }
+
+    fn tick(&mut self, task: Arc<TaskStruct>) {
+        self.place(task, self.min_vruntime.saturating_sub(SLEEPER_CREDIT_US));
+    }
+
+    /// The time the task has run is charged already, it runs again at once only when it is still
+    /// the most behind.
+    fn yield_task(&mut self, task: Arc<TaskStruct>) {
+        self.place(task, self.min_vruntime.saturating_sub(SLEEPER_CREDIT_US));
+    }
+
+    fn wake(&mut self, task: Arc<TaskStruct>) {
+        self.place(task, self.min_vruntime.saturating_sub(SLEEPER_CREDIT_US));
+    }
+
+    fn steal(&mut self) -> Option<Arc<TaskStruct>> {
+        let key = self.tasks.keys().next_back().copied();
+        self.take(key)
+    }
+
+    fn is_empty(&self) -> bool {
+        self.tasks.is_empty()
+    }
+}
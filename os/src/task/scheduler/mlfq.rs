@@ -0,0 +1,60 @@
+use alloc::collections::VecDeque;
+use alloc::sync::Arc;
+use crate::task::TaskStruct;
+use crate::task::scheduler::Scheduler;
+use crate::syscall::MAX_PRIORITY;
+
+const QUEUE_NUM: usize = MAX_PRIORITY as usize + 1;
+
+/// Multi level feedback queues indexed by priority. A task using up its time slice sinks one level
+/// and a task woken up rises one level, so the tasks blocking on ipc are preferred over the cpu
+/// bound ones. A task yielding keeps its level.
+pub struct Mlfq {
+    queues: [VecDeque<Arc<TaskStruct>>; QUEUE_NUM],
+}
+
+impl Mlfq {
+    pub fn new() -> Self {
+        Self {
+            queues: [(); QUEUE_NUM].map(|_| VecDeque::new()),
+        }
+    }
+
+    fn push(&mut self, task: Arc<TaskStruct>) {
+        let priority = task.sched.lock().effective_priority();
+        assert!(priority >= 0);
+        self.queues[priority as usize].push_back(task);
+    }
+}
+
+impl Scheduler for Mlfq {
+    fn enqueue(&mut self, task: Arc<TaskStruct>) {
+        self.push(task);
+    }
+
+    fn dequeue(&mut self) -> Option<Arc<TaskStruct>> {
+        self.queues.iter_mut().find_map(|queue| queue.pop_front())
+    }
+
+    fn tick(&mut self, task: Arc<TaskStruct>) {
+        task.sched.lock().increase_priority();
+        self.push(task);
+    }
+
+    fn yield_task(&mut self, task: Arc<TaskStruct>) {
+        self.push(task);
+    }
+
+    fn wake(&mut self, task: Arc<TaskStruct>) {
+        task.sched.lock().decrease_priority();
+        self.push(task);
+    }
+
+    fn steal(&mut self) -> Option<Arc<TaskStruct>> {
+        self.queues.iter_mut().rev().find_map(|queue| queue.pop_back())
+    }
+
+    fn is_empty(&self) -> bool {
+        self.queues.iter().all(|queue| queue.is_empty())
+    }
+}
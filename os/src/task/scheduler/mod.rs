@@ -0,0 +1,189 @@
+mod mlfq;
+mod cfs;
+
+use alloc::boxed::Box;
+use alloc::sync::Arc;
+use crate::task::TaskStruct;
+use crate::syscall::{MAX_PRIORITY, MIN_PRIORITY};
+
+pub use mlfq::Mlfq;
+pub use cfs::Cfs;
+
+/// Name of the scheduling policy, given by the `SCHEDULER` environment variable at build time:
+/// `mlfq`(the default) or `cfs`. Any other name is rejected by the build script.
+pub const SCHEDULER: &str = match option_env!("SCHEDULER") {
+    Some(name) => name,
+    None => "mlfq",
+};
+
+/// Create the run queue of a hart with the policy selected by [`SCHEDULER`].
+pub fn new_scheduler() -> Box<dyn Scheduler> {
+    match SCHEDULER {
+        "mlfq" => Box::new(Mlfq::new()),
+        "cfs" => Box::new(Cfs::new()),
+        name => unreachable!("unknown scheduler: {}", name),
+    }
+}
+
+/// A scheduling policy, each hart has a run queue of its own.
+///
+/// The methods are called with the run queue locked, so they only take the `sched` lock of the
+/// tasks, which is never held while taking another lock.
+pub trait Scheduler: Send {
+    /// `task` is a new one.
+    fn enqueue(&mut self, task: Arc<TaskStruct>);
+    /// Take the task to run next on this hart.
+    fn dequeue(&mut self) -> Option<Arc<TaskStruct>>;
+    /// `task` has used up its time slice.
+    fn tick(&mut self, task: Arc<TaskStruct>);
+    /// `task` gives up the rest of its time slice.
+    fn yield_task(&mut self, task: Arc<TaskStruct>);
+    /// `task` is READY again after having been blocked.
+    fn wake(&mut self, task: Arc<TaskStruct>);
+    /// Take a task for an idle hart, which is the one going to wait the longest on this hart.
+    fn steal(&mut self) -> Option<Arc<TaskStruct>>;
+    fn is_empty(&self) -> bool;
+}
+
+/// Weight of a task with priority 3 in the fair scheduler.
+const NICE_0_WEIGHT: usize = 1024;
+
+/// Cpu share of the priorities, each priority gets about 1.25 times the cpu time of the next one.
+const PRIO_TO_WEIGHT: [usize; MAX_PRIORITY as usize + 1] = [1991, 1586, 1277, 1024, 820, 655, 526, 423];
+
+/// Scheduling state of a task.
+pub struct SchedEntity {
+    /// The queue of the task in MLFQ and its weight in CFS, a smaller one is a higher priority.
+    pub priority: isize,
+    /// The highest priority the task is able to get.
+    pub min_priority: isize,
+    /// The pid and the priority of a sendrec caller waiting for this task to reply, the task runs
+    /// at that priority if it is higher than its own.
+    inherited: Option<(usize, isize)>,
+    /// Cpu time used by the task in us, scaled by its weight.
+    pub vruntime: usize,
+    /// When the task has been switched in.
+    exec_start: usize,
+}
+
+impl SchedEntity {
+    pub const fn new() -> Self {
+        Self {
+            priority: 0,
+            min_priority: 0,
+            inherited: None,
+            vruntime: 0,
+            exec_start: 0,
+        }
+    }
+
+    /// A child starts with the priorities and the vruntime of its parent, but not the inherited one.
+    pub fn fork(&self) -> Self {
+        Self {
+            priority: self.priority,
+            min_priority: self.min_priority,
+            inherited: None,
+            vruntime: self.vruntime,
+            exec_start: 0,
+        }
+    }
+
+    /// The task is switched in at `now`.
+    pub fn start(&mut self, now: usize) {
+        self.exec_start = now;
+    }
+
+    /// The task has been running until `now`.
+    pub fn charge(&mut self, now: usize) {
+        let delta = now.saturating_sub(self.exec_start);
+        self.vruntime += delta * NICE_0_WEIGHT / self.weight();
+        self.exec_start = now;
+    }
+
+    pub fn weight(&self) -> usize {
+        PRIO_TO_WEIGHT[self.effective_priority() as usize]
+    }
+
+    /// The priority the task runs at, which is the higher one of its own and the inherited one.
+    pub fn effective_priority(&self) -> isize {
+        match self.inherited {
+            Some((_, priority)) => isize::min(self.priority, priority),
+            None => self.priority,
+        }
+    }
+
+    /// Run at `priority` of task `donor` if it is higher, until [`revert`](Self::revert) is called
+    /// for `donor`. Only the highest donor is remembered.
+    pub fn inherit(&mut self, donor: usize, priority: isize) {
+        if priority < self.effective_priority() {
+            self.inherited = Some((donor, priority));
+        }
+    }
+
+    /// Drop the priority inherited from task `donor`, or any inherited priority if `donor` is None.
+    pub fn revert(&mut self, donor: Option<usize>) {
+        if donor.is_none() || self.inherited.map(|(pid, _)| pid) == donor {
+            self.inherited = None;
+        }
+    }
+
+    pub fn increase_priority(&mut self) {
+        if self.priority < MAX_PRIORITY {
+            self.priority += 1;
+        }
+    }
+
+    pub fn decrease_priority(&mut self) {
+        let min_priority = isize::max(MIN_PRIORITY, self.min_priority);
+        if self.priority > min_priority {
+            self.priority -= 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::SchedEntity;
+
+    #[test]
+    pub fn test_sched_entity() {
+        info!("starting scheduler/mod.rs test cases");
+
+        let mut entity = SchedEntity::new();
+        entity.priority = 3;
+        entity.start(100);
+        entity.charge(1100);
+        assert_eq!(entity.vruntime, 1000);
+
+        // a lower priority is charged more for the same time.
+        entity.priority = 7;
+        entity.charge(2100);
+        assert_eq!(entity.vruntime, 1000 + 1000 * 1024 / 423);
+
+        entity.min_priority = 6;
+        entity.decrease_priority();
+        entity.decrease_priority();
+        assert_eq!(entity.priority, 6);
+        entity.increase_priority();
+        entity.increase_priority();
+        assert_eq!(entity.priority, 7);
+
+        // a lower priority is never inherited, and only the donor takes its priority back.
+        entity.inherit(10, 7);
+        assert_eq!(entity.effective_priority(), 7);
+        entity.inherit(11, 3);
+        entity.inherit(12, 4);
+        assert_eq!(entity.effective_priority(), 3);
+        assert_eq!(entity.weight(), 1024);
+        entity.revert(Some(12));
+        assert_eq!(entity.effective_priority(), 3);
+        entity.revert(Some(11));
+        assert_eq!(entity.effective_priority(), 7);
+        entity.inherit(12, 4);
+        entity.revert(None);
+        assert_eq!(entity.effective_priority(), 7);
+        assert_eq!(entity.fork().effective_priority(), 7);
+
+        info!("end of scheduler/mod.rs test\n");
+    }
+}
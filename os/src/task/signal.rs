@@ -0,0 +1,230 @@
+use share::signal::{SigAction, SigSet, SIGNAL_NUM, SIGKILL, SIGSTOP, SIGCONT, SIGTSTP, SIGTTIN, SIGTTOU, SIGCHLD, SIG_DFL, SIG_IGN, sig_bit, SIG_BLOCK, SIG_UNBLOCK, SIG_SETMASK};
+use share::syscall::error::{SysError, EINVAL};
+
+/// Signals which can be neither caught, ignored nor blocked.
+const UNCATCHABLE: SigSet = sig_bit(SIGKILL) | sig_bit(SIGSTOP);
+const STOP_SIGNALS: SigSet = sig_bit(SIGSTOP) | sig_bit(SIGTSTP) | sig_bit(SIGTTIN) | sig_bit(SIGTTOU);
+
+/// What happens to a task when a signal with `SIG_DFL` handler is delivered.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum DefaultAction {
+    Terminate,
+    Ignore,
+    Stop,
+    Continue,
+}
+
+pub fn default_action(signo: usize) -> DefaultAction {
+    match signo {
+        SIGCHLD => DefaultAction::Ignore,
+        SIGCONT => DefaultAction::Continue,
+        _ if sig_bit(signo) & STOP_SIGNALS != 0 => DefaultAction::Stop,
+        _ => DefaultAction::Terminate,
+    }
+}
+
+/// Signal state of a task: signals sent but not delivered yet, signals blocked by sigprocmask and
+/// the handler table set by sigaction.
+#[derive(Copy, Clone)]
+pub struct Signals {
+    pending: SigSet,
+    pub blocked: SigSet,
+    actions: [SigAction; SIGNAL_NUM],
+}
+
+impl Signals {
+    pub const fn new() -> Self {
+        Self {
+            pending: 0,
+            blocked: 0,
+            actions: [SigAction::default(); SIGNAL_NUM],
+        }
+    }
+
+    /// A forked child inherits the handlers and the blocked mask, but no pending signals.
+    pub fn fork(&self) -> Self {
+        Self {
+            pending: 0,
+            ..*self
+        }
+    }
+
+    /// Handlers don't exist in a new program image, so they are reset to `SIG_DFL` by exec.
+    /// Ignored signals stay ignored.
+    pub fn exec(&mut self) {
+        for action in self.actions.iter_mut() {
+            if action.handler != SIG_IGN {
+                *action = SigAction::default();
+            }
+        }
+    }
+
+    /// Mark `signo` as pending. A stop signal discards a pending `SIGCONT` and vice versa.
+    pub fn add(&mut self, signo: usize) {
+        if sig_bit(signo) & STOP_SIGNALS != 0 {
+            self.pending &= !sig_bit(SIGCONT);
+        } else if signo == SIGCONT {
+            self.pending &= !STOP_SIGNALS;
+        }
+        self.pending |= sig_bit(signo);
+    }
+
+    /// Take the lowest pending signal which is not blocked.
+    pub fn take(&mut self) -> Option<usize> {
+        let deliverable = self.pending & !self.blocked;
+        if deliverable == 0 {
+            return None;
+        }
+        let signo = deliverable.trailing_zeros() as usize;
+        self.pending ^= sig_bit(signo);
+        Some(signo)
+    }
+
+    pub fn is_pending(&self, signo: usize) -> bool {
+        self.pending & sig_bit(signo) != 0
+    }
+
+    /// Whether delivering `signo` does anything at all, a task blocked in ipc is interrupted only
+    /// for such signals.
+    pub fn is_wanted(&self, signo: usize) -> bool {
+        if self.blocked & sig_bit(signo) != 0 {
+            return false;
+        }
+        match self.actions[signo].handler {
+            SIG_IGN => false,
+            SIG_DFL => default_action(signo) != DefaultAction::Ignore,
+            _ => true,
+        }
+    }
+
+    /// Whether a blocking call should be interrupted because of pending signals.
+    pub fn has_wanted_pending(&self) -> bool {
+        let deliverable = self.pending & !self.blocked;
+        (1..SIGNAL_NUM).any(|signo| deliverable & sig_bit(signo) != 0 && self.is_wanted(signo))
+    }
+
+    pub fn action(&self, signo: usize) -> SigAction {
+        self.actions[signo]
+    }
+
+    /// Install `action` for `signo` and return the old one.
+    pub fn set_action(&mut self, signo: usize, action: SigAction) -> Result<SigAction, SysError> {
+        check_signo(signo)?;
+        if sig_bit(signo) & UNCATCHABLE != 0 {
+            return Err(SysError::new(EINVAL));
+        }
+        let old = self.actions[signo];
+        self.actions[signo] = action;
+        if action.handler == SIG_IGN { // POSIX: setting SIG_IGN discards the pending signal.
+            self.pending &= !sig_bit(signo);
+        }
+        Ok(old)
+    }
+
+    pub fn reset_action(&mut self, signo: usize) {
+        self.actions[signo] = SigAction::default();
+    }
+
+    /// Change the blocked mask like sigprocmask does and return the old one.
+    pub fn set_blocked(&mut self, how: usize, set: SigSet) -> Result<SigSet, SysError> {
+        let old = self.blocked;
+        let blocked = match how {
+            SIG_BLOCK => old | set,
+            SIG_UNBLOCK => old & !set,
+            SIG_SETMASK => set,
+            _ => return Err(SysError::new(EINVAL)),
+        };
+        self.blocked = blocked & !UNCATCHABLE & !1;
+        Ok(old)
+    }
+
+    /// An exiting task takes no more signals, not even `SIGKILL`.
+    pub fn block_all(&mut self) {
+        self.blocked = SigSet::MAX;
+    }
+
+    /// Make sure `signo` raised by a fault is delivered: it is unblocked and a `SIG_IGN` handler
+    /// goes back to `SIG_DFL`, otherwise the task would run into the same fault forever.
+    pub fn force(&mut self, signo: usize) {
+        self.blocked &= !sig_bit(signo);
+        if self.actions[signo].handler == SIG_IGN {
+            self.actions[signo] = SigAction::default();
+        }
+        self.add(signo);
+    }
+}
+
+pub fn check_signo(signo: usize) -> Result<(), SysError> {
+    if signo == 0 || signo >= SIGNAL_NUM {
+        return Err(SysError::new(EINVAL));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Signals, default_action, DefaultAction};
+    use share::signal::*;
+
+    #[test]
+    pub fn test_signals() {
+        info!("starting signal.rs test cases");
+
+        assert_eq!(default_action(SIGTERM), DefaultAction::Terminate);
+        assert_eq!(default_action(SIGCHLD), DefaultAction::Ignore);
+        assert_eq!(default_action(SIGTSTP), DefaultAction::Stop);
+        assert_eq!(default_action(SIGCONT), DefaultAction::Continue);
+
+        // pending signals are taken from low to high, blocked ones stay pending.
+        let mut signals = Signals::new();
+        signals.add(SIGUSR2);
+        signals.add(SIGUSR1);
+        signals.set_blocked(SIG_BLOCK, sig_bit(SIGUSR1)).unwrap();
+        assert_eq!(signals.take(), Some(SIGUSR2));
+        assert_eq!(signals.take(), None);
+        assert_eq!(signals.set_blocked(SIG_UNBLOCK, sig_bit(SIGUSR1)).unwrap(), sig_bit(SIGUSR1));
+        assert_eq!(signals.take(), Some(SIGUSR1));
+
+        // SIGKILL and SIGSTOP can't be caught or blocked.
+        let mut action = SigAction::default();
+        action.handler = 0x1000;
+        assert!(signals.set_action(SIGKILL, action).is_err());
+        assert!(signals.set_action(0, action).is_err());
+        signals.set_blocked(SIG_SETMASK, u64::MAX).unwrap();
+        assert_eq!(signals.blocked, !(sig_bit(SIGKILL) | sig_bit(SIGSTOP) | 1));
+
+        // stop and continue signals cancel each other.
+        signals.add(SIGSTOP);
+        signals.add(SIGCONT);
+        signals.set_blocked(SIG_SETMASK, 0).unwrap();
+        assert_eq!(signals.take(), Some(SIGCONT));
+        assert_eq!(signals.take(), None);
+
+        // ignored signals are dropped and not wanted, handlers are reset by exec.
+        signals.set_action(SIGUSR1, action).unwrap();
+        assert!(signals.is_wanted(SIGUSR1));
+        assert!(!signals.is_wanted(SIGCHLD));
+        signals.add(SIGCHLD);
+        assert!(!signals.has_wanted_pending());
+        signals.add(SIGUSR1);
+        assert!(signals.has_wanted_pending());
+        assert_eq!(signals.take(), Some(SIGUSR1));
+        assert_eq!(signals.take(), Some(SIGCHLD));
+        action.handler = SIG_IGN;
+        signals.add(SIGINT);
+        signals.set_action(SIGINT, action).unwrap();
+        assert_eq!(signals.take(), None);
+        signals.exec();
+        assert_eq!(signals.action(SIGUSR1).handler, SIG_DFL);
+        assert_eq!(signals.action(SIGINT).handler, SIG_IGN);
+
+        // a fault signal is always delivered.
+        signals.set_blocked(SIG_BLOCK, sig_bit(SIGSEGV)).unwrap();
+        signals.set_action(SIGSEGV, action).unwrap();
+        signals.force(SIGSEGV);
+        assert_eq!(signals.take(), Some(SIGSEGV));
+        assert_eq!(signals.action(SIGSEGV).handler, SIG_DFL);
+
+        info!("end of signal.rs test\n");
+    }
+}
@@ -1,13 +1,27 @@
 use crate::task::task_struct::TaskStruct;
 use spin::Mutex;
-use alloc::sync::{Arc, Weak};
-use alloc::collections::VecDeque;
+use alloc::sync::Arc;
+use alloc::boxed::Box;
 use crate::config::MAX_TASK_NUMBER;
 use alloc::vec::Vec;
-use crate::syscall::MAX_PRIORITY;
+use core::sync::atomic::Ordering;
+use crate::task::scheduler::{Scheduler, new_scheduler};
+use crate::processor::{CPU_NUMS, get_hart_id, kick_idle_hart};
 
+/// Take a task from the run queue of current hart, or steal one from other harts when it is empty.
 pub fn fetch_a_task_from_manager() -> Option<Arc<TaskStruct>> {
-    TASK_MANAGER.lock().dequeue()
+    let hart_id = get_hart_id();
+    if let Some(task) = RUN_QUEUES[hart_id].lock().dequeue() {
+        return Some(task);
+    }
+    (1..CPU_NUMS).map(|i| (hart_id + i) % CPU_NUMS).find_map(|victim| {
+        RUN_QUEUES[victim].lock().steal()
+    })
+}
+
+/// Whether any hart has a task to run, which an idle hart is able to steal.
+pub fn has_ready_task() -> bool {
+    RUN_QUEUES.iter().any(|queue| !queue.lock().is_empty())
 }
 
 pub fn rm_task_from_manager(task_struct: Arc<TaskStruct>) {
@@ -16,28 +30,50 @@ pub fn rm_task_from_manager(task_struct: Arc<TaskStruct>) {
 }
 
 pub fn add_a_task_to_manager(task_struct: Arc<TaskStruct>) {
-    TASK_MANAGER.lock().add(task_struct);
+    TASK_MANAGER.lock().add(task_struct.clone());
+    put_into_run_queue(task_struct, |scheduler, task| scheduler.enqueue(task));
 }
 
+/// Put a task woken up into the run queue.
 pub fn return_task_to_manager(task_struct: Arc<TaskStruct>) {
-    TASK_MANAGER.lock().enqueue(task_struct);
+    put_into_run_queue(task_struct, |scheduler, task| scheduler.wake(task));
+}
+
+/// Put current task back into the run queue, after it has used up its time slice if `preempted`, or
+/// after it has yielded.
+pub fn requeue_task_to_manager(task_struct: Arc<TaskStruct>, preempted: bool) {
+    if preempted {
+        put_into_run_queue(task_struct, |scheduler, task| scheduler.tick(task));
+    } else {
+        put_into_run_queue(task_struct, |scheduler, task| scheduler.yield_task(task));
+    }
+}
+
+/// Put a READY task into the run queue of the hart it has run on last time, which is likely to
+/// still have its memory cached, and kick an idle hart to run it.
+fn put_into_run_queue(task_struct: Arc<TaskStruct>, put: impl FnOnce(&mut dyn Scheduler, Arc<TaskStruct>)) {
+    assert!(TASK_MANAGER.lock().get_task_by_pid(task_struct.pid()).is_some());
+    let hart_id = task_struct.hart.load(Ordering::Relaxed);
+    put(&mut **RUN_QUEUES[hart_id].lock(), task_struct);
+    kick_idle_hart(hart_id);
 }
 
 pub fn get_task_by_pid(pid: usize) -> Option<Arc<TaskStruct>> {
     TASK_MANAGER.lock().get_task_by_pid(pid)
 }
 
+pub fn get_all_tasks() -> Vec<Arc<TaskStruct>> {
+    TASK_MANAGER.lock().get_all_tasks()
+}
+
 lazy_static!{
     pub static ref TASK_MANAGER: Mutex<TaskManager> = Mutex::new(TaskManager::new());
+    static ref RUN_QUEUES: [Mutex<Box<dyn Scheduler>>; CPU_NUMS] = [(); CPU_NUMS].map(|_| Mutex::new(new_scheduler()));
 }
 
-const QUEUE_NUM: usize = MAX_PRIORITY as usize + 1;
-
-/// a simple multi queue scheduler.
+/// All the alive tasks, indexed by pid.
 pub struct TaskManager {
     pid_2_task: Vec<Option<Arc<TaskStruct>>>,
-    queues: [VecDeque<Arc<TaskStruct>>; QUEUE_NUM],
-    last_task: Option<Weak<TaskStruct>>,
 }
 
 impl TaskManager {
@@ -47,64 +83,19 @@ impl TaskManager {
             pid_2_task.push(None);
         });
 
-        let queues = [
-            VecDeque::new(),
-            VecDeque::new(),
-            VecDeque::new(),
-            VecDeque::new(),
-            VecDeque::new(),
-            VecDeque::new(),
-            VecDeque::new(),
-            VecDeque::new(),
-        ];
-
         Self {
             pid_2_task,
-            queues,
-            last_task: None,
         }
     }
 
+    /// `add()` is used when new task is created, the task is put into a run queue by
+    /// [`return_task_to_manager`] afterwards.
     pub fn add(&mut self, task: Arc<TaskStruct>)  {
         let pid = task.pid_handle.0;
         assert!(self.pid_2_task[pid].is_none());
-        self.pid_2_task[pid] = Some(Arc::clone(&task));
-
-        self.enqueue(task);
-    }
-
-    /// difference between `add()` and `enqueue()` is that `add()` is used when new task is created,
-    /// while `enqueue()` is used when an old task is temporarily stopped.
-    pub fn enqueue(&mut self, task: Arc<TaskStruct>) {
-        let pid = task.pid_handle.0;
-        assert!(self.pid_2_task[pid].is_some());
-        if self.is_last_running_task(&task) {
-            task.increase_priority();
-        } else {
-            task.decrease_priority();
-        }
-
-        let priority = task.acquire_inner_lock().priority;
-        assert!(priority >= 0);
-        self.queues[priority as usize].push_back(task);
+        self.pid_2_task[pid] = Some(task);
     }
 
-    pub fn dequeue(&mut self) -> Option<Arc<TaskStruct>> {
-        let result = self.queues.iter().enumerate().find(|(_, queue)| {
-            queue.len() != 0
-        });
-        if result.is_none() {
-            return None;
-        }
-
-        let index = result.unwrap().0;
-        let task: Arc<TaskStruct> = self.queues[index].pop_front().unwrap();
-        self.last_task = Some(Arc::downgrade(&task));
-
-        Some(task)
-    }
-
-
     pub fn get_task_by_pid(&self, pid: usize) -> Option<Arc<TaskStruct>> {
         if pid >= MAX_TASK_NUMBER {
             None
@@ -113,7 +104,11 @@ impl TaskManager {
         }
     }
 
-    /// This function should be removed when the signal module is implemented.
+    pub fn get_all_tasks(&self) -> Vec<Arc<TaskStruct>> {
+        self.pid_2_task.iter().flatten().cloned().collect()
+    }
+
+    /// Zombies are only kept by their parents, so that signals and ipc can not reach them.
     pub fn rm_task_by_pid(&mut self, pid: usize) -> bool {
         if pid >= MAX_TASK_NUMBER {
             return false;
@@ -121,17 +116,4 @@ impl TaskManager {
         self.pid_2_task[pid].take();
         true
     }
-
-    fn is_last_running_task(&self, task: &Arc<TaskStruct>) -> bool {
-        if self.last_task.is_none() {
-            return false;
-        }
-        let result = self.last_task.as_ref().unwrap().upgrade();
-        if result.is_none() { // last running task has already been removed.
-            return false;
-        }
-        let last_task = result.unwrap();
-
-        return Arc::ptr_eq(&last_task, task);
-    }
-}
\ No newline at end of file
+}
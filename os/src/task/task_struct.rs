@@ -8,10 +8,27 @@ use crate::task::task_context::TaskContext;
 use crate::mm::memory_manager::MemoryManager;
 use share::syscall::error::SysError;
 use share::ipc::Msg;
-use crate::syscall::{MAX_PRIORITY, MIN_PRIORITY};
+use crate::task::notification::PendingNotifications;
+use crate::task::grant::GrantTable;
+use crate::task::privilege::Privilege;
+use crate::task::signal::Signals;
+use crate::task::rusage::Rusage;
+use crate::task::scheduler::SchedEntity;
+use crate::processor::get_hart_id;
+use core::sync::atomic::{AtomicBool, AtomicUsize};
 
 pub struct TaskStruct {
-    pub pid_handle: PidHandle,
+    /// Shared with the [`Zombie`] record of the task, so the pid is not reused before it is waited.
+    pub pid_handle: Arc<PidHandle>,
+    /// Pid of the thread group leader, which is the pid of the process seen by the user. The leader
+    /// keeps its own pid here.
+    pub tgid_handle: Arc<PidHandle>,
+    /// Set while a hart is running the task, until its context has been saved by `__switch`.
+    pub on_cpu: AtomicBool,
+    /// The hart the task has run on last time, whose run queue it is put into when it is ready.
+    pub hart: AtomicUsize,
+    /// Taken inside the run queue lock, it is never held while taking another lock.
+    pub sched: Mutex<SchedEntity>,
     pub inner: Mutex<TaskStructInner>
 }
 
@@ -21,15 +38,38 @@ pub struct TaskStructInner {
     pub task_context: TaskContext,
     // ipc
     pub message_holder: Option<Msg>,
-    pub interrupt_flag: bool,
+    pub notifications: PendingNotifications,
+    /// Errno of a blocked ipc call which fails without a message: `ETIMEDOUT` when its deadline
+    /// has passed, `EDEADSRCDST` when its peer has exited.
+    pub ipc_error: Option<i32>,
+    /// Set during a sendrec call. The receiver moves a queued sendrec caller to `RECEIVING` state
+    /// instead of waking it up, and notifications are never taken as the reply.
+    pub sendrec: bool,
+    /// Set when the wait queue of an exiting task has been drained, after which ipc with it fails
+    /// with `EDEADSRCDST` instead of blocking.
+    pub exiting: bool,
     pub wait_queue: Vec<Arc<TaskStruct>>,
-
-    pub mem_manager: MemoryManager,
-
-    pub priority: isize,
-    pub min_priority: isize,
+    /// Memory which this task allows other tasks to access, see [`kcall_grant_create`](crate::syscall).
+    pub grants: GrantTable,
+    /// Kcalls and ipc destinations allowed for this task.
+    pub privilege: Privilege,
+    pub signals: Signals,
+    /// Set when the task stops or continues, and taken when it is reported by waitpid of the parent.
+    pub wait_event: Option<WaitEvent>,
+    pub rusage: Rusage,
+    /// Set by `CLONE_CHILD_CLEARTID`, a zero is written to this address when the task exits.
+    pub clear_child_tid: usize,
+    /// Pid of the process group leader and the session leader, which are the same for the threads
+    /// in the same group. They are held so that the pids are not reused while the group is alive.
+    pub pgrp_handle: Arc<PidHandle>,
+    pub session_handle: Arc<PidHandle>,
+
+    /// Shared by the threads in the same group.
+    pub mem_manager: Arc<Mutex<MemoryManager>>,
 
     pub children:Vec<Arc<TaskStruct>>,
+    /// Children which have exited but not been waited yet.
+    pub zombies: Vec<Zombie>,
     pub parent: Option<Weak<TaskStruct>>,
 }
 
@@ -39,6 +79,7 @@ impl TaskStruct {
         let pid_handle = alloc_pid().unwrap();
         user_sp -= core::mem::size_of::<usize>() * 3; // push argc, NULL and NULL onto stack.
 
+        let pid_handle = Arc::new(pid_handle);
         let kernel_stack = KernelStack::new()?;
         let task_context = TaskContext::new(kernel_stack.sp() - core::mem::size_of::<TrapContext>());
 
@@ -48,11 +89,21 @@ impl TaskStruct {
             flag: RuntimeFlags::READY,
             task_context,
             message_holder: None,
-            interrupt_flag: false,
-            mem_manager,
-            priority: 0,
-            min_priority: 0,
+            notifications: PendingNotifications::empty(),
+            ipc_error: None,
+            sendrec: false,
+            exiting: false,
+            grants: GrantTable::new(),
+            privilege: Privilege::user(),
+            signals: Signals::new(),
+            wait_event: None,
+            rusage: Rusage::new(),
+            clear_child_tid: 0,
+            pgrp_handle: pid_handle.clone(),
+            session_handle: pid_handle.clone(),
+            mem_manager: Arc::new(Mutex::new(mem_manager)),
             children: Vec::new(),
+            zombies: Vec::new(),
             parent: None,
         };
         // push `trap_context` onto `kernel_stack`
@@ -60,7 +111,11 @@ impl TaskStruct {
          *trap_context_ref = TrapContext::new(pc, user_sp);
 
         Ok(Self {
+            tgid_handle: pid_handle.clone(),
             pid_handle,
+            on_cpu: AtomicBool::new(false),
+            hart: AtomicUsize::new(get_hart_id()),
+            sched: Mutex::new(SchedEntity::new()),
             inner: Mutex::new(inner),
         })
     }
@@ -74,19 +129,12 @@ impl TaskStruct {
         self.pid_handle.0
     }
 
-    pub fn increase_priority(&self) {
-        let mut inner = self.acquire_inner_lock();
-        if inner.priority < MAX_PRIORITY {
-            inner.priority += 1;
-        }
+    pub fn tgid(&self) -> usize {
+        self.tgid_handle.0
     }
 
-    pub fn decrease_priority(&self) {
-        let mut inner = self.acquire_inner_lock();
-        let min_priority = isize::max(MIN_PRIORITY, inner.min_priority);
-        if inner.priority > min_priority {
-            inner.priority -= 1;
-        }
+    pub fn is_group_leader(&self) -> bool {
+        Arc::ptr_eq(&self.pid_handle, &self.tgid_handle)
     }
 }
 
@@ -100,6 +148,14 @@ impl TaskStructInner {
         trap_context_pa.as_mut()
     }
 
+    pub fn pgid(&self) -> usize {
+        self.pgrp_handle.0
+    }
+
+    pub fn sid(&self) -> usize {
+        self.session_handle.0
+    }
+
     pub fn is_receiving_from(&self, another_task: &Arc<TaskStruct>) -> bool {
         match self.flag {
             RuntimeFlags::RECEIVING(target_pid) =>
@@ -109,8 +165,12 @@ impl TaskStructInner {
     }
 
     pub fn is_sending_to(&self, another_task: &Arc<TaskStruct>) -> bool {
+        self.is_sending_to_pid(another_task.pid())
+    }
+
+    pub fn is_sending_to_pid(&self, pid: usize) -> bool {
         match self.flag {
-            RuntimeFlags::SENDING(target_pid) => target_pid == another_task.pid(),
+            RuntimeFlags::SENDING(target_pid) => target_pid == pid,
             _ => false
         }
     }
@@ -121,6 +181,57 @@ pub enum RuntimeFlags {
     RECEIVING(isize),
     SENDING(usize),
     READY,
+    /// Blocked in waitpid until a child changes its state.
+    WAITING,
+    /// Blocked in futex wait.
+    FUTEX,
+    /// Sleeping until the deadline(in us), or until a wanted signal arrives.
+    SLEEPING(usize),
+    /// Stopped by the signal, until `SIGCONT` or `SIGKILL` arrives.
+    STOPPED(usize),
+    /// Holds the wait status reported to the parent.
     ZOMBIE(isize),
     RUNNING,
-}
\ No newline at end of file
+}
+
+impl RuntimeFlags {
+    /// Whether the task waits for someone else to make it READY.
+    pub fn is_blocked(&self) -> bool {
+        matches!(self, RuntimeFlags::RECEIVING(_) | RuntimeFlags::SENDING(_) | RuntimeFlags::WAITING
+            | RuntimeFlags::FUTEX | RuntimeFlags::SLEEPING(_) | RuntimeFlags::STOPPED(_))
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum WaitEvent {
+    Stopped(usize),
+    Continued,
+}
+
+/// What is left of an exited task until its parent waits for it.
+pub struct Zombie {
+    pid_handle: Arc<PidHandle>,
+    /// The zombie stays in its process group, so that waitpid can find it by the group.
+    pgrp_handle: Arc<PidHandle>,
+    pub status: isize,
+    pub rusage: Rusage,
+}
+
+impl Zombie {
+    pub fn new(task: &TaskStruct, inner: &TaskStructInner, status: isize) -> Self {
+        Self {
+            pid_handle: task.pid_handle.clone(),
+            pgrp_handle: inner.pgrp_handle.clone(),
+            status,
+            rusage: inner.rusage,
+        }
+    }
+
+    pub fn pid(&self) -> usize {
+        self.pid_handle.0
+    }
+
+    pub fn pgid(&self) -> usize {
+        self.pgrp_handle.0
+    }
+}
@@ -6,6 +6,8 @@ pub struct TrapContext {
     pub x: [usize; 32],
     pub sstatus: usize,
     pub sepc: usize,
+    /// Id of the hart running the task, kept here while `tp` holds the thread pointer of the task.
+    pub hart_id: usize,
 }
 
 impl TrapContext {
@@ -27,7 +29,8 @@ impl TrapContext {
         let mut task_context = TrapContext {
             x: [0; 32],
             sstatus,
-            sepc: entry
+            sepc: entry,
+            hart_id: 0,
         };
         task_context.x[2] = task_sp;
         task_context
@@ -38,6 +41,7 @@ impl TrapContext {
             x: self.x,
             sstatus: self.sstatus,
             sepc: self.sepc,
+            hart_id: self.hart_id,
         }
     }
 }
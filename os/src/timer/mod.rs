@@ -1,6 +1,10 @@
+mod timer_list;
+
 use crate::sbi::sbi_legacy_set_timer;
 use riscv::register::{sie, time};
 
+pub use timer_list::{add_timer, cancel_timer, TimerHandle};
+
 #[cfg(feature = "board_qemu")]
 const CLOCK_FREQUENCY: usize = 12500000;
 #[cfg(feature = "board_k210")]
@@ -9,8 +13,9 @@ const CLOCK_FREQUENCY: usize = 403000000 / 62;
 const MSEC_PER_SEC: usize = 1000;
 
 const TICKS_PER_SEC: usize = 100;
-#[allow(unused)]
-const USEC_PER_SEC: usize = 1000000;
+pub const USEC_PER_SEC: usize = 1000000;
+/// Timers are checked on each tick, a blocked task polling something wakes up once a tick too.
+pub const USEC_PER_TICK: usize = USEC_PER_SEC / TICKS_PER_SEC;
 
 pub fn enable_time_interrupt() {
     unsafe {
@@ -39,3 +44,8 @@ pub fn get_time_us() -> usize {
 pub fn set_timer_ms(slice_ms: usize) {
     sbi_legacy_set_timer(get_time() + CLOCK_FREQUENCY * slice_ms / MSEC_PER_SEC);
 }
+
+/// Fire the expired timers, called on each timer interrupt of every hart.
+pub fn check_timers() {
+    timer_list::check_timers(get_time_us());
+}
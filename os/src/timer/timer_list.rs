@@ -0,0 +1,145 @@
+use alloc::boxed::Box;
+use alloc::collections::BTreeMap;
+use alloc::vec::Vec;
+use spin::Mutex;
+
+/// Run `callback` once at `deadline_us`, or a bit later since timers are checked on each timer
+/// interrupt. The callback runs without any lock held, it must not block.
+pub fn add_timer(deadline_us: usize, callback: impl FnOnce() + Send + 'static) -> TimerHandle {
+    TIMER_LIST.lock().add(deadline_us, Box::new(callback))
+}
+
+/// Return false if the timer has already fired. A callback being run by another hart is waited
+/// for, so it never runs after the timer is cancelled, e.g. when the task it wakes up has moved on.
+pub fn cancel_timer(handle: TimerHandle) -> bool {
+    loop {
+        let mut timer_list = TIMER_LIST.lock();
+        if timer_list.cancel(handle) {
+            return true;
+        }
+        if !timer_list.is_firing(handle) {
+            return false;
+        }
+        drop(timer_list);
+        core::hint::spin_loop();
+    }
+}
+
+/// Fire the timers whose deadline has passed.
+pub fn check_timers(now_us: usize) {
+    let expired = TIMER_LIST.lock().take_expired(now_us);
+    for (handle, callback) in expired {
+        callback();
+        TIMER_LIST.lock().finish(handle);
+    }
+}
+
+lazy_static! {
+    static ref TIMER_LIST: Mutex<TimerList> = Mutex::new(TimerList::new());
+}
+
+type Callback = Box<dyn FnOnce() + Send>;
+
+/// Identifies a timer, which is given back by [`add_timer`] to cancel it.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct TimerHandle {
+    deadline: usize,
+    id: usize,
+}
+
+/// One-shot timers sorted by their deadlines, timers with the same deadline fire in the order they
+/// have been added.
+pub struct TimerList {
+    timers: BTreeMap<(usize, usize), Callback>,
+    /// Timers taken by `take_expired` whose callbacks have not returned yet.
+    firing: Vec<TimerHandle>,
+    next_id: usize,
+}
+
+impl TimerList {
+    pub fn new() -> Self {
+        Self {
+            timers: BTreeMap::new(),
+            firing: Vec::new(),
+            next_id: 0,
+        }
+    }
+
+    pub fn add(&mut self, deadline: usize, callback: Callback) -> TimerHandle {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.timers.insert((deadline, id), callback);
+        TimerHandle { deadline, id }
+    }
+
+    pub fn cancel(&mut self, handle: TimerHandle) -> bool {
+        self.timers.remove(&(handle.deadline, handle.id)).is_some()
+    }
+
+    /// The timers taken are firing until [`finish`](Self::finish) is called for each of them.
+    pub fn take_expired(&mut self, now: usize) -> Vec<(TimerHandle, Callback)> {
+        let rest = self.timers.split_off(&(now + 1, 0));
+        let expired = core::mem::replace(&mut self.timers, rest);
+        let expired: Vec<(TimerHandle, Callback)> = expired.into_iter()
+            .map(|((deadline, id), callback)| (TimerHandle { deadline, id }, callback))
+            .collect();
+        self.firing.extend(expired.iter().map(|(handle, _)| *handle));
+        expired
+    }
+
+    pub fn finish(&mut self, handle: TimerHandle) {
+        self.firing.retain(|firing| *firing != handle);
+    }
+
+    pub fn is_firing(&self, handle: TimerHandle) -> bool {
+        self.firing.contains(&handle)
+    }
+
+    #[allow(unused)]
+    pub fn next_deadline(&self) -> Option<usize> {
+        self.timers.keys().next().map(|(deadline, _)| *deadline)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::TimerList;
+    use alloc::boxed::Box;
+    use alloc::sync::Arc;
+    use spin::Mutex;
+    use alloc::vec::Vec;
+
+    #[test]
+    pub fn test_timer_list() {
+        info!("starting timer_list.rs test cases");
+
+        let fired = Arc::new(Mutex::new(Vec::new()));
+        let mut list = TimerList::new();
+        let mut handles = Vec::new();
+        for (i, deadline) in [30, 10, 20, 10].iter().enumerate() {
+            let fired = fired.clone();
+            handles.push(list.add(*deadline, Box::new(move || fired.lock().push(i))));
+        }
+        assert_eq!(list.next_deadline(), Some(10));
+
+        // a cancelled timer never fires, and can't be cancelled twice.
+        assert!(list.cancel(handles[2]));
+        assert!(!list.cancel(handles[2]));
+
+        assert!(list.take_expired(9).is_empty());
+        for (handle, callback) in list.take_expired(10) {
+            assert!(list.is_firing(handle));
+            callback();
+            list.finish(handle);
+        }
+        assert_eq!(*fired.lock(), [1, 3]);
+        assert!(!list.cancel(handles[1]));
+        assert!(!list.is_firing(handles[1]));
+
+        list.take_expired(100).into_iter().for_each(|(_, callback)| callback());
+        assert_eq!(*fired.lock(), [1, 3, 0]);
+        assert_eq!(list.next_deadline(), None);
+
+        info!("end of timer_list.rs test\n");
+    }
+}
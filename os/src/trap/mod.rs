@@ -1,16 +1,23 @@
 mod trap;
 
-use riscv::register::{scause::{self, Trap, Exception, Interrupt}, stval, stvec, sepc, sstatus};
-use crate::syscall::syscall;
-use crate::task::{RuntimeFlags, schedule};
+use riscv::register::{scause::{self, Trap, Exception, Interrupt}, stval, stvec, sepc, sip};
+use crate::syscall::{syscall, handle_signals};
+use crate::task::preempt;
 
-pub use trap::{__enter_user_mode, __from_user_mode};
-use crate::processor::{get_cur_task_context_in_this_hart};
+pub use trap::{__enter_user_mode, __from_user_mode, __from_kernel_mode};
+use crate::processor::{get_cur_task_context_in_this_hart, get_cur_task_in_this_hart, get_cur_mm_in_this_hart,
+                       flush_tlb_of_other_harts, flush_tlb_if_requested};
+use crate::mm::address::VirtualAddress;
+use crate::mm::memory_manager::RegionFlags;
+use share::signal::{SIGILL, SIGTRAP, SIGBUS, SIGSEGV};
 use crate::plic;
+use crate::timer::{get_time_us, check_timers};
+#[cfg(feature = "board_qemu")]
+use core::arch::asm;
 
 pub fn init_stvec() {
     unsafe {
-        stvec::write(__from_user_mode as usize, stvec::TrapMode::Direct);
+        stvec::write(__from_kernel_mode as usize, stvec::TrapMode::Direct);
     }
 }
 
@@ -19,6 +26,8 @@ pub fn trap_handler() {
     let scause = scause::read();
     let stval = stval::read();
     let sepc = sepc::read();
+    get_cur_task_in_this_hart().acquire_inner_lock().rusage.user_until(get_time_us());
+    flush_tlb_if_requested();
 
     match scause.cause() {
         #[cfg(feature = "board_k210")]
@@ -29,6 +38,11 @@ pub fn trap_handler() {
         Trap::Interrupt(Interrupt::SupervisorExternal) => {
             plic::handle_interrupt();
         }
+        #[cfg(feature = "board_qemu")]
+        Trap::Interrupt(Interrupt::SupervisorSoft) => {
+            // an IPI for an idle hart which has found a task to run meanwhile.
+            clear_ipi();
+        }
         Trap::Exception(Exception::UserEnvCall) => {
             let context = get_cur_task_context_in_this_hart();
             context.sepc += 4;
@@ -37,16 +51,110 @@ pub fn trap_handler() {
                         [context.x[10], context.x[11], context.x[12], context.x[13], context.x[14], context.x[15]]);
         },
         Trap::Interrupt(Interrupt::SupervisorTimer) => {
-            schedule(RuntimeFlags::READY);
+            check_timers();
+            preempt();
+        },
+        Trap::Exception(exception) if handle_page_fault(exception, stval, true) => {},
+        Trap::Exception(exception) => {
+            info!("{:?} in task {}, stval = {:#x}, sepc = {:#x}",
+                  exception, get_cur_task_in_this_hart().pid(), stval, sepc);
+            get_cur_task_in_this_hart().acquire_inner_lock().signals.force(fault_signal(exception));
         },
         _ => {
             info!("Unsupported trap {:?}, stval = {:#x}, sepc = {:#x}",scause.cause(), stval, sepc);
-            // sstatus ：其中的一些控制位标志发生异常时的处理器状态，如 sstatus.SPP 表示发生异常时处理器在哪个特权级
-            schedule(RuntimeFlags::ZOMBIE(1));
-         /*   match sstatus::read().spp() {
-                sstatus::SPP::User => schedule(RuntimeFlags::ZOMBIE(1)),
-                sstatus::SPP::Supervisor => panic!("Supervisor trap!"),
-            };*/
         }
     }
-}
\ No newline at end of file
+
+    handle_signals();
+    get_cur_task_in_this_hart().acquire_inner_lock().rusage.kernel_until(get_time_us());
+}
+
+/// Traps taken in kernel mode, where interrupts are disabled. The kernel accesses user memory
+/// directly, so the page faults on the pages which haven't been touched or are shared copy-on-write
+/// are expected, and nothing else. The pages of shared files are read in before the syscall.
+#[no_mangle]
+pub fn kernel_trap_handler() {
+    let scause = scause::read();
+    let stval = stval::read();
+    let sepc = sepc::read();
+
+    match scause.cause() {
+        // locks may be held here, other harts are not waited for.
+        Trap::Exception(exception) if handle_page_fault(exception, stval, false) => {},
+        cause => {
+            panic!("{:?} in kernel mode, stval = {:#x}, sepc = {:#x}", cause, stval, sepc);
+        }
+    }
+}
+
+/// Resolve a page fault at `va` in the address space of current hart. Return false if `exception`
+/// is not a page fault, or the access is not allowed at all. `wait` is set when no lock is held, the
+/// other harts are waited for and the pages of shared files are read in only then.
+fn handle_page_fault(exception: Exception, va: usize, wait: bool) -> bool {
+    let access = match exception {
+        Exception::LoadPageFault => RegionFlags::R,
+        Exception::StorePageFault => RegionFlags::W,
+        Exception::InstructionPageFault => RegionFlags::X,
+        _ => return false,
+    };
+    let mm = match get_cur_mm_in_this_hart() {
+        Some(mm) => mm,
+        None => return false,
+    };
+    if wait { // no lock is held, so the page of a shared file can be read in from fs server.
+        let missing = mm.lock().missing_file_page(VirtualAddress(va));
+        if let Some((file, index)) = missing {
+            if file.read_in(index).is_err() {
+                return false;
+            }
+        }
+    }
+    let mut mm_guard = mm.lock();
+    let copied = match mm_guard.handle_page_fault(VirtualAddress(va), access) {
+        Ok(copied) => copied,
+        Err(_) => return false,
+    };
+    let satp = mm_guard.page_table.satp();
+    drop(mm_guard);
+    if copied {
+        flush_tlb_of_other_harts(satp, wait);
+    }
+    true
+}
+
+/// Handle the interrupts which have woken up an idle hart, there is no current task on the hart.
+pub fn handle_idle_interrupts() {
+    let sip = sip::read();
+    flush_tlb_if_requested();
+    #[cfg(feature = "board_qemu")]
+    {
+        if sip.ssoft() {
+            clear_ipi();
+        }
+        if sip.sext() {
+            plic::handle_interrupt();
+        }
+    }
+    #[cfg(feature = "board_k210")]
+    if sip.ssoft() {
+        plic::handle_interrupt();
+    }
+    check_timers();
+}
+
+#[cfg(feature = "board_qemu")]
+fn clear_ipi() {
+    unsafe {
+        asm!("csrc sip, {}", in(reg) 1 << 1);
+    }
+}
+
+/// The signal sent to a task which has caused `exception`.
+fn fault_signal(exception: Exception) -> usize {
+    match exception {
+        Exception::IllegalInstruction => SIGILL,
+        Exception::Breakpoint => SIGTRAP,
+        Exception::InstructionMisaligned | Exception::StoreMisaligned => SIGBUS,
+        _ => SIGSEGV,
+    }
+}
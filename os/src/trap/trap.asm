@@ -10,6 +10,7 @@
     .section .text
     .globl __enter_user_mode
     .globl __from_user_mode
+    .globl __from_kernel_mode
     .align 2
 
 __from_user_mode:
@@ -17,9 +18,19 @@ __from_user_mode:
     csrrw sp, sscratch, sp   # now sp-> kernel_stack, sscratch-> user_stack
 
     # decrease stack size
-    addi sp, sp, -34*8
+    addi sp, sp, -35*8
 
-    # store sstatus, sepc, user_stack
+    # store x[0,1,3-31], x2/sp is stored later.
+    sd x0, 0(sp)
+    sd x1, 8(sp)
+
+    .set n, 3
+    .rept 29
+        STORE_REG %n
+        .set n, n+1
+    .endr
+
+    # store sstatus, sepc, user_stack, t0-t2 have been saved above.
     csrr t0, sstatus
     csrr t1, sepc
     csrr t2, sscratch
@@ -27,47 +38,95 @@ __from_user_mode:
     sd t1, 33*8(sp)
     sd t2, 2*8(sp)
 
-    # store x[0,1,3,5-31], x2/sp is stored before, x4/tp won't set.
-    sd x0, 0(sp)
-    sd x1, 8(sp)
-    sd x3, 8*3(sp)
+    # load hart id into tp, which is saved when the task enters user mode.
+    ld tp, 34*8(sp)
 
-    .set n, 5
-    .rept 27
-        STORE_REG %n
-        .set n, n+1
-    .endr
+    # traps are taken by __from_kernel_mode until the task enters user mode again.
+    la t0, __from_kernel_mode
+    csrw stvec, t0
 
     # jump to trap_handler, this address should be set in stvec CSR.
     call trap_handler
 
 __enter_user_mode:
+    la t0, __from_user_mode
+    csrw stvec, t0
+
     # load sstatus,sepc
     ld t0, 32*8(sp)
     ld t1, 33*8(sp)
     csrw sstatus, t0
     csrw sepc, t1
 
-    # load x[0,1,3,5-31], x2/sp will be load later, x4/tp won't load due to it's storing hart id.
+    # save hart id, the task may come back on another hart next time.
+    sd tp, 34*8(sp)
+
+    # load x[0,1,3-31], x2/sp will be load later.
     ld x0, 0(sp)
     ld x1, 8(sp)
-    ld x3, 3*8(sp)
 
-    .set n, 5
-    .rept 27
+    .set n, 3
+    .rept 29
         LOAD_REG %n
         .set n, n+1
     .endr
 
     # increase stack size.
-    addi sp, sp, 34*8
+    addi sp, sp, 35*8
 
     # record current kernel sp
     csrw sscratch, sp
 
     # load sp/x2
-    ld sp, 2*8-34*8(sp)
+    ld sp, 2*8-35*8(sp)
 
     # enter user mode
     # 0x54
     sret
+
+    .align 2
+__from_kernel_mode:
+    # save the caller-saved registers on current kernel stack, the handler saves the others.
+    addi sp, sp, -18*8
+    sd ra, 0(sp)
+    sd t0, 1*8(sp)
+    sd t1, 2*8(sp)
+    sd t2, 3*8(sp)
+    sd t3, 4*8(sp)
+    sd t4, 5*8(sp)
+    sd t5, 6*8(sp)
+    sd t6, 7*8(sp)
+    sd a0, 8*8(sp)
+    sd a1, 9*8(sp)
+    sd a2, 10*8(sp)
+    sd a3, 11*8(sp)
+    sd a4, 12*8(sp)
+    sd a5, 13*8(sp)
+    sd a6, 14*8(sp)
+    sd a7, 15*8(sp)
+    csrr t0, sepc
+    sd t0, 16*8(sp)
+
+    call kernel_trap_handler
+
+    # retry the faulting instruction.
+    ld t0, 16*8(sp)
+    csrw sepc, t0
+    ld ra, 0(sp)
+    ld t0, 1*8(sp)
+    ld t1, 2*8(sp)
+    ld t2, 3*8(sp)
+    ld t3, 4*8(sp)
+    ld t4, 5*8(sp)
+    ld t5, 6*8(sp)
+    ld t6, 7*8(sp)
+    ld a0, 8*8(sp)
+    ld a1, 9*8(sp)
+    ld a2, 10*8(sp)
+    ld a3, 11*8(sp)
+    ld a4, 12*8(sp)
+    ld a5, 13*8(sp)
+    ld a6, 14*8(sp)
+    ld a7, 15*8(sp)
+    addi sp, sp, 18*8
+    sret
@@ -5,4 +5,5 @@ global_asm!(include_str!("trap.asm"));
 extern "C" {
     pub fn __enter_user_mode() -> !;
     pub fn __from_user_mode();
+    pub fn __from_kernel_mode();
 }
\ No newline at end of file
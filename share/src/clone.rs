@@ -0,0 +1,17 @@
+bitflags! {
+    /// Flags of the clone syscall, the values are the same as linux. Other linux flags are ignored.
+    pub struct CloneFlags: u32 {
+        /// Share the address space with the caller.
+        const VM = 0x100;
+        /// Put the child in the thread group of the caller, must come with `VM`.
+        const THREAD = 0x10000;
+        /// Set `tp` of the child to the `tls` argument.
+        const SETTLS = 0x80000;
+        /// Write the tid of the child to `ptid` in the caller.
+        const PARENT_SETTID = 0x100000;
+        /// Write zero to `ctid` in the child when it exits.
+        const CHILD_CLEARTID = 0x200000;
+        /// Write the tid of the child to `ctid` in the child.
+        const CHILD_SETTID = 0x1000000;
+    }
+}
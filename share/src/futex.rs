@@ -0,0 +1,6 @@
+/// Operations of the futex syscall, the values are the same as linux.
+pub const FUTEX_WAIT: usize = 0;
+pub const FUTEX_WAKE: usize = 1;
+pub const FUTEX_REQUEUE: usize = 3;
+/// Accepted and ignored, a futex is always identified by the physical address of its word.
+pub const FUTEX_PRIVATE_FLAG: usize = 128;
@@ -1,9 +1,12 @@
 use core::fmt::{Debug, Formatter};
 use crate::syscall::error::SysError;
 
-pub const TERMINAL_PID: usize = 1;
-pub const VIRTIO_BLK_PID: usize = 2;
-pub const FS_PID: usize = 3;
+/* Service names, used to look up the endpoint of a server */
+pub const TERMINAL_SERVICE: &str = "tty0";
+pub const BLOCK_SERVICE: &str = "blk0"; // virtio-blk on qemu, sdcard on k210.
+pub const FS_SERVICE: &str = "fs";
+pub const RTC_SERVICE: &str = "rtc";
+pub const MAX_SERVICE_NAME_LENGTH: usize = 32;
 
 /* Message Type */
 pub const INTERRUPT: usize = 1;
@@ -16,6 +19,11 @@ pub const REPLY: usize = 7;
 pub const FORK: usize = 8;  // process to filesystem
 pub const EXIT: usize = 9; // process to filesystem
 pub const FSYSCALL: usize = 10; // process to filesystem, when process inovke filesystem syscall like 'open'
+pub const NOTIFY: usize = 11; // notification from the kernel or another process
+
+/* Notification sources, they never collide with a real pid */
+pub const HARDWARE: usize = usize::MAX - 1;
+pub const KERNEL: usize = usize::MAX - 2;
 
 /* Args position constant */
 pub const MSG_ARGS_0: usize = 0;
@@ -24,17 +32,22 @@ pub const MSG_ARGS_2: usize = 2;
 pub const MSG_ARGS_3: usize = 3;
 pub const MSG_ARGS_4: usize = 4;
 pub const MSG_ARGS_5: usize = 5;
+/// Number of args carried by the register based fast path, `MSG_ARGS_5` is not transferred.
+pub const SHORT_MSG_ARGS: usize = 5;
 
 /* device drivers */
 /* read write message */
 pub const DEVICE: usize = MSG_ARGS_0;
 pub const PROC_NR: usize = MSG_ARGS_1;
-pub const BUFFER: usize = MSG_ARGS_2;
+pub const BUFFER: usize = MSG_ARGS_2; // grant id of the buffer, created by the PROC_NR task.
 pub const LENGTH: usize = MSG_ARGS_3;
 pub const POSITION: usize =MSG_ARGS_4;
+pub const CALLER_PID: usize = MSG_ARGS_5; // process the request is made for, checked for terminal job control.
 /* ioctl message */
 pub const IOCTL_TYPE: usize = MSG_ARGS_2;
-pub const ADDRESS: usize = MSG_ARGS_3;
+pub const ADDRESS: usize = MSG_ARGS_3; // grant id, created by the PROC_NR task.
+/* interrupt and notify message, bit n is set for irq line n, kernel event n or pid n */
+pub const NOTIFY_BITMAP: usize = MSG_ARGS_1;
 /* Reply message */
 pub const REPLY_PROC_NR: usize = MSG_ARGS_0;
 pub const REPLY_STATUS: usize = MSG_ARGS_1;
@@ -48,12 +61,46 @@ pub const FS_SYSCALL_ARG1: usize = MSG_ARGS_2;
 pub const FS_SYSCALL_ARG2: usize = MSG_ARGS_3;
 pub const FS_SYSCALL_ARG3: usize = MSG_ARGS_4;
 pub const FS_SYSCALL_ARG4: usize = MSG_ARGS_5;
+pub const FS_SYSCALL_GRANT: usize = FS_SYSCALL_ARG4; // grant of the user buffer, for syscalls like 'read'
 /* fork message */
 pub const FORK_PARENT: usize = MSG_ARGS_0;
 pub const FORK_CHILD: usize = MSG_ARGS_1;
 /* exit message */
 pub const EXIT_PID: usize = MSG_ARGS_0;
 
+bitflags! {
+    /// Access rights of a memory grant, from the grantee's point of view.
+    pub struct GrantFlags: usize {
+        /// The grantee may copy from the granted memory.
+        const READ = 0x1;
+        /// The grantee may copy to the granted memory.
+        const WRITE = 0x2;
+    }
+}
+
+/// A pid tagged with the generation of the process which owns it.
+///
+/// Each time a pid is allocated its generation grows, so an endpoint looked up before a server
+/// restarts never reaches the new process by accident. Generation 0 stands for a plain pid.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct Endpoint(pub usize);
+
+impl Endpoint {
+    const GENERATION_SHIFT: usize = 32;
+
+    pub const fn new(pid: usize, generation: usize) -> Self {
+        Self(generation << Self::GENERATION_SHIFT | pid)
+    }
+
+    pub const fn pid(&self) -> usize {
+        self.0 & ((1 << Self::GENERATION_SHIFT) - 1)
+    }
+
+    pub const fn generation(&self) -> usize {
+        self.0 >> Self::GENERATION_SHIFT
+    }
+}
+
 #[repr(C)]
 #[derive(Copy, Clone)]
 pub struct Msg {
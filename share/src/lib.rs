@@ -9,6 +9,11 @@ pub mod device;
 pub mod mmap;
 pub mod system;
 pub mod time;
+pub mod signal;
+pub mod wait;
+pub mod clone;
+pub mod futex;
+pub mod resource;
 
 extern crate alloc;
 #[macro_use]
@@ -13,4 +13,55 @@ bitflags! {
         const PRIVATE = 0x2;
         const ANONYMOUS = 0x10;
     }
-}
\ No newline at end of file
+}
+
+bitflags! {
+    /// The dirty pages are always written back before `msync` returns.
+    pub struct MsyncFlags: u32 {
+        const ASYNC = 0x1;
+        const INVALIDATE = 0x2;
+        const SYNC = 0x4;
+    }
+}
+
+bitflags! {
+    pub struct MremapFlags: u32 {
+        /// The mapping may be moved if it can't grow in place.
+        const MAYMOVE = 0x1;
+    }
+}
+/// What a region in the memory map of a process is used for.
+#[repr(usize)]
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum MapKind {
+    /// Filled from an executable or a private file mapping.
+    Image = 0,
+    Heap,
+    Stack,
+    Anonymous,
+    /// Shared memory or a shared file mapping.
+    Shared,
+    /// Continuous memory of a device driver.
+    Device,
+}
+
+/// A region in the memory map of a process, `[start, end)` is page aligned.
+#[repr(C)]
+#[derive(Copy, Clone, Debug)]
+pub struct MapEntry {
+    pub start: usize,
+    pub end: usize,
+    pub prot: Prot,
+    pub kind: MapKind,
+}
+
+impl MapEntry {
+    pub const fn empty() -> Self {
+        Self {
+            start: 0,
+            end: 0,
+            prot: Prot::empty(),
+            kind: MapKind::Anonymous,
+        }
+    }
+}
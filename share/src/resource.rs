@@ -0,0 +1,18 @@
+/// The maximum size of the stack of a process, which grows on demand up to the soft limit.
+pub const RLIMIT_STACK: usize = 3;
+
+pub const RLIM_INFINITY: usize = usize::MAX;
+
+/// Limit of a resource, the soft limit `cur` may be raised by the process up to the hard limit `max`.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Rlimit {
+    pub cur: usize,
+    pub max: usize,
+}
+
+impl Rlimit {
+    pub const fn new(cur: usize, max: usize) -> Self {
+        Self { cur, max }
+    }
+}
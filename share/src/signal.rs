@@ -0,0 +1,70 @@
+pub const SIGHUP: usize = 1;
+pub const SIGINT: usize = 2;
+pub const SIGQUIT: usize = 3;
+pub const SIGILL: usize = 4;
+pub const SIGTRAP: usize = 5;
+pub const SIGABRT: usize = 6;
+pub const SIGBUS: usize = 7;
+pub const SIGFPE: usize = 8;
+pub const SIGKILL: usize = 9;
+pub const SIGUSR1: usize = 10;
+pub const SIGSEGV: usize = 11;
+pub const SIGUSR2: usize = 12;
+pub const SIGPIPE: usize = 13;
+pub const SIGALRM: usize = 14;
+pub const SIGTERM: usize = 15;
+pub const SIGCHLD: usize = 17;
+pub const SIGCONT: usize = 18;
+pub const SIGSTOP: usize = 19;
+pub const SIGTSTP: usize = 20;
+pub const SIGTTIN: usize = 21;
+pub const SIGTTOU: usize = 22;
+
+/// Valid signal numbers are `1..SIGNAL_NUM`, signal `n` is bit `n` of a [`SigSet`].
+pub const SIGNAL_NUM: usize = 64;
+
+pub type SigSet = u64;
+
+// `handler` of a `SigAction`
+pub const SIG_DFL: usize = 0;
+pub const SIG_IGN: usize = 1;
+
+// `how` of sigprocmask
+pub const SIG_BLOCK: usize = 0;
+pub const SIG_UNBLOCK: usize = 1;
+pub const SIG_SETMASK: usize = 2;
+
+bitflags! {
+    pub struct SigActionFlags: usize {
+        const RESTORER = 0x04000000;
+        const NODEFER = 0x40000000;
+        const RESETHAND = 0x80000000;
+    }
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug)]
+pub struct SigAction {
+    /// `SIG_DFL`, `SIG_IGN` or the address of `extern "C" fn(signo: usize)`.
+    pub handler: usize,
+    pub flags: SigActionFlags,
+    /// Where the handler returns to, it must issue the sigreturn syscall without touching the stack.
+    pub restorer: usize,
+    /// Signals blocked while the handler is running, besides the delivered signal itself.
+    pub mask: SigSet,
+}
+
+impl SigAction {
+    pub const fn default() -> Self {
+        Self {
+            handler: SIG_DFL,
+            flags: SigActionFlags::empty(),
+            restorer: 0,
+            mask: 0,
+        }
+    }
+}
+
+pub const fn sig_bit(signo: usize) -> SigSet {
+    1 << signo
+}
@@ -25,6 +25,7 @@ impl Debug for SysError {
             EPERM => "EPERM: Operation not permitted",
             ENOENT => "ENOENT: No such file or directory",
             ESRCH => "ESRCH: No such process",
+            EINTR => "EINTR: Interrupted system call",
             EIO => "EIO: input/output error",
             ENOEXEC => "ENOEXEC: Exec format error",
             EBADF => "EBADF: fd is not a valid file descriptor or is not open for reading/writing",
@@ -44,9 +45,11 @@ impl Debug for SysError {
             ERANGE => "ERANGE: The argument is less than the length of the absolute pathname",
             ENAMETOOLONG => "ENAMETOOLONG: File name too long",
             ENOTEMPTY => "ENOTEMPTY: Directory is not empty",
+            ETIMEDOUT => "ETIMEDOUT: Connection timed out",
 
             EUNKOWN => "Unknown error nnn.",
             EDLOCK => "EDLOCK: Ipc dead lock",
+            EDEADSRCDST => "EDEADSRCDST: Ipc peer is dead",
             _ => {
                 return f.write_fmt(format_args!("Unknown errno: {}", self.errno));
             },
@@ -58,6 +61,7 @@ impl Debug for SysError {
 pub const EPERM: i32 = 1;
 pub const ENOENT: i32 = 2;
 pub const ESRCH: i32 = 3;
+pub const EINTR: i32 = 4;
 pub const EIO: i32 = 5;
 pub const ENOEXEC: i32 = 8;
 pub const EBADF: i32 = 9;
@@ -77,7 +81,9 @@ pub const ENFILE: i32 = 23;
 pub const ERANGE: i32 = 34;
 pub const ENAMETOOLONG: i32 = 36;
 pub const ENOTEMPTY: i32 = 39;
+pub const ETIMEDOUT: i32 = 110;
 
 // Self designed error numbers..
 pub const EUNKOWN: i32 = 400;
-pub const EDLOCK: i32 = 401;
\ No newline at end of file
+pub const EDLOCK: i32 = 401;
+pub const EDEADSRCDST: i32 = 402;
\ No newline at end of file
@@ -16,24 +16,47 @@ pub const SYSCALL_FSTAT: usize = 80;
 pub const SYSCALL_UNLINK: usize = 83;
 pub const SYSCALL_RMDIR: usize = 84;
 
+// page cache requests, made to the filesystem by the kernel for shared file mappings.
+pub const FS_MAP_FILE: usize = 2001;
+pub const FS_READ_PAGE: usize = 2002;
+pub const FS_WRITE_PAGE: usize = 2003;
+pub const FS_UNMAP_FILE: usize = 2004;
+
 pub const SYSCALL_NANOSLEEP: usize = 101;
 pub const SYSCALL_EXIT: usize = 93;
+pub const SYSCALL_EXIT_GROUP: usize = 94;
+pub const SYSCALL_FUTEX: usize = 98;
 pub const SYSCALL_YIELD: usize = 124;
+pub const SYSCALL_KILL: usize = 129;
+pub const SYSCALL_SIGACTION: usize = 134;
+pub const SYSCALL_SIGPROCMASK: usize = 135;
+pub const SYSCALL_SIGRETURN: usize = 139;
 pub const SYSCALL_GET_PRIORITY: usize = 140;
 pub const SYSCALL_SET_PRIORITY: usize = 141;
+pub const SYSCALL_SETPGID: usize = 154;
+pub const SYSCALL_GETPGID: usize = 155;
+pub const SYSCALL_GETSID: usize = 156;
+pub const SYSCALL_SETSID: usize = 157;
 pub const SYSCALL_UNAME: usize = 160;
+pub const SYSCALL_GETRLIMIT: usize = 163;
+pub const SYSCALL_SETRLIMIT: usize = 164;
 pub const SYSCALL_GET_TIME: usize = 169;
 pub const SYSCALL_GETPID: usize = 172;
 pub const SYSCALL_GETPPID: usize = 173;
+pub const SYSCALL_GETTID: usize = 178;
 pub const SYSCALL_BRK: usize = 214;
 pub const SYSCALL_MUNMAP: usize = 215;
+pub const SYSCALL_MREMAP: usize = 216;
 pub const SYSCALL_FORK: usize = 220;
 pub const SYSCALL_EXEC: usize = 221;
 pub const SYSCALL_MMAP: usize = 222;
+pub const SYSCALL_MPROTECT: usize = 226;
+pub const SYSCALL_MSYNC: usize = 227;
 pub const SYSCALL_WAITPID: usize = 260;
 pub const SYSCALL_TEST: usize = 1234;
 
 pub const DEBUG_FRAME_USAGE: usize = 1001;
+pub const DEBUG_MEMORY_MAP: usize = 1002;
 
 pub const KCALL_MASK: usize = 0x1000;
 pub const KCALL_SEND: usize = KCALL_MASK | 1;
@@ -48,6 +71,23 @@ pub const KCALL_SBI_READ: usize = KCALL_MASK | 9;
 pub const KCALL_TERMINAL_READ: usize = KCALL_MASK | 10;
 pub const KCALL_SBI_WRITE: usize = KCALL_MASK | 11;
 pub const KCALL_TERMINAL_WRITE: usize = KCALL_MASK | 12;
+pub const KCALL_SEND_NB: usize = KCALL_MASK | 13;
+pub const KCALL_RECEIVE_NB: usize = KCALL_MASK | 14;
+pub const KCALL_SEND_TIMEOUT: usize = KCALL_MASK | 15;
+pub const KCALL_RECEIVE_TIMEOUT: usize = KCALL_MASK | 16;
+pub const KCALL_SENDREC: usize = KCALL_MASK | 17;
+pub const KCALL_SEND_FAST: usize = KCALL_MASK | 18;
+pub const KCALL_RECEIVE_FAST: usize = KCALL_MASK | 19;
 
 pub const KCALL_SDCARD_READ: usize = KCALL_MASK | 20;
 pub const KCALL_SDCARD_WRITE: usize = KCALL_MASK | 21;
+pub const KCALL_NOTIFY: usize = KCALL_MASK | 22;
+pub const KCALL_GRANT_CREATE: usize = KCALL_MASK | 23;
+pub const KCALL_GRANT_REVOKE: usize = KCALL_MASK | 24;
+pub const KCALL_SAFECOPY_FROM: usize = KCALL_MASK | 25;
+pub const KCALL_SAFECOPY_TO: usize = KCALL_MASK | 26;
+pub const KCALL_SHM_CREATE: usize = KCALL_MASK | 27;
+pub const KCALL_SHM_MAP: usize = KCALL_MASK | 28;
+pub const KCALL_SHM_UNMAP: usize = KCALL_MASK | 29;
+pub const KCALL_PUBLISH: usize = KCALL_MASK | 30;
+pub const KCALL_LOOKUP: usize = KCALL_MASK | 31;
@@ -27,7 +27,7 @@ impl Termios {
             c_iflag: Ciflag::ICRNL,
             c_oflag: Coflag::empty(),
             c_cflag: Ccflag::empty(),
-            c_lflag: Clflag::ECHO | Clflag::ECHOE | Clflag::ICANON,
+            c_lflag: Clflag::ECHO | Clflag::ECHOE | Clflag::ICANON | Clflag::ISIG,
         }
     }
 }
@@ -60,6 +60,6 @@ bitflags! {
         const IEXTEN = 0x0020;	/* enable extended functions */
         const ISIG	 = 0x0040;	/* enable signals */
         const NOFLSH = 0x0080;  /* disable flush after interrupt or quit */
-        const TOSTOP = 0x0100;	/* send SIGTTOU (job control, not implemented*/
+        const TOSTOP = 0x0100;	/* send SIGTTOU for background output */
     }
 }
@@ -1,4 +1,5 @@
 #[repr(C)]
+#[derive(Copy, Clone, Debug)]
 pub struct Timespec {
     pub tv_sec: u64,
     pub tv_usec: u64,
@@ -11,4 +12,30 @@ impl Timespec {
             tv_usec: 0,
         }
     }
+
+    pub fn from_us(us: usize) -> Self {
+        Self {
+            tv_sec: (us / 1000000) as u64,
+            tv_usec: (us % 1000000) as u64,
+        }
+    }
+}
+
+/// Resource usage of a child reported by waitpid. Only the cpu time is accounted.
+#[repr(C)]
+#[derive(Copy, Clone, Debug)]
+pub struct Rusage {
+    /// Time spent in user mode.
+    pub ru_utime: Timespec,
+    /// Time spent in the kernel.
+    pub ru_stime: Timespec,
+}
+
+impl Rusage {
+    pub fn empty() -> Self {
+        Self {
+            ru_utime: Timespec::empty(),
+            ru_stime: Timespec::empty(),
+        }
+    }
 }
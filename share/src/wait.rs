@@ -0,0 +1,45 @@
+// options of waitpid
+pub const WNOHANG: usize = 1;
+pub const WUNTRACED: usize = 2;
+pub const WCONTINUED: usize = 8;
+
+/// Status reported for a continued child, see [`wifcontinued`].
+pub const W_CONTINUED: usize = 0xffff;
+
+/// Status of a child which has exited with `exit_code`, or been killed by `signo` when it is not 0.
+pub const fn w_exitcode(exit_code: usize, signo: usize) -> usize {
+    (exit_code & 0xff) << 8 | signo & 0x7f
+}
+
+/// Status of a child which has been stopped by `signo`.
+pub const fn w_stopcode(signo: usize) -> usize {
+    signo << 8 | 0x7f
+}
+
+pub const fn wifexited(status: usize) -> bool {
+    status & 0x7f == 0
+}
+
+pub const fn wexitstatus(status: usize) -> usize {
+    (status >> 8) & 0xff
+}
+
+pub const fn wifsignaled(status: usize) -> bool {
+    status & 0x7f != 0 && status & 0x7f != 0x7f
+}
+
+pub const fn wtermsig(status: usize) -> usize {
+    status & 0x7f
+}
+
+pub const fn wifstopped(status: usize) -> bool {
+    status & 0xff == 0x7f
+}
+
+pub const fn wstopsig(status: usize) -> usize {
+    wexitstatus(status)
+}
+
+pub const fn wifcontinued(status: usize) -> bool {
+    status == W_CONTINUED
+}
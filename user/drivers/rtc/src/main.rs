@@ -15,6 +15,7 @@ use mods::*;
 use share::ipc::Msg;
 use share::ipc::*;
 use user_lib::syscall::*;
+use share::syscall::error::{EINVAL, ENODEV};
 
 use core::assert;
 
@@ -22,6 +23,7 @@ use core::assert;
 fn main() {
     let mut rtc = Rtc::new();
     rtc.init();
+    publish(RTC_SERVICE).unwrap();
 
     println!("rtc init.");
 
@@ -51,10 +53,10 @@ fn main() {
     loop {
         receive(-1, &mut message).unwrap();
 
-        let nr = message.args[DEVICE];
-        assert_eq!(nr, 0);
-
         match message.mtype {
+            // a notification from another process carries no request.
+            NOTIFY => {}
+            _ if message.args[DEVICE] != 0 => reject(&message, ENODEV),
             INTERRUPT => do_interrupt(&mut rtc),
             OPEN => do_open(&mut rtc, message),
             READ => do_read(&mut rtc, message),
@@ -62,9 +64,7 @@ fn main() {
             IOCTL => do_ioctl(&mut rtc, message),
             CLOSE => do_close(&mut rtc, message),
 
-            _ => {
-                panic!("Unknown message type:{}", message.mtype);
-            }
+            _ => reject(&message, EINVAL),
         }
     }
 }
@@ -80,3 +80,14 @@ pub fn do_read(rtc: &mut Rtc, message: Msg) {}
 pub fn do_write(rtc: &mut Rtc, message: Msg) {}
 pub fn do_ioctl(rtc: &mut Rtc, message: Msg) {}
 pub fn do_close(rtc: &mut Rtc, message: Msg) {}
+
+/// Fail a request the driver doesn't serve. The caller is left without a reply if it isn't waiting
+/// for one, rather than blocking the driver.
+fn reject(message: &Msg, errno: i32) {
+    let mut reply_message = Msg::empty();
+    reply_message.mtype = REPLY;
+    reply_message.args[REPLY_PROC_NR] = message.args[PROC_NR];
+    reply_message.args[REPLY_STATUS] = -errno as isize as usize;
+
+    let _ = send_nb(message.src_pid, &reply_message);
+}
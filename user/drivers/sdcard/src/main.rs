@@ -4,8 +4,9 @@
 use sdcard::SDCardWrapper;
 
 use crate::sdcard::BLOCK_DEVICE;
-use share::ipc::{Msg, READ, WRITE, DEVICE, REPLY_PROC_NR, REPLY_STATUS, REPLY, PROC_NR, BUFFER, LENGTH, POSITION};
-use user_lib::syscall::{receive, send, virt_copy, getpid, sdcard_read, sdcard_write};
+use share::ipc::{Msg, READ, WRITE, NOTIFY, DEVICE, REPLY_PROC_NR, REPLY_STATUS, REPLY, PROC_NR, BUFFER, LENGTH, POSITION};
+use share::syscall::error::{EINVAL, ENODEV};
+use user_lib::syscall::{receive, send, send_nb, safecopy_from, safecopy_to, sdcard_read, sdcard_write};
 
 #[macro_use]
 extern crate user_lib;
@@ -21,37 +22,35 @@ fn main() {
      loop {
          receive(-1, &mut message).unwrap();
 
-         let nr = message.args[DEVICE];
-         assert_eq!(nr, 0);
-
          match message.mtype {
+             // a notification from another process carries no request.
+             NOTIFY => {}
+             _ if message.args[DEVICE] != 0 => reject(&message, ENODEV),
              READ => do_read(message),
              WRITE => do_write(message),
-             _ => {
-                 panic!("Unknown message type:{}", message.mtype);
-             }
+             _ => reject(&message, EINVAL),
          }
      }
 }
 
 pub fn do_read(message: Msg) {
     let proc_nr = message.args[PROC_NR];
-    let dst_ptr = message.args[BUFFER];
+    let grant = message.args[BUFFER];
     let block_id = message.args[POSITION];
     let mut buffer = [0u8; BLOCK_SZ];
 
     sdcard_read(block_id, buffer.as_mut_slice()).unwrap();
-    virt_copy(getpid(), buffer.as_ptr() as usize, proc_nr, dst_ptr, BLOCK_SZ).unwrap();
+    safecopy_to(proc_nr, grant, 0, buffer.as_ptr() as usize, BLOCK_SZ).unwrap();
     reply(message.src_pid, REPLY, proc_nr, BLOCK_SZ as isize);
 }
 
 pub fn do_write(message: Msg) {
     let proc_nr = message.args[PROC_NR];
-    let src_ptr = message.args[BUFFER];
+    let grant = message.args[BUFFER];
     let block_id = message.args[POSITION];
     let mut buffer = [0; BLOCK_SZ];
 
-    virt_copy(proc_nr, src_ptr, getpid(), buffer.as_mut_ptr() as usize, BLOCK_SZ).unwrap();
+    safecopy_from(proc_nr, grant, 0, buffer.as_mut_ptr() as usize, BLOCK_SZ).unwrap();
     sdcard_write(block_id, buffer.as_slice()).unwrap();
     reply(message.src_pid, REPLY, proc_nr, BLOCK_SZ as isize);
 }
@@ -63,4 +62,15 @@ fn reply(caller: usize, mtype: usize, proc_nr: usize, status: isize) {
     message.args[REPLY_STATUS] = status as usize;
 
     send(caller, &message).unwrap();
-}
\ No newline at end of file
+}
+
+/// Fail a request the driver doesn't serve. The caller is left without a reply if it isn't waiting
+/// for one, rather than blocking the driver.
+fn reject(message: &Msg, errno: i32) {
+    let mut reply_message = Msg::empty();
+    reply_message.mtype = REPLY;
+    reply_message.args[REPLY_PROC_NR] = message.args[PROC_NR];
+    reply_message.args[REPLY_STATUS] = -errno as isize as usize;
+
+    let _ = send_nb(message.src_pid, &reply_message);
+}
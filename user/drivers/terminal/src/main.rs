@@ -11,17 +11,20 @@ use crate::uart::Uart;
 use core::mem::size_of;
 use share::ipc::Msg;
 use share::ipc::*;
+use share::signal::{SIGINT, SIGQUIT, SIGTSTP, SIGTTIN, SIGTTOU};
+use share::syscall::error::{EINTR, EINVAL, ENODEV};
 use share::terminal::{
     Ciflag, Clflag, Termios, TC_GET_ATTR, TC_GET_PGRP, TC_SET_ATTR, TC_SET_PGRP,
 };
-use user_lib::syscall::{dev_write_u8, getpid, receive, send, virt_copy};
+use user_lib::syscall::{dev_write_u8, receive, send, send_nb, safecopy_from, safecopy_to, getpgid, killpg};
 
 const BS: u8 = 0x08;
 const LF: u8 = 0x0a;
 const CR: u8 = 0x0d;
 const DL: u8 = 0x7f;
-#[allow(unused)]
 const CTRL_C: u8 = 0x3;
+const CTRL_Z: u8 = 0x1a;
+const CTRL_BACKSLASH: u8 = 0x1c;
 
 #[no_mangle]
 fn main() {
@@ -31,10 +34,10 @@ fn main() {
     loop {
         receive(-1, &mut message).unwrap();
 
-        let nr = message.args[DEVICE];
-        assert_eq!(nr, 0);
-
         match message.mtype {
+            // a notification from another process carries no request.
+            NOTIFY => {}
+            _ if message.args[DEVICE] != 0 => reject(&message, ENODEV),
             INTERRUPT => do_interrupt(&mut uart),
             OPEN => do_open(&mut uart, message),
             READ => do_read(&mut uart, message),
@@ -42,9 +45,7 @@ fn main() {
             IOCTL => do_ioctl(&mut uart, message),
             CLOSE => do_close(&mut uart, message),
 
-            _ => {
-                panic!("Unknown message type:{}", message.mtype);
-            }
+            _ => reject(&message, EINVAL),
         }
     }
 }
@@ -53,6 +54,13 @@ pub fn do_interrupt(uart: &mut Uart) {
     let mut byte = uart.dev_read();
     uart.enable_recv_intr();
 
+    if uart.termios.c_lflag.contains(Clflag::ISIG) {
+        if let Some(signo) = signal_of(byte) {
+            do_signal_char(uart, byte, signo);
+            return;
+        }
+    }
+
     /* Map CR to LF, ignore CR, or map LF to CR. */
     if byte == CR {
         if uart.termios.c_iflag.contains(Ciflag::IGNCR) {
@@ -81,9 +89,13 @@ pub fn do_read(uart: &mut Uart, message: Msg) {
     if uart.in_left > 0 {
         return;
     }
+    if !is_foreground(uart, message.args[CALLER_PID], SIGTTIN) {
+        reply(message.src_pid, REPLY, message.args[PROC_NR], -EINTR as isize);
+        return;
+    }
     uart.in_caller = message.src_pid;
     uart.in_proc = message.args[PROC_NR];
-    uart.buf_ptr = message.args[BUFFER];
+    uart.buf_grant = message.args[BUFFER];
     uart.in_left = message.args[LENGTH];
 
     transfer_to_usr(uart);
@@ -93,14 +105,17 @@ pub fn do_write(uart: &mut Uart, message: Msg) {
     const BUFFER_SIZE: usize = 512;
 
     let proc_nr = message.args[PROC_NR];
-    let mut buf_ptr = message.args[BUFFER];
+    if uart.termios.c_lflag.contains(Clflag::TOSTOP) && !is_foreground(uart, message.args[CALLER_PID], SIGTTOU) {
+        reply(message.src_pid, REPLY, proc_nr, -EINTR as isize);
+        return;
+    }
+    let buf_grant = message.args[BUFFER];
     let mut buf_len = message.args[LENGTH];
     let mut buffer: [u8; BUFFER_SIZE] = [0; BUFFER_SIZE];
     let mut cnt = 0;
     while buf_len != 0 {
         let length = BUFFER_SIZE.min(buf_len);
-        virt_copy(proc_nr, buf_ptr, getpid(), buffer.as_mut_ptr() as usize, length).unwrap();
-        buf_ptr += length;
+        safecopy_from(proc_nr, buf_grant, cnt, buffer.as_mut_ptr() as usize, length).unwrap();
         buf_len -= length;
         cnt += length;
 
@@ -119,23 +134,30 @@ pub fn do_ioctl(uart: &mut Uart, message: Msg) {
         TC_GET_ATTR => {
             let src_ptr = &uart.termios as *const _ as usize;
             let size = size_of::<Termios>();
-            let dst_ptr = message.args[ADDRESS];
-            virt_copy(getpid(), src_ptr, proc_nr, dst_ptr, size).unwrap();
+            let grant = message.args[ADDRESS];
+            safecopy_to(proc_nr, grant, 0, src_ptr, size).unwrap();
         }
         TC_SET_ATTR => {
-            let termios_ptr = message.args[ADDRESS];
+            let grant = message.args[ADDRESS];
             let size = size_of::<Termios>();
             let dst_ptr = &mut uart.termios as *mut _ as usize;
-            virt_copy(proc_nr, termios_ptr, getpid(), dst_ptr, size).unwrap();
+            safecopy_from(proc_nr, grant, 0, dst_ptr, size).unwrap();
         }
         TC_GET_PGRP => {
-            unimplemented!();
+            let pgrp = uart.pgrp.unwrap_or(0);
+            let grant = message.args[ADDRESS];
+            safecopy_to(proc_nr, grant, 0, &pgrp as *const _ as usize, size_of::<usize>()).unwrap();
         }
         TC_SET_PGRP => {
-            unimplemented!();
+            let mut pgrp: usize = 0;
+            let grant = message.args[ADDRESS];
+            safecopy_from(proc_nr, grant, 0, &mut pgrp as *mut _ as usize, size_of::<usize>()).unwrap();
+            // 0 gives the terminal back to every process, as if it had never been set.
+            uart.pgrp = if pgrp == 0 { None } else { Some(pgrp) };
         }
         _ => {
-            panic!("Unknown IOCTL message: {}", message.args[IOCTL_TYPE]);
+            reject(&message, EINVAL);
+            return;
         }
     }
 
@@ -146,6 +168,54 @@ pub fn do_ioctl(uart: &mut Uart, message: Msg) {
 pub fn do_close(_uart: &mut Uart, _message: Msg) {
 }
 
+fn signal_of(byte: u8) -> Option<usize> {
+    match byte {
+        CTRL_C => Some(SIGINT),
+        CTRL_Z => Some(SIGTSTP),
+        CTRL_BACKSLASH => Some(SIGQUIT),
+        _ => None,
+    }
+}
+
+/// Send the signal typed on the terminal to the foreground process group. The pending read is
+/// ended with `EINTR`, otherwise the reader could not take the signal until a line is typed.
+fn do_signal_char(uart: &mut Uart, byte: u8, signo: usize) {
+    if uart.termios.c_lflag.contains(Clflag::ECHO) {
+        uart.dev_write('^' as u8);
+        uart.dev_write(byte + 0x40);
+        uart.dev_write(LF);
+    }
+    if !uart.termios.c_lflag.contains(Clflag::NOFLSH) {
+        uart.read_buffer.clear();
+    }
+    if uart.in_left > 0 {
+        uart.in_left = 0;
+        uart.usr_buffer.clear();
+        reply(uart.in_caller, REPLY, uart.in_proc, -EINTR as isize);
+    }
+    if let Some(pgrp) = uart.pgrp {
+        let _ = killpg(pgrp, signo);
+    }
+}
+
+/// Whether `caller` is allowed to use the terminal. A process outside the foreground process group
+/// is sent `signo` along with its group, and its request fails with `EINTR`. Every process is
+/// allowed before the foreground group has been set, so is init, whose pid 0 can't be passed to
+/// getpgid.
+fn is_foreground(uart: &Uart, caller: usize, signo: usize) -> bool {
+    let pgrp = match uart.pgrp {
+        Some(pgrp) if caller != 0 => pgrp,
+        _ => return true,
+    };
+    match getpgid(caller) {
+        Ok(caller_pgrp) if caller_pgrp != pgrp => {
+            let _ = killpg(caller_pgrp, signo);
+            false
+        }
+        _ => true,
+    }
+}
+
 fn echo(uart: &mut Uart, byte: u8) {
     match byte {
         DL => {
@@ -185,7 +255,7 @@ fn transfer_to_usr(uart: &mut Uart) {
         let buffer = uart.usr_buffer.as_slice();
         let buffer_ptr = buffer.as_ptr() as usize;
         let length = buffer.len();
-        virt_copy(getpid(), buffer_ptr, uart.in_proc, uart.buf_ptr, length).unwrap();
+        safecopy_to(uart.in_proc, uart.buf_grant, 0, buffer_ptr, length).unwrap();
         uart.usr_buffer.clear();
         reply(uart.in_caller, REPLY, uart.in_proc, length as isize);
     }
@@ -199,3 +269,14 @@ fn reply(caller: usize, mtype: usize, proc_nr: usize, status: isize) {
 
     send(caller, &message).unwrap();
 }
+
+/// Fail a request the driver doesn't serve. The caller is left without a reply if it isn't waiting
+/// for one, rather than blocking the driver.
+fn reject(message: &Msg, errno: i32) {
+    let mut reply_message = Msg::empty();
+    reply_message.mtype = REPLY;
+    reply_message.args[REPLY_PROC_NR] = message.args[PROC_NR];
+    reply_message.args[REPLY_STATUS] = -errno as isize as usize;
+
+    let _ = send_nb(message.src_pid, &reply_message);
+}
@@ -15,7 +15,7 @@ pub struct Uart {
     pub in_caller: usize,
     pub in_proc: usize,
     pub in_left: usize,
-    pub buf_ptr: usize,
+    pub buf_grant: usize,
     pub pgrp: Option<usize>,
 
     pub termios: Termios,
@@ -35,7 +35,7 @@ impl Uart {
             in_caller: 0,
             in_proc: 0,
             in_left: 0,
-            buf_ptr: 0,
+            buf_grant: 0,
             pgrp: None,
             termios: Termios::default(),
         }
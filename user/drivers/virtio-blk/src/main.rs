@@ -9,9 +9,9 @@ extern crate bitflags;
 extern crate log;
 extern crate volatile;
 
-use user_lib::syscall::{receive, virt_copy, getpid, send};
+use user_lib::syscall::{receive, safecopy_from, safecopy_to, send, send_nb};
 use crate::virtio_driver::{VirtIOBlk, VirtIOHeader};
-use share::ipc::{Msg, READ, WRITE, POSITION, PROC_NR, BUFFER, REPLY_PROC_NR, REPLY_STATUS, REPLY};
+use share::ipc::{Msg, READ, WRITE, NOTIFY, POSITION, PROC_NR, BUFFER, REPLY_PROC_NR, REPLY_STATUS, REPLY};
 use share::syscall::error::EINVAL;
 
 /*
@@ -46,8 +46,11 @@ fn main() {
         let ret = match message.mtype {
             READ => do_read(&mut virtio_blk, message),
             WRITE => do_write(&mut virtio_blk, message),
+            // a notification from another process carries no request.
+            NOTIFY => continue,
             _ => {
-                panic!("Unknown message type:{}", message.mtype);
+                reject(&message, EINVAL);
+                continue;
             }
         };
 
@@ -57,23 +60,23 @@ fn main() {
 
 pub fn do_read(virtio_blk: &mut VirtIOBlk, message: Msg) -> isize {
     let proc_nr = message.args[PROC_NR];
-    let dst_ptr = message.args[BUFFER];
+    let grant = message.args[BUFFER];
     let block_id = message.args[POSITION];
     let mut buffer = [0; BLOCK_SZ];
     if virtio_blk.read_block(block_id, &mut buffer).is_err() {
         return -EINVAL as isize;
     }
-    virt_copy(getpid(), buffer.as_ptr() as usize, proc_nr, dst_ptr, BLOCK_SZ).unwrap();
+    safecopy_to(proc_nr, grant, 0, buffer.as_ptr() as usize, BLOCK_SZ).unwrap();
 
     BLOCK_SZ as isize
 }
 
 pub fn do_write(virtio_blk: &mut VirtIOBlk, message: Msg) -> isize {
     let proc_nr = message.args[PROC_NR];
-    let src_ptr = message.args[BUFFER];
+    let grant = message.args[BUFFER];
     let block_id = message.args[POSITION];
     let mut buffer = [0; BLOCK_SZ];
-    virt_copy(proc_nr, src_ptr, getpid(), buffer.as_mut_ptr() as usize, BLOCK_SZ).unwrap();
+    safecopy_from(proc_nr, grant, 0, buffer.as_mut_ptr() as usize, BLOCK_SZ).unwrap();
     if virtio_blk.write_block(block_id, &buffer).is_err() {
         return -EINVAL as isize;
     }
@@ -89,3 +92,14 @@ fn reply(caller: usize, mtype: usize, proc_nr: usize, status: isize) {
 
     send(caller, &message).unwrap();
 }
+
+/// Fail a request the driver doesn't serve. The caller is left without a reply if it isn't waiting
+/// for one, rather than blocking the driver.
+fn reject(message: &Msg, errno: i32) {
+    let mut reply_message = Msg::empty();
+    reply_message.mtype = REPLY;
+    reply_message.args[REPLY_PROC_NR] = message.args[PROC_NR];
+    reply_message.args[REPLY_STATUS] = -errno as isize as usize;
+
+    let _ = send_nb(message.src_pid, &reply_message);
+}
@@ -1,5 +1,5 @@
-use share::ipc::{Msg, READ, DEVICE, PROC_NR, BUFFER, LENGTH, POSITION, VIRTIO_BLK_PID, REPLY_STATUS, WRITE};
-use user_lib::syscall::{getpid, send, receive};
+use share::ipc::{Msg, READ, DEVICE, PROC_NR, BUFFER, LENGTH, POSITION, BLOCK_SERVICE, REPLY_STATUS, WRITE, GrantFlags};
+use user_lib::syscall::{getpid, sendrec, grant_create, grant_revoke, lookup};
 use crate::vfs::inode::Rdev;
 use share::device::BlockDevice;
 use alloc::sync::Arc;
@@ -8,6 +8,7 @@ pub const BLOCK_SIZE: usize = 512;
 
 pub struct Block {
     rdev: Rdev,
+    driver: usize, // endpoint of the block device driver.
 }
 
 impl Block {
@@ -16,6 +17,7 @@ impl Block {
         Arc::new(
             Self {
                 rdev,
+                driver: lookup(BLOCK_SERVICE).unwrap(),
             }
         )
     }
@@ -29,11 +31,12 @@ impl BlockDevice for Block {
         message.mtype = READ;
         message.args[DEVICE] = 0;
         message.args[PROC_NR] = getpid();
-        message.args[BUFFER] = buf.as_ptr() as usize;
+        let grant = grant_create(self.driver, buf.as_ptr() as usize, buf.len(), GrantFlags::WRITE).unwrap();
+        message.args[BUFFER] = grant;
         message.args[LENGTH] = buf.len();
         message.args[POSITION] = block_id;
-        send(VIRTIO_BLK_PID, &message).unwrap();
-        receive(VIRTIO_BLK_PID as isize, &mut message).unwrap();
+        sendrec(self.driver, &mut message).unwrap();
+        grant_revoke(grant).unwrap();
         assert_eq!(message.args[REPLY_STATUS], BLOCK_SIZE);
     }
 
@@ -43,11 +46,12 @@ impl BlockDevice for Block {
         let mut message = Msg::empty();
         message.mtype = WRITE;
         message.args[PROC_NR] = getpid();
-        message.args[BUFFER] = buf.as_ptr() as usize;
+        let grant = grant_create(self.driver, buf.as_ptr() as usize, buf.len(), GrantFlags::READ).unwrap();
+        message.args[BUFFER] = grant;
         message.args[LENGTH] = buf.len();
         message.args[POSITION] = block_id;
-        send(VIRTIO_BLK_PID, &message).unwrap();
-        receive(VIRTIO_BLK_PID as isize, &mut message).unwrap();
+        sendrec(self.driver, &mut message).unwrap();
+        grant_revoke(grant).unwrap();
         assert_eq!(message.args[REPLY_STATUS], BLOCK_SIZE);
     }
 }
\ No newline at end of file
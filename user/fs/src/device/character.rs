@@ -1,39 +1,49 @@
 use crate::vfs::inode::Rdev;
-use share::ipc::{Msg, READ, DEVICE, PROC_NR, BUFFER, LENGTH, TERMINAL_PID, WRITE};
-use user_lib::syscall::{getpid, send, receive};
+use share::ipc::{Msg, READ, DEVICE, PROC_NR, BUFFER, LENGTH, CALLER_PID, TERMINAL_SERVICE, WRITE, GrantFlags};
+use share::syscall::error::SysError;
+use user_lib::syscall::{getpid, sendrec, grant_create, grant_revoke, lookup};
 
 pub struct Character {
     rdev: Rdev,
+    driver: usize, // endpoint of the terminal driver.
 }
 
 impl Character {
     pub fn new(rdev: Rdev) -> Self {
         Self {
             rdev,
+            driver: lookup(TERMINAL_SERVICE).unwrap(),
         }
     }
 
-    pub fn read(&self, buf: &mut [u8]) {
+    /// `caller` is the process doing the read, a background process is refused by the driver.
+    pub fn read(&self, buf: &mut [u8], caller: usize) -> Result<usize, SysError> {
         let mut message = Msg::empty();
         message.mtype = READ;
         message.args[DEVICE] = 0;
         message.args[PROC_NR] = getpid();
-        message.args[BUFFER] = buf.as_ptr() as usize;
+        let grant = grant_create(self.driver, buf.as_ptr() as usize, buf.len(), GrantFlags::WRITE).unwrap();
+        message.args[BUFFER] = grant;
         message.args[LENGTH] = buf.len();
         // message.args[POSITION] = ???;
-        send(TERMINAL_PID, &message).unwrap();
-        receive(TERMINAL_PID as isize, &mut message).unwrap();
+        message.args[CALLER_PID] = caller;
+        sendrec(self.driver, &mut message).unwrap();
+        grant_revoke(grant).unwrap();
+        message.cvt_reply_message_to_result()
     }
 
-    pub fn write(&self, buf: &[u8]) {
+    pub fn write(&self, buf: &[u8], caller: usize) -> Result<usize, SysError> {
         let mut message = Msg::empty();
         message.mtype = WRITE;
         message.args[DEVICE] = 0;
         message.args[PROC_NR] = getpid();
-        message.args[BUFFER] = buf.as_ptr() as usize;
+        let grant = grant_create(self.driver, buf.as_ptr() as usize, buf.len(), GrantFlags::READ).unwrap();
+        message.args[BUFFER] = grant;
         message.args[LENGTH] = buf.len();
         // message.args[POSITION] = ???;
-        send(TERMINAL_PID, &message).unwrap();
-        receive(TERMINAL_PID as isize, &mut message).unwrap();
+        message.args[CALLER_PID] = caller;
+        sendrec(self.driver, &mut message).unwrap();
+        grant_revoke(grant).unwrap();
+        message.cvt_reply_message_to_result()
     }
-}
\ No newline at end of file
+}
@@ -11,7 +11,7 @@ use crate::device::block::{Block, BLOCK_SIZE};
 use share::device::BlockDevice;
 use crate::device::character::Character;
 use share::syscall::error::{SysError, EPERM};
-use user_lib::syscall::{virt_copy, getpid};
+use user_lib::syscall::{safecopy_to, safecopy_from};
 
 pub fn create_devfs_super_block(rdev: Rdev) -> Option<Rc<RefCell<SuperBlock>>> {
     let val: u64 = rdev.into();
@@ -69,7 +69,7 @@ impl InodeOperations for  DevFsInodeOperations {
 }
 
 impl FileOperations for DevFsFileOperations {
-    fn read(&self, file: Rc<RefCell<File>>, buf_ptr: usize, cnt: usize, proc_nr: usize) -> Result<usize, SysError> {
+    fn read(&self, file: Rc<RefCell<File>>, grant: usize, offset: usize, cnt: usize, proc_nr: usize) -> Result<usize, SysError> {
         let inode = file.borrow().dentry.borrow().inode.clone();
         let rdev = inode.borrow().rdev.unwrap();
         let mut content = vec![0; cnt];
@@ -103,24 +103,24 @@ impl FileOperations for DevFsFileOperations {
             },
             FileTypeFlag::DT_CHR => {
                 let chr_device = Character::new(rdev);
-                chr_device.read(content.as_mut_slice());
+                chr_device.read(content.as_mut_slice(), proc_nr)?;
             },
             _ => panic!("devfs cannot read on file type except block or chr!")
         };
 
         let length = content.len();
         if length > 0 {
-            virt_copy(getpid(), content.as_ptr() as usize, proc_nr, buf_ptr, length)?;
+            safecopy_to(proc_nr, grant, offset, content.as_ptr() as usize, length)?;
         }
 
         Ok(length)
     }
 
-    fn write(&self, file: Rc<RefCell<File>>, buf_ptr: usize, cnt: usize, proc_nr: usize) -> Result<(), SysError> {
+    fn write(&self, file: Rc<RefCell<File>>, grant: usize, offset: usize, cnt: usize, proc_nr: usize) -> Result<(), SysError> {
         let inode = file.borrow().dentry.borrow().inode.clone();
         let rdev = inode.borrow().rdev.unwrap();
         let content = vec![0; cnt];
-        virt_copy(proc_nr, buf_ptr, getpid(), content.as_ptr() as usize, cnt)?;
+        safecopy_from(proc_nr, grant, offset, content.as_ptr() as usize, cnt)?;
 
         match inode.borrow().file_type {
             FileTypeFlag::DT_BLK => {
@@ -128,7 +128,7 @@ impl FileOperations for DevFsFileOperations {
             },
             FileTypeFlag::DT_CHR => {
                 let chr_device = Character::new(rdev);
-                chr_device.write(content.as_slice());
+                chr_device.write(content.as_slice(), proc_nr)?;
             },
             _ => panic!("devfs cannot write on file type except block or chr!")
         };
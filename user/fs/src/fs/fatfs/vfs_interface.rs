@@ -13,7 +13,7 @@ use simple_fat32::{FAT32Manager,VFile,ATTRIBUTE_DIRECTORY, ATTRIBUTE_ARCHIVE};
 use alloc::sync::Arc;
 use crate::device::block::Block;
 use share::syscall::error::{SysError, EEXIST, EPERM, ENOENT};
-use user_lib::syscall::{virt_copy, getpid};
+use user_lib::syscall::{safecopy_to, safecopy_from};
 
 pub fn create_fatfs_super_block(rdev: Rdev) -> Option<Rc<RefCell<SuperBlock>>> {
     // create a new easy filesystem instance.
@@ -124,7 +124,7 @@ impl InodeOperations for FATFsInodeOperations {
 
 impl FileOperations for FATFsFileOperations {
     //s
-    fn read(&self, file: Rc<RefCell<File>>, buf_ptr: usize, cnt: usize, proc_nr: usize) -> Result<usize, SysError> {
+    fn read(&self, file: Rc<RefCell<File>>, grant: usize, offset: usize, cnt: usize, proc_nr: usize) -> Result<usize, SysError> {
         /*let rdev = file.borrow().dentry.borrow().inode.borrow().super_block.borrow().rdev.into();
         let name = file.borrow().dentry.borrow().name.clone();
         let inode =file.borrow().dentry.borrow().parent.as_ref().unwrap().borrow().inode.clone();
@@ -152,7 +152,7 @@ impl FileOperations for FATFsFileOperations {
         fatfs_inode.read_at(pos, content.as_mut_slice());
         let length = content.len();
         if length > 0 {
-            virt_copy(getpid(), content.as_ptr() as usize, proc_nr, buf_ptr, length)?;
+            safecopy_to(proc_nr, grant, offset, content.as_ptr() as usize, length)?;
         }
         Ok(length)*/
         let rdev = file.borrow().dentry.borrow().inode.borrow().super_block.borrow().rdev.into();
@@ -167,7 +167,7 @@ impl FileOperations for FATFsFileOperations {
         fatfs_inode.read_at(pos, content.as_mut_slice());
         let length = content.len();
         if length > 0 {
-            virt_copy(getpid(), content.as_ptr() as usize, proc_nr, buf_ptr, length)?;
+            safecopy_to(proc_nr, grant, offset, content.as_ptr() as usize, length)?;
         }
 
         Ok(length)
@@ -175,7 +175,7 @@ impl FileOperations for FATFsFileOperations {
     }
 
     //unmatch
-    fn write(&self, file: Rc<RefCell<File>>, buf_ptr: usize, cnt: usize, proc_nr: usize) -> Result<(), SysError> {
+    fn write(&self, file: Rc<RefCell<File>>, grant: usize, offset: usize, cnt: usize, proc_nr: usize) -> Result<(), SysError> {
         let rdev = file.borrow().dentry.borrow().inode.borrow().super_block.borrow().rdev.into();
         let root_inode = get_fatfs_root_inode(rdev).unwrap();
 
@@ -188,7 +188,7 @@ impl FileOperations for FATFsFileOperations {
         assert_eq!(ino, write_ino);
 
         let content = vec![0; cnt];
-        virt_copy(proc_nr, buf_ptr, getpid(), content.as_ptr() as usize, cnt)?;
+        safecopy_from(proc_nr, grant, offset, content.as_ptr() as usize, cnt)?;
         fatfs_inode.write_at(pos, content.as_slice());
 
         Ok(())
@@ -8,7 +8,7 @@ use crate::vfs::file::{FileOperations, File};
 use alloc::vec::Vec;
 use share::file::FileTypeFlag;
 use share::syscall::error::{SysError, ENOENT, EEXIST};
-use user_lib::syscall::{virt_copy, getpid};
+use user_lib::syscall::{safecopy_to, safecopy_from};
 
 /// The function will create a new ramfs.
 pub fn create_ramfs_super_block(rdev: Rdev) -> Option<Rc<RefCell<SuperBlock>>> {
@@ -116,7 +116,7 @@ impl InodeOperations for RamFsInodeOperations {
 }
 
 impl FileOperations for RamFsFileOperations {
-    fn read(&self, file: Rc<RefCell<File>>, buf_ptr: usize, cnt: usize, proc_nr: usize) -> Result<usize, SysError> {
+    fn read(&self, file: Rc<RefCell<File>>, grant: usize, offset: usize, cnt: usize, proc_nr: usize) -> Result<usize, SysError> {
         let file_ref = file.borrow();
 
         let rdev = file_ref.dentry.borrow().inode.borrow().super_block.borrow().rdev.into();
@@ -126,13 +126,13 @@ impl FileOperations for RamFsFileOperations {
         let content = ram_fs_inode.borrow().read(file_ref.pos, cnt);
         let length = content.len();
         if length > 0 {
-            virt_copy(getpid(), content.as_ptr() as usize, proc_nr, buf_ptr, length)?;
+            safecopy_to(proc_nr, grant, offset, content.as_ptr() as usize, length)?;
         }
 
         Ok(length)
     }
 
-    fn write(&self, file: Rc<RefCell<File>>, buf_ptr: usize, cnt: usize, proc_nr: usize) -> Result<(), SysError> {
+    fn write(&self, file: Rc<RefCell<File>>, grant: usize, offset: usize, cnt: usize, proc_nr: usize) -> Result<(), SysError> {
         let file_ref = file.borrow();
 
         let rdev = file_ref.dentry.borrow().inode.borrow().super_block.borrow().rdev.into();
@@ -140,7 +140,7 @@ impl FileOperations for RamFsFileOperations {
         let ram_fs_inode = get_ramfs_inode_from_related_ramfs(rdev, ino).unwrap();
 
         let content = vec![0; cnt];
-        virt_copy(proc_nr, buf_ptr, getpid(), content.as_ptr() as usize, cnt)?;
+        safecopy_from(proc_nr, grant, offset, content.as_ptr() as usize, cnt)?;
         ram_fs_inode.borrow_mut().write(file_ref.pos, content.as_slice());
 
         Ok(())
@@ -24,7 +24,7 @@ use crate::proc::fs_struct::FsStruct;
 use crate::proc::fs_manager::*;
 use crate::syscall::*;
 use user_lib::syscall::{receive, copy_path_from, send};
-use share::ipc::{Msg, FORK, EXIT, FS_SYSCALL_ARG0, FS_SYSCALL_ARG1, SYSCALL_TYPE, FS_SYSCALL_ARG2, FS_SYSCALL_ARG3, REPLY_PROC_NR, REPLY_STATUS, REPLY, FORK_PARENT, FSYSCALL, FS_SYSCALL_ARG4, FORK_CHILD, EXIT_PID};
+use share::ipc::{Msg, FORK, EXIT, FS_SYSCALL_ARG0, FS_SYSCALL_ARG1, SYSCALL_TYPE, FS_SYSCALL_ARG2, FS_SYSCALL_ARG3, REPLY_PROC_NR, REPLY_STATUS, REPLY, FORK_PARENT, FSYSCALL, FS_SYSCALL_ARG4, FORK_CHILD, EXIT_PID, FS_SYSCALL_GRANT};
 use share::syscall::sys_const::*;
 use core::cell::RefCell;
 use alloc::rc::Rc;
@@ -113,7 +113,7 @@ fn handle_syscall(message: &mut Msg) -> Result<usize, SysError> {
     let cur_fs = get_fs_struct_by_pid(src_pid);
     let result = match message.args[SYSCALL_TYPE] {
         SYSCALL_LSEEK => do_lseek(message.args[FS_SYSCALL_ARG0], message.args[FS_SYSCALL_ARG1], message.args[FS_SYSCALL_ARG2], cur_fs),
-        SYSCALL_GETCWD => do_getcwd(message.args[FS_SYSCALL_ARG0], message.args[FS_SYSCALL_ARG1], src_pid, cur_fs),
+        SYSCALL_GETCWD => do_getcwd(message.args[FS_SYSCALL_ARG0], message.args[FS_SYSCALL_GRANT], message.args[FS_SYSCALL_ARG1], src_pid, cur_fs),
         SYSCALL_DUP => do_dup(message.args[FS_SYSCALL_ARG0], cur_fs),
         SYSCALL_DUP3 => do_dup3(message.args[FS_SYSCALL_ARG0], message.args[FS_SYSCALL_ARG1], cur_fs),
         SYSCALL_UNMOUNT => {
@@ -137,14 +137,14 @@ fn handle_syscall(message: &mut Msg) -> Result<usize, SysError> {
             do_open(message.args[FS_SYSCALL_ARG0], path.as_str(), message.args[FS_SYSCALL_ARG2] as u32, message.args[FS_SYSCALL_ARG3] as u32, cur_fs)
         },
         SYSCALL_CLOSE => do_close(message.args[FS_SYSCALL_ARG0], cur_fs),
-        SYSCALL_GETDENTS => unsafe {do_get_dents(message.args[FS_SYSCALL_ARG0], message.args[FS_SYSCALL_ARG1], message.args[FS_SYSCALL_ARG2], src_pid, cur_fs) },
-        SYSCALL_READ => do_read(message.args[FS_SYSCALL_ARG0], message.args[FS_SYSCALL_ARG1], message.args[FS_SYSCALL_ARG2], src_pid, cur_fs),
-        SYSCALL_WRITE => do_write(message.args[FS_SYSCALL_ARG0], message.args[FS_SYSCALL_ARG1], message.args[FS_SYSCALL_ARG2], src_pid, cur_fs),
+        SYSCALL_GETDENTS => unsafe {do_get_dents(message.args[FS_SYSCALL_ARG0], message.args[FS_SYSCALL_ARG1], message.args[FS_SYSCALL_GRANT], message.args[FS_SYSCALL_ARG2], src_pid, cur_fs) },
+        SYSCALL_READ => do_read(message.args[FS_SYSCALL_ARG0], message.args[FS_SYSCALL_GRANT], message.args[FS_SYSCALL_ARG2], src_pid, cur_fs),
+        SYSCALL_WRITE => do_write(message.args[FS_SYSCALL_ARG0], message.args[FS_SYSCALL_GRANT], message.args[FS_SYSCALL_ARG2], src_pid, cur_fs),
         SYSCALL_MKDIRAT => {
             let path = copy_path_from(src_pid, message.args[FS_SYSCALL_ARG1])?;
             do_mkdir_at(message.args[FS_SYSCALL_ARG0],path.as_str(), message.args[FS_SYSCALL_ARG2], cur_fs)
         },
-        SYSCALL_FSTAT => do_fstat(message.args[FS_SYSCALL_ARG0], message.args[FS_SYSCALL_ARG1], src_pid, cur_fs),
+        SYSCALL_FSTAT => do_fstat(message.args[FS_SYSCALL_ARG0], message.args[FS_SYSCALL_GRANT], src_pid, cur_fs),
         SYSCALL_UNLINK => {
             let path = copy_path_from(src_pid, message.args[FS_SYSCALL_ARG0])?;
             do_unlink(path.as_str(), cur_fs)
@@ -153,6 +153,10 @@ fn handle_syscall(message: &mut Msg) -> Result<usize, SysError> {
             let path = copy_path_from(src_pid, message.args[FS_SYSCALL_ARG0])?;
             do_rmdir(path.as_str(), cur_fs)
         },
+        FS_MAP_FILE => do_map_file(message.args[FS_SYSCALL_ARG0], message.args[FS_SYSCALL_ARG1] != 0, cur_fs),
+        FS_READ_PAGE => do_read_page(message.args[FS_SYSCALL_ARG0], message.args[FS_SYSCALL_ARG1], message.args[FS_SYSCALL_GRANT], src_pid),
+        FS_WRITE_PAGE => do_write_page(message.args[FS_SYSCALL_ARG0], message.args[FS_SYSCALL_ARG1], message.args[FS_SYSCALL_GRANT], src_pid),
+        FS_UNMAP_FILE => do_unmap_file(message.args[FS_SYSCALL_ARG0]),
         _ => {
             panic!("Unknown FSYSCALL id: {}", message.args[SYSCALL_TYPE]);
         }
@@ -0,0 +1,96 @@
+use alloc::rc::Rc;
+use core::cell::RefCell;
+use alloc::collections::BTreeMap;
+use crate::vfs::file::File;
+
+/// Pin `file` for the page cache of the kernel, and return the key of its inode. The pinned file is
+/// a private one, so that its position is not disturbed by the tasks. A mapping with `write` also
+/// pins the file for the pages to be written back, `file` must have been opened for writing then.
+pub fn map_file(file: Rc<RefCell<File>>, write: bool) -> usize {
+    unsafe {
+        FILE_MAPPING_MANAGER.map_file(file, write)
+    }
+}
+
+pub fn get_mapped_file(key: usize) -> Option<Rc<RefCell<File>>> {
+    unsafe {
+        FILE_MAPPING_MANAGER.get_mapped_file(key)
+    }
+}
+
+/// Return the file the pages are written back through, which is None unless any of the mappings
+/// was made with `write`.
+pub fn get_mapped_writer(key: usize) -> Option<Rc<RefCell<File>>> {
+    unsafe {
+        FILE_MAPPING_MANAGER.get_mapped_writer(key)
+    }
+}
+
+/// Return false if `key` is not mapped.
+pub fn unmap_file(key: usize) -> bool {
+    unsafe {
+        FILE_MAPPING_MANAGER.unmap_file(key)
+    }
+}
+
+static mut FILE_MAPPING_MANAGER: FileMappingManager = FileMappingManager::new();
+
+pub struct FileMappingManager {
+    /// The pinned files by the key of the inode.
+    key2file: BTreeMap<usize, MappedFile>,
+}
+
+struct MappedFile {
+    file: Rc<RefCell<File>>,
+    /// Pinned from the first mapper which is allowed to write the file.
+    writer: Option<Rc<RefCell<File>>>,
+    count: usize,
+}
+
+fn pin(file: &File) -> Rc<RefCell<File>> {
+    Rc::new(RefCell::new(File::new(file.fop.clone(), file.dentry.clone(), file.open_flags, file.mnt.clone())))
+}
+
+impl FileMappingManager {
+    pub const fn new() -> Self {
+        Self {
+            key2file: BTreeMap::new(),
+        }
+    }
+
+    pub fn map_file(&mut self, file: Rc<RefCell<File>>, write: bool) -> usize {
+        let file_ref = file.borrow();
+        let inode = file_ref.dentry.borrow().inode.clone();
+        let key = Rc::as_ptr(&inode) as usize;
+
+        let mapped = self.key2file.entry(key).or_insert_with(|| MappedFile {
+            file: pin(&file_ref),
+            writer: None,
+            count: 0,
+        });
+        if write && mapped.writer.is_none() {
+            mapped.writer = Some(pin(&file_ref));
+        }
+        mapped.count += 1;
+
+        key
+    }
+
+    pub fn get_mapped_file(&self, key: usize) -> Option<Rc<RefCell<File>>> {
+        self.key2file.get(&key).map(|mapped| mapped.file.clone())
+    }
+
+    pub fn get_mapped_writer(&self, key: usize) -> Option<Rc<RefCell<File>>> {
+        self.key2file.get(&key).and_then(|mapped| mapped.writer.clone())
+    }
+
+    pub fn unmap_file(&mut self, key: usize) -> bool {
+        match self.key2file.get_mut(&key) {
+            Some(mapped) if mapped.count > 1 => mapped.count -= 1,
+            Some(_) => { self.key2file.remove(&key); },
+            None => return false,
+        }
+
+        true
+    }
+}
@@ -1,2 +1,3 @@
 pub mod fs_struct;
-pub mod fs_manager;
\ No newline at end of file
+pub mod fs_manager;
+pub mod file_mapping;
\ No newline at end of file
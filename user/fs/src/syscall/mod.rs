@@ -2,9 +2,9 @@ use alloc::rc::Rc;
 use crate::proc::fs_struct::FsStruct;
 use core::cell::RefCell;
 use crate::vfs::dentry::{VfsDentry, VfsMount};
-use share::syscall::error::{SysError, ENOENT, EBADF, ENOTDIR, EINVAL, ERANGE, ENOTBLK, ENODEV, EISDIR, EBUSY, ENOTEMPTY};
+use share::syscall::error::{SysError, ENOENT, EBADF, ENOTDIR, EINVAL, ERANGE, ENOTBLK, ENODEV, EISDIR, EBUSY, ENOTEMPTY, EACCES};
 use crate::vfs::file::File;
-use user_lib::syscall::{virt_copy, getpid};
+use user_lib::syscall::safecopy_to;
 use share::file::{OpenFlag, FileTypeFlag, Dirent, AT_FD_CWD, DIRENT_BUFFER_SZ, SEEKFlag, Stat};
 use alloc::vec::Vec;
 use alloc::string::String;
@@ -13,6 +13,7 @@ use alloc::collections::VecDeque;
 use crate::vfs::filesystem::read_super_block;
 use crate::vfs::inode::Rdev;
 use core::mem::size_of;
+use crate::proc::file_mapping::{map_file, get_mapped_file, get_mapped_writer, unmap_file};
 
 /// The return value of `path_lookup` function.
 #[derive(Clone)]
@@ -59,7 +60,7 @@ pub fn do_lseek(fd: usize, offset: usize, whence: usize, cur_fs: Rc<RefCell<FsSt
     Ok(result)
 }
 
-pub fn do_getcwd(buf: usize, size: usize, proc_nr: usize, cur_fs: Rc<RefCell<FsStruct>>) -> Result<usize, SysError> {
+pub fn do_getcwd(buf: usize, grant: usize, size: usize, proc_nr: usize, cur_fs: Rc<RefCell<FsStruct>>) -> Result<usize, SysError> {
     let fs_ref = cur_fs.borrow();
     let path = get_path_name(fs_ref.pwd.clone(), fs_ref.pwd_mnt.clone(),
                              fs_ref.root.clone(), fs_ref.root_mnt.clone());
@@ -68,7 +69,7 @@ pub fn do_getcwd(buf: usize, size: usize, proc_nr: usize, cur_fs: Rc<RefCell<FsS
         return Err(SysError::new(ERANGE));
     }
 
-    virt_copy(getpid(), path.as_ptr() as usize, proc_nr, buf, length).unwrap();
+    safecopy_to(proc_nr, grant, 0, path.as_ptr() as usize, length)?;
 
     Ok(buf)
 }
@@ -182,7 +183,9 @@ pub fn do_close(fd: usize, cur_fs: Rc<RefCell<FsStruct>>) -> Result<usize, SysEr
 }
 
 static mut DIRENT_BUFFER: [u8; DIRENT_BUFFER_SZ] = [0; DIRENT_BUFFER_SZ];
-pub unsafe fn do_get_dents(fd: usize, buf: usize, length: usize, proc_nr: usize, cur_fs: Rc<RefCell<FsStruct>>) -> Result<usize, SysError> {
+/// `buf` is only used to build `d_name` pointers in the caller's address space, the buffer itself is
+/// accessed through `grant`.
+pub unsafe fn do_get_dents(fd: usize, buf: usize, grant: usize, length: usize, proc_nr: usize, cur_fs: Rc<RefCell<FsStruct>>) -> Result<usize, SysError> {
     let file = cur_fs.borrow().get_file(fd)?;
     if !file.borrow().is_directory() {
         return Err(SysError::new(ENOTDIR));
@@ -236,18 +239,18 @@ pub unsafe fn do_get_dents(fd: usize, buf: usize, length: usize, proc_nr: usize,
     }
 
     // Copy the buffer to destination
-    virt_copy(getpid(), DIRENT_BUFFER.as_ptr() as usize, proc_nr, buf, offset).unwrap();
+    safecopy_to(proc_nr, grant, 0, DIRENT_BUFFER.as_ptr() as usize, offset)?;
 
     Ok(offset)
 }
 
-pub fn do_read(fd: usize, buf: usize, count: usize, proc_nr: usize, cur_fs: Rc<RefCell<FsStruct>>) -> Result<usize, SysError> {
+pub fn do_read(fd: usize, grant: usize, count: usize, proc_nr: usize, cur_fs: Rc<RefCell<FsStruct>>) -> Result<usize, SysError> {
     let file = cur_fs.borrow().get_file(fd)?;
     if !file.borrow().readable() {
         return Err(SysError::new(EBADF));
     }
 
-    let length = file.borrow().fop.read(file.clone(), buf, count, proc_nr)?;
+    let length = file.borrow().fop.read(file.clone(), grant, 0, count, proc_nr)?;
     file.borrow_mut().pos += length;
 
     Ok(length)
@@ -255,7 +258,7 @@ pub fn do_read(fd: usize, buf: usize, count: usize, proc_nr: usize, cur_fs: Rc<R
 
 const BUFFER_SIZE: usize = 512;
 
-pub fn do_write(fd: usize, buf: usize, count: usize, proc_nr: usize, cur_fs: Rc<RefCell<FsStruct>>) -> Result<usize, SysError> {
+pub fn do_write(fd: usize, grant: usize, count: usize, proc_nr: usize, cur_fs: Rc<RefCell<FsStruct>>) -> Result<usize, SysError> {
     let file = cur_fs.borrow().get_file(fd)?;
     if !file.borrow().writable() {
         return Err(SysError::new(EBADF));
@@ -263,7 +266,7 @@ pub fn do_write(fd: usize, buf: usize, count: usize, proc_nr: usize, cur_fs: Rc<
 
     for offset in (0..count).step_by(BUFFER_SIZE) {
         let length = usize::min(BUFFER_SIZE, count - offset);
-        file.borrow().fop.write(file.clone(), buf + offset, length, proc_nr)?;
+        file.borrow().fop.write(file.clone(), grant, offset, length, proc_nr)?;
         file.borrow_mut().pos += length;
     }
 
@@ -297,11 +300,72 @@ pub fn do_mkdir_at(dir_fd: usize, path: &str, _mode: usize, cur_fs: Rc<RefCell<F
     Ok(0)
 }
 
-pub fn do_fstat(fd: usize, stat_ptr: usize, proc_nr: usize, cur_fs: Rc<RefCell<FsStruct>>) -> Result<usize, SysError> {
+pub fn do_fstat(fd: usize, grant: usize, proc_nr: usize, cur_fs: Rc<RefCell<FsStruct>>) -> Result<usize, SysError> {
     let file = cur_fs.borrow().get_file(fd)?;
     let stat = file.borrow().fstat();
-    virt_copy(getpid(), &stat as *const _ as usize,
-              proc_nr, stat_ptr, core::mem::size_of::<Stat>()).unwrap();
+    safecopy_to(proc_nr, grant, 0, &stat as *const _ as usize, core::mem::size_of::<Stat>())?;
+
+    Ok(0)
+}
+
+/// The page size of the kernel, whose page cache asks for the pages of mapped files.
+const PAGE_SIZE: usize = 4096;
+
+pub fn do_map_file(fd: usize, write: bool, cur_fs: Rc<RefCell<FsStruct>>) -> Result<usize, SysError> {
+    let file = cur_fs.borrow().get_file(fd)?;
+    if !file.borrow().readable() || file.borrow().is_directory() {
+        return Err(SysError::new(EACCES));
+    }
+    if write && !file.borrow().writable() {
+        return Err(SysError::new(EACCES));
+    }
+
+    Ok(map_file(file, write))
+}
+
+/// Read the page at `offset` of the mapped file into the frame granted by the kernel, return the
+/// length read, which is 0 beyond the end of the file.
+pub fn do_read_page(key: usize, offset: usize, grant: usize, proc_nr: usize) -> Result<usize, SysError> {
+    let file = get_mapped_file(key).ok_or(SysError::new(EINVAL))?;
+    let size = file.borrow().dentry.borrow().inode.borrow().size;
+    if offset >= size {
+        return Ok(0);
+    }
+
+    file.borrow_mut().pos = offset;
+    let count = usize::min(PAGE_SIZE, size - offset);
+    let length = file.borrow().fop.read(file.clone(), grant, 0, count, proc_nr)?;
+
+    Ok(length)
+}
+
+/// Write the frame granted by the kernel to the page at `offset` of the mapped file, the part
+/// beyond the end of the file is dropped.
+pub fn do_write_page(key: usize, offset: usize, grant: usize, proc_nr: usize) -> Result<usize, SysError> {
+    if get_mapped_file(key).is_none() {
+        return Err(SysError::new(EINVAL));
+    }
+    let file = get_mapped_writer(key).ok_or(SysError::new(EACCES))?;
+    let size = file.borrow().dentry.borrow().inode.borrow().size;
+    if offset >= size {
+        return Ok(0);
+    }
+
+    file.borrow_mut().pos = offset;
+    let count = usize::min(PAGE_SIZE, size - offset);
+    for grant_offset in (0..count).step_by(BUFFER_SIZE) {
+        let length = usize::min(BUFFER_SIZE, count - grant_offset);
+        file.borrow().fop.write(file.clone(), grant, grant_offset, length, proc_nr)?;
+        file.borrow_mut().pos += length;
+    }
+
+    Ok(count)
+}
+
+pub fn do_unmap_file(key: usize) -> Result<usize, SysError> {
+    if !unmap_file(key) {
+        return Err(SysError::new(EINVAL));
+    }
 
     Ok(0)
 }
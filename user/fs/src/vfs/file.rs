@@ -48,8 +48,9 @@ impl File {
     }
 }
 
+/// `grant` is the grant of the user buffer made by `proc_nr`, and `offset` is where to start inside it.
 pub trait FileOperations {
-    fn read(&self, file: Rc<RefCell<File>>, buf_ptr: usize, cnt: usize, proc_nr: usize) -> Result<usize, SysError>;
-    fn write(&self, file: Rc<RefCell<File>>, buf_ptr: usize, cnt: usize, proc_nr: usize) -> Result<(), SysError>;
+    fn read(&self, file: Rc<RefCell<File>>, grant: usize, offset: usize, cnt: usize, proc_nr: usize) -> Result<usize, SysError>;
+    fn write(&self, file: Rc<RefCell<File>>, grant: usize, offset: usize, cnt: usize, proc_nr: usize) -> Result<(), SysError>;
     fn readdir(&self, file: Rc<RefCell<File>>) -> Vec<Rc<RefCell<VfsDentry>>>;
 }
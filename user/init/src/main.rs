@@ -17,14 +17,13 @@ fn main() {
     open_standard_fd();
     mount_fatfs_on("/bin");
     mkdir_at(0,"/bin/mnt",0).unwrap();
-    fork_and_exec("/bin/idle");
     fork_and_exec("/bin/shell");
 
+    // reap children and orphans, waitpid blocks until one of them exits.
     loop {
-        match waitpid(-1, None, 0) {
-            Ok(_) => continue,
-            Err(_) => sys_yield(),
-        };
+        if waitpid(-1, None, 0).is_err() {
+            sys_yield();
+        }
     }
 }
 
@@ -0,0 +1,127 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::syscall::{fork, waitpid, exit, mmap, munmap, mprotect, mremap};
+use share::mmap::{Prot, MMAPFlags, MremapFlags};
+use share::wait::{wifsignaled, wtermsig};
+use share::signal::SIGSEGV;
+use share::syscall::error::{EINVAL, ENOMEM};
+
+const PAGE_SIZE: usize = 4096;
+
+#[no_mangle]
+fn main() {
+    test_munmap_across_regions();
+    test_mprotect();
+    test_mremap();
+    test_huge_lengths();
+}
+
+/// Unmap a range which covers parts of two regions, the pages around it are kept.
+fn test_munmap_across_regions() {
+    let start = map_anonymous(4);
+    mprotect(start + PAGE_SIZE, PAGE_SIZE, Prot::READ).unwrap();
+    munmap(start + PAGE_SIZE, PAGE_SIZE * 2).unwrap();
+
+    write(start, 1);
+    write(start + PAGE_SIZE * 3, 4);
+    assert_eq!(read(start), 1);
+    assert_eq!(read(start + PAGE_SIZE * 3), 4);
+    expect_segmentation_fault(|| { read(start + PAGE_SIZE); });
+    expect_segmentation_fault(|| { read(start + PAGE_SIZE * 2); });
+
+    // holes in the range are skipped.
+    munmap(start, PAGE_SIZE * 4).unwrap();
+    expect_segmentation_fault(|| { read(start); });
+    println!("test_munmap_across_regions success!");
+}
+
+fn test_mprotect() {
+    let start = map_anonymous(2);
+    write(start, 1);
+    mprotect(start, PAGE_SIZE * 2, Prot::READ).unwrap();
+    assert_eq!(read(start), 1);
+    expect_segmentation_fault(|| write(start, 2));
+    expect_segmentation_fault(|| write(start + PAGE_SIZE, 2));
+
+    mprotect(start, PAGE_SIZE, Prot::empty()).unwrap();
+    expect_segmentation_fault(|| { read(start); });
+    assert_eq!(read(start + PAGE_SIZE), 0);
+
+    mprotect(start, PAGE_SIZE * 2, Prot::READ | Prot::WRITE).unwrap();
+    write(start, 3);
+    write(start + PAGE_SIZE, 4);
+    assert_eq!(read(start), 3);
+    assert_eq!(read(start + PAGE_SIZE), 4);
+
+    // a range with unmapped pages is refused.
+    munmap(start + PAGE_SIZE, PAGE_SIZE).unwrap();
+    assert!(mprotect(start, PAGE_SIZE * 2, Prot::READ).is_err());
+    write(start, 5);
+    println!("test_mprotect success!");
+}
+
+fn test_mremap() {
+    let start = map_anonymous(2);
+    write(start, 1);
+    write(start + PAGE_SIZE, 2);
+
+    // grow, the content is kept wherever the mapping goes.
+    let start = mremap(start, PAGE_SIZE * 2, PAGE_SIZE * 8, MremapFlags::MAYMOVE).unwrap();
+    assert_eq!(read(start), 1);
+    assert_eq!(read(start + PAGE_SIZE), 2);
+    assert_eq!(read(start + PAGE_SIZE * 7), 0);
+    write(start + PAGE_SIZE * 7, 8);
+
+    // shrink in place.
+    assert_eq!(mremap(start, PAGE_SIZE * 8, PAGE_SIZE, MremapFlags::empty()).unwrap(), start);
+    assert_eq!(read(start), 1);
+    expect_segmentation_fault(|| { read(start + PAGE_SIZE); });
+
+    // an unmapped range can't be remapped.
+    assert!(mremap(start + PAGE_SIZE, PAGE_SIZE, PAGE_SIZE * 2, MremapFlags::MAYMOVE).is_err());
+    println!("test_mremap success!");
+}
+
+/// Lengths which would wrap around when rounded up to pages are refused, and the mapping is kept.
+fn test_huge_lengths() {
+    let start = map_anonymous(1);
+    write(start, 1);
+    assert_eq!(mremap(start, PAGE_SIZE, usize::MAX, MremapFlags::empty()).unwrap_err().errno, ENOMEM);
+    assert_eq!(mremap(start, PAGE_SIZE, usize::MAX, MremapFlags::MAYMOVE).unwrap_err().errno, ENOMEM);
+    assert_eq!(mremap(start, usize::MAX, PAGE_SIZE, MremapFlags::empty()).unwrap_err().errno, EINVAL);
+    assert_eq!(munmap(start, usize::MAX).unwrap_err().errno, EINVAL);
+    assert_eq!(munmap(start, usize::MAX - start + 1).unwrap_err().errno, EINVAL);
+    assert_eq!(read(start), 1);
+
+    munmap(start, PAGE_SIZE).unwrap();
+    println!("test_huge_lengths success!");
+}
+
+fn map_anonymous(pages: usize) -> usize {
+    mmap(None, pages * PAGE_SIZE, Prot::READ | Prot::WRITE, MMAPFlags::ANONYMOUS, 0, 0).unwrap()
+}
+
+fn read(addr: usize) -> usize {
+    unsafe { (addr as *const usize).read_volatile() }
+}
+
+fn write(addr: usize, value: usize) {
+    unsafe { (addr as *mut usize).write_volatile(value) }
+}
+
+/// Run `f` in a child, which should be killed by SIGSEGV.
+fn expect_segmentation_fault(f: impl Fn()) {
+    let ret = fork().unwrap();
+    if ret == 0 {
+        f();
+        exit(0);
+    }
+    let mut status = 0;
+    waitpid(ret as isize, Some(&mut status), 0).unwrap();
+    assert!(wifsignaled(status));
+    assert_eq!(wtermsig(status), SIGSEGV);
+}
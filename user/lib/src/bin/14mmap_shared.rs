@@ -0,0 +1,151 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::syscall::{open, close, read, write, lseek, unlink, mmap, munmap, mprotect, msync, fork, waitpid, exit};
+use share::file::{OpenFlag, SEEKFlag};
+use share::mmap::{Prot, MMAPFlags, MsyncFlags};
+use share::wait::wexitstatus;
+use share::syscall::error::EACCES;
+
+const PAGE_SIZE: usize = 4096;
+const PAGES: usize = 3;
+const FILE_NAME: &str = "test_mmap_shared.txt";
+
+#[no_mangle]
+fn main() {
+    create_file();
+    test_mappers_share_pages();
+    test_msync_writes_back();
+    test_munmap_writes_back();
+    test_exit_writes_back();
+    test_read_only_fd();
+    test_kernel_writes_unread_page();
+    unlink(FILE_NAME).unwrap();
+}
+
+fn create_file() {
+    let fd = open(FILE_NAME, OpenFlag::RDWR | OpenFlag::CREAT, 0).unwrap();
+    let data = [b'a'; PAGES * PAGE_SIZE];
+    write(fd, &data).unwrap();
+    close(fd).unwrap();
+}
+
+fn map_file(fd: usize) -> &'static mut [u8] {
+    let start =
+        mmap(None, PAGES * PAGE_SIZE, Prot::READ | Prot::WRITE, MMAPFlags::SHARED, fd, 0).unwrap();
+    unsafe { core::slice::from_raw_parts_mut(start as *mut u8, PAGES * PAGE_SIZE) }
+}
+
+fn read_file_at(offset: usize) -> u8 {
+    let fd = open(FILE_NAME, OpenFlag::RDONLY, 0).unwrap();
+    lseek(fd, offset, SEEKFlag::empty()).unwrap();
+    let mut byte = [0; 1];
+    read(fd, &mut byte).unwrap();
+    close(fd).unwrap();
+
+    byte[0]
+}
+
+/// Parent and child open the file on their own, and see each other's writes through the mapping.
+fn test_mappers_share_pages() {
+    let ret = fork().unwrap();
+    let fd = open(FILE_NAME, OpenFlag::RDWR, 0).unwrap();
+    let mapping = map_file(fd);
+    if ret == 0 {
+        assert_eq!(mapping[PAGE_SIZE], b'a');
+        mapping[PAGE_SIZE] = b'b';
+        exit(0);
+    }
+    let mut status = 0;
+    waitpid(ret as isize, Some(&mut status), 0).unwrap();
+    assert_eq!(wexitstatus(status), 0);
+    assert_eq!(mapping[PAGE_SIZE], b'b');
+    mapping[PAGE_SIZE] = b'a';
+    munmap(mapping.as_ptr() as usize, PAGES * PAGE_SIZE).unwrap();
+    close(fd).unwrap();
+    println!("test_mappers_share_pages success!");
+}
+
+fn test_msync_writes_back() {
+    let fd = open(FILE_NAME, OpenFlag::RDWR, 0).unwrap();
+    let mapping = map_file(fd);
+    mapping[0] = b'c';
+    msync(mapping.as_ptr() as usize, PAGE_SIZE, MsyncFlags::SYNC).unwrap();
+    assert_eq!(read_file_at(0), b'c');
+    // a buffer in the mapping can be written to the file it maps.
+    lseek(fd, PAGE_SIZE * 2, SEEKFlag::empty()).unwrap();
+    write(fd, &mapping[..1]).unwrap();
+    assert_eq!(read_file_at(PAGE_SIZE * 2), b'c');
+    // a page written back is clean, until it is written again.
+    lseek(fd, 0, SEEKFlag::empty()).unwrap();
+    write(fd, b"x").unwrap();
+    msync(mapping.as_ptr() as usize, PAGE_SIZE, MsyncFlags::SYNC).unwrap();
+    assert_eq!(read_file_at(0), b'x');
+    mapping[0] = b'c';
+    msync(mapping.as_ptr() as usize, PAGE_SIZE, MsyncFlags::SYNC).unwrap();
+    assert_eq!(read_file_at(0), b'c');
+    munmap(mapping.as_ptr() as usize, PAGES * PAGE_SIZE).unwrap();
+    close(fd).unwrap();
+    println!("test_msync_writes_back success!");
+}
+
+fn test_munmap_writes_back() {
+    let fd = open(FILE_NAME, OpenFlag::RDWR, 0).unwrap();
+    let mapping = map_file(fd);
+    // the file can be closed once mapped.
+    close(fd).unwrap();
+    mapping[PAGE_SIZE * 2 + 1] = b'd';
+    munmap(mapping.as_ptr() as usize, PAGES * PAGE_SIZE).unwrap();
+    assert_eq!(read_file_at(PAGE_SIZE * 2 + 1), b'd');
+    println!("test_munmap_writes_back success!");
+}
+
+fn test_exit_writes_back() {
+    let ret = fork().unwrap();
+    if ret == 0 {
+        let fd = open(FILE_NAME, OpenFlag::RDWR, 0).unwrap();
+        let mapping = map_file(fd);
+        mapping[PAGE_SIZE * 3 - 1] = b'e';
+        exit(0);
+    }
+    let mut status = 0;
+    waitpid(ret as isize, Some(&mut status), 0).unwrap();
+    assert_eq!(wexitstatus(status), 0);
+    assert_eq!(read_file_at(PAGE_SIZE * 3 - 1), b'e');
+    println!("test_exit_writes_back success!");
+}
+
+/// A file opened read only can be mapped shared, but not written through the mapping.
+fn test_read_only_fd() {
+    let fd = open(FILE_NAME, OpenFlag::RDONLY, 0).unwrap();
+    let error = mmap(None, PAGE_SIZE, Prot::READ | Prot::WRITE, MMAPFlags::SHARED, fd, 0).unwrap_err();
+    assert_eq!(error.errno, EACCES);
+    let start = mmap(None, PAGE_SIZE, Prot::READ, MMAPFlags::SHARED, fd, 0).unwrap();
+    assert_eq!(mprotect(start, PAGE_SIZE, Prot::READ | Prot::WRITE).unwrap_err().errno, EACCES);
+    assert_eq!(unsafe { *(start as *const u8) }, b'c');
+    munmap(start, PAGE_SIZE).unwrap();
+    // a private mapping gets its own copies of the pages.
+    let start = mmap(None, PAGE_SIZE, Prot::READ | Prot::WRITE, MMAPFlags::PRIVATE, fd, 0).unwrap();
+    munmap(start, PAGE_SIZE).unwrap();
+    close(fd).unwrap();
+    println!("test_read_only_fd success!");
+}
+
+/// The kernel writes the results of a syscall to a page of the mapping which hasn't been read in.
+fn test_kernel_writes_unread_page() {
+    let fd = open(FILE_NAME, OpenFlag::RDWR, 0).unwrap();
+    let mapping = map_file(fd);
+    let ret = fork().unwrap();
+    if ret == 0 {
+        exit(3);
+    }
+    let status = unsafe { &mut *(mapping[PAGE_SIZE..].as_mut_ptr() as *mut usize) };
+    waitpid(ret as isize, Some(status), 0).unwrap();
+    assert_eq!(wexitstatus(*status), 3);
+    munmap(mapping.as_ptr() as usize, PAGES * PAGE_SIZE).unwrap();
+    close(fd).unwrap();
+    println!("test_kernel_writes_unread_page success!");
+}
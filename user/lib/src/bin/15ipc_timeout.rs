@@ -0,0 +1,53 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::syscall::{fork, getppid, receive_nb, receive_timeout, send_timeout, waitpid, exit, get_time};
+use share::ipc::Msg;
+use share::syscall::error::{EAGAIN, ETIMEDOUT};
+
+const TIMEOUT_MS: usize = 100;
+
+#[no_mangle]
+fn main() {
+    test_receive_nb_without_sender();
+    test_receive_timeout_without_sender();
+    test_send_timeout_without_receiver();
+}
+
+fn test_receive_nb_without_sender() {
+    let mut msg = Msg::empty();
+    let err = receive_nb(-1, &mut msg).unwrap_err();
+    assert_eq!(err.errno, EAGAIN);
+    println!("test_receive_nb_without_sender success!");
+}
+
+fn test_receive_timeout_without_sender() {
+    let mut msg = Msg::empty();
+    let start = get_time();
+    let err = receive_timeout(-1, &mut msg, TIMEOUT_MS).unwrap_err();
+    assert_eq!(err.errno, ETIMEDOUT);
+    assert!(get_time() - start >= TIMEOUT_MS);
+    println!("test_receive_timeout_without_sender success!");
+}
+
+fn test_send_timeout_without_receiver() {
+    let ret = fork().unwrap();
+    if ret == 0 {
+        let msg = Msg::empty();
+        let err = send_timeout(getppid(), &msg, TIMEOUT_MS).unwrap_err();
+        assert_eq!(err.errno, ETIMEDOUT);
+        exit(0);
+    } else {
+        let mut status = 0;
+        waitpid(ret as isize, Some(&mut status), 0).unwrap();
+        assert_eq!(status, 0);
+        // the timed out sender must have been removed from our wait queue.
+        let mut msg = Msg::empty();
+        let err = receive_nb(ret as isize, &mut msg).unwrap_err();
+        assert_eq!(err.errno, EAGAIN);
+        println!("test_send_timeout_without_receiver success!");
+    }
+}
@@ -0,0 +1,61 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::syscall::{fork, getppid, send, receive, send_fast, receive_fast, waitpid, exit, get_time};
+use share::ipc::Msg;
+
+const ROUNDS: usize = 1000;
+
+#[no_mangle]
+fn main() {
+    let ret = fork().unwrap();
+    if ret == 0 {
+        echo_server();
+    } else {
+        let child_pid = ret;
+        let mut msg = Msg::empty();
+
+        let start = get_time();
+        for i in 0..ROUNDS {
+            msg.args[0] = i;
+            send(child_pid, &msg).unwrap();
+            receive(child_pid as isize, &mut msg).unwrap();
+            assert_eq!(msg.args[0], i + 1);
+        }
+        let end = get_time();
+        println!("Gap after {} send/receive round trips: {}", ROUNDS, end - start);
+
+        let start = get_time();
+        for i in 0..ROUNDS {
+            msg.args[0] = i;
+            send_fast(child_pid, &msg).unwrap();
+            receive_fast(child_pid as isize, &mut msg).unwrap();
+            assert_eq!(msg.args[0], i + 1);
+        }
+        let end = get_time();
+        println!("Gap after {} send_fast/receive_fast round trips: {}", ROUNDS, end - start);
+
+        let mut status = 0;
+        waitpid(child_pid as isize, Some(&mut status), 0).unwrap();
+        assert_eq!(status, 0);
+    }
+}
+
+fn echo_server() {
+    let parent_pid = getppid();
+    let mut msg = Msg::empty();
+    for _ in 0..ROUNDS {
+        receive(parent_pid as isize, &mut msg).unwrap();
+        msg.args[0] += 1;
+        send(parent_pid, &msg).unwrap();
+    }
+    for _ in 0..ROUNDS {
+        receive_fast(parent_pid as isize, &mut msg).unwrap();
+        msg.args[0] += 1;
+        send_fast(parent_pid, &msg).unwrap();
+    }
+    exit(0);
+}
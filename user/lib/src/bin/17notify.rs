@@ -0,0 +1,69 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::syscall::{fork, getppid, notify, receive, receive_nb, waitpid, exit};
+use share::ipc::{Msg, NOTIFY};
+use share::syscall::error::EAGAIN;
+
+const NUM: usize = 3;
+
+#[no_mangle]
+fn main() {
+    test_notify_is_merged();
+    test_notify_from_multiple_sources();
+}
+
+fn test_notify_is_merged() {
+    let ret = fork().unwrap();
+    if ret == 0 {
+        let ppid = getppid();
+        for _ in 0..NUM {
+            notify(ppid).unwrap();
+        }
+        exit(0);
+    } else {
+        // the child never blocks in notify, so all notifications are pending once it has exited.
+        let mut status = 0;
+        waitpid(ret as isize, Some(&mut status), 0).unwrap();
+        assert_eq!(status, 0);
+
+        let mut msg = Msg::empty();
+        receive(ret as isize, &mut msg).unwrap();
+        assert_eq!(msg.mtype, NOTIFY);
+        assert_eq!(msg.src_pid, ret);
+        let err = receive_nb(-1, &mut msg).unwrap_err();
+        assert_eq!(err.errno, EAGAIN);
+        println!("test_notify_is_merged success!");
+    }
+}
+
+fn test_notify_from_multiple_sources() {
+    let mut children = [0; NUM];
+    for i in 0..NUM {
+        let ret = fork().unwrap();
+        if ret == 0 {
+            notify(getppid()).unwrap();
+            exit(0);
+        }
+        children[i] = ret;
+    }
+
+    for _ in 0..NUM {
+        let mut status = 0;
+        waitpid(-1, Some(&mut status), 0).unwrap();
+        assert_eq!(status, 0);
+    }
+    let mut received = [false; NUM];
+    for _ in 0..NUM {
+        let mut msg = Msg::empty();
+        receive(-1, &mut msg).unwrap();
+        assert_eq!(msg.mtype, NOTIFY);
+        let idx = children.iter().position(|pid| *pid == msg.src_pid).unwrap();
+        assert!(!received[idx]);
+        received[idx] = true;
+    }
+    println!("test_notify_from_multiple_sources success!");
+}
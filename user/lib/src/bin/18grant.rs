@@ -0,0 +1,85 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::syscall::{fork, getpid, getppid, receive, send, waitpid, exit, grant_create, grant_revoke, safecopy_from, safecopy_to, virt_copy, read, open, close, unlink};
+use share::file::OpenFlag;
+use share::ipc::{Msg, GrantFlags};
+use share::syscall::error::{EPERM, EFAULT};
+
+/// The kernel is mapped here in every page table.
+const KERNEL_ADDRESS: usize = 0xFFFFFFC080200000;
+
+const LENGTH: usize = 64;
+
+#[no_mangle]
+fn main() {
+    test_safecopy_with_grant();
+    test_grant_outside_user_space();
+}
+
+fn test_safecopy_with_grant() {
+    let ret = fork().unwrap();
+    if ret == 0 {
+        let ppid = getppid();
+        let mut buffer = [0u8; LENGTH];
+        let mut msg = Msg::empty();
+        receive(ppid as isize, &mut msg).unwrap();
+        let grant = msg.args[0];
+
+        // read only grant.
+        safecopy_from(ppid, grant, 0, buffer.as_mut_ptr() as usize, LENGTH).unwrap();
+        for i in 0..LENGTH {
+            assert_eq!(buffer[i], i as u8);
+        }
+        let err = safecopy_to(ppid, grant, 0, buffer.as_ptr() as usize, LENGTH).unwrap_err();
+        assert_eq!(err.errno, EPERM);
+        // out of range.
+        let err = safecopy_from(ppid, grant, 1, buffer.as_mut_ptr() as usize, LENGTH).unwrap_err();
+        assert_eq!(err.errno, EPERM);
+        send(ppid, &msg).unwrap();
+
+        // the grant has been revoked.
+        receive(ppid as isize, &mut msg).unwrap();
+        let err = safecopy_from(ppid, grant, 0, buffer.as_mut_ptr() as usize, LENGTH).unwrap_err();
+        assert_eq!(err.errno, EPERM);
+
+        // normal processes are not allowed to use virt_copy.
+        let err = virt_copy(ppid, buffer.as_ptr() as usize, getpid(), buffer.as_mut_ptr() as usize, LENGTH).unwrap_err();
+        assert_eq!(err.errno, EPERM);
+        exit(0);
+    } else {
+        let mut buffer = [0u8; LENGTH];
+        for i in 0..LENGTH {
+            buffer[i] = i as u8;
+        }
+        let grant = grant_create(ret, buffer.as_ptr() as usize, LENGTH, GrantFlags::READ).unwrap();
+        let mut msg = Msg::empty();
+        msg.args[0] = grant;
+        send(ret, &msg).unwrap();
+        receive(ret as isize, &mut msg).unwrap();
+        grant_revoke(grant).unwrap();
+        send(ret, &msg).unwrap();
+
+        let mut status = 0;
+        waitpid(ret as isize, Some(&mut status), 0).unwrap();
+        assert_eq!(status, 0);
+        println!("test_safecopy_with_grant success!");
+    }
+}
+
+/// Memory of the kernel can't be granted, neither directly nor as a buffer forwarded to fs server.
+fn test_grant_outside_user_space() {
+    let err = grant_create(getppid(), KERNEL_ADDRESS, LENGTH, GrantFlags::READ).unwrap_err();
+    assert_eq!(err.errno, EFAULT);
+
+    let fd = open("test_grant.txt", OpenFlag::RDWR | OpenFlag::CREAT, 0).unwrap();
+    let buffer = unsafe { core::slice::from_raw_parts_mut(KERNEL_ADDRESS as *mut u8, LENGTH) };
+    let err = read(fd, buffer).unwrap_err();
+    assert_eq!(err.errno, EFAULT);
+    close(fd).unwrap();
+    unlink("test_grant.txt").unwrap();
+    println!("test_grant_outside_user_space success!");
+}
@@ -0,0 +1,89 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::syscall::{fork, getppid, receive, send, waitpid, exit, shm_create, shm_map, shm_unmap};
+use share::ipc::Msg;
+use share::mmap::Prot;
+use share::syscall::error::{EACCES, EINVAL, EPERM};
+
+const LENGTH: usize = 4096;
+
+#[no_mangle]
+fn main() {
+    test_shm_map();
+    test_shm_shared_by_fork();
+}
+
+fn test_shm_map() {
+    let ret = fork().unwrap();
+    if ret == 0 {
+        let ppid = getppid();
+        let mut msg = Msg::empty();
+        receive(ppid as isize, &mut msg).unwrap();
+        let buffer = unsafe {
+            core::slice::from_raw_parts_mut(msg.args[0] as *mut u8, LENGTH)
+        };
+        for i in 0..LENGTH {
+            assert_eq!(buffer[i], i as u8);
+            buffer[i] = !(i as u8);
+        }
+        // the child can not grant more rights than it has.
+        let err = shm_map(msg.args[0], ppid, Prot::READ | Prot::EXEC).unwrap_err();
+        assert_eq!(err.errno, EACCES);
+        // nor map it into its parent.
+        let err = shm_map(msg.args[0], ppid, Prot::READ).unwrap_err();
+        assert_eq!(err.errno, EPERM);
+        shm_unmap(msg.args[0]).unwrap();
+        send(ppid, &msg).unwrap();
+        exit(0);
+    } else {
+        let addr = shm_create(LENGTH, Prot::READ | Prot::WRITE).unwrap();
+        let buffer = unsafe {
+            core::slice::from_raw_parts_mut(addr as *mut u8, LENGTH)
+        };
+        for i in 0..LENGTH {
+            buffer[i] = i as u8;
+        }
+        let mut msg = Msg::empty();
+        msg.args[0] = shm_map(addr, ret, Prot::READ | Prot::WRITE).unwrap();
+        send(ret, &msg).unwrap();
+        receive(ret as isize, &mut msg).unwrap();
+
+        // the frames are still alive after the child has unmapped them.
+        for i in 0..LENGTH {
+            assert_eq!(buffer[i], !(i as u8));
+        }
+        shm_unmap(addr).unwrap();
+        let err = shm_unmap(addr).unwrap_err();
+        assert_eq!(err.errno, EINVAL);
+
+        let mut status = 0;
+        waitpid(ret as isize, Some(&mut status), 0).unwrap();
+        assert_eq!(status, 0);
+        println!("test_shm_map success!");
+    }
+}
+
+fn test_shm_shared_by_fork() {
+    let addr = shm_create(LENGTH, Prot::READ | Prot::WRITE).unwrap();
+    let buffer = unsafe {
+        core::slice::from_raw_parts_mut(addr as *mut u8, LENGTH)
+    };
+    buffer.fill(0);
+
+    let ret = fork().unwrap();
+    if ret == 0 {
+        buffer.fill(0x5a);
+        exit(0);
+    } else {
+        let mut status = 0;
+        waitpid(ret as isize, Some(&mut status), 0).unwrap();
+        assert_eq!(status, 0);
+        assert!(buffer.iter().all(|byte| *byte == 0x5a));
+        shm_unmap(addr).unwrap();
+        println!("test_shm_shared_by_fork success!");
+    }
+}
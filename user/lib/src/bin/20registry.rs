@@ -0,0 +1,39 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::syscall::{lookup, publish, sendrec};
+use share::ipc::{Msg, Endpoint, FS_SERVICE, TERMINAL_SERVICE};
+use share::syscall::error::{EPERM, ESRCH, EDEADSRCDST};
+
+#[no_mangle]
+fn main() {
+    test_lookup_and_publish();
+    test_stale_endpoint();
+}
+
+fn test_lookup_and_publish() {
+    let fs = lookup(FS_SERVICE).unwrap();
+    let terminal = lookup(TERMINAL_SERVICE).unwrap();
+    assert_ne!(Endpoint(fs).pid(), Endpoint(terminal).pid());
+    assert_ne!(Endpoint(fs).generation(), 0);
+    let err = lookup("no-such-service").unwrap_err();
+    assert_eq!(err.errno, ESRCH);
+
+    // normal processes are not allowed to publish services.
+    let err = publish("fake-fs").unwrap_err();
+    assert_eq!(err.errno, EPERM);
+    println!("test_lookup_and_publish success!");
+}
+
+fn test_stale_endpoint() {
+    // an endpoint of another generation never reaches the process which owns the pid now.
+    let fs = Endpoint(lookup(FS_SERVICE).unwrap());
+    let stale = Endpoint::new(fs.pid(), fs.generation() + 1);
+    let mut message = Msg::empty();
+    let err = sendrec(stale.0, &mut message).unwrap_err();
+    assert_eq!(err.errno, EDEADSRCDST);
+    println!("test_stale_endpoint success!");
+}
@@ -0,0 +1,53 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::syscall::{fork, exit, waitpid, lookup, sendrec, continuous_alloc, virt_to_phys, k_write_dev};
+use user_lib::termios::tc_get_attr;
+use share::ipc::{Msg, FSYSCALL, SYSCALL_TYPE, FS_SERVICE};
+use share::syscall::error::EPERM;
+use share::syscall::sys_const::SYSCALL_GETCWD;
+
+#[no_mangle]
+fn main() {
+    test_user_privilege();
+    test_privilege_inherited_by_fork();
+}
+
+fn test_user_privilege() {
+    check_restricted();
+    println!("test_user_privilege success!");
+}
+
+fn test_privilege_inherited_by_fork() {
+    let ret = fork().unwrap();
+    if ret == 0 {
+        check_restricted();
+        exit(0);
+    } else {
+        let mut status = 0;
+        waitpid(ret as isize, Some(&mut status), 0).unwrap();
+        assert_eq!(status, 0);
+        println!("test_privilege_inherited_by_fork success!");
+    }
+}
+
+fn check_restricted() {
+    // kcalls for drivers are not allowed.
+    assert_eq!(continuous_alloc(4096).unwrap_err().errno, EPERM);
+    let value = 0usize;
+    assert_eq!(virt_to_phys(&value as *const _ as usize).unwrap_err().errno, EPERM);
+    assert_eq!(k_write_dev(0, 0, 1), -EPERM as isize);
+
+    // fs server can only be reached through syscalls.
+    let fs = lookup(FS_SERVICE).unwrap();
+    let mut message = Msg::empty();
+    message.mtype = FSYSCALL;
+    message.args[SYSCALL_TYPE] = SYSCALL_GETCWD;
+    assert_eq!(sendrec(fs, &mut message).unwrap_err().errno, EPERM);
+
+    // while the terminal accepts ioctl requests from user processes.
+    tc_get_attr(0).unwrap();
+}
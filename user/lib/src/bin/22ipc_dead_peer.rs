@@ -0,0 +1,62 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::syscall::{fork, getppid, receive, send, sendrec, waitpid, exit};
+use share::ipc::Msg;
+use share::syscall::error::EDEADSRCDST;
+
+#[no_mangle]
+fn main() {
+    test_server_dies_during_sendrec();
+    test_receiver_dies_before_receiving();
+    test_sender_dies_before_sending();
+}
+
+/// The server takes the request and crashes before replying.
+fn test_server_dies_during_sendrec() {
+    let ret = fork().unwrap();
+    if ret == 0 {
+        let mut msg = Msg::empty();
+        receive(-1, &mut msg).unwrap();
+        exit(1);
+    } else {
+        let mut msg = Msg::empty();
+        let err = sendrec(ret, &mut msg).unwrap_err();
+        assert_eq!(err.errno, EDEADSRCDST);
+        waitpid(ret as isize, None, 0).unwrap();
+        println!("test_server_dies_during_sendrec success!");
+    }
+}
+
+/// The second message is still queued when the receiver exits.
+fn test_receiver_dies_before_receiving() {
+    let ret = fork().unwrap();
+    if ret == 0 {
+        let mut msg = Msg::empty();
+        receive(getppid() as isize, &mut msg).unwrap();
+        exit(1);
+    } else {
+        let msg = Msg::empty();
+        send(ret, &msg).unwrap();
+        let err = send(ret, &msg).unwrap_err();
+        assert_eq!(err.errno, EDEADSRCDST);
+        waitpid(ret as isize, None, 0).unwrap();
+        println!("test_receiver_dies_before_receiving success!");
+    }
+}
+
+fn test_sender_dies_before_sending() {
+    let ret = fork().unwrap();
+    if ret == 0 {
+        exit(1);
+    } else {
+        let mut msg = Msg::empty();
+        let err = receive(ret as isize, &mut msg).unwrap_err();
+        assert_eq!(err.errno, EDEADSRCDST);
+        waitpid(ret as isize, None, 0).unwrap();
+        println!("test_sender_dies_before_sending success!");
+    }
+}
@@ -0,0 +1,74 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::syscall::{fork, getpid, getppid, receive, send, waitpid, exit, sleep};
+use share::ipc::Msg;
+use share::syscall::error::EDLOCK;
+
+#[no_mangle]
+fn main() {
+    test_receive_from_self();
+    test_receive_cycle();
+    test_send_receive_cycle();
+}
+
+fn test_receive_from_self() {
+    let mut msg = Msg::empty();
+    let err = receive(getpid() as isize, &mut msg).unwrap_err();
+    assert_eq!(err.errno, EDLOCK);
+    println!("test_receive_from_self success!");
+}
+
+/// Parent is receiving from the child, which then tries to receive from the parent.
+fn test_receive_cycle() {
+    let ret = fork().unwrap();
+    if ret == 0 {
+        let ppid = getppid();
+        let mut msg = Msg::empty();
+        let err = receive(ppid as isize, &mut msg).unwrap_err();
+        assert_eq!(err.errno, EDLOCK);
+        send(ppid, &msg).unwrap();
+        exit(0);
+    } else {
+        let mut msg = Msg::empty();
+        receive(ret as isize, &mut msg).unwrap();
+        let mut status = 0;
+        waitpid(ret as isize, Some(&mut status), 0).unwrap();
+        assert_eq!(status, 0);
+        println!("test_receive_cycle success!");
+    }
+}
+
+/// Task `c` is receiving from the parent, and task `b` is sending to `c`. Then the parent sending to
+/// `b` closes the cycle: parent -> b -> c -> parent.
+fn test_send_receive_cycle() {
+    let c = fork().unwrap();
+    if c == 0 {
+        let mut msg = Msg::empty();
+        receive(getppid() as isize, &mut msg).unwrap();
+        receive(-1, &mut msg).unwrap(); // release `b`.
+        exit(0);
+    }
+    let b = fork().unwrap();
+    if b == 0 {
+        let msg = Msg::empty();
+        send(c, &msg).unwrap();
+        exit(0);
+    }
+
+    sleep(1); // wait for `b` and `c` to block.
+    let msg = Msg::empty();
+    let err = send(b, &msg).unwrap_err();
+    assert_eq!(err.errno, EDLOCK);
+
+    send(c, &msg).unwrap();
+    let mut status = 0;
+    waitpid(b as isize, Some(&mut status), 0).unwrap();
+    assert_eq!(status, 0);
+    waitpid(c as isize, Some(&mut status), 0).unwrap();
+    assert_eq!(status, 0);
+    println!("test_send_receive_cycle success!");
+}
@@ -0,0 +1,125 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use core::sync::atomic::{AtomicUsize, Ordering};
+use user_lib::syscall::{fork, getpid, getppid, kill, signal, sigprocmask, receive, waitpid, exit, sleep, yield_};
+use share::ipc::Msg;
+use share::signal::{SIGUSR1, SIGTERM, SIGSEGV, SIGKILL, SIGSTOP, SIGCONT, SIG_DFL, SIG_IGN, SIG_BLOCK, SIG_UNBLOCK, sig_bit};
+use share::syscall::error::EINTR;
+
+static CAUGHT: AtomicUsize = AtomicUsize::new(0);
+
+extern "C" fn handler(signo: usize) {
+    CAUGHT.store(signo, Ordering::SeqCst);
+}
+
+#[no_mangle]
+fn main() {
+    test_handler();
+    test_blocked_signal();
+    test_ignored_signal();
+    test_default_terminate();
+    test_fault();
+    test_stop_and_continue();
+    test_interrupt_ipc();
+}
+
+fn test_handler() {
+    CAUGHT.store(0, Ordering::SeqCst);
+    signal(SIGUSR1, handler as usize).unwrap();
+    kill(getpid(), SIGUSR1).unwrap();
+    assert_eq!(CAUGHT.load(Ordering::SeqCst), SIGUSR1);
+    println!("test_handler success!");
+}
+
+/// A blocked signal is delivered as soon as it is unblocked.
+fn test_blocked_signal() {
+    CAUGHT.store(0, Ordering::SeqCst);
+    sigprocmask(SIG_BLOCK, Some(sig_bit(SIGUSR1)), None).unwrap();
+    kill(getpid(), SIGUSR1).unwrap();
+    assert_eq!(CAUGHT.load(Ordering::SeqCst), 0);
+    sigprocmask(SIG_UNBLOCK, Some(sig_bit(SIGUSR1)), None).unwrap();
+    assert_eq!(CAUGHT.load(Ordering::SeqCst), SIGUSR1);
+    println!("test_blocked_signal success!");
+}
+
+fn test_ignored_signal() {
+    signal(SIGTERM, SIG_IGN).unwrap();
+    kill(getpid(), SIGTERM).unwrap();
+    signal(SIGTERM, SIG_DFL).unwrap();
+    println!("test_ignored_signal success!");
+}
+
+fn test_default_terminate() {
+    let ret = fork().unwrap();
+    if ret == 0 {
+        loop {
+            yield_();
+        }
+    } else {
+        kill(ret, SIGTERM).unwrap();
+        let mut status = 0;
+        waitpid(ret as isize, Some(&mut status), 0).unwrap();
+        assert_eq!(status & 0x7f, SIGTERM);
+        println!("test_default_terminate success!");
+    }
+}
+
+fn test_fault() {
+    let ret = fork().unwrap();
+    if ret == 0 {
+        unsafe {
+            (0 as *mut usize).write_volatile(1);
+        }
+        exit(0);
+    } else {
+        let mut status = 0;
+        waitpid(ret as isize, Some(&mut status), 0).unwrap();
+        assert_eq!(status & 0x7f, SIGSEGV);
+        println!("test_fault success!");
+    }
+}
+
+/// A stopped task is still able to be killed.
+fn test_stop_and_continue() {
+    let ret = fork().unwrap();
+    if ret == 0 {
+        loop {
+            yield_();
+        }
+    } else {
+        kill(ret, SIGSTOP).unwrap();
+        sleep(1);
+        kill(ret, SIGCONT).unwrap();
+        kill(ret, SIGSTOP).unwrap();
+        sleep(1);
+        kill(ret, SIGKILL).unwrap();
+        let mut status = 0;
+        waitpid(ret as isize, Some(&mut status), 0).unwrap();
+        assert_eq!(status & 0x7f, SIGKILL);
+        println!("test_stop_and_continue success!");
+    }
+}
+
+/// A task blocked in ipc runs the handler, then its ipc call returns `EINTR`.
+fn test_interrupt_ipc() {
+    let ret = fork().unwrap();
+    if ret == 0 {
+        CAUGHT.store(0, Ordering::SeqCst);
+        let mut msg = Msg::empty();
+        let err = receive(getppid() as isize, &mut msg).unwrap_err();
+        assert_eq!(err.errno, EINTR);
+        assert_eq!(CAUGHT.load(Ordering::SeqCst), SIGUSR1);
+        exit(0);
+    } else {
+        sleep(1);
+        kill(ret, SIGUSR1).unwrap();
+        let mut status = 0;
+        waitpid(ret as isize, Some(&mut status), 0).unwrap();
+        assert_eq!(status, 0);
+        println!("test_interrupt_ipc success!");
+    }
+}
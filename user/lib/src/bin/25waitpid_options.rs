@@ -0,0 +1,75 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::syscall::{fork, kill, waitpid, exit, sleep, yield_};
+use share::signal::{SIGSTOP, SIGCONT, SIGKILL};
+use share::wait::*;
+use share::syscall::error::ECHILD;
+
+#[no_mangle]
+fn main() {
+    test_exit_status();
+    test_no_hang();
+    test_stopped_and_continued();
+    test_no_child();
+}
+
+fn test_exit_status() {
+    let ret = fork().unwrap();
+    if ret == 0 {
+        exit(3);
+    } else {
+        let mut status = 0;
+        assert_eq!(waitpid(-1, Some(&mut status), 0).unwrap(), ret);
+        assert!(wifexited(status));
+        assert_eq!(wexitstatus(status), 3);
+        println!("test_exit_status success!");
+    }
+}
+
+fn test_no_hang() {
+    let ret = fork().unwrap();
+    if ret == 0 {
+        sleep(1);
+        exit(0);
+    } else {
+        assert_eq!(waitpid(ret as isize, None, WNOHANG).unwrap(), 0);
+        assert_eq!(waitpid(ret as isize, None, 0).unwrap(), ret);
+        println!("test_no_hang success!");
+    }
+}
+
+fn test_stopped_and_continued() {
+    let ret = fork().unwrap();
+    if ret == 0 {
+        loop {
+            yield_();
+        }
+    } else {
+        let mut status = 0;
+        kill(ret, SIGSTOP).unwrap();
+        assert_eq!(waitpid(ret as isize, Some(&mut status), WUNTRACED).unwrap(), ret);
+        assert!(wifstopped(status));
+        assert_eq!(wstopsig(status), SIGSTOP);
+
+        kill(ret, SIGCONT).unwrap();
+        assert_eq!(waitpid(ret as isize, Some(&mut status), WCONTINUED).unwrap(), ret);
+        assert!(wifcontinued(status));
+        // each change is reported only once.
+        assert_eq!(waitpid(ret as isize, None, WUNTRACED | WCONTINUED | WNOHANG).unwrap(), 0);
+
+        kill(ret, SIGKILL).unwrap();
+        assert_eq!(waitpid(ret as isize, Some(&mut status), 0).unwrap(), ret);
+        assert!(wifsignaled(status));
+        assert_eq!(wtermsig(status), SIGKILL);
+        println!("test_stopped_and_continued success!");
+    }
+}
+
+fn test_no_child() {
+    assert_eq!(waitpid(-1, None, WNOHANG).unwrap_err().errno, ECHILD);
+    println!("test_no_child success!");
+}
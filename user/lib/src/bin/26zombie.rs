@@ -0,0 +1,78 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::syscall::{fork, waitpid, wait4, exit, sleep, debug_frame_usage};
+use share::time::Rusage;
+
+const CHILDREN: usize = 8;
+
+#[no_mangle]
+fn main() {
+    test_zombie_releases_memory();
+    test_pid_freed_on_wait();
+    test_orphan_zombies_reaped();
+    test_rusage();
+}
+
+/// Only a root page table is kept for each zombie until it is waited.
+fn test_zombie_releases_memory() {
+    let available = debug_frame_usage();
+    for _ in 0..CHILDREN {
+        if fork().unwrap() == 0 {
+            exit(0);
+        }
+    }
+    sleep(1);
+    assert!(available - debug_frame_usage() <= CHILDREN * 2);
+    for _ in 0..CHILDREN {
+        waitpid(-1, None, 0).unwrap();
+    }
+    println!("test_zombie_releases_memory success!");
+}
+
+/// There are only 64 pids, they must be reused after the children are waited.
+fn test_pid_freed_on_wait() {
+    for _ in 0..100 {
+        let ret = fork().unwrap();
+        if ret == 0 {
+            exit(0);
+        }
+        assert_eq!(waitpid(ret as isize, None, 0).unwrap(), ret);
+    }
+    println!("test_pid_freed_on_wait success!");
+}
+
+/// Zombies left by an exited task are reaped along with it.
+fn test_orphan_zombies_reaped() {
+    for _ in 0..40 {
+        let ret = fork().unwrap();
+        if ret == 0 {
+            if fork().unwrap() == 0 {
+                exit(0);
+            }
+            sleep(1);
+            exit(0);
+        }
+        waitpid(ret as isize, None, 0).unwrap();
+    }
+    println!("test_orphan_zombies_reaped success!");
+}
+
+fn test_rusage() {
+    let ret = fork().unwrap();
+    if ret == 0 {
+        let mut sum: usize = 0;
+        for i in 0..1000000 {
+            sum = sum.wrapping_add(i);
+        }
+        exit(sum & 1);
+    } else {
+        let mut rusage = Rusage::empty();
+        wait4(ret as isize, None, 0, Some(&mut rusage)).unwrap();
+        assert!(rusage.ru_utime.tv_sec > 0 || rusage.ru_utime.tv_usec > 0);
+        println!("test_rusage success!");
+    }
+}
@@ -0,0 +1,103 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+extern crate alloc;
+
+use alloc::vec::Vec;
+use core::arch::asm;
+use core::sync::atomic::{AtomicUsize, AtomicU32, Ordering};
+use user_lib::syscall::{fork, clone, getpid, gettid, waitpid, exit, yield_};
+use user_lib::thread;
+use share::clone::CloneFlags;
+use share::syscall::error::ECHILD;
+use share::wait::{WNOHANG, wifexited, wexitstatus};
+
+static COUNTER: AtomicUsize = AtomicUsize::new(0);
+static TP: AtomicUsize = AtomicUsize::new(0);
+static CTID: AtomicU32 = AtomicU32::new(0);
+static mut STACK: [u8; 0x1000] = [0; 0x1000];
+
+#[no_mangle]
+fn main() {
+    test_spawn_and_join();
+    test_thread_ids();
+    test_thread_not_waitable();
+    test_settls();
+    test_exit_group();
+}
+
+/// Threads share the memory of the process.
+fn test_spawn_and_join() {
+    let handles: Vec<_> = (0..4).map(|i| {
+        thread::spawn(move || {
+            for _ in 0..100 {
+                COUNTER.fetch_add(1, Ordering::SeqCst);
+                yield_();
+            }
+            i * 2
+        }).unwrap()
+    }).collect();
+    let results: Vec<_> = handles.into_iter().map(|handle| handle.join().unwrap()).collect();
+    assert_eq!(results, [0, 2, 4, 6]);
+    assert_eq!(COUNTER.load(Ordering::SeqCst), 400);
+    println!("test_spawn_and_join success!");
+}
+
+fn test_thread_ids() {
+    let pid = getpid();
+    let handle = thread::spawn(|| (getpid(), gettid())).unwrap();
+    let tid = handle.tid();
+    let (thread_pid, thread_tid) = handle.join().unwrap();
+    assert_eq!(thread_pid, pid);
+    assert_eq!(thread_tid, tid);
+    assert_ne!(thread_tid, gettid());
+    assert_eq!(gettid(), pid);
+    println!("test_thread_ids success!");
+}
+
+fn test_thread_not_waitable() {
+    let handle = thread::spawn(|| yield_()).unwrap();
+    assert_eq!(waitpid(-1, None, WNOHANG).unwrap_err().errno, ECHILD);
+    handle.join().unwrap();
+    println!("test_thread_not_waitable success!");
+}
+
+extern "C" fn save_tp(_arg: usize) -> usize {
+    let tp: usize;
+    unsafe { asm!("mv {}, tp", out(reg) tp) };
+    TP.store(tp, Ordering::SeqCst);
+    0
+}
+
+fn test_settls() {
+    let stack_top = unsafe { STACK.as_ptr() as usize + STACK.len() };
+    let ctid_ptr = &CTID as *const _ as usize;
+    let flags = CloneFlags::VM | CloneFlags::THREAD | CloneFlags::SETTLS | CloneFlags::CHILD_SETTID | CloneFlags::CHILD_CLEARTID;
+    let tid = clone(save_tp, stack_top, flags, 0, 0, 0x1234, ctid_ptr).unwrap();
+    assert_eq!(CTID.load(Ordering::SeqCst), tid as u32);
+    // the tid is cleared when the thread exits.
+    while CTID.load(Ordering::SeqCst) != 0 {
+        yield_();
+    }
+    assert_eq!(TP.load(Ordering::SeqCst), 0x1234);
+    println!("test_settls success!");
+}
+
+/// `exit` of any thread terminates the whole process.
+fn test_exit_group() {
+    let ret = fork().unwrap();
+    if ret == 0 {
+        thread::spawn(|| loop {
+            yield_();
+        }).unwrap();
+        exit(5);
+    } else {
+        let mut status = 0;
+        assert_eq!(waitpid(ret as isize, Some(&mut status), 0).unwrap(), ret);
+        assert!(wifexited(status));
+        assert_eq!(wexitstatus(status), 5);
+        println!("test_exit_group success!");
+    }
+}
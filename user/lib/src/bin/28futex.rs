@@ -0,0 +1,145 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+extern crate alloc;
+
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::sync::atomic::{AtomicU32, Ordering};
+use user_lib::syscall::{futex_wait, futex_wake, futex_requeue, get_time, yield_, fork, exit, waitpid};
+use user_lib::sync::{Mutex, Condvar};
+use user_lib::thread;
+use share::syscall::error::{EAGAIN, ETIMEDOUT};
+use share::time::Timespec;
+
+static FUTEX: AtomicU32 = AtomicU32::new(0);
+static OTHER_FUTEX: AtomicU32 = AtomicU32::new(0);
+static STARTED: AtomicU32 = AtomicU32::new(0);
+
+#[no_mangle]
+fn main() {
+    test_wait_and_wake();
+    test_timeout();
+    test_requeue();
+    test_wake_after_fork();
+    test_mutex();
+    test_condvar();
+}
+
+fn test_wait_and_wake() {
+    assert_eq!(futex_wait(&FUTEX, 1, None).unwrap_err().errno, EAGAIN);
+    let handle = thread::spawn(|| futex_wait(&FUTEX, 0, None).unwrap()).unwrap();
+    while futex_wake(&FUTEX, 1).unwrap() == 0 {
+        yield_();
+    }
+    assert_eq!(handle.join().unwrap(), 0);
+    println!("test_wait_and_wake success!");
+}
+
+fn test_timeout() {
+    let start = get_time();
+    let timeout = Timespec::from_us(100000);
+    assert_eq!(futex_wait(&FUTEX, 0, Some(&timeout)).unwrap_err().errno, ETIMEDOUT);
+    assert!(get_time() - start >= 100);
+    println!("test_timeout success!");
+}
+
+/// Requeued waiters are only woken up through the new futex.
+fn test_requeue() {
+    let handles: Vec<_> = (0..3).map(|_| {
+        thread::spawn(|| {
+            STARTED.fetch_add(1, Ordering::SeqCst);
+            futex_wait(&FUTEX, 0, None).unwrap()
+        }).unwrap()
+    }).collect();
+    // let all of them fall asleep.
+    while STARTED.load(Ordering::SeqCst) < 3 {
+        yield_();
+    }
+    for _ in 0..10 {
+        yield_();
+    }
+    assert_eq!(futex_requeue(&FUTEX, 1, &OTHER_FUTEX, 3).unwrap(), 1);
+    assert_eq!(futex_wake(&FUTEX, 3).unwrap(), 0);
+    assert_eq!(futex_wake(&OTHER_FUTEX, 3).unwrap(), 2);
+    handles.into_iter().for_each(|handle| assert_eq!(handle.join().unwrap(), 0));
+    println!("test_requeue success!");
+}
+
+/// The page of the word is shared copy-on-write by a fork and copied by the next write, the waiter
+/// is still found by a wake.
+fn test_wake_after_fork() {
+    STARTED.store(0, Ordering::SeqCst);
+    let handle = thread::spawn(|| {
+        STARTED.store(1, Ordering::SeqCst);
+        futex_wait(&FUTEX, 0, None).unwrap()
+    }).unwrap();
+    while STARTED.load(Ordering::SeqCst) == 0 {
+        yield_();
+    }
+    for _ in 0..10 {
+        yield_();
+    }
+    let pid = fork().unwrap();
+    if pid == 0 {
+        exit(0);
+    }
+    FUTEX.store(0, Ordering::SeqCst);
+    while futex_wake(&FUTEX, 1).unwrap() == 0 {
+        yield_();
+    }
+    assert_eq!(handle.join().unwrap(), 0);
+    let mut status = 0;
+    waitpid(pid as isize, Some(&mut status), 0).unwrap();
+    assert_eq!(status, 0);
+    println!("test_wake_after_fork success!");
+}
+
+fn test_mutex() {
+    let counter = Arc::new(Mutex::new(0));
+    let handles: Vec<_> = (0..4).map(|_| {
+        let counter = counter.clone();
+        thread::spawn(move || {
+            for _ in 0..50 {
+                let mut guard = counter.lock();
+                let value = *guard;
+                yield_(); // let the others run into the locked mutex.
+                *guard = value + 1;
+            }
+        }).unwrap()
+    }).collect();
+    handles.into_iter().for_each(|handle| handle.join().unwrap());
+    assert_eq!(*counter.lock(), 200);
+    println!("test_mutex success!");
+}
+
+fn test_condvar() {
+    let pair = Arc::new((Mutex::new(0), Condvar::new()));
+    let handles: Vec<_> = (0..3).map(|_| {
+        let pair = pair.clone();
+        thread::spawn(move || {
+            let (ready, condvar) = &*pair;
+            let mut ready = ready.lock();
+            while *ready == 0 {
+                ready = condvar.wait(ready);
+            }
+            *ready += 1;
+        }).unwrap()
+    }).collect();
+
+    let (ready, condvar) = &*pair;
+    for _ in 0..10 {
+        yield_();
+    }
+    *ready.lock() = 1;
+    condvar.notify_all();
+    handles.into_iter().for_each(|handle| handle.join().unwrap());
+    assert_eq!(*ready.lock(), 4);
+
+    let guard = ready.lock();
+    let (_guard, timed_out) = condvar.wait_timeout(guard, 50);
+    assert!(timed_out);
+    println!("test_condvar success!");
+}
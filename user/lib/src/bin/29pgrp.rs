@@ -0,0 +1,147 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::syscall::{fork, getpid, getpgid, setpgid, getsid, setsid, kill, killpg, waitpid, read, exit, yield_};
+use user_lib::termios::{tc_get_pgrp, tc_set_pgrp};
+use share::signal::{SIGKILL, SIGTERM, SIGTTIN};
+use share::syscall::error::{ECHILD, EPERM, ESRCH};
+use share::wait::*;
+
+#[no_mangle]
+fn main() {
+    test_pgrp_inherited();
+    test_setpgid();
+    test_setsid();
+    test_kill_group();
+    test_wait_group();
+    test_background_read();
+}
+
+fn test_pgrp_inherited() {
+    let pgid = getpgid(0).unwrap();
+    let sid = getsid(0).unwrap();
+    let ret = fork().unwrap();
+    if ret == 0 {
+        let same = getpgid(0).unwrap() == pgid && getsid(0).unwrap() == sid;
+        exit(same as usize);
+    } else {
+        let mut status = 0;
+        waitpid(ret as isize, Some(&mut status), 0).unwrap();
+        assert_eq!(wexitstatus(status), 1);
+        println!("test_pgrp_inherited success!");
+    }
+}
+
+fn test_setpgid() {
+    let ret = fork().unwrap();
+    if ret == 0 {
+        loop {
+            yield_();
+        }
+    } else {
+        setpgid(ret, 0).unwrap();
+        assert_eq!(getpgid(ret).unwrap(), ret);
+        assert_ne!(getpgid(0).unwrap(), ret);
+        // a group in the session must exist before joining it.
+        assert_eq!(setpgid(ret, 63).unwrap_err().errno, EPERM);
+        kill(ret, SIGKILL).unwrap();
+        waitpid(ret as isize, None, 0).unwrap();
+        println!("test_setpgid success!");
+    }
+}
+
+/// A session leader can neither start another session nor change its group.
+fn test_setsid() {
+    let ret = fork().unwrap();
+    if ret == 0 {
+        let pid = getpid();
+        assert_eq!(setsid().unwrap(), pid);
+        assert_eq!(getsid(0).unwrap(), pid);
+        assert_eq!(getpgid(0).unwrap(), pid);
+        assert_eq!(setsid().unwrap_err().errno, EPERM);
+        assert_eq!(setpgid(0, 0).unwrap_err().errno, EPERM);
+        exit(0);
+    } else {
+        let mut status = 0;
+        waitpid(ret as isize, Some(&mut status), 0).unwrap();
+        assert!(wifexited(status));
+        assert_eq!(wexitstatus(status), 0);
+        assert_eq!(getsid(ret).unwrap_err().errno, ESRCH);
+        println!("test_setsid success!");
+    }
+}
+
+fn spin_child() -> usize {
+    let ret = fork().unwrap();
+    if ret == 0 {
+        loop {
+            yield_();
+        }
+    }
+    ret
+}
+
+/// Every process in the group receives the signal.
+fn test_kill_group() {
+    let leader = spin_child();
+    let member = spin_child();
+    setpgid(leader, leader).unwrap();
+    setpgid(member, leader).unwrap();
+    assert_eq!(getpgid(member).unwrap(), leader);
+
+    killpg(leader, SIGTERM).unwrap();
+    for _ in 0..2 {
+        let mut status = 0;
+        let pid = waitpid(-(leader as isize), Some(&mut status), 0).unwrap();
+        assert!(pid == leader || pid == member);
+        assert!(wifsignaled(status));
+        assert_eq!(wtermsig(status), SIGTERM);
+    }
+    assert_eq!(waitpid(-(leader as isize), None, WNOHANG).unwrap_err().errno, ECHILD);
+    println!("test_kill_group success!");
+}
+
+/// waitpid(0) only waits for the children in the group of the caller.
+fn test_wait_group() {
+    let other = spin_child();
+    setpgid(other, other).unwrap();
+    let ret = fork().unwrap();
+    if ret == 0 {
+        exit(7);
+    }
+
+    let mut status = 0;
+    assert_eq!(waitpid(0, Some(&mut status), 0).unwrap(), ret);
+    assert_eq!(wexitstatus(status), 7);
+    assert_eq!(waitpid(0, None, WNOHANG).unwrap_err().errno, ECHILD);
+    kill(other, SIGKILL).unwrap();
+    assert_eq!(waitpid(-(other as isize), None, 0).unwrap(), other);
+    println!("test_wait_group success!");
+}
+
+/// A process outside the foreground group is stopped by `SIGTTIN` when reading the terminal.
+fn test_background_read() {
+    let old_pgrp = tc_get_pgrp(0).unwrap();
+    tc_set_pgrp(0, getpgid(0).unwrap()).unwrap();
+    assert_eq!(tc_get_pgrp(0).unwrap(), getpgid(0).unwrap());
+
+    let ret = fork().unwrap();
+    if ret == 0 {
+        setpgid(0, 0).unwrap();
+        let mut buf = [0; 1];
+        let _ = read(0, &mut buf);
+        exit(0);
+    } else {
+        let mut status = 0;
+        assert_eq!(waitpid(ret as isize, Some(&mut status), WUNTRACED).unwrap(), ret);
+        assert!(wifstopped(status));
+        assert_eq!(wstopsig(status), SIGTTIN);
+        kill(ret, SIGKILL).unwrap();
+        waitpid(ret as isize, None, 0).unwrap();
+        tc_set_pgrp(0, old_pgrp).unwrap();
+        println!("test_background_read success!");
+    }
+}
@@ -0,0 +1,106 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::syscall::{fork, getppid, getcwd, send, receive, sendrec, send_fast, receive_fast, waitpid, exit, get_time};
+use share::ipc::Msg;
+
+/// Enough pairs to keep every hart of `-smp 4` busy.
+const PAIRS: usize = 8;
+const ROUNDS: usize = 500;
+
+#[no_mangle]
+fn main() {
+    test_ipc_pairs();
+    test_fs_clients();
+}
+
+/// Each pair keeps waking up its peer, which is likely to be still running on another hart.
+fn test_ipc_pairs() {
+    let start = get_time();
+    let mut workers = [0; PAIRS];
+    for worker in workers.iter_mut() {
+        let ret = fork().unwrap();
+        if ret == 0 {
+            ping_pong();
+        }
+        *worker = ret;
+    }
+
+    for &worker in workers.iter() {
+        let mut status = 0;
+        waitpid(worker as isize, Some(&mut status), 0).unwrap();
+        assert_eq!(status, 0);
+    }
+    println!("{} pairs * {} round trips take: {}", PAIRS, ROUNDS * 3, get_time() - start);
+    println!("test_ipc_pairs success!");
+}
+
+fn ping_pong() -> ! {
+    let ret = fork().unwrap();
+    if ret == 0 {
+        echo_server();
+    }
+
+    let mut msg = Msg::empty();
+    for i in 0..ROUNDS {
+        msg.args[0] = i;
+        send(ret, &msg).unwrap();
+        receive(ret as isize, &mut msg).unwrap();
+        assert_eq!(msg.args[0], i + 1);
+
+        msg.args[0] = i;
+        sendrec(ret, &mut msg).unwrap();
+        assert_eq!(msg.args[0], i + 1);
+
+        msg.args[0] = i;
+        send_fast(ret, &msg).unwrap();
+        receive_fast(ret as isize, &mut msg).unwrap();
+        assert_eq!(msg.args[0], i + 1);
+    }
+
+    let mut status = 0;
+    waitpid(ret as isize, Some(&mut status), 0).unwrap();
+    exit(status as usize);
+}
+
+fn echo_server() -> ! {
+    let parent_pid = getppid();
+    let mut msg = Msg::empty();
+    for _ in 0..ROUNDS {
+        for _ in 0..2 {
+            receive(parent_pid as isize, &mut msg).unwrap();
+            msg.args[0] += 1;
+            send(parent_pid, &msg).unwrap();
+        }
+        receive_fast(parent_pid as isize, &mut msg).unwrap();
+        msg.args[0] += 1;
+        send_fast(parent_pid, &msg).unwrap();
+    }
+    exit(0);
+}
+
+/// Many clients calling the fs server at the same time.
+fn test_fs_clients() {
+    let cwd = getcwd().unwrap();
+    let mut clients = [0; PAIRS * 2];
+    for client in clients.iter_mut() {
+        let ret = fork().unwrap();
+        if ret == 0 {
+            for _ in 0..ROUNDS {
+                assert_eq!(getcwd().unwrap(), cwd);
+            }
+            exit(0);
+        }
+        *client = ret;
+    }
+
+    for &client in clients.iter() {
+        let mut status = 0;
+        waitpid(client as isize, Some(&mut status), 0).unwrap();
+        assert_eq!(status, 0);
+    }
+    println!("test_fs_clients success!");
+}
@@ -0,0 +1,89 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::syscall::{fork, kill, signal, nanosleep, waitpid, exit, get_time};
+use share::signal::SIGUSR1;
+use share::syscall::error::{EINTR, EINVAL};
+use share::time::Timespec;
+
+extern "C" fn handler(_: usize) {}
+
+#[no_mangle]
+fn main() {
+    test_nanosleep();
+    test_invalid_request();
+    test_interrupted_sleep();
+    test_sleepers();
+}
+
+fn test_nanosleep() {
+    let start = get_time();
+    let req = Timespec { tv_sec: 0, tv_usec: 300_000 };
+    nanosleep(&req, None).unwrap();
+    assert!(get_time() - start >= 300);
+
+    // the deadline crosses a second boundary.
+    let start = get_time();
+    let req = Timespec { tv_sec: 1, tv_usec: 900_000 };
+    nanosleep(&req, None).unwrap();
+    assert!(get_time() - start >= 1900);
+    println!("test_nanosleep success!");
+}
+
+fn test_invalid_request() {
+    let req = Timespec { tv_sec: 0, tv_usec: 1_000_000 };
+    assert_eq!(nanosleep(&req, None).unwrap_err().errno, EINVAL);
+    println!("test_invalid_request success!");
+}
+
+/// A caught signal ends the sleep early, and the time left is reported.
+fn test_interrupted_sleep() {
+    signal(SIGUSR1, handler as usize).unwrap();
+    let ret = fork().unwrap();
+    if ret == 0 {
+        let start = get_time();
+        let req = Timespec { tv_sec: 10, tv_usec: 0 };
+        let mut rem = Timespec::empty();
+        assert_eq!(nanosleep(&req, Some(&mut rem)).unwrap_err().errno, EINTR);
+        let slept = get_time() - start;
+        let left = rem.tv_sec as usize * 1000 + rem.tv_usec as usize / 1000;
+        assert!(slept < 10_000);
+        assert!(left > 0 && left <= 10_000 - slept + 10);
+        exit(0);
+    } else {
+        let req = Timespec { tv_sec: 0, tv_usec: 200_000 };
+        nanosleep(&req, None).unwrap();
+        kill(ret, SIGUSR1).unwrap();
+        let mut status = 0;
+        waitpid(ret as isize, Some(&mut status), 0).unwrap();
+        assert_eq!(status, 0);
+        println!("test_interrupted_sleep success!");
+    }
+}
+
+/// Sleepers with different deadlines are all woken up in time.
+fn test_sleepers() {
+    const SLEEPERS: usize = 8;
+    let mut pids = [0; SLEEPERS];
+    for (i, pid) in pids.iter_mut().enumerate() {
+        let ret = fork().unwrap();
+        if ret == 0 {
+            let start = get_time();
+            let req = Timespec { tv_sec: 0, tv_usec: (i as u64 + 1) * 100_000 };
+            nanosleep(&req, None).unwrap();
+            assert!(get_time() - start >= (i + 1) * 100);
+            exit(0);
+        }
+        *pid = ret;
+    }
+
+    for &pid in pids.iter() {
+        let mut status = 0;
+        waitpid(pid as isize, Some(&mut status), 0).unwrap();
+        assert_eq!(status, 0);
+    }
+    println!("test_sleepers success!");
+}
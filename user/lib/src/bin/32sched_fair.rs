@@ -0,0 +1,131 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::syscall::{fork, getpid, getppid, send, receive, sendrec, waitpid, exit, get_time};
+use share::ipc::Msg;
+
+/// Both kinds of tasks outnumber the harts of `-smp 4`, so they compete for cpu time.
+const CPU_TASKS: usize = 6;
+const IPC_PAIRS: usize = 6;
+const DURATION_MS: usize = 3000;
+const STOP: usize = 1;
+
+/// Compare how cpu time is shared among cpu bound tasks, and how ipc bound tasks get along with them.
+/// Build the kernel with `SCHEDULER=mlfq` or `SCHEDULER=cfs` to compare the policies.
+#[no_mangle]
+fn main() {
+    let deadline = get_time() + DURATION_MS;
+    let parent_pid = getpid();
+    let mut cpu_tasks = [0; CPU_TASKS];
+    for task in cpu_tasks.iter_mut() {
+        let ret = fork().unwrap();
+        if ret == 0 {
+            report(parent_pid, spin_until(deadline));
+        }
+        *task = ret;
+    }
+    let mut ipc_tasks = [0; IPC_PAIRS];
+    for task in ipc_tasks.iter_mut() {
+        let ret = fork().unwrap();
+        if ret == 0 {
+            report(parent_pid, ping_pong_until(deadline));
+        }
+        *task = ret;
+    }
+
+    let loops = collect(&cpu_tasks);
+    let round_trips = collect(&ipc_tasks);
+    for (i, n) in loops.iter().enumerate() {
+        println!("cpu bound task {}: {} loops", i, n);
+    }
+    for (i, n) in round_trips.iter().enumerate() {
+        println!("ipc bound pair {}: {} round trips", i, n);
+    }
+    println!("fairness index of cpu bound tasks: {}/1000", fairness_index(&loops));
+    println!("fairness index of ipc bound pairs: {}/1000", fairness_index(&round_trips));
+    assert!(loops.iter().chain(round_trips.iter()).all(|&n| n > 0));
+    println!("test_sched_fair success!");
+}
+
+fn spin_until(deadline: usize) -> usize {
+    let mut loops = 0;
+    let mut counter = 0;
+    while get_time() < deadline {
+        for _ in 0..1000 {
+            unsafe {
+                core::ptr::write_volatile(&mut counter, counter + 1);
+            }
+        }
+        loops += 1;
+    }
+    loops
+}
+
+fn ping_pong_until(deadline: usize) -> usize {
+    let ret = fork().unwrap();
+    if ret == 0 {
+        echo_server();
+    }
+
+    let mut msg = Msg::empty();
+    let mut round_trips = 0;
+    while get_time() < deadline {
+        msg.args[0] = round_trips;
+        sendrec(ret, &mut msg).unwrap();
+        assert_eq!(msg.args[0], round_trips + 1);
+        round_trips += 1;
+    }
+    msg.args[1] = STOP;
+    sendrec(ret, &mut msg).unwrap();
+
+    let mut status = 0;
+    waitpid(ret as isize, Some(&mut status), 0).unwrap();
+    assert_eq!(status, 0);
+    round_trips
+}
+
+fn echo_server() -> ! {
+    let parent_pid = getppid();
+    let mut msg = Msg::empty();
+    loop {
+        receive(parent_pid as isize, &mut msg).unwrap();
+        msg.args[0] += 1;
+        send(parent_pid, &msg).unwrap();
+        if msg.args[1] == STOP {
+            exit(0);
+        }
+    }
+}
+
+fn report(parent_pid: usize, result: usize) -> ! {
+    let mut msg = Msg::empty();
+    msg.args[0] = result;
+    send(parent_pid, &msg).unwrap();
+    exit(0);
+}
+
+fn collect<const N: usize>(tasks: &[usize; N]) -> [usize; N] {
+    let mut results = [0; N];
+    for (result, &task) in results.iter_mut().zip(tasks.iter()) {
+        let mut msg = Msg::empty();
+        receive(task as isize, &mut msg).unwrap();
+        *result = msg.args[0];
+        let mut status = 0;
+        waitpid(task as isize, Some(&mut status), 0).unwrap();
+        assert_eq!(status, 0);
+    }
+    results
+}
+
+/// Jain's fairness index scaled by 1000, which is 1000 when everyone gets the same share.
+fn fairness_index(results: &[usize]) -> u128 {
+    let sum: u128 = results.iter().map(|&n| n as u128).sum();
+    let square_sum: u128 = results.iter().map(|&n| n as u128 * n as u128).sum();
+    if square_sum == 0 {
+        return 0;
+    }
+    sum * sum * 1000 / (results.len() as u128 * square_sum)
+}
@@ -0,0 +1,95 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::syscall::{fork, set_priority, get_priority, send, receive, sendrec, waitpid, exit, get_time};
+use share::ipc::Msg;
+
+/// More hogs than the harts of `-smp 4`, so a server without the priority of its client waits behind them.
+const HOGS: usize = 8;
+const REQUESTS: usize = 20;
+const WORK_MS: usize = 5;
+const STOP: usize = 1;
+const CLIENT_PRIORITY: isize = 3;
+const HOG_PRIORITY: isize = 5;
+const SERVER_PRIORITY: isize = 7;
+
+/// A client at priority 3 calls a frontend server at priority 7, which calls a backend server at
+/// priority 7 for each request, while hogs at priority 5 keep every hart busy. Both servers run at
+/// the priority of the client while they handle its requests, so above the hogs. The servers report
+/// in `args[2]` the lower one of the priorities they have run at.
+#[no_mangle]
+fn main() {
+    set_priority(0, 0, CLIENT_PRIORITY).unwrap();
+    let backend = fork().unwrap();
+    if backend == 0 {
+        server(None);
+    }
+    let frontend = fork().unwrap();
+    if frontend == 0 {
+        server(Some(backend));
+    }
+
+    let deadline = get_time() + REQUESTS * WORK_MS * 2 * 10;
+    let mut hogs = [0; HOGS];
+    for hog in hogs.iter_mut() {
+        let ret = fork().unwrap();
+        if ret == 0 {
+            set_priority(0, 0, HOG_PRIORITY).unwrap();
+            while get_time() < deadline {}
+            exit(0);
+        }
+        *hog = ret;
+    }
+
+    let mut msg = Msg::empty();
+    let mut max_latency = 0;
+    let start = get_time();
+    for i in 0..REQUESTS {
+        let begin = get_time();
+        msg.args[0] = i;
+        // priorities drift in MLFQ, the client lends exactly its own one.
+        set_priority(0, 0, CLIENT_PRIORITY).unwrap();
+        sendrec(frontend, &mut msg).unwrap();
+        assert_eq!(msg.args[0], i + 2);
+        assert!((msg.args[2] as isize) < HOG_PRIORITY);
+        max_latency = usize::max(max_latency, get_time() - begin);
+    }
+    println!("{} requests take: {}, the slowest one takes: {}", REQUESTS, get_time() - start, max_latency);
+
+    msg.args[1] = STOP;
+    sendrec(frontend, &mut msg).unwrap();
+    for &pid in [frontend, backend].iter().chain(hogs.iter()) {
+        let mut status = 0;
+        waitpid(pid as isize, Some(&mut status), 0).unwrap();
+        assert_eq!(status, 0);
+    }
+    println!("test_prio_inherit success!");
+}
+
+/// Each request takes `WORK_MS` of cpu time, and is passed on to `backend` if there is one.
+fn server(backend: Option<usize>) -> ! {
+    let mut msg = Msg::empty();
+    loop {
+        set_priority(0, 0, SERVER_PRIORITY).unwrap();
+        receive(-1, &mut msg).unwrap();
+        let client = msg.src_pid;
+        // a server woken up rises one level, which is still below the hogs without the inherited one.
+        let priority = get_priority(0, 0).unwrap();
+        let start = get_time();
+        while get_time() - start < WORK_MS {}
+        if let Some(backend) = backend {
+            sendrec(backend, &mut msg).unwrap();
+        } else {
+            msg.args[2] = 0;
+        }
+        msg.args[2] = usize::max(msg.args[2], priority);
+        msg.args[0] += 1;
+        send(client, &msg).unwrap();
+        if msg.args[1] == STOP {
+            exit(0);
+        }
+    }
+}
@@ -0,0 +1,89 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::syscall::{fork, waitpid, exit, send, receive, getppid, debug_frame_usage};
+use share::ipc::Msg;
+use share::wait::wexitstatus;
+
+const PAGES: usize = 64;
+const PAGE_SIZE: usize = 4096;
+
+/// In the data section, so it is loaded from the elf file instead of being zeroed.
+static mut DATA: [u8; PAGES * PAGE_SIZE] = [1; PAGES * PAGE_SIZE];
+static mut STATUS: usize = 0;
+
+#[no_mangle]
+fn main() {
+    test_fork_shares_frames();
+    test_writes_are_private();
+    test_kernel_writes_are_private();
+}
+
+/// The pages of `DATA` are not copied until they are written.
+fn test_fork_shares_frames() {
+    let available = debug_frame_usage();
+    let ret = fork().unwrap();
+    if ret == 0 {
+        exit(0);
+    }
+    assert!(available - debug_frame_usage() < PAGES / 2);
+    let mut status = 0;
+    waitpid(ret as isize, Some(&mut status), 0).unwrap();
+    assert_eq!(status, 0);
+    println!("test_fork_shares_frames success!");
+}
+
+/// Parent and child write to the same pages, and each of them only sees its own writes.
+fn test_writes_are_private() {
+    let ret = fork().unwrap();
+    if ret == 0 {
+        fill(2);
+        exit(if check(2) { 0 } else { 1 });
+    }
+    fill(3);
+    let mut status = 0;
+    waitpid(ret as isize, Some(&mut status), 0).unwrap();
+    assert_eq!(status, 0);
+    assert!(check(3));
+    println!("test_writes_are_private success!");
+}
+
+/// The kernel writing the status of `waitpid` to a shared page doesn't change the page of the
+/// other child.
+fn test_kernel_writes_are_private() {
+    let receiver = fork().unwrap();
+    if receiver == 0 {
+        let mut msg = Msg::empty();
+        receive(getppid() as isize, &mut msg).unwrap();
+        exit(unsafe { STATUS });
+    }
+    let ret = fork().unwrap();
+    if ret == 0 {
+        exit(7);
+    }
+    unsafe {
+        waitpid(ret as isize, Some(&mut STATUS), 0).unwrap();
+        assert_eq!(wexitstatus(STATUS), 7);
+    }
+    send(receiver, &Msg::empty()).unwrap();
+    let mut status = 0;
+    waitpid(receiver as isize, Some(&mut status), 0).unwrap();
+    assert_eq!(status, 0);
+    println!("test_kernel_writes_are_private success!");
+}
+
+fn fill(value: u8) {
+    for i in (0..PAGES * PAGE_SIZE).step_by(PAGE_SIZE / 4) {
+        unsafe {
+            core::ptr::write_volatile(&mut DATA[i], value);
+        }
+    }
+}
+
+fn check(value: u8) -> bool {
+    (0..PAGES * PAGE_SIZE).step_by(PAGE_SIZE / 4)
+        .all(|i| unsafe { core::ptr::read_volatile(&DATA[i]) } == value)
+}
@@ -0,0 +1,81 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::syscall::{fork, waitpid, exit, brk, mmap, debug_frame_usage};
+use share::mmap::{Prot, MMAPFlags};
+use share::wait::{wifsignaled, wtermsig};
+use share::signal::SIGSEGV;
+
+const PAGES: usize = 128;
+const PAGE_SIZE: usize = 4096;
+const TOUCHED: usize = 8;
+
+/// In the bss section, so it costs nothing until it is touched.
+static mut BSS: [u8; PAGES * PAGE_SIZE] = [0; PAGES * PAGE_SIZE];
+
+#[no_mangle]
+fn main() {
+    test_lazy_brk();
+    test_lazy_mmap();
+    test_lazy_bss();
+    test_segmentation_fault();
+}
+
+fn test_lazy_brk() {
+    let start = brk(None).unwrap();
+    let available = debug_frame_usage();
+    brk(Some(start + PAGES * PAGE_SIZE)).unwrap();
+    assert!(available - debug_frame_usage() < PAGES / 4);
+    touch(start);
+    brk(Some(start)).unwrap();
+    println!("test_lazy_brk success!");
+}
+
+fn test_lazy_mmap() {
+    let available = debug_frame_usage();
+    let start = mmap(None, PAGES * PAGE_SIZE, Prot::READ | Prot::WRITE, MMAPFlags::ANONYMOUS, 0, 0).unwrap();
+    assert!(available - debug_frame_usage() < PAGES / 4);
+    touch(start);
+    println!("test_lazy_mmap success!");
+}
+
+fn test_lazy_bss() {
+    touch(unsafe { BSS.as_mut_ptr() as usize });
+    println!("test_lazy_bss success!");
+}
+
+/// Pages read before being written are zero, and only the pages touched take frames.
+fn touch(start: usize) {
+    let available = debug_frame_usage();
+    for i in 0..TOUCHED {
+        let ptr = (start + i * PAGE_SIZE * (PAGES / TOUCHED)) as *mut usize;
+        unsafe {
+            assert_eq!(ptr.read_volatile(), 0);
+            ptr.write_volatile(i + 1);
+            assert_eq!(ptr.read_volatile(), i + 1);
+        }
+    }
+    let used = available - debug_frame_usage();
+    assert!(used >= TOUCHED && used < PAGES / 2);
+}
+
+/// An access outside of the address space kills the task with SIGSEGV.
+fn test_segmentation_fault() {
+    for &addr in [0usize, 0x1000_0000_0000].iter() {
+        let ret = fork().unwrap();
+        if ret == 0 {
+            unsafe {
+                (addr as *mut usize).write_volatile(1);
+            }
+            exit(0);
+        }
+        let mut status = 0;
+        waitpid(ret as isize, Some(&mut status), 0).unwrap();
+        assert!(wifsignaled(status));
+        assert_eq!(wtermsig(status), SIGSEGV);
+    }
+    println!("test_segmentation_fault success!");
+}
@@ -0,0 +1,78 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::syscall::{fork, waitpid, exit, getrlimit, setrlimit, memory_map};
+use share::mmap::{MapEntry, MapKind};
+use share::resource::{Rlimit, RLIMIT_STACK};
+use share::wait::{wifsignaled, wtermsig};
+use share::signal::SIGSEGV;
+
+const PAGE_SIZE: usize = 4096;
+const FRAMES: usize = 64;
+
+#[no_mangle]
+fn main() {
+    test_stack_grows();
+    test_rlimit();
+    test_stack_overflow();
+}
+
+/// Touching the pages below the stack grows it, and the memory map shows the new size.
+fn test_stack_grows() {
+    let before = stack_size();
+    assert_eq!(recurse(FRAMES), FRAMES);
+    assert!(stack_size() >= before + FRAMES * PAGE_SIZE);
+    println!("test_stack_grows success!");
+}
+
+fn test_rlimit() {
+    let limit = getrlimit(RLIMIT_STACK).unwrap();
+    assert!(limit.cur <= limit.max);
+    assert!(setrlimit(RLIMIT_STACK, &Rlimit::new(limit.cur + 1, limit.cur)).is_err());
+    setrlimit(RLIMIT_STACK, &Rlimit::new(limit.cur / 2, limit.max)).unwrap();
+    assert_eq!(getrlimit(RLIMIT_STACK).unwrap().cur, limit.cur / 2);
+    setrlimit(RLIMIT_STACK, &limit).unwrap();
+    println!("test_rlimit success!");
+}
+
+/// Growing the stack beyond the limit kills the task with SIGSEGV.
+fn test_stack_overflow() {
+    let ret = fork().unwrap();
+    if ret == 0 {
+        let limit = getrlimit(RLIMIT_STACK).unwrap();
+        setrlimit(RLIMIT_STACK, &Rlimit::new(FRAMES * PAGE_SIZE, limit.max)).unwrap();
+        recurse(FRAMES * 2);
+        exit(0);
+    }
+    let mut status = 0;
+    waitpid(ret as isize, Some(&mut status), 0).unwrap();
+    assert!(wifsignaled(status));
+    assert_eq!(wtermsig(status), SIGSEGV);
+    println!("test_stack_overflow success!");
+}
+
+/// Each call takes a little more than a page of stack.
+#[inline(never)]
+fn recurse(depth: usize) -> usize {
+    let mut frame = [0u8; PAGE_SIZE];
+    unsafe {
+        core::ptr::write_volatile(&mut frame[0], 1);
+    }
+    if depth == 0 {
+        return 0;
+    }
+    let ret = recurse(depth - 1) + 1;
+    unsafe { core::ptr::read_volatile(&frame[0]) as usize - 1 + ret }
+}
+
+fn stack_size() -> usize {
+    let mut entries = [MapEntry::empty(); 32];
+    let len = memory_map(0, &mut entries).unwrap();
+    entries[..usize::min(len, entries.len())].iter()
+        .find(|entry| entry.kind == MapKind::Stack)
+        .map(|entry| entry.end - entry.start)
+        .unwrap()
+}
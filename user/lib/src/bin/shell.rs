@@ -6,43 +6,173 @@ extern crate user_lib;
 extern crate alloc;
 
 use user_lib::io::read_line;
-use user_lib::syscall::{fork, exec, exit, waitpid, debug_frame_usage, getcwd, chdir, open, write, close, dup};
+use user_lib::syscall::{fork, exec, exit, waitpid, debug_frame_usage, getcwd, chdir, open, write, close, dup, getpid, setpgid, killpg, signal};
 use share::terminal::{Termios, Clflag};
-use user_lib::termios::tc_set_attr;
+use share::signal::{SIGINT, SIGQUIT, SIGTSTP, SIGTTIN, SIGTTOU, SIGCONT, SIG_DFL, SIG_IGN};
+use share::wait::{WNOHANG, WUNTRACED, wifstopped};
+use user_lib::termios::{tc_set_attr, tc_set_pgrp};
 use alloc::vec::Vec;
+use alloc::string::String;
 use user_lib::env::get_args;
 use share::file::OpenFlag;
 
+/// Signals generated by the terminal, the shell ignores them and its jobs take the default action.
+const JOB_CONTROL_SIGNALS: [usize; 5] = [SIGINT, SIGQUIT, SIGTSTP, SIGTTIN, SIGTTOU];
+
+#[derive(Copy, Clone, PartialEq)]
+enum JobState {
+    Running,
+    Stopped,
+}
+
+/// A command running in its own process group, whose pgid is the pid of the command.
+struct Job {
+    id: usize,
+    pgid: usize,
+    command: String,
+    state: JobState,
+}
+
+struct Shell {
+    termios: Termios,
+    jobs: Vec<Job>,
+}
+
 #[no_mangle]
 fn main() {
     let mut shell_termios = Termios::default();
     shell_termios.c_lflag.remove(Clflag::ECHO);
     tc_set_attr(1, shell_termios).unwrap();
 
+    JOB_CONTROL_SIGNALS.iter().for_each(|&signo| { signal(signo, SIG_IGN).unwrap(); });
+    setpgid(0, 0).unwrap();
+    tc_set_pgrp(0, getpid()).unwrap();
+
+    let mut shell = Shell { termios: shell_termios, jobs: Vec::new() };
     loop {
+        shell.report_jobs();
         let cur_dir = getcwd().unwrap();
         print!("root@los:{}$ ", cur_dir);
         let line = read_line();
-        let args: Vec<&str> = line.split_whitespace().collect();
+        let mut args: Vec<&str> = line.split_whitespace().collect();
         if args.len() == 0 {
             continue;
         }
+        let background = args.last() == Some(&"&");
+        if background {
+            args.pop();
+            if args.len() == 0 {
+                continue;
+            }
+        }
 
-        if handle_inner_command(&args) {
+        if handle_inner_command(&args) || shell.handle_job_command(&args) {
             continue;
         }
 
         let ret = fork().unwrap();
         if ret == 0 {
+            setpgid(0, 0).unwrap();
+            if !background {
+                tc_set_pgrp(0, getpid()).unwrap();
+            }
+            JOB_CONTROL_SIGNALS.iter().for_each(|&signo| { signal(signo, SIG_DFL).unwrap(); });
             tc_set_attr(1, Termios::default()).unwrap();
             if exec(args[0], args).is_err() {
                 println!("{}: no such file", line);
                 exit(127);
             }
         } else {
-            let pid = waitpid(ret as isize, None, 0).unwrap();
-            assert_eq!(pid, ret);
-            tc_set_attr(1, shell_termios).unwrap(); // reset default shell termios.
+            // set in both processes, so that it is done whichever runs first.
+            let _ = setpgid(ret, ret);
+            let id = shell.jobs.last().map_or(1, |job| job.id + 1);
+            shell.jobs.push(Job { id, pgid: ret, command: args.join(" "), state: JobState::Running });
+            if background {
+                println!("[{}] {}", id, ret);
+            } else {
+                shell.wait_for_foreground(id);
+            }
+        }
+    }
+}
+
+impl Shell {
+    /// `fg`, `bg` and `jobs`, a job is given as `%id` and defaults to the latest one.
+    fn handle_job_command(&mut self, args: &Vec<&str>) -> bool {
+        if args[0] == "jobs" {
+            for job in self.jobs.iter() {
+                let state = if job.state == JobState::Running { "Running" } else { "Stopped" };
+                println!("[{}] {}\t{}", job.id, state, job.command);
+            }
+            return true;
+        }
+        if args[0] != "fg" && args[0] != "bg" {
+            return false;
+        }
+
+        let id = match args.get(1) {
+            Some(arg) => arg.trim_start_matches('%').parse().ok(),
+            None => self.jobs.last().map(|job| job.id),
+        };
+        let job = match id.and_then(|id| self.jobs.iter_mut().find(|job| job.id == id)) {
+            Some(job) => job,
+            None => {
+                println!("{}: {}: no such job", get_args()[0].as_str(), args[0]);
+                return true;
+            }
+        };
+        job.state = JobState::Running;
+        let (id, pgid) = (job.id, job.pgid);
+        if args[0] == "fg" {
+            println!("{}", job.command);
+            tc_set_pgrp(0, pgid).unwrap();
+            let _ = killpg(pgid, SIGCONT);
+            self.wait_for_foreground(id);
+        } else {
+            println!("[{}] {} &", id, job.command);
+            let _ = killpg(pgid, SIGCONT);
+        }
+        true
+    }
+
+    /// Wait until job `id` exits or stops, then take the terminal back.
+    fn wait_for_foreground(&mut self, id: usize) {
+        let index = self.jobs.iter().position(|job| job.id == id).unwrap();
+        let pgid = self.jobs[index].pgid;
+        let mut status = 0;
+        let pid = waitpid(pgid as isize, Some(&mut status), WUNTRACED).unwrap();
+        assert_eq!(pid, pgid);
+        if wifstopped(status) {
+            let job = &mut self.jobs[index];
+            job.state = JobState::Stopped;
+            println!("[{}] Stopped\t{}", job.id, job.command);
+        } else {
+            self.jobs.remove(index);
+        }
+
+        tc_set_pgrp(0, getpid()).unwrap();
+        tc_set_attr(1, self.termios).unwrap(); // reset default shell termios.
+    }
+
+    /// Report background jobs which have exited or stopped since the last prompt.
+    fn report_jobs(&mut self) {
+        let mut status = 0;
+        while let Ok(pid) = waitpid(-1, Some(&mut status), WNOHANG | WUNTRACED) {
+            if pid == 0 {
+                break;
+            }
+            let index = match self.jobs.iter().position(|job| job.pgid == pid) {
+                Some(index) => index,
+                None => continue,
+            };
+            if wifstopped(status) {
+                let job = &mut self.jobs[index];
+                job.state = JobState::Stopped;
+                println!("[{}] Stopped\t{}", job.id, job.command);
+            } else {
+                let job = self.jobs.remove(index);
+                println!("[{}] Done\t{}", job.id, job.command);
+            }
         }
     }
 }
@@ -4,8 +4,8 @@
 #[macro_use]
 extern crate user_lib;
 
-use user_lib::syscall::{getpid, send, receive};
-use share::ipc::{Msg, READ, PROC_NR, BUFFER, POSITION, WRITE, REPLY_STATUS, VIRTIO_BLK_PID};
+use user_lib::syscall::{getpid, send, receive, lookup};
+use share::ipc::{Msg, READ, PROC_NR, BUFFER, POSITION, WRITE, REPLY_STATUS, BLOCK_SERVICE};
 
 const BLK_SZ: usize = 512;
 #[allow(unused)]
@@ -32,6 +32,7 @@ fn main() {
 }
 
 fn read_block(block_id: usize, ptr: usize) {
+    let driver = lookup(BLOCK_SERVICE).unwrap();
     let mut message = Msg::empty();
 
     message.mtype = READ;
@@ -39,13 +40,14 @@ fn read_block(block_id: usize, ptr: usize) {
     message.args[BUFFER] = ptr;
     message.args[POSITION] = block_id;
 
-    send(VIRTIO_BLK_PID, &message).unwrap();
-    receive(VIRTIO_BLK_PID as isize, &mut message).unwrap();
+    send(driver, &message).unwrap();
+    receive(driver as isize, &mut message).unwrap();
     assert_eq!(message.args[REPLY_STATUS] as isize, BLK_SZ as isize);
 }
 
 #[allow(unused)]
 fn write_block(block_id: usize, ptr: usize) {
+    let driver = lookup(BLOCK_SERVICE).unwrap();
     let mut message = Msg::empty();
 
     message.mtype = WRITE;
@@ -53,7 +55,7 @@ fn write_block(block_id: usize, ptr: usize) {
     message.args[BUFFER] = ptr;
     message.args[POSITION] = block_id;
 
-    send(VIRTIO_BLK_PID, &message).unwrap();
-    receive(VIRTIO_BLK_PID as isize, &mut message).unwrap();
+    send(driver, &message).unwrap();
+    receive(driver as isize, &mut message).unwrap();
     assert_eq!(message.args[REPLY_STATUS] as isize, BLK_SZ as isize);
 }
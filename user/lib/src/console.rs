@@ -1,5 +1,6 @@
 use core::fmt;
 use core::fmt::Write;
+use share::syscall::error::EINTR;
 use crate::syscall::{write, sbi_write};
 
 const STDOUT: usize = 1;
@@ -8,9 +9,18 @@ struct Stdout;
 struct SbiStdout;
 
 impl Write for Stdout {
+    /// A background process is refused by the terminal with `TOSTOP` set, it tries again after
+    /// being stopped and continued.
     fn write_str(&mut self, s: &str) -> fmt::Result {
-        write(STDOUT, s.as_bytes()).unwrap();
-        Ok(())
+        loop {
+            match write(STDOUT, s.as_bytes()) {
+                Err(err) if err.errno == EINTR => continue,
+                result => {
+                    result.unwrap();
+                    return Ok(());
+                }
+            }
+        }
     }
 }
 
@@ -1,7 +1,7 @@
 use alloc::collections::BTreeMap;
 use alloc::string::String;
 use lazy_static::*;
-use spin::Mutex;
+use crate::sync::Mutex;
 use share::syscall::error::{SysError, EINVAL};
 use core::str::from_utf8;
 use alloc::vec::Vec;
@@ -3,6 +3,7 @@ use core::alloc::{GlobalAlloc, Layout};
 use buddy_system_allocator::LockedHeap;
 use core::cmp::max;
 use core::mem::size_of;
+use crate::sync::Mutex;
 
 const USER_HEAP_SIZE: usize = 0x1000;
 
@@ -25,13 +26,16 @@ pub fn handle_alloc_error(layout: core::alloc::Layout) -> ! {
 }
 
 pub struct LockedHeapWrapper{
-    inner: LockedHeap
+    inner: LockedHeap,
+    /// Threads grow the heap one at a time, or they would add the same memory twice.
+    rescue_lock: Mutex<()>,
 }
 
 impl LockedHeapWrapper {
     pub const fn empty() -> Self {
         Self {
             inner: LockedHeap::empty(),
+            rescue_lock: Mutex::new(()),
         }
     }
 
@@ -66,6 +70,12 @@ impl LockedHeapWrapper {
 
 unsafe impl GlobalAlloc for LockedHeapWrapper {
     unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = self.inner.alloc(layout);
+        if ptr as usize != 0 {
+            return ptr;
+        }
+        let _guard = self.rescue_lock.lock();
+        // another thread may have grown the heap while waiting for the lock.
         let ptr = self.inner.alloc(layout);
         if ptr as usize == 0 {
             self.rescue(layout);
@@ -1,4 +1,5 @@
 use alloc::string::String;
+use share::syscall::error::EINTR;
 use crate::syscall::read;
 
 const STDIN: usize = 0;
@@ -12,7 +13,11 @@ pub fn read_line() -> String {
     let mut buf = [0];
     let mut cnt = 0;
     loop {
-        assert_eq!(read(STDIN, &mut buf).unwrap(), 1);
+        // interrupted by a signal from the terminal, e.g. when it is read in the background.
+        match read(STDIN, &mut buf) {
+            Err(err) if err.errno == EINTR => continue,
+            result => assert_eq!(result.unwrap(), 1),
+        }
         match buf[0] {
             LF | CR =>
                 break,
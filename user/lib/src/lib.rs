@@ -16,9 +16,13 @@ mod heap;
 pub mod io;
 mod panic;
 pub mod syscall;
+pub mod sync;
 pub mod termios;
+pub mod thread;
 
 global_asm!(include_str!("entry.asm"));
+global_asm!(include_str!("signal.asm"));
+global_asm!(include_str!("thread.asm"));
 
 #[no_mangle]
 pub extern "C" fn rust_start(argv: *const *const u8, envp: *const *const u8) {
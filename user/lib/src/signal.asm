@@ -0,0 +1,7 @@
+    .section .text
+    .globl __sigreturn
+# Signal handlers return here. The stack must be left as it is, because the kernel finds the saved
+# context at sp. a7 = SYSCALL_SIGRETURN
+__sigreturn:
+    li a7, 139
+    ecall
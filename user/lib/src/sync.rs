@@ -0,0 +1,141 @@
+use core::cell::UnsafeCell;
+use core::ops::{Deref, DerefMut};
+use core::sync::atomic::{AtomicU32, AtomicUsize, Ordering};
+use share::time::Timespec;
+use share::syscall::error::ETIMEDOUT;
+use crate::syscall::{futex_wait, futex_wake, futex_requeue};
+
+const UNLOCKED: u32 = 0;
+const LOCKED: u32 = 1;
+/// Locked, and some tasks may be sleeping on the futex.
+const CONTENDED: u32 = 2;
+
+/// A mutex whose waiters sleep on a futex instead of spinning.
+pub struct Mutex<T: ?Sized> {
+    state: AtomicU32,
+    data: UnsafeCell<T>,
+}
+
+unsafe impl<T: ?Sized + Send> Send for Mutex<T> {}
+unsafe impl<T: ?Sized + Send> Sync for Mutex<T> {}
+
+pub struct MutexGuard<'a, T: ?Sized> {
+    mutex: &'a Mutex<T>,
+}
+
+impl<T> Mutex<T> {
+    pub const fn new(data: T) -> Self {
+        Self {
+            state: AtomicU32::new(UNLOCKED),
+            data: UnsafeCell::new(data),
+        }
+    }
+}
+
+impl<T: ?Sized> Mutex<T> {
+    pub fn lock(&self) -> MutexGuard<T> {
+        if self.state.compare_exchange(UNLOCKED, LOCKED, Ordering::Acquire, Ordering::Relaxed).is_err() {
+            self.lock_contended();
+        }
+        MutexGuard { mutex: self }
+    }
+
+    pub fn try_lock(&self) -> Option<MutexGuard<T>> {
+        self.state.compare_exchange(UNLOCKED, LOCKED, Ordering::Acquire, Ordering::Relaxed)
+            .ok()
+            .map(|_| MutexGuard { mutex: self })
+    }
+
+    /// The lock is taken as `CONTENDED`, since we can't tell whether anyone else is still sleeping.
+    fn lock_contended(&self) {
+        while self.state.swap(CONTENDED, Ordering::Acquire) != UNLOCKED {
+            let _ = futex_wait(&self.state, CONTENDED, None);
+        }
+    }
+
+    fn unlock(&self) {
+        if self.state.swap(UNLOCKED, Ordering::Release) == CONTENDED {
+            futex_wake(&self.state, 1).unwrap();
+        }
+    }
+}
+
+impl<T: ?Sized> Deref for MutexGuard<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        unsafe { &*self.mutex.data.get() }
+    }
+}
+
+impl<T: ?Sized> DerefMut for MutexGuard<'_, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        unsafe { &mut *self.mutex.data.get() }
+    }
+}
+
+impl<T: ?Sized> Drop for MutexGuard<'_, T> {
+    fn drop(&mut self) {
+        self.mutex.unlock();
+    }
+}
+
+/// A condition variable, which must always be used with the same mutex.
+pub struct Condvar {
+    /// Bumped by every notification, so a notification between unlocking the mutex and sleeping
+    /// is not missed.
+    seq: AtomicU32,
+    /// Address of the state of the mutex, where the waiters are requeued by `notify_all`.
+    mutex: AtomicUsize,
+}
+
+impl Condvar {
+    pub const fn new() -> Self {
+        Self {
+            seq: AtomicU32::new(0),
+            mutex: AtomicUsize::new(0),
+        }
+    }
+
+    /// Unlock the mutex and sleep until notified, then lock the mutex again. It may also return
+    /// spuriously, so the condition should be checked in a loop.
+    pub fn wait<'a, T: ?Sized>(&self, guard: MutexGuard<'a, T>) -> MutexGuard<'a, T> {
+        self.wait_until(guard, None).0
+    }
+
+    /// The same as `wait`, except that it gives up after `timeout_ms` milliseconds and the
+    /// returned flag tells whether it has timed out.
+    pub fn wait_timeout<'a, T: ?Sized>(&self, guard: MutexGuard<'a, T>, timeout_ms: usize) -> (MutexGuard<'a, T>, bool) {
+        let timeout = Timespec::from_us(timeout_ms * 1000);
+        self.wait_until(guard, Some(&timeout))
+    }
+
+    fn wait_until<'a, T: ?Sized>(&self, guard: MutexGuard<'a, T>, timeout: Option<&Timespec>) -> (MutexGuard<'a, T>, bool) {
+        let mutex = guard.mutex;
+        self.mutex.store(&mutex.state as *const _ as usize, Ordering::Relaxed);
+        let seq = self.seq.load(Ordering::Relaxed);
+        drop(guard);
+
+        let result = futex_wait(&self.seq, seq, timeout);
+        mutex.lock_contended();
+        let timed_out = matches!(result, Err(err) if err.errno == ETIMEDOUT);
+        (MutexGuard { mutex }, timed_out)
+    }
+
+    pub fn notify_one(&self) {
+        self.seq.fetch_add(1, Ordering::Relaxed);
+        futex_wake(&self.seq, 1).unwrap();
+    }
+
+    /// Wake up one waiter and move the others onto the mutex, they are woken up one by one as the
+    /// mutex is unlocked instead of all rushing for it at once.
+    pub fn notify_all(&self) {
+        self.seq.fetch_add(1, Ordering::Relaxed);
+        let mutex = self.mutex.load(Ordering::Relaxed);
+        if mutex == 0 {
+            return;
+        }
+        let mutex_state = unsafe { &*(mutex as *const AtomicU32) };
+        futex_requeue(&self.seq, 1, mutex_state, usize::MAX).unwrap();
+    }
+}
@@ -1,15 +1,20 @@
 mod raw;
 
 pub use raw::*;
-use share::syscall::error::{SysError, ENFILE};
+use share::syscall::error::{SysError, ENFILE, EINVAL};
 use alloc::vec::Vec;
 use alloc::string::String;
 use crate::env::{get_envp_copy, getenv};
-use share::ipc::Msg;
+use share::ipc::{Msg, GrantFlags};
 use share::file::{MAX_PATH_LENGTH, OpenFlag, RDirent, Dirent, DIRENT_BUFFER_SZ, SEEKFlag, Stat, AT_FD_CWD};
 use share::ffi::{CString, CStr};
-use share::mmap::{Prot, MMAPFlags};
-use share::time::Timespec;
+use share::mmap::{Prot, MMAPFlags, MremapFlags, MsyncFlags, MapEntry};
+use share::resource::Rlimit;
+use share::time::{Timespec, Rusage};
+use share::signal::{SigAction, SigActionFlags, SigSet, SIG_DFL, SIG_IGN};
+use share::clone::CloneFlags;
+use share::futex::{FUTEX_WAIT, FUTEX_WAKE, FUTEX_REQUEUE};
+use core::sync::atomic::AtomicU32;
 
 fn isize2result(ret: isize) -> Result<usize, SysError> {
     if ret < 0 {
@@ -95,14 +100,79 @@ pub fn get_dents(fd: usize) -> Result<Vec<RDirent>, SysError> {
     Ok(rdirents)
 }
 
+/// Terminate the whole process, all the threads included.
 pub fn exit(exit_code: usize) -> isize {
+    sys_exit_group(exit_code)
+}
+
+/// Terminate the calling thread only.
+pub fn exit_thread(exit_code: usize) -> isize {
     sys_exit(exit_code)
 }
 
+/// Block until woken up by [`futex_wake`] if `futex` still holds `val`, otherwise `EAGAIN` is
+/// returned. `timeout` is relative, after which `ETIMEDOUT` is returned.
+pub fn futex_wait(futex: &AtomicU32, val: u32, timeout: Option<&Timespec>) -> Result<usize, SysError> {
+    let timeout_ptr = timeout.map_or(0, |timeout| timeout as *const _ as usize);
+    isize2result(sys_futex(futex as *const _ as usize, FUTEX_WAIT, val as usize, timeout_ptr, 0))
+}
+
+/// Wake up at most `n` tasks waiting on `futex`, and return how many have been woken up.
+pub fn futex_wake(futex: &AtomicU32, n: usize) -> Result<usize, SysError> {
+    isize2result(sys_futex(futex as *const _ as usize, FUTEX_WAKE, n, 0, 0))
+}
+
+/// Wake up at most `n` tasks waiting on `futex`, and move at most `requeue_n` of the rest to
+/// `new_futex` without waking them up.
+pub fn futex_requeue(futex: &AtomicU32, n: usize, new_futex: &AtomicU32, requeue_n: usize) -> Result<usize, SysError> {
+    isize2result(sys_futex(futex as *const _ as usize, FUTEX_REQUEUE, n, requeue_n, new_futex as *const _ as usize))
+}
+
 pub fn yield_() {
     sys_yield();
 }
 
+pub fn kill(pid: usize, signo: usize) -> Result<usize, SysError> {
+    isize2result(sys_kill(pid, signo))
+}
+
+/// Send `signo` to every process in group `pgid`.
+pub fn killpg(pgid: usize, signo: usize) -> Result<usize, SysError> {
+    isize2result(sys_kill(-(pgid as isize) as usize, signo))
+}
+
+/// Install `action` for `signo`. A handler returns through `__sigreturn` of user_lib, so callers
+/// don't have to set the restorer themselves.
+pub fn sigaction(signo: usize, action: Option<&SigAction>, old_action: Option<&mut SigAction>) -> Result<usize, SysError> {
+    extern "C" {
+        fn __sigreturn();
+    }
+    let action = action.map(|action| {
+        let mut action = *action;
+        if action.handler != SIG_DFL && action.handler != SIG_IGN {
+            action.flags |= SigActionFlags::RESTORER;
+            action.restorer = __sigreturn as usize;
+        }
+        action
+    });
+    let act_ptr = action.as_ref().map_or(0, |action| action as *const _ as usize);
+    let old_act_ptr = old_action.map_or(0, |old_action| old_action as *mut _ as usize);
+    isize2result(sys_sigaction(signo, act_ptr, old_act_ptr))
+}
+
+/// Set the handler of `signo`, which is `SIG_DFL`, `SIG_IGN` or an `extern "C" fn(usize)`.
+pub fn signal(signo: usize, handler: usize) -> Result<usize, SysError> {
+    let mut action = SigAction::default();
+    action.handler = handler;
+    sigaction(signo, Some(&action), None)
+}
+
+pub fn sigprocmask(how: usize, set: Option<SigSet>, old_set: Option<&mut SigSet>) -> Result<usize, SysError> {
+    let set_ptr = set.as_ref().map_or(0, |set| set as *const _ as usize);
+    let old_set_ptr = old_set.map_or(0, |old_set| old_set as *mut _ as usize);
+    isize2result(sys_sigprocmask(how, set_ptr, old_set_ptr))
+}
+
 pub fn read(fd: usize, buf: &mut [u8]) -> Result<usize, SysError> {
     isize2result(sys_read(fd, buf))
 }
@@ -134,19 +204,23 @@ pub fn rmdir(path: &str) -> Result<(), SysError> {
     Ok(())
 }
 
+/// Sleep for `seconds`, the rest of the time is slept again after a signal has been handled.
 pub fn sleep(seconds: usize) {
-    let start_time = get_time();
-    let mseconds = seconds * 1000;
-    loop {
-        let current_time = get_time();
-        if current_time < mseconds + start_time {
-            sys_yield();
-        } else {
-            break;
-        }
+    let mut req = Timespec { tv_sec: seconds as u64, tv_usec: 0 };
+    let mut rem = Timespec::empty();
+    while nanosleep(&req, Some(&mut rem)).is_err() {
+        req = rem;
     }
 }
 
+/// Sleep for `req`. When a signal interrupts the sleep, `EINTR` is returned and the time left is
+/// written to `rem`.
+pub fn nanosleep(req: &Timespec, rem: Option<&mut Timespec>) -> Result<(), SysError> {
+    let rem_ptr = rem.map_or(0, |rem| rem as *mut _ as usize);
+    isize2result(sys_nanosleep(req as *const _ as usize, rem_ptr))?;
+    Ok(())
+}
+
 pub fn get_priority(which: usize, who: usize) -> Result<usize, SysError> {
     isize2result(sys_get_priority(which, who))
 }
@@ -155,11 +229,29 @@ pub fn set_priority(which: usize, who: usize, prio: isize) -> Result<usize, SysE
     isize2result(sys_set_priority(which, who, prio))
 }
 
+/// Move process `pid` into group `pgid`, 0 stands for the caller in `pid` and for `pid` in `pgid`.
+pub fn setpgid(pid: usize, pgid: usize) -> Result<usize, SysError> {
+    isize2result(sys_setpgid(pid, pgid))
+}
+
+pub fn getpgid(pid: usize) -> Result<usize, SysError> {
+    isize2result(sys_getpgid(pid))
+}
+
+pub fn getsid(pid: usize) -> Result<usize, SysError> {
+    isize2result(sys_getsid(pid))
+}
+
+/// Start a new session and a new process group led by the caller, and return the session id.
+pub fn setsid() -> Result<usize, SysError> {
+    isize2result(sys_setsid())
+}
+
 pub fn get_time() -> usize {
     let mut time_spec = Timespec::empty();
     isize2result(sys_get_time(&mut time_spec as *mut _ as usize)).unwrap();
 
-    (time_spec.tv_usec as usize) / 1000
+    time_spec.tv_sec as usize * 1000 + time_spec.tv_usec as usize / 1000
 }
 
 pub fn getpid() -> usize {
@@ -170,6 +262,10 @@ pub fn getppid() -> usize {
     sys_get_ppid() as usize
 }
 
+pub fn gettid() -> usize {
+    sys_get_tid() as usize
+}
+
 pub fn brk(new_brk: Option<usize>) -> Result<usize, SysError> {
     let new_brk = if new_brk.is_some() { new_brk.unwrap() } else { 0 };
 
@@ -184,6 +280,22 @@ pub fn fork() -> Result<usize, SysError> {
     isize2result(sys_fork(0, 0, 0, 0, 0))
 }
 
+/// Create a child task which runs `entry(arg)` on `stack` and exits with its return value, the
+/// other arguments are the same as the clone syscall. `stack` is the top of the stack and can't be 0.
+pub fn clone(entry: extern "C" fn(usize) -> usize, stack: usize, flags: CloneFlags, arg: usize,
+             ptid_ptr: usize, tls: usize, ctid_ptr: usize) -> Result<usize, SysError> {
+    extern "C" {
+        fn __clone(entry: usize, stack: usize, flags: usize, arg: usize, ptid_ptr: usize, tls: usize, ctid_ptr: usize) -> isize;
+    }
+    if stack == 0 {
+        return Err(SysError::new(EINVAL));
+    }
+    let ret = unsafe {
+        __clone(entry as usize, stack, flags.bits() as usize, arg, ptid_ptr, tls, ctid_ptr)
+    };
+    isize2result(ret)
+}
+
 pub fn exec(path: &str, args: Vec<&str>) -> Result<usize, SysError> {
     // search from current directory and directory name in PATH env.
     let mut search_paths = Vec::new();
@@ -227,12 +339,49 @@ pub fn mmap(start: Option<usize>, len: usize, prot: Prot, flags: MMAPFlags, fd:
     isize2result(sys_mmap(start, len, prot.bits(), flags.bits(), fd, offset))
 }
 
+pub fn mprotect(start: usize, len: usize, prot: Prot) -> Result<(), SysError> {
+    isize2result(sys_mprotect(start, len, prot.bits()))?;
+    Ok(())
+}
+
+pub fn mremap(old_start: usize, old_len: usize, new_len: usize, flags: MremapFlags) -> Result<usize, SysError> {
+    isize2result(sys_mremap(old_start, old_len, new_len, flags.bits()))
+}
+
+pub fn msync(start: usize, len: usize, flags: MsyncFlags) -> Result<(), SysError> {
+    isize2result(sys_msync(start, len, flags.bits()))?;
+    Ok(())
+}
+
+pub fn getrlimit(resource: usize) -> Result<Rlimit, SysError> {
+    let mut rlimit = Rlimit::new(0, 0);
+    isize2result(sys_getrlimit(resource, &mut rlimit as *mut _ as usize))?;
+    Ok(rlimit)
+}
+
+pub fn setrlimit(resource: usize, rlimit: &Rlimit) -> Result<(), SysError> {
+    isize2result(sys_setrlimit(resource, rlimit as *const _ as usize))?;
+    Ok(())
+}
+
+/// Fill `buf` with the memory map of task `pid`(0 for current task), and return the number of all
+/// entries, which can be larger than `buf.len()`.
+pub fn memory_map(pid: usize, buf: &mut [MapEntry]) -> Result<usize, SysError> {
+    isize2result(sys_debug_memory_map(pid, buf.as_mut_ptr() as usize, buf.len()))
+}
+
 pub fn waitpid(pid: isize, status: Option<&mut usize>, options: usize) -> Result<usize, SysError> {
+    wait4(pid, status, options, None)
+}
+
+/// Same as `waitpid`, and the resource usage of an exited child is saved in `rusage`.
+pub fn wait4(pid: isize, status: Option<&mut usize>, options: usize, rusage: Option<&mut Rusage>) -> Result<usize, SysError> {
     let status_ptr = match status {
         Some(status) => status as *mut usize as usize,
         None => 0,
     };
-    isize2result(sys_waitpid(pid as usize, status_ptr, options))
+    let rusage_ptr = rusage.map_or(0, |rusage| rusage as *mut Rusage as usize);
+    isize2result(sys_waitpid(pid as usize, status_ptr, options, rusage_ptr))
 }
 
 /************************************** kcall wrapper ****************************************/
@@ -245,6 +394,46 @@ pub fn receive(dst_pid: isize, msg: &mut Msg) -> Result<usize, SysError> {
     isize2result(sys_receive(dst_pid, msg))
 }
 
+/// Send `msg` to `dst_pid` and wait for its reply, which overwrites `msg`.
+pub fn sendrec(dst_pid: usize, msg: &mut Msg) -> Result<usize, SysError> {
+    isize2result(sys_sendrec(dst_pid, msg))
+}
+
+/// Return `EAGAIN` instead of blocking if `dst_pid` is not receiving.
+pub fn send_nb(dst_pid: usize, msg: &Msg) -> Result<usize, SysError> {
+    isize2result(sys_send_nb(dst_pid, msg))
+}
+
+/// Return `EAGAIN` instead of blocking if there is no message.
+pub fn receive_nb(dst_pid: isize, msg: &mut Msg) -> Result<usize, SysError> {
+    isize2result(sys_receive_nb(dst_pid, msg))
+}
+
+/// Return `ETIMEDOUT` if `dst_pid` doesn't receive the message within `timeout_ms` milliseconds.
+pub fn send_timeout(dst_pid: usize, msg: &Msg, timeout_ms: usize) -> Result<usize, SysError> {
+    isize2result(sys_send_timeout(dst_pid, msg, timeout_ms))
+}
+
+/// Return `ETIMEDOUT` if no message arrives within `timeout_ms` milliseconds.
+pub fn receive_timeout(dst_pid: isize, msg: &mut Msg, timeout_ms: usize) -> Result<usize, SysError> {
+    isize2result(sys_receive_timeout(dst_pid, msg, timeout_ms))
+}
+
+/// Notify `dst_pid` without blocking, it receives a `NOTIFY` message whose `src_pid` is the caller.
+pub fn notify(dst_pid: usize) -> Result<usize, SysError> {
+    isize2result(sys_notify(dst_pid))
+}
+
+/// Pass the message through registers, only `mtype` and the first `SHORT_MSG_ARGS` args are sent.
+pub fn send_fast(dst_pid: usize, msg: &Msg) -> Result<usize, SysError> {
+    isize2result(sys_send_fast(dst_pid, msg))
+}
+
+/// Receive the message through registers, args after the first `SHORT_MSG_ARGS` are zeroed.
+pub fn receive_fast(dst_pid: isize, msg: &mut Msg) -> Result<usize, SysError> {
+    isize2result(sys_receive_fast(dst_pid, msg))
+}
+
 pub fn dev_read(dev_phys_addr: usize, byte_size: usize) -> Result<usize, SysError> {
     isize2result(k_read_dev(dev_phys_addr, byte_size))
 }
@@ -273,6 +462,49 @@ pub fn virt_copy(src_proc: usize, src_ptr: usize, dst_proc: usize, dst_ptr: usiz
     isize2result(k_virt_copy(src_proc, src_ptr, dst_proc, dst_ptr, length))
 }
 
+/// Allow `grantee` to access `[addr, addr + len)` of current process, return the grant id.
+pub fn grant_create(grantee: usize, addr: usize, len: usize, flags: GrantFlags) -> Result<usize, SysError> {
+    isize2result(k_grant_create(grantee, addr, len, flags.bits()))
+}
+
+pub fn grant_revoke(grant_id: usize) -> Result<usize, SysError> {
+    isize2result(k_grant_revoke(grant_id))
+}
+
+/// Copy `len` bytes at `offset` of the grant made by `granter` to `dst_ptr`.
+pub fn safecopy_from(granter: usize, grant_id: usize, offset: usize, dst_ptr: usize, len: usize) -> Result<usize, SysError> {
+    isize2result(k_safecopy_from(granter, grant_id, offset, dst_ptr, len))
+}
+
+/// Copy `len` bytes at `src_ptr` to `offset` of the grant made by `granter`.
+pub fn safecopy_to(granter: usize, grant_id: usize, offset: usize, src_ptr: usize, len: usize) -> Result<usize, SysError> {
+    isize2result(k_safecopy_to(granter, grant_id, offset, src_ptr, len))
+}
+
+/// Create a shared memory object of `size` bytes, return its start address.
+pub fn shm_create(size: usize, prot: Prot) -> Result<usize, SysError> {
+    isize2result(k_shm_create(size, prot.bits() as usize))
+}
+
+/// Map the shared memory object at `addr` into `dst_pid`, return its start address in `dst_pid`.
+pub fn shm_map(addr: usize, dst_pid: usize, prot: Prot) -> Result<usize, SysError> {
+    isize2result(k_shm_map(addr, dst_pid, prot.bits() as usize))
+}
+
+pub fn shm_unmap(addr: usize) -> Result<usize, SysError> {
+    isize2result(k_shm_unmap(addr))
+}
+
+/// Publish current process as the server of service `name`, return its endpoint.
+pub fn publish(name: &str) -> Result<usize, SysError> {
+    isize2result(k_publish(name))
+}
+
+/// Return the endpoint of service `name`, which can be used in place of a pid by ipc calls.
+pub fn lookup(name: &str) -> Result<usize, SysError> {
+    isize2result(k_lookup(name))
+}
+
 pub fn continuous_alloc(size: usize) -> Result<usize, SysError> {
     isize2result(k_continuous_alloc(size))
 }
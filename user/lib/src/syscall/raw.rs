@@ -118,6 +118,74 @@ pub fn sys_receive(dst_pid: isize, msg: &mut Msg) -> isize {
     syscall2(KCALL_RECEIVE, dst_pid as usize, msg_ptr)
 }
 
+pub fn sys_sendrec(dst_pid: usize, msg: &mut Msg) -> isize {
+    let msg_ptr = msg as *mut _ as usize;
+    syscall2(KCALL_SENDREC, dst_pid, msg_ptr)
+}
+
+pub fn sys_send_nb(dst_pid: usize, msg: &Msg) -> isize {
+    let msg_ptr = msg as *const _ as usize;
+    syscall2(KCALL_SEND_NB, dst_pid, msg_ptr)
+}
+
+pub fn sys_receive_nb(dst_pid: isize, msg: &mut Msg) -> isize {
+    let msg_ptr = msg as *mut _ as usize;
+    syscall2(KCALL_RECEIVE_NB, dst_pid as usize, msg_ptr)
+}
+
+pub fn sys_send_timeout(dst_pid: usize, msg: &Msg, timeout_ms: usize) -> isize {
+    let msg_ptr = msg as *const _ as usize;
+    syscall3(KCALL_SEND_TIMEOUT, dst_pid, msg_ptr, timeout_ms)
+}
+
+pub fn sys_receive_timeout(dst_pid: isize, msg: &mut Msg, timeout_ms: usize) -> isize {
+    let msg_ptr = msg as *mut _ as usize;
+    syscall3(KCALL_RECEIVE_TIMEOUT, dst_pid as usize, msg_ptr, timeout_ms)
+}
+
+pub fn sys_notify(dst_pid: usize) -> isize {
+    syscall1(KCALL_NOTIFY, dst_pid)
+}
+
+/// `mtype` and the first `SHORT_MSG_ARGS` args of `msg` are passed in a1-a6.
+pub fn sys_send_fast(dst_pid: usize, msg: &Msg) -> isize {
+    let ret;
+    unsafe {
+        asm!(
+        "ecall",
+        inout("a0") dst_pid => ret,
+        in("a1") msg.mtype,
+        in("a2") msg.args[0],
+        in("a3") msg.args[1],
+        in("a4") msg.args[2],
+        in("a5") msg.args[3],
+        in("a6") msg.args[4],
+        in("a7") KCALL_SEND_FAST,
+        );
+    }
+    ret
+}
+
+/// `src_pid`, `mtype` and the first `SHORT_MSG_ARGS` args of `msg` are returned in a1-a7.
+pub fn sys_receive_fast(dst_pid: isize, msg: &mut Msg) -> isize {
+    let ret;
+    unsafe {
+        asm!(
+        "ecall",
+        inout("a0") dst_pid => ret,
+        lateout("a1") msg.src_pid,
+        lateout("a2") msg.mtype,
+        lateout("a3") msg.args[0],
+        lateout("a4") msg.args[1],
+        lateout("a5") msg.args[2],
+        lateout("a6") msg.args[3],
+        inlateout("a7") KCALL_RECEIVE_FAST => msg.args[4],
+        );
+    }
+    msg.args[5] = 0;
+    ret
+}
+
 pub fn sys_lseek(fd: usize, offset: usize, whence: usize) -> isize {
     syscall3(SYSCALL_LSEEK, fd, offset, whence)
 }
@@ -186,10 +254,30 @@ pub fn sys_exit(exit_code: usize) -> isize {
     syscall1(SYSCALL_EXIT, exit_code)
 }
 
+pub fn sys_exit_group(exit_code: usize) -> isize {
+    syscall1(SYSCALL_EXIT_GROUP, exit_code)
+}
+
+pub fn sys_futex(uaddr: usize, op: usize, val: usize, timeout_ptr: usize, uaddr2: usize) -> isize {
+    syscall5(SYSCALL_FUTEX, uaddr, op, val, timeout_ptr, uaddr2)
+}
+
 pub fn sys_yield() -> isize {
     syscall0(SYSCALL_YIELD)
 }
 
+pub fn sys_kill(pid: usize, signo: usize) -> isize {
+    syscall2(SYSCALL_KILL, pid, signo)
+}
+
+pub fn sys_sigaction(signo: usize, act_ptr: usize, old_act_ptr: usize) -> isize {
+    syscall3(SYSCALL_SIGACTION, signo, act_ptr, old_act_ptr)
+}
+
+pub fn sys_sigprocmask(how: usize, set_ptr: usize, old_set_ptr: usize) -> isize {
+    syscall3(SYSCALL_SIGPROCMASK, how, set_ptr, old_set_ptr)
+}
+
 pub fn sys_get_priority(which: usize, who: usize) -> isize {
     syscall2(SYSCALL_GET_PRIORITY, which, who)
 }
@@ -198,6 +286,22 @@ pub fn sys_set_priority(which: usize, who: usize, prio: isize) -> isize {
     syscall3(SYSCALL_SET_PRIORITY, which, who, prio as usize)
 }
 
+pub fn sys_setpgid(pid: usize, pgid: usize) -> isize {
+    syscall2(SYSCALL_SETPGID, pid, pgid)
+}
+
+pub fn sys_getpgid(pid: usize) -> isize {
+    syscall1(SYSCALL_GETPGID, pid)
+}
+
+pub fn sys_getsid(pid: usize) -> isize {
+    syscall1(SYSCALL_GETSID, pid)
+}
+
+pub fn sys_setsid() -> isize {
+    syscall0(SYSCALL_SETSID)
+}
+
 pub fn sys_uname(which: usize) -> isize {
     syscall1(SYSCALL_UNAME, which)
 }
@@ -206,6 +310,10 @@ pub fn sys_get_time(ptr: usize) -> isize {
     syscall1(SYSCALL_GET_TIME, ptr)
 }
 
+pub fn sys_nanosleep(req: usize, rem: usize) -> isize {
+    syscall2(SYSCALL_NANOSLEEP, req, rem)
+}
+
 pub fn sys_get_pid() -> isize {
     syscall0(SYSCALL_GETPID)
 }
@@ -214,6 +322,10 @@ pub fn sys_get_ppid() -> isize {
     syscall0(SYSCALL_GETPPID)
 }
 
+pub fn sys_get_tid() -> isize {
+    syscall0(SYSCALL_GETTID)
+}
+
 pub fn sys_brk(new_brk: usize) -> isize {
     syscall1(SYSCALL_BRK, new_brk)
 }
@@ -235,8 +347,28 @@ pub fn sys_mmap(start: usize, len: usize, prot: u32, flags: u32, fd: usize, offs
     syscall6(SYSCALL_MMAP, start, len, prot as usize, flags as usize, fd, offset)
 }
 
-pub fn sys_waitpid(pid: usize, status_ptr: usize, options: usize) -> isize {
-    syscall3(SYSCALL_WAITPID, pid, status_ptr, options)
+pub fn sys_mprotect(start: usize, len: usize, prot: u32) -> isize {
+    syscall3(SYSCALL_MPROTECT, start, len, prot as usize)
+}
+
+pub fn sys_mremap(old_start: usize, old_len: usize, new_len: usize, flags: u32) -> isize {
+    syscall4(SYSCALL_MREMAP, old_start, old_len, new_len, flags as usize)
+}
+
+pub fn sys_msync(start: usize, len: usize, flags: u32) -> isize {
+    syscall3(SYSCALL_MSYNC, start, len, flags as usize)
+}
+
+pub fn sys_waitpid(pid: usize, status_ptr: usize, options: usize, rusage_ptr: usize) -> isize {
+    syscall4(SYSCALL_WAITPID, pid, status_ptr, options, rusage_ptr)
+}
+
+pub fn sys_getrlimit(resource: usize, rlimit_ptr: usize) -> isize {
+    syscall2(SYSCALL_GETRLIMIT, resource, rlimit_ptr)
+}
+
+pub fn sys_setrlimit(resource: usize, rlimit_ptr: usize) -> isize {
+    syscall2(SYSCALL_SETRLIMIT, resource, rlimit_ptr)
 }
 
 pub fn sys_test() -> isize {
@@ -247,6 +379,10 @@ pub fn debug_frame_usage() -> usize {
     syscall0(DEBUG_FRAME_USAGE) as usize
 }
 
+pub fn sys_debug_memory_map(pid: usize, buf_ptr: usize, len: usize) -> isize {
+    syscall3(DEBUG_MEMORY_MAP, pid, buf_ptr, len)
+}
+
 pub fn k_read_dev(dev_phys_addr: usize, byte_size: usize) -> isize {
     syscall2(KCALL_READ_DEV, dev_phys_addr, byte_size)
 }
@@ -272,6 +408,42 @@ pub fn k_virt_copy(
     )
 }
 
+pub fn k_grant_create(grantee: usize, addr: usize, len: usize, flags: usize) -> isize {
+    syscall4(KCALL_GRANT_CREATE, grantee, addr, len, flags)
+}
+
+pub fn k_grant_revoke(grant_id: usize) -> isize {
+    syscall1(KCALL_GRANT_REVOKE, grant_id)
+}
+
+pub fn k_safecopy_from(granter: usize, grant_id: usize, offset: usize, dst_ptr: usize, len: usize) -> isize {
+    syscall5(KCALL_SAFECOPY_FROM, granter, grant_id, offset, dst_ptr, len)
+}
+
+pub fn k_safecopy_to(granter: usize, grant_id: usize, offset: usize, src_ptr: usize, len: usize) -> isize {
+    syscall5(KCALL_SAFECOPY_TO, granter, grant_id, offset, src_ptr, len)
+}
+
+pub fn k_shm_create(size: usize, prot: usize) -> isize {
+    syscall2(KCALL_SHM_CREATE, size, prot)
+}
+
+pub fn k_shm_map(addr: usize, dst_pid: usize, prot: usize) -> isize {
+    syscall3(KCALL_SHM_MAP, addr, dst_pid, prot)
+}
+
+pub fn k_shm_unmap(addr: usize) -> isize {
+    syscall1(KCALL_SHM_UNMAP, addr)
+}
+
+pub fn k_publish(name: &str) -> isize {
+    syscall2(KCALL_PUBLISH, name.as_ptr() as usize, name.len())
+}
+
+pub fn k_lookup(name: &str) -> isize {
+    syscall2(KCALL_LOOKUP, name.as_ptr() as usize, name.len())
+}
+
 pub fn k_continuous_alloc(size: usize) -> isize {
     syscall1(KCALL_CONTINUOUS_ALLOC, size)
 }
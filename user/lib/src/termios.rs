@@ -1,12 +1,12 @@
-use share::terminal::{Termios, TC_GET_ATTR, TC_SET_ATTR};
-use share::ipc::{Msg, IOCTL, IOCTL_TYPE, ADDRESS, PROC_NR, DEVICE};
-use crate::syscall::{send, receive, getpid};
+use share::terminal::{Termios, TC_GET_ATTR, TC_SET_ATTR, TC_GET_PGRP, TC_SET_PGRP};
+use share::ipc::{Msg, IOCTL, IOCTL_TYPE, ADDRESS, PROC_NR, DEVICE, TERMINAL_SERVICE, GrantFlags};
+use crate::syscall::{sendrec, getpid, grant_create, grant_revoke, lookup};
+use core::mem::size_of;
 use share::syscall::error::SysError;
 
-const TERMINAL_PID: usize = 1;
-
 pub fn tc_get_attr(fd: usize) -> Result<Termios, SysError> {
     assert!(fd == 0 || fd == 1);
+    let terminal = lookup(TERMINAL_SERVICE)?;
     let mut termios = Termios::empty();
 
     let mut message = Msg::empty();
@@ -14,10 +14,11 @@ pub fn tc_get_attr(fd: usize) -> Result<Termios, SysError> {
     message.args[DEVICE] = 0;
     message.args[PROC_NR] = getpid();
     message.args[IOCTL_TYPE] = TC_GET_ATTR;
-    message.args[ADDRESS] = &mut termios as *mut _ as usize;
+    let grant = grant_create(terminal, &mut termios as *mut _ as usize, size_of::<Termios>(), GrantFlags::WRITE)?;
+    message.args[ADDRESS] = grant;
 
-    send(TERMINAL_PID, &message)?;
-    receive(TERMINAL_PID as isize, &mut message)?;
+    sendrec(terminal, &mut message)?;
+    grant_revoke(grant)?;
     message.cvt_reply_message_to_result()?;
 
     Ok(termios)
@@ -25,16 +26,57 @@ pub fn tc_get_attr(fd: usize) -> Result<Termios, SysError> {
 
 pub fn tc_set_attr(fd: usize, termios: Termios) -> Result<(), SysError> {
     assert!(fd == 0 || fd == 1);
+    let terminal = lookup(TERMINAL_SERVICE)?;
     let mut message = Msg::empty();
     message.mtype = IOCTL;
     message.args[DEVICE] = 0;
     message.args[PROC_NR] = getpid();
     message.args[IOCTL_TYPE] = TC_SET_ATTR;
-    message.args[ADDRESS] = &termios as *const _ as usize;
+    let grant = grant_create(terminal, &termios as *const _ as usize, size_of::<Termios>(), GrantFlags::READ)?;
+    message.args[ADDRESS] = grant;
 
-    send(TERMINAL_PID, &message)?;
-    receive(TERMINAL_PID as isize, &mut message)?;
+    sendrec(terminal, &mut message)?;
+    grant_revoke(grant)?;
     message.cvt_reply_message_to_result()?;
 
     Ok(())
-}
\ No newline at end of file
+}
+/// Return the foreground process group of the terminal, 0 if it has never been set.
+pub fn tc_get_pgrp(fd: usize) -> Result<usize, SysError> {
+    assert!(fd == 0 || fd == 1);
+    let terminal = lookup(TERMINAL_SERVICE)?;
+    let mut pgid: usize = 0;
+
+    let mut message = Msg::empty();
+    message.mtype = IOCTL;
+    message.args[DEVICE] = 0;
+    message.args[PROC_NR] = getpid();
+    message.args[IOCTL_TYPE] = TC_GET_PGRP;
+    let grant = grant_create(terminal, &mut pgid as *mut _ as usize, size_of::<usize>(), GrantFlags::WRITE)?;
+    message.args[ADDRESS] = grant;
+
+    sendrec(terminal, &mut message)?;
+    grant_revoke(grant)?;
+    message.cvt_reply_message_to_result()?;
+
+    Ok(pgid)
+}
+
+/// Make `pgid` the foreground process group of the terminal.
+pub fn tc_set_pgrp(fd: usize, pgid: usize) -> Result<(), SysError> {
+    assert!(fd == 0 || fd == 1);
+    let terminal = lookup(TERMINAL_SERVICE)?;
+    let mut message = Msg::empty();
+    message.mtype = IOCTL;
+    message.args[DEVICE] = 0;
+    message.args[PROC_NR] = getpid();
+    message.args[IOCTL_TYPE] = TC_SET_PGRP;
+    let grant = grant_create(terminal, &pgid as *const _ as usize, size_of::<usize>(), GrantFlags::READ)?;
+    message.args[ADDRESS] = grant;
+
+    sendrec(terminal, &mut message)?;
+    grant_revoke(grant)?;
+    message.cvt_reply_message_to_result()?;
+
+    Ok(())
+}
@@ -0,0 +1,24 @@
+    .section .text
+    .globl __clone
+# __clone(entry, stack, flags, arg, ptid, tls, ctid)
+# The child calls entry(arg) on `stack` and exits with the return value, it must not touch anything
+# on the stack of the caller. a7 = SYSCALL_FORK, SYSCALL_EXIT
+__clone:
+    andi a1, a1, -16
+    addi a1, a1, -16
+    sd a0, 0(a1)
+    sd a3, 8(a1)
+    mv a0, a2
+    mv a2, a4
+    mv a3, a5
+    mv a4, a6
+    li a7, 220
+    ecall
+    beqz a0, 1f
+    ret
+1:
+    ld t0, 0(sp)
+    ld a0, 8(sp)
+    jalr t0
+    li a7, 93
+    ecall
@@ -0,0 +1,94 @@
+use alloc::boxed::Box;
+use alloc::sync::Arc;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::cell::UnsafeCell;
+use core::sync::atomic::{AtomicU32, Ordering};
+use share::clone::CloneFlags;
+use share::syscall::error::SysError;
+use crate::syscall::{clone, futex_wait};
+
+const STACK_SIZE: usize = 0x4000;
+
+type ThreadMain = Box<dyn FnOnce() + Send>;
+
+/// Where the return value of a thread is put.
+struct Packet<T>(UnsafeCell<Option<T>>);
+
+unsafe impl<T: Send> Sync for Packet<T> {}
+
+/// Memory used by a running thread, it must stay alive until the thread exits.
+struct ThreadMemory {
+    stack: Vec<u8>,
+    /// Set to the tid by the kernel when the thread is created, and cleared when it exits, then
+    /// the joining thread waiting on it is woken up.
+    tid: AtomicU32,
+}
+
+pub struct JoinHandle<T> {
+    tid: usize,
+    memory: Option<Box<ThreadMemory>>,
+    packet: Arc<Packet<T>>,
+}
+
+/// Run `f` in a new thread of current process.
+pub fn spawn<F, T>(f: F) -> Result<JoinHandle<T>, SysError>
+    where F: FnOnce() -> T + Send + 'static, T: Send + 'static {
+    let packet = Arc::new(Packet(UnsafeCell::new(None)));
+    let their_packet = packet.clone();
+    let main: ThreadMain = Box::new(move || {
+        let result = f();
+        unsafe { *their_packet.0.get() = Some(result) };
+    });
+    let arg = Box::into_raw(Box::new(main)) as usize;
+
+    let memory = Box::new(ThreadMemory {
+        stack: vec![0; STACK_SIZE],
+        tid: AtomicU32::new(0),
+    });
+    let stack_top = memory.stack.as_ptr() as usize + STACK_SIZE;
+    let ctid_ptr = &memory.tid as *const _ as usize;
+    let flags = CloneFlags::VM | CloneFlags::THREAD | CloneFlags::CHILD_SETTID | CloneFlags::CHILD_CLEARTID;
+    match clone(thread_start, stack_top, flags, arg, 0, 0, ctid_ptr) {
+        Ok(tid) => Ok(JoinHandle { tid, memory: Some(memory), packet }),
+        Err(err) => {
+            drop(unsafe { Box::from_raw(arg as *mut ThreadMain) });
+            Err(err)
+        }
+    }
+}
+
+extern "C" fn thread_start(arg: usize) -> usize {
+    let main = unsafe { Box::from_raw(arg as *mut ThreadMain) };
+    main();
+    0
+}
+
+impl<T> JoinHandle<T> {
+    pub fn tid(&self) -> usize {
+        self.tid
+    }
+
+    /// Wait for the thread to exit, and return what it returns. `None` is returned if the thread
+    /// has exited without returning, e.g. it is killed or calls `exit_thread`.
+    pub fn join(mut self) -> Option<T> {
+        let memory = self.memory.take().unwrap();
+        loop {
+            let tid = memory.tid.load(Ordering::Acquire);
+            if tid == 0 {
+                break;
+            }
+            let _ = futex_wait(&memory.tid, tid, None);
+        }
+        unsafe { (*self.packet.0.get()).take() }
+    }
+}
+
+impl<T> Drop for JoinHandle<T> {
+    /// A detached thread may still be running on its stack, which is never freed.
+    fn drop(&mut self) {
+        if let Some(memory) = self.memory.take() {
+            core::mem::forget(memory);
+        }
+    }
+}